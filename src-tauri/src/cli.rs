@@ -0,0 +1,418 @@
+//! Headless subcommands. They run the same services as the app without
+//! starting Tauri, so docs repositories can be checked and exported in CI.
+
+use std::ffi::{OsStr, OsString};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+
+use crate::error::{AppError, AppResult};
+use crate::services::di::build_app_container;
+use crate::services::document_store::decode_text;
+use crate::services::AppServices;
+use crate::state::{FsState, FsStateData};
+
+/// Exit code when the command ran but found problems or nothing matched.
+const EXIT_FAILURE: i32 = 1;
+/// Exit code when the command could not run.
+const EXIT_ERROR: i32 = 2;
+
+#[derive(Debug, Parser)]
+#[command(
+  name = "marko",
+  about = "Work with a marko workspace without opening a window"
+)]
+struct Cli {
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+  /// Builds the full-text search index of a workspace.
+  Index {
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+  },
+  /// Searches a workspace. Exits with 1 when nothing matches.
+  Search {
+    query: String,
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+    #[arg(long)]
+    json: bool,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+  },
+  /// Reports broken links and headings. Exits with 1 on errors, or on
+  /// warnings with `--deny-warnings`.
+  Check {
+    #[arg(long)]
+    deny_warnings: bool,
+    #[arg(long)]
+    json: bool,
+    #[command(flatten)]
+    workspace: WorkspaceArgs,
+  },
+  /// Exports a Markdown file.
+  Export {
+    file: PathBuf,
+    #[arg(long, value_enum)]
+    format: CliExportFormat,
+    /// Defaults to the input path with the format's extension.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+  },
+}
+
+#[derive(Debug, clap::Args)]
+struct WorkspaceArgs {
+  /// Workspace folder.
+  #[arg(long, short, default_value = ".")]
+  workspace: PathBuf,
+  /// Where the search index is kept, `marko` in the temp directory by
+  /// default.
+  #[arg(long)]
+  index_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CliExportFormat {
+  Pdf,
+  Docx,
+  Html,
+}
+
+impl CliExportFormat {
+  fn as_str(self) -> &'static str {
+    match self {
+      Self::Pdf => "pdf",
+      Self::Docx => "docx",
+      Self::Html => "html",
+    }
+  }
+}
+
+/// Runs a headless subcommand when the arguments start with one and returns
+/// its exit code. Other arguments, such as paths to open, are left to the
+/// app. A file named like a subcommand opens in the app when it exists or
+/// follows `--`; a folder of that name does not count, so `marko index` in a
+/// repository with an `index/` folder still runs headless.
+pub async fn run_from_args(args: Vec<OsString>) -> Option<i32> {
+  let cwd = std::env::current_dir().unwrap_or_default();
+  if !is_subcommand(args.get(1)?, &cwd) {
+    return None;
+  }
+  attach_parent_console();
+  let cli = match Cli::try_parse_from(args) {
+    Ok(cli) => cli,
+    Err(err) => {
+      let _ = err.print();
+      return Some(if err.use_stderr() { EXIT_ERROR } else { 0 });
+    }
+  };
+
+  let container = match build_app_container().await {
+    Ok(container) => container,
+    Err(err) => {
+      eprintln!("error: failed to start services: {err}");
+      return Some(EXIT_ERROR);
+    }
+  };
+  let code = match run(cli.command, &container.services, &mut std::io::stdout()).await {
+    Ok(code) => code,
+    Err(err) => {
+      eprintln!("error: {err}");
+      EXIT_ERROR
+    }
+  };
+  if let Err(err) = container.lifecycle.shutdown_blocking() {
+    log::warn!("application lifecycle shutdown failed: {err}");
+  }
+  Some(code)
+}
+
+fn is_subcommand(arg: &OsStr, cwd: &Path) -> bool {
+  let Some(name) = arg.to_str() else {
+    return false;
+  };
+  Cli::command()
+    .get_subcommands()
+    .any(|command| command.get_name() == name)
+    && !cwd.join(arg).is_file()
+}
+
+/// Release builds on Windows use the GUI subsystem and start without a
+/// console, so output goes nowhere unless the shell's console is attached.
+#[cfg(windows)]
+fn attach_parent_console() {
+  use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+  // SAFETY: AttachConsole takes a plain process id; it fails harmlessly when
+  // there is no parent console or one is already attached.
+  unsafe {
+    AttachConsole(ATTACH_PARENT_PROCESS);
+  }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
+
+async fn run(command: Command, services: &AppServices, out: &mut impl Write) -> AppResult<i32> {
+  match command {
+    Command::Index { workspace } => {
+      let (state, index_dir) = open_workspace(&workspace)?;
+      let task = services
+        .background_tasks
+        .start("search-index", "Search index")?;
+      let result = services
+        .workspace
+        .rebuild_search_index(index_dir.clone(), &state, task.clone())
+        .await;
+      task.finish(&result);
+      result?;
+      let documents = services
+        .workspace
+        .list_entries(&state)
+        .await?
+        .iter()
+        .filter(|entry| entry.kind == "file")
+        .count();
+      write_line(
+        out,
+        format!("Indexed {documents} documents in {}", index_dir.display()),
+      )?;
+      Ok(0)
+    }
+    Command::Search {
+      query,
+      limit,
+      json,
+      workspace,
+    } => {
+      let (state, index_dir) = open_workspace(&workspace)?;
+      let results = services
+        .workspace
+        .search_workspace(index_dir, query, limit, &state)
+        .await?;
+      if json {
+        write_json(out, &results)?;
+      } else {
+        for result in &results {
+          write_line(
+            out,
+            format!(
+              "{}:{}:{}: {}",
+              result.path, result.line, result.column, result.snippet
+            ),
+          )?;
+        }
+      }
+      Ok(if results.is_empty() { EXIT_FAILURE } else { 0 })
+    }
+    Command::Check {
+      deny_warnings,
+      json,
+      workspace,
+    } => {
+      let (state, _) = open_workspace(&workspace)?;
+      let files = services.workspace.workspace_diagnostics(&state).await?;
+      let diagnostics = files.iter().flat_map(|file| {
+        file
+          .diagnostics
+          .iter()
+          .map(move |diagnostic| (&file.path, diagnostic))
+      });
+      let (mut errors, mut warnings) = (0, 0);
+      for (path, diagnostic) in diagnostics {
+        if diagnostic.severity == "error" {
+          errors += 1;
+        } else {
+          warnings += 1;
+        }
+        if !json {
+          write_line(
+            out,
+            format!(
+              "{path}:{}:{}: {}: {}",
+              diagnostic.line, diagnostic.start_column, diagnostic.severity, diagnostic.message
+            ),
+          )?;
+        }
+      }
+      if json {
+        write_json(out, &files)?;
+      } else {
+        write_line(out, format!("{errors} error(s), {warnings} warning(s)"))?;
+      }
+      let failed = errors > 0 || (deny_warnings && warnings > 0);
+      Ok(if failed { EXIT_FAILURE } else { 0 })
+    }
+    Command::Export {
+      file,
+      format,
+      output,
+    } => {
+      let bytes = tokio::fs::read(&file)
+        .await
+        .map_err(|err| AppError::io("Failed to read file", &file, err))?;
+      let (markdown, _) = decode_text(&bytes)?;
+      let output = output.unwrap_or_else(|| file.with_extension(format.as_str()));
+      let output_path = output.to_string_lossy().to_string();
+      let export = services.export.clone();
+      tokio::task::spawn_blocking(move || {
+        export.export_markdown(&markdown, format.as_str(), &output_path)
+      })
+      .await
+      .map_err(|err| AppError::from(format!("Export task failed: {err}")))??;
+      write_line(out, format!("Exported {}", output.display()))?;
+      Ok(0)
+    }
+  }
+}
+
+fn open_workspace(args: &WorkspaceArgs) -> AppResult<(FsState, PathBuf)> {
+  let root = std::fs::canonicalize(&args.workspace)
+    .map_err(|err| AppError::io("Failed to open workspace", &args.workspace, err))?;
+  if !root.is_dir() {
+    return Err(AppError::invalid_path(
+      root.to_string_lossy(),
+      "Workspace is not a directory",
+    ));
+  }
+  let index_dir = args.index_dir.clone().unwrap_or_else(default_index_dir);
+  let state = FsState(RwLock::new(FsStateData {
+    root_kind: "external".to_string(),
+    root_path: root.clone(),
+    internal_root: root,
+    single_file: None,
+    mounted_roots: Vec::new(),
+  }));
+  Ok((state, index_dir))
+}
+
+/// The index is keyed by workspace below this directory, so every workspace
+/// shares it and nothing is written into the repository.
+fn default_index_dir() -> PathBuf {
+  std::env::temp_dir().join("marko")
+}
+
+fn write_line(out: &mut impl Write, line: String) -> AppResult<()> {
+  writeln!(out, "{line}").map_err(|err| AppError::io("Failed to write output", "stdout", err))
+}
+
+fn write_json(out: &mut impl Write, value: &impl serde::Serialize) -> AppResult<()> {
+  let json = serde_json::to_string_pretty(value)
+    .map_err(|err| AppError::from(format!("Failed to serialize output: {err}")))?;
+  write_line(out, json)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::test_support::temp_dir;
+
+  use super::*;
+
+  fn temp_workspace(name: &str) -> PathBuf {
+    let root = temp_dir(&format!("cli-{name}"));
+    std::fs::create_dir_all(&root).expect("temp dir should be created");
+    root
+  }
+
+  async fn run_args(services: &AppServices, args: &[&str]) -> (i32, String) {
+    let cli = Cli::try_parse_from(args).expect("arguments should parse");
+    let mut out = Vec::new();
+    let code = run(cli.command, services, &mut out)
+      .await
+      .expect("command should run");
+    (
+      code,
+      String::from_utf8(out).expect("output should be utf-8"),
+    )
+  }
+
+  #[tokio::test(flavor = "multi_thread")]
+  async fn indexes_searches_and_checks_a_workspace() {
+    let base = temp_workspace("workspace");
+    let root = base.join("notes");
+    std::fs::create_dir_all(&root).expect("workspace should be created");
+    std::fs::write(
+      root.join("guide.md"),
+      "# Guide\n\nInstall the lighthouse.\n\n[Setup](setup.md#missing)\n",
+    )
+    .expect("guide should be written");
+    std::fs::write(root.join("setup.md"), "# Setup\n").expect("setup should be written");
+    let workspace = root.to_string_lossy().to_string();
+    let index_dir = base.join("index").to_string_lossy().to_string();
+    let scoped = |args: &[&'static str]| {
+      let mut args: Vec<&str> = args.to_vec();
+      args.extend(["-w", &workspace, "--index-dir", &index_dir]);
+      args
+    };
+    let container = build_app_container()
+      .await
+      .expect("app services should resolve");
+    let services = &container.services;
+
+    let (code, out) = run_args(services, &scoped(&["marko", "index"])).await;
+    assert_eq!(code, 0);
+    assert!(out.starts_with("Indexed 2 documents"));
+    assert!(out.contains(&index_dir));
+    assert!(!root.join(".marko").exists());
+
+    let (code, out) = run_args(services, &scoped(&["marko", "search", "lighthouse"])).await;
+    assert_eq!(code, 0);
+    assert!(out.starts_with("guide.md:3:"), "{out}");
+    let (code, _) = run_args(services, &scoped(&["marko", "search", "absent"])).await;
+    assert_eq!(code, EXIT_FAILURE);
+
+    let (code, out) = run_args(services, &scoped(&["marko", "check"])).await;
+    assert_eq!(code, 0);
+    assert!(out.contains("guide.md:5:"), "{out}");
+    assert!(out.ends_with("0 error(s), 1 warning(s)\n"));
+    let (code, _) = run_args(services, &scoped(&["marko", "check", "--deny-warnings"])).await;
+    assert_eq!(code, EXIT_FAILURE);
+
+    // Each run is a new process, so files are read fresh.
+    std::fs::write(root.join("setup.md"), "[Gone](gone.md)\n").expect("setup should be written");
+    let fresh = build_app_container()
+      .await
+      .expect("app services should resolve");
+    let (code, out) = run_args(&fresh.services, &scoped(&["marko", "check"])).await;
+    assert_eq!(code, EXIT_FAILURE);
+    assert!(out.contains("setup.md:1:1: error:"), "{out}");
+
+    let guide = root.join("guide.md").to_string_lossy().to_string();
+    let (code, _) = run_args(services, &["marko", "export", &guide, "--format", "html"]).await;
+    assert_eq!(code, 0);
+    assert!(root.join("guide.html").exists());
+    std::fs::remove_dir_all(base).expect("temp dir should be removed");
+  }
+
+  #[tokio::test]
+  async fn leaves_other_arguments_to_the_app() {
+    assert_eq!(
+      run_from_args(vec!["marko".into(), "notes/today.md".into()]).await,
+      None
+    );
+    assert_eq!(run_from_args(vec!["marko".into()]).await, None);
+    assert_eq!(
+      run_from_args(vec!["marko".into(), "--".into(), "index".into()]).await,
+      None
+    );
+  }
+
+  #[test]
+  fn opens_files_named_like_a_subcommand() {
+    let root = temp_workspace("subcommand-names");
+    std::fs::write(root.join("search"), "# Search\n").expect("file should be written");
+    std::fs::create_dir_all(root.join("export")).expect("folder should be created");
+    assert!(is_subcommand(OsStr::new("index"), &root));
+    assert!(!is_subcommand(OsStr::new("search"), &root));
+    assert!(!is_subcommand(OsStr::new("notes"), &root));
+    assert!(is_subcommand(OsStr::new("export"), &root));
+    std::fs::remove_dir_all(root).expect("temp dir should be removed");
+  }
+}
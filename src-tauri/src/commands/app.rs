@@ -1,4 +1,42 @@
-use tauri::{AppHandle, Emitter};
+use serde::Serialize;
+use tauri::{AppHandle, CloseRequestApi, Manager, Runtime, State};
+
+use crate::error::AppResult;
+use crate::models::{EventTopic, LoggedEvent};
+use crate::services::di::AppLifecycle;
+use crate::services::events::{emit_event, replay_events, FrontendEvent};
+use crate::services::AppServices;
+use crate::state::{FsState, FsWatcherState};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppCloseBlockedEvent {
+  pub message: String,
+  pub paths: Vec<String>,
+}
+
+impl FrontendEvent for AppCloseBlockedEvent {
+  const TOPIC: EventTopic = EventTopic::App;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MenuActionEvent {
+  pub id: String,
+}
+
+impl FrontendEvent for MenuActionEvent {
+  const TOPIC: EventTopic = EventTopic::App;
+}
+
+/// A second launch handed over to the running instance.
+#[derive(Debug, Clone, Serialize)]
+pub struct SingleInstanceEvent {
+  pub args: Vec<String>,
+  pub cwd: String,
+}
+
+impl FrontendEvent for SingleInstanceEvent {
+  const TOPIC: EventTopic = EventTopic::App;
+}
 
 #[tauri::command]
 pub fn app_get_platform() -> String {
@@ -6,12 +44,116 @@ pub fn app_get_platform() -> String {
 }
 
 #[tauri::command]
-pub fn menu_dispatch(id: String, app: AppHandle) -> Result<(), String> {
-  app.emit("menu-action", id).map_err(|err| err.to_string())
+pub fn menu_dispatch(id: String, app: AppHandle) -> AppResult<()> {
+  emit_event(&app, "menu-action", &MenuActionEvent { id })
+}
+
+/// Logged frontend events after `since`, oldest first.
+#[tauri::command]
+pub fn app_get_event_log(
+  since: Option<u64>,
+  topic: Option<EventTopic>,
+  services: State<'_, AppServices>,
+) -> AppResult<Vec<LoggedEvent>> {
+  services.events.log(since, topic)
+}
+
+/// Emits logged events again with `replayed` set, so a listener can be
+/// debugged against what the backend sent earlier. The frontend bridge only
+/// delivers them to listeners that opt in.
+#[tauri::command]
+pub fn app_replay_events(
+  since: Option<u64>,
+  topic: Option<EventTopic>,
+  services: State<'_, AppServices>,
+  app: AppHandle,
+) -> AppResult<usize> {
+  let events = services.events.log(since, topic)?;
+  replay_events(&app, &events);
+  Ok(events.len())
+}
+
+/// Saves pending buffers before the main window closes. If some could not
+/// be saved the first close is cancelled and `app-close-blocked` tells the
+/// user which files; closing again quits with them kept in the recovery
+/// journal. Saving and stopping the workers run off the main thread, so
+/// the close is always held back and the app exits once they are done.
+pub fn on_main_window_close_requested(app: &AppHandle, api: &CloseRequestApi) {
+  let Some(lifecycle) = app.try_state::<AppLifecycle>() else {
+    return;
+  };
+  api.prevent_close();
+  if !lifecycle.begin_close() {
+    return;
+  }
+  let lifecycle = lifecycle.inner().clone();
+  let app = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    if save_before_close(&app, &lifecycle) {
+      shutdown_app(&app);
+      app.exit(0);
+    } else {
+      lifecycle.end_close();
+    }
+  });
+}
+
+/// Returns `false` if the close was blocked because some files could not be
+/// saved.
+fn save_before_close(app: &AppHandle, lifecycle: &AppLifecycle) -> bool {
+  let (Some(state), Some(services)) = (app.try_state::<FsState>(), app.try_state::<AppServices>())
+  else {
+    return true;
+  };
+  let unsaved: Vec<String> =
+    match crate::commands::fs::flush_all_buffers_with_status(&state, &services) {
+      Ok(statuses) => statuses
+        .into_iter()
+        .filter(|status| status.error.is_some() || status.conflict)
+        .map(|status| status.path)
+        .collect(),
+      Err(err) => {
+        log::warn!("flush buffers on close failed: {err}");
+        vec![err.to_string()]
+      }
+    };
+  // Whatever could not be saved stays journaled for the next launch.
+  if let Err(err) = crate::commands::recovery::checkpoint_recovery_journal(app, &state, &services) {
+    log::warn!("checkpoint recovery journal failed: {err}");
+  }
+  if unsaved.is_empty() || !lifecycle.block_close_once() {
+    return true;
+  }
+  let event = AppCloseBlockedEvent {
+    message: format!(
+      "{} file(s) could not be saved. Close again to quit; unsaved changes are kept for recovery.",
+      unsaved.len()
+    ),
+    paths: unsaved,
+  };
+  if let Err(err) = emit_event(app, "app-close-blocked", &event) {
+    log::warn!("emit app close blocked failed: {err}");
+  }
+  false
+}
+
+/// Drops the filesystem watchers and stops the container, blocking until
+/// every background worker has finished or the shutdown timeout passed.
+pub fn shutdown_app<R: Runtime>(app: &AppHandle<R>) {
+  if let Some(watchers) = app.try_state::<FsWatcherState>() {
+    if let Ok(mut watchers) = watchers.0.lock() {
+      watchers.clear();
+    }
+  }
+  if let Some(lifecycle) = app.try_state::<AppLifecycle>() {
+    if let Err(err) = lifecycle.shutdown_blocking() {
+      log::warn!("application lifecycle shutdown failed: {err}");
+    }
+  }
 }
 
 #[cfg(target_os = "macos")]
-pub fn setup_native_menu(app: &AppHandle) -> Result<(), String> {
+pub fn setup_native_menu(app: &AppHandle) -> AppResult<()> {
   use tauri::menu::{MenuBuilder, SubmenuBuilder};
 
   let file = SubmenuBuilder::new(app, "File")
@@ -72,6 +214,6 @@ pub fn setup_native_menu(app: &AppHandle) -> Result<(), String> {
 }
 
 #[cfg(not(target_os = "macos"))]
-pub fn setup_native_menu(_app: &AppHandle) -> Result<(), String> {
+pub fn setup_native_menu(_app: &AppHandle) -> AppResult<()> {
   Ok(())
 }
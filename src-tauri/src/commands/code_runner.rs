@@ -0,0 +1,194 @@
+use std::path::{Path, PathBuf};
+
+use tauri::{Manager, State};
+
+use crate::commands::terminal::terminal_working_directory;
+use crate::error::{AppError, AppResult};
+use crate::models::{CodeRunInfo, CodeRunnerSettings};
+use crate::services::code_runner::{
+  interpreter_for_language, read_code_runner_settings, write_code_runner_settings, CodeRunExitEvent,
+};
+use crate::services::events::emit_event;
+use crate::services::terminal::TerminalLaunch;
+use crate::services::AppServices;
+use crate::state::{FsState, FsStateData};
+
+#[tauri::command]
+pub fn code_runner_get_settings(
+  state: State<'_, FsState>,
+  app: tauri::AppHandle,
+) -> AppResult<CodeRunnerSettings> {
+  let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+  code_runner_settings(&app_data_dir(&app)?, &data)
+}
+
+#[tauri::command]
+pub fn code_runner_set_settings(
+  settings: CodeRunnerSettings,
+  state: State<'_, FsState>,
+  app: tauri::AppHandle,
+) -> AppResult<CodeRunnerSettings> {
+  let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+  if data.root_kind == "single" {
+    return Err(AppError::unsupported(
+      "Code runner settings need a workspace folder",
+    ));
+  }
+  write_code_runner_settings(&app_data_dir(&app)?, &data.root_path, &settings)?;
+  Ok(settings)
+}
+
+/// Runs a code block of a note in the workspace directory. The `capture`
+/// target streams `code-run-output` events and ends with `code-run-exit`;
+/// with `write_output` the captured output is written below the block. The
+/// `terminal` target opens a terminal session running the block instead;
+/// the frontend resizes it with `terminal_resize` once attached.
+#[tauri::command]
+pub async fn code_block_run(
+  path: String,
+  block_id: String,
+  target: Option<String>,
+  write_output: Option<bool>,
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<CodeRunInfo> {
+  let target = target.unwrap_or_else(|| "capture".to_string());
+  if target != "capture" && target != "terminal" {
+    return Err(AppError::invalid_input(format!(
+      "Unknown run target: {target}"
+    )));
+  }
+  let data = state
+    .0
+    .read()
+    .map_err(|_| AppError::lock("fs state"))?
+    .clone();
+  let cwd = terminal_working_directory(&data)?;
+  let app_data = app_data_dir(&app)?;
+  let settings = code_runner_settings(&app_data, &data)?;
+  let block = services
+    .workspace
+    .code_block(&path, &block_id, &state)
+    .await?;
+  let language = block
+    .language
+    .ok_or_else(|| "Code block has no language".to_string())?;
+  let interpreter = interpreter_for_language(&settings, &language)?.clone();
+
+  let id = services.code_runner.next_run_id();
+  let script = services
+    .code_runner
+    .write_script(&app_data, &id, &interpreter, &block.code)?;
+  let mut info = CodeRunInfo {
+    id: id.clone(),
+    path: path.clone(),
+    block_id: block_id.clone(),
+    language,
+    target: target.clone(),
+    terminal_id: None,
+  };
+
+  if target == "terminal" {
+    // The session removes the script once its process exits.
+    let mut args = interpreter.args;
+    args.push(script.to_string_lossy().to_string());
+    let session = services
+      .terminal
+      .create(
+        app,
+        TerminalLaunch {
+          profile: None,
+          program: interpreter.program,
+          args,
+          env: Default::default(),
+          cwd,
+          temp_files: vec![script.clone()],
+        },
+        24,
+        80,
+      )
+      .inspect_err(|_| {
+        let _ = std::fs::remove_file(&script);
+      })?;
+    info.terminal_id = Some(session.id);
+    return Ok(info);
+  }
+
+  let cancel = services.code_runner.register(&id)?;
+  let write_output = write_output.unwrap_or(false);
+  tauri::async_runtime::spawn(async move {
+    let services = app.state::<AppServices>();
+    let state = app.state::<FsState>();
+    let outcome = match services
+      .code_runner
+      .run_captured(app.clone(), &id, cancel, &interpreter, &script, &cwd)
+      .await
+    {
+      Ok(outcome) => outcome,
+      Err(err) => {
+        log::warn!("code block run failed: {err}");
+        let _ = emit_event(
+          &app,
+          "code-run-exit",
+          &CodeRunExitEvent {
+            id,
+            exit_code: None,
+            cancelled: false,
+            output: err,
+            written: false,
+          },
+        );
+        return;
+      }
+    };
+    let written = write_output && !outcome.cancelled && {
+      match services
+        .workspace
+        .write_code_output(&path, &block_id, &block.code, &outcome.output, &state)
+        .await
+      {
+        Ok(()) => true,
+        Err(err) => {
+          log::warn!("write code block output failed: {err}");
+          false
+        }
+      }
+    };
+    let _ = emit_event(
+      &app,
+      "code-run-exit",
+      &CodeRunExitEvent {
+        id,
+        exit_code: outcome.exit_code,
+        cancelled: outcome.cancelled,
+        output: outcome.output,
+        written,
+      },
+    );
+  });
+
+  Ok(info)
+}
+
+#[tauri::command]
+pub fn code_block_cancel(id: String, services: State<'_, AppServices>) -> AppResult<()> {
+  Ok(services.code_runner.cancel(&id)?)
+}
+
+/// Settings are stored per workspace; a single file uses the defaults.
+fn code_runner_settings(app_data: &Path, data: &FsStateData) -> AppResult<CodeRunnerSettings> {
+  if data.root_kind == "single" {
+    return Ok(CodeRunnerSettings::default());
+  }
+  Ok(read_code_runner_settings(app_data, &data.root_path)?)
+}
+
+fn app_data_dir(app: &tauri::AppHandle) -> AppResult<PathBuf> {
+  Ok(
+    app
+      .path()
+      .app_data_dir()
+      .map_err(|err| format!("Failed to resolve app data dir: {err}"))?,
+  )
+}
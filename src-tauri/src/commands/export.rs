@@ -5,6 +5,7 @@ use path_clean::PathClean;
 use tauri::State;
 use tauri_plugin_opener::OpenerExt;
 
+use crate::error::{AppError, AppResult};
 use crate::services::events::{AppEvent, ExportTaskEvent};
 use crate::services::AppServices;
 use crate::state::AllowedSystemPathsState;
@@ -17,9 +18,12 @@ pub async fn export_markdown(
   output_path: String,
   services: State<'_, AppServices>,
   allowed_paths: State<'_, AllowedSystemPathsState>,
-) -> Result<String, String> {
+) -> AppResult<String> {
   let task_id = create_export_task_id(&format);
   allow_export_output_path(&output_path, &allowed_paths)?;
+  let task = services
+    .background_tasks
+    .start(&task_id, &format!("Export {format}"))?;
   publish_export_event(&services, &task_id, &format, &output_path, "started", None);
 
   let task_services = services.inner().clone();
@@ -34,8 +38,10 @@ pub async fn export_markdown(
         markdown,
         format_for_worker.clone(),
         output_path_for_worker.clone(),
+        task.clone(),
       )
       .await;
+    task.finish(&result);
 
     match result {
       Ok(()) => {
@@ -48,6 +54,16 @@ pub async fn export_markdown(
           None,
         );
       }
+      Err(AppError::Cancelled { .. }) => {
+        publish_export_event(
+          &task_services,
+          &task_id_for_worker,
+          &format_for_worker,
+          &output_path_for_worker,
+          "cancelled",
+          None,
+        );
+      }
       Err(err) => {
         publish_export_event(
           &task_services,
@@ -55,7 +71,7 @@ pub async fn export_markdown(
           &format_for_worker,
           &output_path_for_worker,
           "failed",
-          Some(err),
+          Some(err.to_string()),
         );
       }
     }
@@ -69,21 +85,26 @@ pub async fn export_open_output_path(
   path: String,
   allowed_paths: State<'_, AllowedSystemPathsState>,
   app: tauri::AppHandle,
-) -> Result<(), String> {
+) -> AppResult<()> {
   let normalized = normalize_system_path(&path)?;
   let allowed = allowed_paths
     .0
     .lock()
-    .map_err(|_| "Failed to lock allowed export paths".to_string())?
+    .map_err(|_| AppError::lock("allowed export paths"))?
     .contains(&normalized);
   if !allowed {
-    return Err("Path was not selected by the export dialog".to_string());
+    return Err(AppError::invalid_path(
+      path,
+      "Path was not selected by the export dialog",
+    ));
   }
 
-  app
-    .opener()
-    .open_path(normalized.to_string_lossy().to_string(), None::<String>)
-    .map_err(|err| format!("Failed to open exported file: {err}"))
+  Ok(
+    app
+      .opener()
+      .open_path(normalized.to_string_lossy().to_string(), None::<String>)
+      .map_err(|err| format!("Failed to open exported file: {err}"))?,
+  )
 }
 
 fn create_export_task_id(format: &str) -> String {
@@ -119,26 +140,29 @@ fn publish_export_event(
 fn allow_export_output_path(
   output_path: &str,
   allowed_paths: &AllowedSystemPathsState,
-) -> Result<(), String> {
+) -> AppResult<()> {
   let normalized = normalize_system_path(output_path)?;
   allowed_paths
     .0
     .lock()
-    .map_err(|_| "Failed to lock allowed export paths".to_string())?
+    .map_err(|_| AppError::lock("allowed export paths"))?
     .insert(normalized);
   Ok(())
 }
 
-fn normalize_system_path(path: &str) -> Result<PathBuf, String> {
+fn normalize_system_path(path: &str) -> AppResult<PathBuf> {
   let path = PathBuf::from(path);
   if !path.is_absolute() {
-    return Err("System path must be absolute".to_string());
+    return Err(AppError::invalid_path(
+      path.to_string_lossy(),
+      "System path must be absolute",
+    ));
   }
 
   if path.exists() {
     return std::fs::canonicalize(&path)
       .map(|path| path.clean())
-      .map_err(|err| format!("Failed to resolve path: {err}"));
+      .map_err(|err| AppError::io("Failed to resolve path", &path, err));
   }
 
   let parent = path
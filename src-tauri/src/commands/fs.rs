@@ -1,12 +1,26 @@
+use std::collections::HashMap;
+
 use base64::{engine::general_purpose, Engine as _};
 use tauri::{Manager, State};
 use tauri_plugin_opener::OpenerExt;
 
-use crate::commands::fs_runtime::{emit_buffer_status, emit_buffer_statuses, set_background_task};
-pub use crate::commands::fs_runtime::{start_buffer_flush_worker, start_fs_watcher};
-use crate::models::{BackgroundTaskStatus, FsBufferStatus, FsRootInfo};
+use crate::commands::fs_runtime::{
+  emit_buffer_status, emit_buffer_statuses, emit_external_conflicts, failed_flush_message,
+};
+pub use crate::commands::fs_runtime::{
+  start_buffer_flush_worker, start_fs_watcher, start_recovery_journal_worker, start_snapshot_worker,
+};
+use crate::commands::history::{record_deleted_history, record_flushed_history};
+use crate::commands::recovery::{checkpoint_recovery_journal, journal_buffer_update};
+use crate::commands::session::record_recent_workspace;
+use crate::error::AppResult;
+use crate::models::{
+  BackgroundTaskState, BackgroundTaskStatus, DocumentCacheStats, FsBufferStatus, FsCreatedNote,
+  FsDocumentType, FsExternalConflict, FsRootInfo, FsTemplate, FsTextFormat, FsTrashEntry,
+  FsTrashRestoreResult, FsWorkspaceRoot, WorkspaceIgnoreSettings, WorkspaceTemplateSettings,
+};
 use crate::services::events::AppEvent;
-use crate::state::{BackgroundTasksState, FsState, FsWatcherState};
+use crate::state::{FsState, FsWatcherState};
 
 pub use crate::services::workspace::ensure_default_file;
 
@@ -14,7 +28,7 @@ pub use crate::services::workspace::ensure_default_file;
 pub fn fs_get_root_info(
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<FsRootInfo, String> {
+) -> AppResult<FsRootInfo> {
   services.workspace.root_info(&state)
 }
 
@@ -22,7 +36,7 @@ pub fn fs_get_root_info(
 pub async fn fs_get_snapshot(
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<crate::models::FsSnapshot, String> {
+) -> AppResult<crate::models::FsSnapshot> {
   services.workspace.snapshot(&state).await
 }
 
@@ -33,13 +47,53 @@ pub async fn fs_set_root(
   watcher_state: State<'_, FsWatcherState>,
   services: State<'_, crate::services::AppServices>,
   app: tauri::AppHandle,
-) -> Result<FsRootInfo, String> {
+) -> AppResult<FsRootInfo> {
   let root_info = services.workspace.set_root(path, &state).await?;
   start_fs_watcher(&app, &state, &watcher_state)?;
+  record_recent_workspace(&app, &state, &services);
   publish_app_event(&services, AppEvent::WorkspaceChanged)?;
   Ok(root_info)
 }
 
+#[tauri::command]
+pub fn fs_list_roots(
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<Vec<FsWorkspaceRoot>> {
+  services.workspace.mounted_roots(&state)
+}
+
+#[tauri::command]
+pub async fn fs_mount_root(
+  name: String,
+  path: String,
+  state: State<'_, FsState>,
+  watcher_state: State<'_, FsWatcherState>,
+  services: State<'_, crate::services::AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<FsWorkspaceRoot>> {
+  let roots = services.workspace.mount_root(name, path, &state).await?;
+  start_fs_watcher(&app, &state, &watcher_state)?;
+  record_recent_workspace(&app, &state, &services);
+  publish_app_event(&services, AppEvent::WorkspaceChanged)?;
+  Ok(roots)
+}
+
+#[tauri::command]
+pub fn fs_unmount_root(
+  name: String,
+  state: State<'_, FsState>,
+  watcher_state: State<'_, FsWatcherState>,
+  services: State<'_, crate::services::AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<FsWorkspaceRoot>> {
+  let roots = services.workspace.unmount_root(&name, &state)?;
+  start_fs_watcher(&app, &state, &watcher_state)?;
+  record_recent_workspace(&app, &state, &services);
+  publish_app_event(&services, AppEvent::WorkspaceChanged)?;
+  Ok(roots)
+}
+
 #[tauri::command]
 pub async fn fs_set_single_file(
   path: String,
@@ -47,9 +101,10 @@ pub async fn fs_set_single_file(
   watcher_state: State<'_, FsWatcherState>,
   services: State<'_, crate::services::AppServices>,
   app: tauri::AppHandle,
-) -> Result<FsRootInfo, String> {
+) -> AppResult<FsRootInfo> {
   let root_info = services.workspace.set_single_file(path, &state).await?;
   start_fs_watcher(&app, &state, &watcher_state)?;
+  record_recent_workspace(&app, &state, &services);
   publish_app_event(&services, AppEvent::WorkspaceChanged)?;
   Ok(root_info)
 }
@@ -58,7 +113,7 @@ pub async fn fs_set_single_file(
 pub async fn fs_list_entries(
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<Vec<crate::models::FsEntry>, String> {
+) -> AppResult<Vec<crate::models::FsEntry>> {
   services.workspace.list_entries(&state).await
 }
 
@@ -67,7 +122,7 @@ pub async fn fs_read_file(
   path: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<String, String> {
+) -> AppResult<String> {
   services.workspace.read_file(&path, &state).await
 }
 
@@ -75,7 +130,7 @@ pub async fn fs_read_file(
 pub async fn fs_get_workspace_index(
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<crate::models::FsWorkspaceIndex, String> {
+) -> AppResult<crate::models::FsWorkspaceIndex> {
   services.workspace.workspace_index(&state).await
 }
 
@@ -83,7 +138,7 @@ pub async fn fs_get_workspace_index(
 pub async fn fs_get_workspace_graph(
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<crate::models::FsGraph, String> {
+) -> AppResult<crate::models::FsGraph> {
   services.workspace.workspace_graph(&state).await
 }
 
@@ -92,7 +147,7 @@ pub async fn fs_get_outline_graph(
   path: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<crate::models::FsGraph, String> {
+) -> AppResult<crate::models::FsGraph> {
   services.workspace.outline_graph(&path, &state).await
 }
 
@@ -102,7 +157,7 @@ pub async fn fs_analyze_markdown_buffer(
   content: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<Vec<crate::models::FsMarkdownDiagnostic>, String> {
+) -> AppResult<Vec<crate::models::FsMarkdownDiagnostic>> {
   services
     .workspace
     .analyze_markdown_buffer(path, content, &state)
@@ -116,7 +171,7 @@ pub async fn fs_search_workspace(
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
   app: tauri::AppHandle,
-) -> Result<Vec<crate::models::FsSearchResult>, String> {
+) -> AppResult<Vec<crate::models::FsSearchResult>> {
   let index_parent = search_index_parent(&app)?;
   services
     .workspace
@@ -129,12 +184,17 @@ pub async fn fs_rebuild_search_index(
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
   app: tauri::AppHandle,
-) -> Result<(), String> {
+) -> AppResult<()> {
   let index_parent = search_index_parent(&app)?;
-  services
+  let task = services
+    .background_tasks
+    .start("search-index", "Search index")?;
+  let result = services
     .workspace
-    .rebuild_search_index(index_parent, &state)
-    .await
+    .rebuild_search_index(index_parent, &state, task.clone())
+    .await;
+  task.finish(&result);
+  result
 }
 
 #[tauri::command]
@@ -142,7 +202,7 @@ pub async fn fs_open_file(
   path: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<String, String> {
+) -> AppResult<String> {
   services.workspace.open_file(&path, &state).await
 }
 
@@ -153,8 +213,13 @@ pub fn fs_update_buffer(
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
   app: tauri::AppHandle,
-) -> Result<FsBufferStatus, String> {
+) -> AppResult<FsBufferStatus> {
   let status = services.workspace.update_buffer(&path, &content, &state)?;
+  if status.dirty {
+    if let Err(err) = journal_buffer_update(&app, &state, &services, &path, &content) {
+      log::warn!("journal buffer update failed: {err}");
+    }
+  }
   emit_buffer_status(&app, &status)?;
   if status.dirty {
     publish_app_event(&services, AppEvent::DocumentChanged)?;
@@ -165,27 +230,46 @@ pub fn fs_update_buffer(
 #[tauri::command]
 pub async fn fs_flush_buffers(
   state: State<'_, FsState>,
-  task_state: State<'_, BackgroundTasksState>,
   services: State<'_, crate::services::AppServices>,
   app: tauri::AppHandle,
-) -> Result<usize, String> {
-  set_background_task(&task_state, "buffer-flush", "Save queue", "running", None)?;
+) -> AppResult<usize> {
+  services.background_tasks.set(
+    "buffer-flush",
+    "Save queue",
+    BackgroundTaskState::Running,
+    None,
+  )?;
   let statuses = match services.workspace.flush_buffers(&state).await {
     Ok(statuses) => statuses,
     Err(err) => {
-      let _ = set_background_task(
-        &task_state,
+      let _ = services.background_tasks.set(
         "buffer-flush",
         "Save queue",
-        "error",
-        Some(err.clone()),
+        BackgroundTaskState::Failed,
+        Some(err.to_string()),
       );
       return Err(err);
     }
   };
   emit_buffer_statuses(&app, &statuses)?;
+  emit_external_conflicts(&app, &services)?;
+  if let Err(err) = checkpoint_recovery_journal(&app, &state, &services) {
+    log::warn!("checkpoint recovery journal failed: {err}");
+  }
+  services.snapshots.record_flushed(&statuses)?;
+  if let Err(err) = record_flushed_history(&app, &state, &services, &statuses).await {
+    log::warn!("record file history failed: {err}");
+  }
   publish_app_event(&services, AppEvent::BuffersFlushed)?;
-  set_background_task(&task_state, "buffer-flush", "Save queue", "idle", None)?;
+  let failed = failed_flush_message(&statuses);
+  let status = if failed.is_some() {
+    BackgroundTaskState::Failed
+  } else {
+    BackgroundTaskState::Idle
+  };
+  services
+    .background_tasks
+    .set("buffer-flush", "Save queue", status, failed)?;
   Ok(statuses.len())
 }
 
@@ -193,19 +277,122 @@ pub async fn fs_flush_buffers(
 pub fn fs_get_buffer_status(
   path: String,
   services: State<'_, crate::services::AppServices>,
-) -> Result<Option<FsBufferStatus>, String> {
+) -> AppResult<Option<FsBufferStatus>> {
   services.documents.status(&path)
 }
 
+#[tauri::command]
+pub fn fs_get_external_conflict(
+  path: String,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<Option<FsExternalConflict>> {
+  services.documents.external_conflict(&path)
+}
+
+#[tauri::command]
+pub fn fs_resolve_external_conflict(
+  path: String,
+  choice: String,
+  content: Option<String>,
+  services: State<'_, crate::services::AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<FsBufferStatus> {
+  let status = services
+    .documents
+    .resolve_external_conflict(&path, &choice, content.as_deref())?;
+  emit_buffer_status(&app, &status)?;
+  publish_app_event(&services, AppEvent::DocumentChanged)?;
+  Ok(status)
+}
+
+/// Re-saves a document with another encoding, BOM or line ending. Omitted
+/// options keep the document's current format.
+#[tauri::command]
+pub async fn fs_convert_encoding(
+  path: String,
+  encoding: Option<String>,
+  bom: Option<bool>,
+  line_ending: Option<String>,
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<FsBufferStatus> {
+  services.workspace.read_file(&path, &state).await?;
+  let current = services.documents.text_format(&path)?.unwrap_or_default();
+  let format = FsTextFormat {
+    encoding: encoding.unwrap_or(current.encoding),
+    bom: bom.unwrap_or(current.bom),
+    line_ending: line_ending.unwrap_or(current.line_ending),
+  };
+  let status = services
+    .documents
+    .convert_text_format(&state, &path, format)
+    .await?;
+  emit_buffer_status(&app, &status)?;
+  publish_app_event(&services, AppEvent::DocumentChanged)?;
+  Ok(status)
+}
+
+#[tauri::command]
+pub fn fs_get_document_types() -> Vec<FsDocumentType> {
+  crate::services::document_types::document_type_infos()
+}
+
+#[tauri::command]
+pub fn fs_get_ignore_settings(
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<WorkspaceIgnoreSettings> {
+  services.workspace.ignore_settings(&state)
+}
+
+#[tauri::command]
+pub fn fs_set_ignore_settings(
+  settings: WorkspaceIgnoreSettings,
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<WorkspaceIgnoreSettings> {
+  let settings = services.workspace.set_ignore_settings(settings, &state)?;
+  publish_app_event(&services, AppEvent::FileSystemChanged(Vec::new()))?;
+  Ok(settings)
+}
+
+#[tauri::command]
+pub fn fs_get_document_cache_stats(
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<DocumentCacheStats> {
+  services.documents.cache_stats()
+}
+
+#[tauri::command]
+pub fn fs_set_document_cache_budget(
+  budget_bytes: u64,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<DocumentCacheStats> {
+  services.documents.set_cache_budget(budget_bytes)
+}
+
 #[tauri::command]
 pub fn fs_get_background_tasks(
-  task_state: State<'_, BackgroundTasksState>,
-) -> Result<Vec<BackgroundTaskStatus>, String> {
-  let tasks = task_state
-    .0
-    .lock()
-    .map_err(|_| "Failed to lock task state")?;
-  Ok(tasks.values().cloned().collect())
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<Vec<BackgroundTaskStatus>> {
+  Ok(services.background_tasks.list()?)
+}
+
+/// Finished background task runs, most recent first.
+#[tauri::command]
+pub fn fs_get_background_task_history(
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<Vec<BackgroundTaskStatus>> {
+  Ok(services.background_tasks.history()?)
+}
+
+#[tauri::command]
+pub fn fs_cancel_background_task(
+  id: String,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<()> {
+  Ok(services.background_tasks.cancel(&id)?)
 }
 
 #[tauri::command]
@@ -213,7 +400,7 @@ pub async fn fs_get_path_metadata(
   path: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<crate::models::FsPathMetadata, String> {
+) -> AppResult<crate::models::FsPathMetadata> {
   services.workspace.path_metadata(path, &state).await
 }
 
@@ -223,12 +410,14 @@ pub async fn fs_open_path_in_system(
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
   app: tauri::AppHandle,
-) -> Result<(), String> {
+) -> AppResult<()> {
   let metadata = services.workspace.path_metadata(path, &state).await?;
-  app
-    .opener()
-    .open_path(metadata.absolute_path, None::<String>)
-    .map_err(|err| format!("Failed to open path: {err}"))
+  Ok(
+    app
+      .opener()
+      .open_path(metadata.absolute_path, None::<String>)
+      .map_err(|err| format!("Failed to open path: {err}"))?,
+  )
 }
 
 #[tauri::command]
@@ -239,7 +428,7 @@ pub async fn fs_import_markdown_asset(
   title: Option<String>,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<crate::models::FsMarkdownAssetImportResult, String> {
+) -> AppResult<crate::models::FsMarkdownAssetImportResult> {
   let result = services
     .markdown_assets
     .import_asset(source_path, document_path, strategy, title, &state)
@@ -258,7 +447,7 @@ pub async fn fs_import_markdown_asset_base64(
   title: Option<String>,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<crate::models::FsMarkdownAssetImportResult, String> {
+) -> AppResult<crate::models::FsMarkdownAssetImportResult> {
   let bytes = general_purpose::STANDARD
     .decode(base64_data)
     .map_err(|err| format!("Failed to decode asset content: {err}"))?;
@@ -276,11 +465,13 @@ pub async fn fs_resolve_markdown_asset(
   target: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<crate::models::FsMarkdownAssetResolveResult, String> {
-  services
-    .markdown_assets
-    .resolve_asset(document_path, target, &state)
-    .await
+) -> AppResult<crate::models::FsMarkdownAssetResolveResult> {
+  Ok(
+    services
+      .markdown_assets
+      .resolve_asset(document_path, target, &state)
+      .await?,
+  )
 }
 
 // Kept for backward compatibility with old frontend calls.
@@ -290,10 +481,15 @@ pub async fn fs_write_file(
   content: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<(), String> {
+  app: tauri::AppHandle,
+) -> AppResult<()> {
   services
     .workspace
-    .write_file_buffered(&path, &content, &state)
+    .write_file_buffered(&path, &content, &state)?;
+  if let Err(err) = journal_buffer_update(&app, &state, &services, &path, &content) {
+    log::warn!("journal buffer update failed: {err}");
+  }
+  Ok(())
 }
 
 #[tauri::command]
@@ -301,18 +497,72 @@ pub async fn fs_create_file(
   path: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<(), String> {
+) -> AppResult<()> {
   services.workspace.create_file(path, &state).await?;
   publish_app_event(&services, AppEvent::FileSystemChanged(Vec::new()))?;
   Ok(())
 }
 
+#[tauri::command]
+pub fn fs_get_template_settings(
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<WorkspaceTemplateSettings> {
+  services.workspace.template_settings(&state)
+}
+
+#[tauri::command]
+pub fn fs_set_template_settings(
+  settings: WorkspaceTemplateSettings,
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<WorkspaceTemplateSettings> {
+  services.workspace.set_template_settings(settings, &state)
+}
+
+#[tauri::command]
+pub async fn fs_list_templates(
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<Vec<FsTemplate>> {
+  services.workspace.list_templates(&state).await
+}
+
+#[tauri::command]
+pub async fn fs_create_from_template(
+  path: String,
+  template: Option<String>,
+  variables: Option<HashMap<String, String>>,
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<FsCreatedNote> {
+  let note = services
+    .workspace
+    .create_from_template(path, template, variables.unwrap_or_default(), &state)
+    .await?;
+  publish_app_event(&services, AppEvent::FileSystemChanged(Vec::new()))?;
+  Ok(note)
+}
+
+#[tauri::command]
+pub async fn fs_open_daily_note(
+  date: Option<String>,
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+) -> AppResult<FsCreatedNote> {
+  let note = services.workspace.open_daily_note(date, &state).await?;
+  if note.created {
+    publish_app_event(&services, AppEvent::FileSystemChanged(Vec::new()))?;
+  }
+  Ok(note)
+}
+
 #[tauri::command]
 pub async fn fs_create_dir(
   path: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<(), String> {
+) -> AppResult<()> {
   services.workspace.create_dir(path, &state).await?;
   publish_app_event(&services, AppEvent::FileSystemChanged(Vec::new()))?;
   Ok(())
@@ -321,21 +571,74 @@ pub async fn fs_create_dir(
 #[tauri::command]
 pub async fn fs_delete_path(
   path: String,
+  permanent: Option<bool>,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<(), String> {
-  services.workspace.delete_path(path, &state).await?;
+  app: tauri::AppHandle,
+) -> AppResult<()> {
+  if let Err(err) = record_deleted_history(&app, &state, &services, &path).await {
+    log::warn!("record file history failed: {err}");
+  }
+  if permanent.unwrap_or(false) {
+    services.workspace.delete_path(path, &state).await?;
+  } else {
+    let trash_parent = app_data_dir(&app)?;
+    services
+      .workspace
+      .trash_path(path, trash_parent, &state)
+      .await?;
+  }
   publish_app_event(&services, AppEvent::FileSystemChanged(Vec::new()))?;
   Ok(())
 }
 
+#[tauri::command]
+pub async fn fs_list_trash(
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<FsTrashEntry>> {
+  services
+    .workspace
+    .list_trash(app_data_dir(&app)?, &state)
+    .await
+}
+
+#[tauri::command]
+pub async fn fs_restore_from_trash(
+  id: String,
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<FsTrashRestoreResult> {
+  let result = services
+    .workspace
+    .restore_from_trash(id, app_data_dir(&app)?, &state)
+    .await?;
+  publish_app_event(&services, AppEvent::FileSystemChanged(Vec::new()))?;
+  Ok(result)
+}
+
+#[tauri::command]
+pub async fn fs_empty_trash(
+  ids: Option<Vec<String>>,
+  state: State<'_, FsState>,
+  services: State<'_, crate::services::AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<usize> {
+  services
+    .workspace
+    .empty_trash(ids, app_data_dir(&app)?, &state)
+    .await
+}
+
 #[tauri::command]
 pub async fn fs_rename_path(
   from: String,
   to: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<(), String> {
+) -> AppResult<()> {
   services.workspace.rename_path(from, to, &state).await?;
   publish_app_event(&services, AppEvent::FileSystemChanged(Vec::new()))?;
   Ok(())
@@ -347,36 +650,39 @@ pub async fn fs_move_path(
   to: String,
   state: State<'_, FsState>,
   services: State<'_, crate::services::AppServices>,
-) -> Result<(), String> {
+) -> AppResult<()> {
   services.workspace.move_path(from, to, &state).await?;
   publish_app_event(&services, AppEvent::FileSystemChanged(Vec::new()))?;
   Ok(())
 }
 
-fn publish_app_event(
-  services: &crate::services::AppServices,
-  event: AppEvent,
-) -> Result<(), String> {
+fn publish_app_event(services: &crate::services::AppServices, event: AppEvent) -> AppResult<()> {
   services.events.publish(event)
 }
 
-fn search_index_parent(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
-  app
-    .path()
-    .app_data_dir()
-    .map_err(|err| format!("Failed to resolve app data dir: {err}"))
+fn search_index_parent(app: &tauri::AppHandle) -> AppResult<std::path::PathBuf> {
+  app_data_dir(app)
+}
+
+fn app_data_dir(app: &tauri::AppHandle) -> AppResult<std::path::PathBuf> {
+  Ok(
+    app
+      .path()
+      .app_data_dir()
+      .map_err(|err| format!("Failed to resolve app data dir: {err}"))?,
+  )
 }
 
 pub fn flush_all_buffers(
   state: &FsState,
   services: &crate::services::AppServices,
-) -> Result<usize, String> {
+) -> AppResult<usize> {
   Ok(flush_all_buffers_with_status(state, services)?.len())
 }
 
-fn flush_all_buffers_with_status(
+pub fn flush_all_buffers_with_status(
   state: &FsState,
   services: &crate::services::AppServices,
-) -> Result<Vec<FsBufferStatus>, String> {
+) -> AppResult<Vec<FsBufferStatus>> {
   services.documents.flush_all_with_status(state)
 }
@@ -1,74 +1,112 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
 use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult};
-use tauri::{Emitter, Manager};
+use tauri::{AppHandle, Manager, Runtime};
 use tokio::runtime::Handle;
 
-use crate::models::{BackgroundTaskStatus, FsBufferStatus};
-use crate::services::events::AppEvent;
-use crate::state::{BackgroundTasksState, FsState, FsWatcherState};
+use crate::commands::history::record_flushed_history;
+use crate::commands::recovery::checkpoint_recovery_journal;
+use crate::error::{AppError, AppResult};
+use crate::models::{BackgroundTaskState, FsBufferStatus};
+use crate::services::document_types::is_document_path;
+use crate::services::events::{emit_event, AppEvent};
+use crate::services::workspace::IgnoreRules;
+use crate::state::{FsState, FsWatcherState};
 
 const BUFFER_FLUSH_INTERVAL_MS: u64 = 1200;
+const SNAPSHOT_CHECK_INTERVAL_MS: u64 = 5000;
+const RECOVERY_JOURNAL_INTERVAL_MS: u64 = 250;
 
-pub fn start_fs_watcher(
-  app: &tauri::AppHandle,
+/// Replaces the watchers with one per workspace root: the primary root (or
+/// the directory of a single open file) and every mounted root.
+pub fn start_fs_watcher<R: Runtime>(
+  app: &AppHandle<R>,
   state: &FsState,
   watcher_state: &FsWatcherState,
-) -> Result<(), String> {
-  let root_path = {
-    let data = state.0.read().map_err(|_| "Failed to lock fs state")?;
-    data.root_path.clone()
+) -> AppResult<()> {
+  let (roots, single) = {
+    let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+    let single = data.root_kind == "single";
+    let mut roots = vec![data.root_path.clone()];
+    if !single {
+      roots.extend(data.mounted_roots.iter().map(|root| root.path.clone()));
+    }
+    (roots, single)
   };
-  if !root_path.exists() {
-    return Ok(());
-  }
 
-  let (watch_path, mode) = if root_path.is_file() {
-    (
-      root_path
-        .parent()
-        .ok_or_else(|| "Failed to resolve parent directory".to_string())?
-        .to_path_buf(),
-      RecursiveMode::NonRecursive,
-    )
-  } else {
-    (root_path, RecursiveMode::Recursive)
-  };
+  let shutdown = app
+    .try_state::<crate::services::AppServices>()
+    .map(|services| services.runtime.shutdown_token());
+  let mut watchers = HashMap::new();
+  for root_path in roots {
+    if !root_path.exists() {
+      continue;
+    }
+    let (watch_path, mode) = if root_path.is_file() {
+      (
+        root_path
+          .parent()
+          .ok_or_else(|| "Failed to resolve parent directory".to_string())?
+          .to_path_buf(),
+        RecursiveMode::NonRecursive,
+      )
+    } else {
+      (root_path.clone(), RecursiveMode::Recursive)
+    };
 
-  let runtime = Handle::try_current()
-    .map_err(|err| format!("Tokio runtime unavailable for fs watcher: {err}"))?;
-  let app_handle = app.clone();
-  let mut debouncer = new_debouncer(Duration::from_millis(250), move |result| {
-    handle_fs_watch_events(result, &runtime, &app_handle);
-  })
-  .map_err(|err| format!("Failed to create fs watcher: {err}"))?;
+    let runtime = Handle::try_current()
+      .map_err(|err| format!("Tokio runtime unavailable for fs watcher: {err}"))?;
+    let app_handle = app.clone();
+    // Rules are loaded once per root and rebuilt when an ignore file changes.
+    let mut rules = (!single).then(|| Arc::new(IgnoreRules::load(&root_path)));
+    // The watcher counts as a worker until its debouncer thread drops it.
+    let worker = shutdown.as_ref().map(|shutdown| shutdown.worker());
+    let mut debouncer = new_debouncer(Duration::from_millis(250), move |result| {
+      let _worker = &worker;
+      handle_fs_watch_events(result, &runtime, &app_handle, &mut rules);
+    })
+    .map_err(|err| format!("Failed to create fs watcher: {err}"))?;
 
-  debouncer
-    .watcher()
-    .watch(&watch_path, mode)
-    .map_err(|err| format!("Failed to watch path: {err}"))?;
+    debouncer
+      .watcher()
+      .watch(&watch_path, mode)
+      .map_err(|err| format!("Failed to watch path: {err}"))?;
+    watchers.insert(root_path, debouncer);
+  }
 
   let mut holder = watcher_state
     .0
     .lock()
     .map_err(|_| "Failed to lock watcher state")?;
-  *holder = Some(debouncer);
+  *holder = watchers;
   Ok(())
 }
 
-pub fn start_buffer_flush_worker(app: &tauri::AppHandle) {
+pub fn start_buffer_flush_worker<R: Runtime>(app: &AppHandle<R>) {
+  let Some(shutdown) = app
+    .try_state::<crate::services::AppServices>()
+    .map(|services| services.runtime.shutdown_token())
+  else {
+    return;
+  };
+  let worker = shutdown.worker();
   let app_handle = app.clone();
   tokio::spawn(async move {
+    let _worker = worker;
     let mut ticker = tokio::time::interval(Duration::from_millis(BUFFER_FLUSH_INTERVAL_MS));
     loop {
-      ticker.tick().await;
+      tokio::select! {
+        _ = ticker.tick() => {}
+        _ = shutdown.cancelled() => break,
+      }
       let state = app_handle.try_state::<FsState>();
-      let task_state = app_handle.try_state::<BackgroundTasksState>();
       let services = app_handle.try_state::<crate::services::AppServices>();
-      match (state, task_state, services) {
-        (Some(state), Some(task_state), Some(services)) => {
-          match services.documents.has_dirty() {
+      match (state, services) {
+        (Some(state), Some(services)) => {
+          match services.documents.has_pending_writes() {
             Ok(true) => {}
             Ok(false) => continue,
             Err(err) => {
@@ -76,32 +114,56 @@ pub fn start_buffer_flush_worker(app: &tauri::AppHandle) {
               continue;
             }
           }
-          if let Err(err) =
-            set_background_task(&task_state, "buffer-flush", "Save queue", "running", None)
-          {
+          if let Err(err) = services.background_tasks.set(
+            "buffer-flush",
+            "Save queue",
+            BackgroundTaskState::Running,
+            None,
+          ) {
             log::warn!("set background task failed: {err}");
           }
           match services.workspace.flush_buffers(&state).await {
             Ok(statuses) => {
+              let failed = failed_flush_message(&statuses);
+              let status = if failed.is_some() {
+                BackgroundTaskState::Failed
+              } else {
+                BackgroundTaskState::Idle
+              };
               if let Err(err) =
-                set_background_task(&task_state, "buffer-flush", "Save queue", "idle", None)
+                services
+                  .background_tasks
+                  .set("buffer-flush", "Save queue", status, failed)
               {
                 log::warn!("set background task failed: {err}");
               }
               if let Err(err) = emit_buffer_statuses(&app_handle, &statuses) {
                 log::warn!("emit buffer statuses failed: {err}");
               }
+              if let Err(err) = emit_external_conflicts(&app_handle, &services) {
+                log::warn!("emit external conflicts failed: {err}");
+              }
+              if let Err(err) = checkpoint_recovery_journal(&app_handle, &state, &services) {
+                log::warn!("checkpoint recovery journal failed: {err}");
+              }
+              if let Err(err) = services.snapshots.record_flushed(&statuses) {
+                log::warn!("record snapshot changes failed: {err}");
+              }
+              if let Err(err) =
+                record_flushed_history(&app_handle, &state, &services, &statuses).await
+              {
+                log::warn!("record file history failed: {err}");
+              }
               if let Err(err) = services.events.publish(AppEvent::BuffersFlushed) {
                 log::warn!("publish buffers flushed event failed: {err}");
               }
             }
             Err(err) => {
-              let _ = set_background_task(
-                &task_state,
+              let _ = services.background_tasks.set(
                 "buffer-flush",
                 "Save queue",
-                "error",
-                Some(err.clone()),
+                BackgroundTaskState::Failed,
+                Some(err.to_string()),
               );
               log::warn!("flush_all_buffers failed: {err}");
             }
@@ -113,64 +175,196 @@ pub fn start_buffer_flush_worker(app: &tauri::AppHandle) {
   });
 }
 
-pub fn emit_buffer_statuses(
-  app: &tauri::AppHandle,
+/// Writes queued recovery journal updates once their edits pause, keeping
+/// disk writes off the buffer update path.
+pub fn start_recovery_journal_worker<R: Runtime>(app: &AppHandle<R>) {
+  let Some(shutdown) = app
+    .try_state::<crate::services::AppServices>()
+    .map(|services| services.runtime.shutdown_token())
+  else {
+    return;
+  };
+  let worker = shutdown.worker();
+  let app_handle = app.clone();
+  tokio::spawn(async move {
+    let _worker = worker;
+    let mut ticker = tokio::time::interval(Duration::from_millis(RECOVERY_JOURNAL_INTERVAL_MS));
+    loop {
+      tokio::select! {
+        _ = ticker.tick() => {}
+        _ = shutdown.cancelled() => break,
+      }
+      let Some(services) = app_handle.try_state::<crate::services::AppServices>() else {
+        break;
+      };
+      let recovery = services.recovery.clone();
+      match tokio::task::spawn_blocking(move || recovery.write_pending(false)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(err)) => log::warn!("write recovery journal failed: {err}"),
+        Err(err) => log::warn!("join recovery journal task failed: {err}"),
+      }
+    }
+    if let Some(services) = app_handle.try_state::<crate::services::AppServices>() {
+      if let Err(err) = services.recovery.write_pending(true) {
+        log::warn!("write recovery journal failed: {err}");
+      }
+    }
+  });
+}
+
+/// Summarizes per-file write failures of a flush for the save queue task.
+pub fn failed_flush_message(statuses: &[FsBufferStatus]) -> Option<String> {
+  let failed = statuses
+    .iter()
+    .filter(|status| status.error.is_some())
+    .count();
+  (failed > 0).then(|| format!("{failed} file(s) failed to save"))
+}
+
+pub fn start_snapshot_worker<R: Runtime>(app: &AppHandle<R>) {
+  let Some(shutdown) = app
+    .try_state::<crate::services::AppServices>()
+    .map(|services| services.runtime.shutdown_token())
+  else {
+    return;
+  };
+  let worker = shutdown.worker();
+  let app_handle = app.clone();
+  tokio::spawn(async move {
+    let _worker = worker;
+    let mut ticker = tokio::time::interval(Duration::from_millis(SNAPSHOT_CHECK_INTERVAL_MS));
+    loop {
+      tokio::select! {
+        _ = ticker.tick() => {}
+        _ = shutdown.cancelled() => break,
+      }
+      let state = app_handle.try_state::<FsState>();
+      let services = app_handle.try_state::<crate::services::AppServices>();
+      let (Some(state), Some(services)) = (state, services) else {
+        break;
+      };
+      let snapshot_parent = match app_handle.path().app_data_dir() {
+        Ok(path) => path,
+        Err(err) => {
+          log::warn!("resolve snapshot dir failed: {err}");
+          continue;
+        }
+      };
+      let changed = match services.snapshots.take_due(&snapshot_parent) {
+        Ok(Some(changed)) => changed,
+        Ok(None) => continue,
+        Err(err) => {
+          log::warn!("check snapshot queue failed: {err}");
+          continue;
+        }
+      };
+
+      if let Err(err) =
+        services
+          .background_tasks
+          .set("snapshot", "Snapshots", BackgroundTaskState::Running, None)
+      {
+        log::warn!("set background task failed: {err}");
+      }
+      match crate::commands::snapshot::snapshot_workspace(
+        &app_handle,
+        &state,
+        &services,
+        changed.clone(),
+      )
+      .await
+      {
+        Ok(_) => {
+          if let Err(err) =
+            services
+              .background_tasks
+              .set("snapshot", "Snapshots", BackgroundTaskState::Idle, None)
+          {
+            log::warn!("set background task failed: {err}");
+          }
+        }
+        Err(err) => {
+          let _ = services.background_tasks.set(
+            "snapshot",
+            "Snapshots",
+            BackgroundTaskState::Failed,
+            Some(err.to_string()),
+          );
+          if let Err(err) = services.snapshots.requeue(changed) {
+            log::warn!("requeue snapshot changes failed: {err}");
+          }
+          log::warn!("workspace snapshot failed: {err}");
+        }
+      }
+    }
+  });
+}
+
+pub fn emit_buffer_statuses<R: Runtime>(
+  app: &AppHandle<R>,
   statuses: &[FsBufferStatus],
-) -> Result<(), String> {
+) -> AppResult<()> {
   for status in statuses {
     emit_buffer_status(app, status)?;
   }
   Ok(())
 }
 
-pub fn emit_buffer_status(app: &tauri::AppHandle, status: &FsBufferStatus) -> Result<(), String> {
-  app
-    .emit("fs-buffer-status", status.clone())
-    .map_err(|err| err.to_string())
+/// Emits conflicts that flushes held because the file changed on disk.
+pub fn emit_external_conflicts<R: Runtime>(
+  app: &AppHandle<R>,
+  services: &crate::services::AppServices,
+) -> AppResult<()> {
+  for conflict in services.documents.take_external_conflicts()? {
+    emit_event(app, "fs-external-conflict", &conflict)?;
+  }
+  Ok(())
 }
 
-pub fn set_background_task(
-  task_state: &BackgroundTasksState,
-  id: &str,
-  label: &str,
-  status: &str,
-  message: Option<String>,
-) -> Result<(), String> {
-  let mut tasks = task_state
-    .0
-    .lock()
-    .map_err(|_| "Failed to lock task state")?;
-  tasks.insert(
-    id.to_string(),
-    BackgroundTaskStatus {
-      id: id.to_string(),
-      label: label.to_string(),
-      status: status.to_string(),
-      message,
-    },
-  );
-  Ok(())
+pub fn emit_buffer_status<R: Runtime>(
+  app: &AppHandle<R>,
+  status: &FsBufferStatus,
+) -> AppResult<()> {
+  emit_event(app, "fs-buffer-status", status)
 }
 
-fn handle_fs_watch_events(
+fn handle_fs_watch_events<R: Runtime>(
   result: DebounceEventResult,
   runtime: &Handle,
-  app_handle: &tauri::AppHandle,
+  app_handle: &AppHandle<R>,
+  rules: &mut Option<Arc<IgnoreRules>>,
 ) {
   match result {
     Ok(events) => {
       if events.is_empty() || events.iter().all(|event| is_temp_write_path(&event.path)) {
         return;
       }
+      if let Some(current) = rules.as_mut() {
+        if events
+          .iter()
+          .any(|event| current.is_rules_file(&event.path))
+        {
+          *current = Arc::new(IgnoreRules::load(current.root()));
+        }
+      }
+      let rules = rules.clone();
       let app_handle = app_handle.clone();
       runtime.spawn(async move {
         if let Some(services) = app_handle.try_state::<crate::services::AppServices>() {
           let changed_paths = events
             .iter()
             .filter(|event| !is_temp_write_path(&event.path))
+            .filter(|event| {
+              rules
+                .as_ref()
+                .map_or(true, |rules| !rules.is_ignored_path(&event.path))
+            })
             .map(|event| event.path.clone())
             .collect::<Vec<_>>();
-          let event = if changed_paths.iter().any(|path| is_markdown_file_path(path)) {
+          if changed_paths.is_empty() {
+            return;
+          }
+          let event = if changed_paths.iter().any(|path| is_document_path(path)) {
             AppEvent::FileSystemChanged(changed_paths)
           } else {
             AppEvent::AssetChanged
@@ -185,18 +379,10 @@ fn handle_fs_watch_events(
   }
 }
 
-fn is_markdown_file_path(path: &Path) -> bool {
-  path
-    .extension()
-    .and_then(|ext| ext.to_str())
-    .map(|ext| matches!(ext.to_ascii_lowercase().as_str(), "md" | "markdown"))
-    .unwrap_or(false)
-}
-
 fn is_temp_write_path(path: &Path) -> bool {
   path
     .extension()
     .and_then(|ext| ext.to_str())
-    .map(|ext| ext.eq_ignore_ascii_case("tmp"))
+    .map(|ext| ext.eq_ignore_ascii_case("tmp") || ext.eq_ignore_ascii_case("marko-tmp"))
     .unwrap_or(false)
 }
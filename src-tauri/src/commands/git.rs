@@ -1,13 +1,19 @@
 use tauri::State;
 
-use crate::models::{GitFileDiff, GitRepoInfo, GitStatusSnapshot};
+use crate::error::{AppError, AppResult};
+use crate::models::{
+  GitConflictFile, GitConflictResolution, GitFileDiff, GitRepoInfo, GitStatusSnapshot,
+};
+use crate::services::events::AppEvent;
+use crate::services::path_resolver::relative_from_absolute;
 use crate::services::AppServices;
+use crate::state::FsState;
 
 #[tauri::command]
 pub async fn git_discover_repo(
   root_path: String,
   services: State<'_, AppServices>,
-) -> Result<GitRepoInfo, String> {
+) -> AppResult<GitRepoInfo> {
   services.git.discover(root_path).await
 }
 
@@ -15,7 +21,7 @@ pub async fn git_discover_repo(
 pub async fn git_init_repo(
   root_path: String,
   services: State<'_, AppServices>,
-) -> Result<GitRepoInfo, String> {
+) -> AppResult<GitRepoInfo> {
   services.git.init(root_path).await
 }
 
@@ -23,7 +29,7 @@ pub async fn git_init_repo(
 pub async fn git_get_status(
   root_path: String,
   services: State<'_, AppServices>,
-) -> Result<GitStatusSnapshot, String> {
+) -> AppResult<GitStatusSnapshot> {
   services.git.status(root_path).await
 }
 
@@ -32,9 +38,13 @@ pub async fn git_get_file_diff(
   root_path: String,
   path: String,
   section: Option<String>,
+  granularity: Option<String>,
   services: State<'_, AppServices>,
-) -> Result<GitFileDiff, String> {
-  services.git.file_diff(root_path, path, section).await
+) -> AppResult<GitFileDiff> {
+  services
+    .git
+    .file_diff(root_path, path, section, granularity)
+    .await
 }
 
 #[tauri::command]
@@ -42,6 +52,44 @@ pub async fn git_commit_all(
   root_path: String,
   message: String,
   services: State<'_, AppServices>,
-) -> Result<GitStatusSnapshot, String> {
+) -> AppResult<GitStatusSnapshot> {
   services.git.commit_all(root_path, message).await
 }
+
+#[tauri::command]
+pub async fn git_get_conflict(
+  root_path: String,
+  path: String,
+  services: State<'_, AppServices>,
+) -> AppResult<GitConflictFile> {
+  services.git.conflict_file(root_path, path).await
+}
+
+#[tauri::command]
+pub async fn git_resolve_conflict(
+  root_path: String,
+  path: String,
+  resolutions: Vec<GitConflictResolution>,
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+) -> AppResult<GitStatusSnapshot> {
+  let (snapshot, written) = services
+    .git
+    .resolve_conflict(root_path, path, resolutions)
+    .await?;
+  // Drop the cached note so a later flush neither writes the conflicted text
+  // back nor reports the resolution as an external change.
+  let data = state
+    .0
+    .read()
+    .map_err(|_| AppError::lock("fs state"))?
+    .clone();
+  if let Some(relative) = relative_from_absolute(&data, &written) {
+    services.documents.remove_path(&relative)?;
+  }
+  services.workspace.clear_index_cache();
+  services
+    .events
+    .publish(AppEvent::FileSystemChanged(vec![written]))?;
+  Ok(snapshot)
+}
@@ -0,0 +1,214 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{FileHistoryConfig, FileHistoryVersion, FsBufferStatus};
+use crate::services::document_store::{decode_text, DocumentSnapshot};
+use crate::services::events::AppEvent;
+use crate::services::path_resolver::resolve_path;
+use crate::services::search::stable_hash;
+use crate::services::AppServices;
+use crate::state::FsState;
+
+const DELETED_HISTORY_BATCH_BYTES: u64 = 8 * 1024 * 1024;
+
+#[tauri::command]
+pub fn fs_get_file_history_config(
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<FileHistoryConfig> {
+  services.file_history.config(&history_parent(&app)?)
+}
+
+#[tauri::command]
+pub fn fs_set_file_history_config(
+  config: FileHistoryConfig,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<FileHistoryConfig> {
+  services
+    .file_history
+    .set_config(&history_parent(&app)?, config)
+}
+
+#[tauri::command]
+pub async fn fs_list_file_history(
+  path: String,
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<FileHistoryVersion>> {
+  let root = history_root(&state)?;
+  services
+    .file_history
+    .list(history_parent(&app)?, root, path)
+    .await
+}
+
+#[tauri::command]
+pub async fn fs_list_deleted_files(
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<FileHistoryVersion>> {
+  let root = history_root(&state)?;
+  services
+    .file_history
+    .list_deleted(history_parent(&app)?, root)
+    .await
+}
+
+#[tauri::command]
+pub async fn fs_restore_file_version(
+  path: String,
+  version_id: String,
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<()> {
+  let parent = history_parent(&app)?;
+  let root = history_root(&state)?;
+  let content = services
+    .file_history
+    .version_content(parent.clone(), root.clone(), path.clone(), version_id)
+    .await?;
+
+  let statuses = services.workspace.flush_buffers(&state).await?;
+  record_flushed_history(&app, &state, &services, &statuses).await?;
+  let data = state
+    .0
+    .read()
+    .map_err(|_| AppError::lock("fs state"))?
+    .clone();
+  let resolved = resolve_path(&data, &path)?;
+  if let Ok(bytes) = tokio::fs::read(&resolved).await {
+    // Keep the content being replaced so the restore itself can be undone.
+    let (current, _) = decode_text(&bytes)?;
+    services
+      .file_history
+      .record_versions(
+        parent,
+        root,
+        vec![DocumentSnapshot {
+          path: path.clone(),
+          content_hash: stable_hash(&current),
+          content: current,
+        }],
+        "restore",
+      )
+      .await?;
+  }
+
+  services
+    .documents
+    .replace_document(&state, &path, content)
+    .await?;
+  services.workspace.clear_index_cache();
+  services
+    .events
+    .publish(AppEvent::FileSystemChanged(Vec::new()))?;
+  Ok(())
+}
+
+/// Records the content of freshly flushed buffers as history versions.
+pub async fn record_flushed_history<R: Runtime>(
+  app: &AppHandle<R>,
+  state: &FsState,
+  services: &AppServices,
+  statuses: &[FsBufferStatus],
+) -> AppResult<usize> {
+  let history_parent = history_parent(app)?;
+  if !services.file_history.is_enabled(&history_parent)? {
+    return Ok(0);
+  }
+  let mut documents = Vec::with_capacity(statuses.len());
+  for status in statuses.iter().filter(|status| status.error.is_none()) {
+    if let Some(snapshot) = services.documents.cached_snapshot(&status.path)? {
+      documents.push(snapshot);
+    }
+  }
+  services
+    .file_history
+    .record_versions(history_parent, history_root(state)?, documents, "save")
+    .await
+}
+
+/// Records the last content of `path`, or of every file below it, before it
+/// is deleted so it can be restored later. Files are read and recorded in
+/// bounded batches so large folders are never held in memory at once.
+pub async fn record_deleted_history(
+  app: &tauri::AppHandle,
+  state: &FsState,
+  services: &AppServices,
+  path: &str,
+) -> AppResult<usize> {
+  let history_parent = history_parent(app)?;
+  let config = services.file_history.config(&history_parent)?;
+  if !config.enabled {
+    return Ok(0);
+  }
+  let root = history_root(state)?;
+  let prefix = format!("{}/", path.trim_end_matches('/'));
+  let files = services
+    .workspace
+    .list_entries(state)
+    .await?
+    .into_iter()
+    .filter(|entry| entry.kind == "file" && (entry.path == path || entry.path.starts_with(&prefix)))
+    .map(|entry| entry.path)
+    .collect::<Vec<_>>();
+
+  let mut recorded = 0;
+  let mut batch = Vec::new();
+  let mut batch_bytes = 0;
+  for file in files {
+    let size = match services.workspace.path_metadata(file.clone(), state).await {
+      Ok(metadata) => metadata.size_bytes,
+      Err(_) => continue,
+    };
+    if size > config.max_file_bytes {
+      continue;
+    }
+    let Ok(content) = services.workspace.read_file(&file, state).await else {
+      continue;
+    };
+    batch_bytes += content.len() as u64;
+    batch.push(DocumentSnapshot {
+      path: file,
+      content_hash: stable_hash(&content),
+      content,
+    });
+    if batch_bytes >= DELETED_HISTORY_BATCH_BYTES {
+      recorded += services
+        .file_history
+        .record_versions(
+          history_parent.clone(),
+          root.clone(),
+          std::mem::take(&mut batch),
+          "delete",
+        )
+        .await?;
+      batch_bytes = 0;
+    }
+  }
+  recorded += services
+    .file_history
+    .record_versions(history_parent, root, batch, "delete")
+    .await?;
+  Ok(recorded)
+}
+
+fn history_root(state: &FsState) -> AppResult<PathBuf> {
+  let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+  Ok(data.root_path.clone())
+}
+
+fn history_parent<R: Runtime>(app: &AppHandle<R>) -> AppResult<PathBuf> {
+  Ok(
+    app
+      .path()
+      .app_data_dir()
+      .map_err(|err| format!("Failed to resolve app data dir: {err}"))?,
+  )
+}
@@ -1,35 +1,41 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::error::{AppError, AppResult};
 use crate::models::MarkdownFile;
 
 #[tauri::command]
-pub async fn list_markdown_files(root: String) -> Result<Vec<MarkdownFile>, String> {
+pub async fn list_markdown_files(root: String) -> AppResult<Vec<MarkdownFile>> {
   tokio::task::spawn_blocking(move || list_markdown_files_blocking(PathBuf::from(root)))
     .await
     .map_err(|err| format!("Markdown list task failed: {err}"))?
 }
 
 #[tauri::command]
-pub async fn read_markdown_file(path: String) -> Result<String, String> {
-  tokio::fs::read_to_string(path)
+pub async fn read_markdown_file(path: String) -> AppResult<String> {
+  tokio::fs::read_to_string(&path)
     .await
-    .map_err(|err| format!("Failed to read file: {err}"))
+    .map_err(|err| AppError::io("Failed to read file", &path, err))
 }
 
 #[tauri::command]
-pub async fn write_markdown_file(path: String, content: String) -> Result<(), String> {
-  tokio::fs::write(path, content)
+pub async fn write_markdown_file(path: String, content: String) -> AppResult<()> {
+  tokio::fs::write(&path, content)
     .await
-    .map_err(|err| format!("Failed to write file: {err}"))
+    .map_err(|err| AppError::io("Failed to write file", &path, err))
 }
 
-fn list_markdown_files_blocking(root_path: PathBuf) -> Result<Vec<MarkdownFile>, String> {
+fn list_markdown_files_blocking(root_path: PathBuf) -> AppResult<Vec<MarkdownFile>> {
   if !root_path.exists() {
-    return Err("Project path does not exist".to_string());
+    return Err(AppError::NotFound {
+      path: root_path.to_string_lossy().to_string(),
+    });
   }
   if !root_path.is_dir() {
-    return Err("Project path is not a directory".to_string());
+    return Err(AppError::invalid_path(
+      root_path.to_string_lossy(),
+      "Project path is not a directory",
+    ));
   }
 
   let mut files = Vec::new();
@@ -42,7 +48,7 @@ fn collect_markdown_files(
   root: &Path,
   current: &Path,
   out: &mut Vec<MarkdownFile>,
-) -> Result<(), String> {
+) -> AppResult<()> {
   let entries = fs::read_dir(current).map_err(|err| format!("Failed to read dir: {err}"))?;
   for entry in entries {
     let entry = entry.map_err(|err| format!("Failed to read entry: {err}"))?;
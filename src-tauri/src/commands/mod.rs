@@ -1,7 +1,13 @@
 pub mod app;
+pub mod code_runner;
 pub mod export;
 pub mod fs;
 pub mod fs_runtime;
 pub mod git;
+pub mod history;
 pub mod markdown;
+pub mod recovery;
+pub mod session;
+pub mod snapshot;
+pub mod tasks;
 pub mod terminal;
@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::commands::fs_runtime::emit_buffer_status;
+use crate::error::{AppError, AppResult};
+use crate::models::{FsBufferStatus, FsRecoverableBuffer};
+use crate::services::document_store::decode_text;
+use crate::services::path_resolver::resolve_path;
+use crate::services::AppServices;
+use crate::state::FsState;
+
+/// Lists unsaved buffers recovered from a previous session of the current
+/// workspace. Buffers that already match the file on disk are dropped.
+#[tauri::command]
+pub async fn fs_get_recoverable_buffers(
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<FsRecoverableBuffer>> {
+  let parent = recovery_parent(&app)?;
+  let data = state
+    .0
+    .read()
+    .map_err(|_| AppError::lock("fs state"))?
+    .clone();
+  let buffers = services.recovery.recoverable(&parent, &data.root_path)?;
+
+  let mut recoverable = Vec::with_capacity(buffers.len());
+  let mut unchanged = Vec::new();
+  for buffer in buffers {
+    let on_disk = match resolve_path(&data, &buffer.path) {
+      Ok(resolved) => tokio::fs::read(resolved)
+        .await
+        .ok()
+        .and_then(|bytes| decode_text(&bytes).ok())
+        .map(|(content, _)| content),
+      Err(_) => None,
+    };
+    if on_disk.as_deref() == Some(buffer.content.as_str()) {
+      unchanged.push(buffer.path);
+    } else {
+      recoverable.push(buffer);
+    }
+  }
+  if !unchanged.is_empty() {
+    services
+      .recovery
+      .take(&parent, &data.root_path, Some(&unchanged))?;
+  }
+  Ok(recoverable)
+}
+
+/// Loads a recovered buffer back into the document cache as a dirty buffer so
+/// it is saved by the next flush.
+#[tauri::command]
+pub async fn fs_restore_recoverable_buffer(
+  path: String,
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<FsBufferStatus> {
+  let parent = recovery_parent(&app)?;
+  let root = recovery_root(&state)?;
+  let buffer = services
+    .recovery
+    .recoverable(&parent, &root)?
+    .into_iter()
+    .find(|buffer| buffer.path == path)
+    .ok_or("Recoverable buffer not found")?;
+
+  // Load the disk version first so later external edits are still detected.
+  let _ = services.workspace.read_file(&path, &state).await;
+  let status = services
+    .workspace
+    .update_buffer(&path, &buffer.content, &state)?;
+  services
+    .recovery
+    .record_update(&parent, &root, &path, &buffer.content)?;
+  services
+    .recovery
+    .take(&parent, &root, Some(std::slice::from_ref(&path)))?;
+  emit_buffer_status(&app, &status)?;
+  Ok(status)
+}
+
+#[tauri::command]
+pub fn fs_discard_recoverable_buffers(
+  paths: Option<Vec<String>>,
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<usize> {
+  let discarded = services.recovery.take(
+    &recovery_parent(&app)?,
+    &recovery_root(&state)?,
+    paths.as_deref(),
+  )?;
+  Ok(discarded.len())
+}
+
+/// Queues a buffer update for the recovery journal, which the journal worker
+/// writes once edits pause.
+pub fn journal_buffer_update(
+  app: &tauri::AppHandle,
+  state: &FsState,
+  services: &AppServices,
+  path: &str,
+  content: &str,
+) -> AppResult<()> {
+  services.recovery.record_update(
+    &recovery_parent(app)?,
+    &recovery_root(state)?,
+    path,
+    content,
+  )
+}
+
+/// Compacts the journal down to the buffers that are still dirty.
+pub fn checkpoint_recovery_journal<R: Runtime>(
+  app: &AppHandle<R>,
+  state: &FsState,
+  services: &AppServices,
+) -> AppResult<()> {
+  services
+    .recovery
+    .checkpoint(&recovery_parent(app)?, &recovery_root(state)?, || {
+      services.documents.dirty_snapshots()
+    })
+}
+
+/// Replays journals left by a previous session that did not shut down cleanly.
+pub fn replay_recovery_journal(app: &tauri::AppHandle, services: &AppServices) -> AppResult<usize> {
+  services.recovery.replay(&recovery_parent(app)?)
+}
+
+fn recovery_root(state: &FsState) -> AppResult<PathBuf> {
+  let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+  Ok(data.root_path.clone())
+}
+
+fn recovery_parent<R: Runtime>(app: &AppHandle<R>) -> AppResult<PathBuf> {
+  Ok(
+    app
+      .path()
+      .app_data_dir()
+      .map_err(|err| format!("Failed to resolve app data dir: {err}"))?,
+  )
+}
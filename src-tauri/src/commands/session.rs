@@ -0,0 +1,134 @@
+use std::path::{Path, PathBuf};
+
+use tauri::{Manager, State};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{RecentWorkspace, WorkspaceSession, WorkspaceSessionFile};
+use crate::services::path_resolver::resolve_path;
+use crate::services::AppServices;
+use crate::state::FsState;
+
+#[tauri::command]
+pub fn fs_get_recent_workspaces(
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<RecentWorkspace>> {
+  services.session.recent_workspaces(&session_parent(&app)?)
+}
+
+#[tauri::command]
+pub fn fs_set_recent_workspace_pinned(
+  path: String,
+  pinned: bool,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<RecentWorkspace>> {
+  services
+    .session
+    .set_pinned(&session_parent(&app)?, &path, pinned)
+}
+
+#[tauri::command]
+pub fn fs_remove_recent_workspace(
+  path: String,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<RecentWorkspace>> {
+  services
+    .session
+    .remove_recent(&session_parent(&app)?, &path)
+}
+
+/// Returns the last session if it belongs to the current workspace, without
+/// files that no longer exist.
+#[tauri::command]
+pub fn fs_get_last_session(
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Option<WorkspaceSession>> {
+  let data = state
+    .0
+    .read()
+    .map_err(|_| AppError::lock("fs state"))?
+    .clone();
+  let Some(mut session) = services.session.last_session(&session_parent(&app)?)? else {
+    return Ok(None);
+  };
+  if session.root_kind != data.root_kind || data.root_path != Path::new(&session.root_path) {
+    return Ok(None);
+  }
+  session.open_files.retain(|file| {
+    resolve_path(&data, &file.path)
+      .map(|path| path.is_file())
+      .unwrap_or(false)
+  });
+  if !session
+    .open_files
+    .iter()
+    .any(|file| Some(&file.path) == session.active_path.as_ref())
+  {
+    session.active_path = session.open_files.first().map(|file| file.path.clone());
+  }
+  Ok(Some(session))
+}
+
+#[tauri::command]
+pub fn fs_save_session(
+  open_files: Vec<WorkspaceSessionFile>,
+  active_path: Option<String>,
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<()> {
+  let data = state
+    .0
+    .read()
+    .map_err(|_| AppError::lock("fs state"))?
+    .clone();
+  services.session.save_open_files(
+    &session_parent(&app)?,
+    &data,
+    WorkspaceSession {
+      open_files,
+      active_path,
+      ..WorkspaceSession::default()
+    },
+  )
+}
+
+/// Records the current workspace in the recent list and the last session.
+/// Failures are logged so they never block switching workspaces.
+pub fn record_recent_workspace(app: &tauri::AppHandle, state: &FsState, services: &AppServices) {
+  let result = session_parent(app).and_then(|parent| {
+    let data = state
+      .0
+      .read()
+      .map_err(|_| AppError::lock("fs state"))?
+      .clone();
+    services.session.record_workspace(&parent, &data)
+  });
+  if let Err(err) = result {
+    log::warn!("record recent workspace failed: {err}");
+  }
+}
+
+/// Reopens the workspace of the last session, if it still exists.
+pub fn restore_last_session(
+  app: &tauri::AppHandle,
+  state: &FsState,
+  services: &AppServices,
+) -> AppResult<bool> {
+  let parent = session_parent(app)?;
+  let mut data = state.0.write().map_err(|_| AppError::lock("fs state"))?;
+  services.session.restore(&parent, &mut data)
+}
+
+fn session_parent(app: &tauri::AppHandle) -> AppResult<PathBuf> {
+  Ok(
+    app
+      .path()
+      .app_data_dir()
+      .map_err(|err| format!("Failed to resolve app data dir: {err}"))?,
+  )
+}
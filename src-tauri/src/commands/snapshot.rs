@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{SnapshotConfig, SnapshotInfo};
+use crate::services::events::AppEvent;
+use crate::services::AppServices;
+use crate::state::FsState;
+
+#[tauri::command]
+pub fn snapshot_get_config(
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<SnapshotConfig> {
+  services.snapshots.config(&snapshot_parent(&app)?)
+}
+
+#[tauri::command]
+pub fn snapshot_set_config(
+  config: SnapshotConfig,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<SnapshotConfig> {
+  services
+    .snapshots
+    .set_config(&snapshot_parent(&app)?, config)
+}
+
+#[tauri::command]
+pub async fn snapshot_create(
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Option<SnapshotInfo>> {
+  services.workspace.flush_buffers(&state).await?;
+  snapshot_workspace(&app, &state, &services, Vec::new()).await
+}
+
+#[tauri::command]
+pub async fn snapshot_list(
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<SnapshotInfo>> {
+  let root = snapshot_root(&state)?;
+  services.snapshots.list(snapshot_parent(&app)?, root).await
+}
+
+#[tauri::command]
+pub async fn snapshot_restore(
+  id: String,
+  paths: Option<Vec<String>>,
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<Vec<String>> {
+  let root = snapshot_root(&state)?;
+  let restored = services
+    .snapshots
+    .restore(snapshot_parent(&app)?, root, id, paths)
+    .await?;
+  for path in &restored {
+    services.documents.remove_path(path)?;
+  }
+  services.workspace.clear_index_cache();
+  services
+    .events
+    .publish(AppEvent::FileSystemChanged(Vec::new()))?;
+  Ok(restored)
+}
+
+pub async fn snapshot_workspace<R: Runtime>(
+  app: &AppHandle<R>,
+  state: &FsState,
+  services: &AppServices,
+  changed: Vec<String>,
+) -> AppResult<Option<SnapshotInfo>> {
+  let root = snapshot_root(state)?;
+  let files = services
+    .workspace
+    .list_entries(state)
+    .await?
+    .into_iter()
+    .filter(|entry| entry.kind == "file")
+    .map(|entry| entry.path)
+    .collect::<Vec<_>>();
+  services
+    .snapshots
+    .create_snapshot(snapshot_parent(app)?, root, files, changed)
+    .await
+}
+
+fn snapshot_root(state: &FsState) -> AppResult<PathBuf> {
+  let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+  if data.root_kind == "single" {
+    return Err(AppError::unsupported(
+      "Snapshots are not supported in single-file mode",
+    ));
+  }
+  Ok(data.root_path.clone())
+}
+
+fn snapshot_parent<R: Runtime>(app: &AppHandle<R>) -> AppResult<PathBuf> {
+  Ok(
+    app
+      .path()
+      .app_data_dir()
+      .map_err(|err| format!("Failed to resolve app data dir: {err}"))?,
+  )
+}
@@ -0,0 +1,102 @@
+use tauri::{Manager, State};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{BackgroundTaskState, WorkspaceTask};
+use crate::services::events::emit_event;
+use crate::services::tasks::TaskExitEvent;
+use crate::services::AppServices;
+use crate::state::FsState;
+
+#[tauri::command]
+pub fn task_list(
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+) -> AppResult<Vec<WorkspaceTask>> {
+  let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+  if data.root_kind == "single" {
+    return Ok(Vec::new());
+  }
+  Ok(services.tasks.list(&data.root_path)?)
+}
+
+/// Starts a task in the background. Its status shows in the background task
+/// list as `task:<name>`; output streams as `task-output` events and the run
+/// ends with a `task-exit` event.
+#[tauri::command]
+pub fn task_run(
+  name: String,
+  state: State<'_, FsState>,
+  services: State<'_, AppServices>,
+  app: tauri::AppHandle,
+) -> AppResult<()> {
+  let root = {
+    let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+    if data.root_kind == "single" {
+      return Err(AppError::unsupported("Tasks need a workspace folder"));
+    }
+    data.root_path.clone()
+  };
+  let start = services.tasks.start(&root, &name)?;
+  let task_id = format!("task:{name}");
+  let label = start.label.clone();
+  if let Err(err) =
+    services
+      .background_tasks
+      .set(&task_id, &label, BackgroundTaskState::Running, None)
+  {
+    services.tasks.discard(start);
+    return Err(err.into());
+  }
+
+  tauri::async_runtime::spawn(async move {
+    let services = app.state::<AppServices>();
+    let result = services.tasks.run(app.clone(), start).await;
+    let (status, message, exit_code, cancelled) = match result {
+      Ok(outcome) if outcome.cancelled => (
+        BackgroundTaskState::Cancelled,
+        None,
+        outcome.exit_code,
+        true,
+      ),
+      Ok(outcome) if outcome.exit_code == Some(0) => {
+        (BackgroundTaskState::Succeeded, None, Some(0), false)
+      }
+      Ok(outcome) => (
+        BackgroundTaskState::Failed,
+        Some(match outcome.exit_code {
+          Some(code) => format!("Exited with code {code}"),
+          None => "Terminated by a signal".to_string(),
+        }),
+        outcome.exit_code,
+        false,
+      ),
+      Err(err) => (BackgroundTaskState::Failed, Some(err), None, false),
+    };
+    if let Err(err) = services
+      .background_tasks
+      .set(&task_id, &label, status, message)
+    {
+      log::warn!("set background task failed: {err}");
+    }
+    let _ = emit_event(
+      &app,
+      "task-exit",
+      &TaskExitEvent {
+        name,
+        exit_code,
+        cancelled,
+      },
+    );
+  });
+  Ok(())
+}
+
+#[tauri::command]
+pub fn task_cancel(name: String, services: State<'_, AppServices>) -> AppResult<()> {
+  Ok(services.tasks.cancel(&name)?)
+}
+
+#[tauri::command]
+pub fn task_log(name: String, services: State<'_, AppServices>) -> AppResult<String> {
+  Ok(services.tasks.log(&name)?)
+}
@@ -1,30 +1,88 @@
 use std::path::PathBuf;
 
-use tauri::State;
+use tauri::{Manager, State};
 
-use crate::services::terminal::TerminalSessionInfo;
+use crate::error::{AppError, AppResult};
+use crate::services::path_resolver::resolve_path;
+use crate::services::terminal::{
+  read_terminal_profiles, write_terminal_profiles, TerminalAttachment, TerminalLaunch,
+  TerminalProfileSettings, TerminalSessionInfo,
+};
 use crate::services::AppServices;
-use crate::state::FsState;
+use crate::state::{FsState, FsStateData};
 
+/// Opens a terminal with the named profile, or the workspace's default
+/// profile. `path` is the current file, used by the `file_directory` policy.
 #[tauri::command]
 pub fn terminal_create(
   rows: u16,
   cols: u16,
+  profile: Option<String>,
+  path: Option<String>,
   state: State<'_, FsState>,
   services: State<'_, AppServices>,
   app: tauri::AppHandle,
-) -> Result<TerminalSessionInfo, String> {
-  let cwd = terminal_working_directory(&state)?;
-  services.terminal.create(app, cwd, rows, cols)
+) -> AppResult<TerminalSessionInfo> {
+  let data = state
+    .0
+    .read()
+    .map_err(|_| AppError::lock("fs state"))?
+    .clone();
+  let cwd = terminal_working_directory(&data)?;
+  let settings = terminal_profiles(&app, &data)?;
+  let file_directory = path
+    .as_deref()
+    .and_then(|path| resolve_path(&data, path).ok())
+    .and_then(|path| path.parent().map(PathBuf::from));
+  let launch = TerminalLaunch::for_profile(
+    settings.profile(profile.as_deref())?,
+    &cwd,
+    file_directory.as_deref(),
+  );
+  Ok(services.terminal.create(app, launch, rows, cols)?)
+}
+
+#[tauri::command]
+pub fn terminal_get_profiles(
+  state: State<'_, FsState>,
+  app: tauri::AppHandle,
+) -> AppResult<TerminalProfileSettings> {
+  let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+  terminal_profiles(&app, &data)
+}
+
+#[tauri::command]
+pub fn terminal_set_profiles(
+  settings: TerminalProfileSettings,
+  state: State<'_, FsState>,
+  app: tauri::AppHandle,
+) -> AppResult<TerminalProfileSettings> {
+  let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+  if data.root_kind == "single" {
+    return Err(AppError::unsupported(
+      "Terminal profiles need a workspace folder",
+    ));
+  }
+  write_terminal_profiles(&profiles_parent(&app)?, &data.root_path, &settings)?;
+  Ok(settings)
 }
 
 #[tauri::command]
-pub fn terminal_write(
+pub fn terminal_list(services: State<'_, AppServices>) -> AppResult<Vec<TerminalSessionInfo>> {
+  Ok(services.terminal.list()?)
+}
+
+#[tauri::command]
+pub fn terminal_attach(
   id: String,
-  data: String,
   services: State<'_, AppServices>,
-) -> Result<(), String> {
-  services.terminal.write(&id, &data)
+) -> AppResult<TerminalAttachment> {
+  Ok(services.terminal.attach(&id)?)
+}
+
+#[tauri::command]
+pub fn terminal_write(id: String, data: String, services: State<'_, AppServices>) -> AppResult<()> {
+  Ok(services.terminal.write(&id, &data)?)
 }
 
 #[tauri::command]
@@ -33,25 +91,44 @@ pub fn terminal_resize(
   rows: u16,
   cols: u16,
   services: State<'_, AppServices>,
-) -> Result<(), String> {
-  services.terminal.resize(&id, rows, cols)
+) -> AppResult<()> {
+  Ok(services.terminal.resize(&id, rows, cols)?)
 }
 
 #[tauri::command]
-pub fn terminal_close(id: String, services: State<'_, AppServices>) -> Result<(), String> {
-  services.terminal.close(&id)
+pub fn terminal_close(id: String, services: State<'_, AppServices>) -> AppResult<()> {
+  Ok(services.terminal.close(&id)?)
 }
 
-fn terminal_working_directory(state: &FsState) -> Result<PathBuf, String> {
-  let data = state
-    .0
-    .read()
-    .map_err(|_| "Failed to lock fs state")?
-    .clone();
+/// Profiles are stored per workspace in app data; a single file has none.
+fn terminal_profiles(
+  app: &tauri::AppHandle,
+  data: &FsStateData,
+) -> AppResult<TerminalProfileSettings> {
+  if data.root_kind == "single" {
+    return Ok(TerminalProfileSettings::default());
+  }
+  Ok(read_terminal_profiles(
+    &profiles_parent(app)?,
+    &data.root_path,
+  )?)
+}
+
+fn profiles_parent(app: &tauri::AppHandle) -> AppResult<PathBuf> {
+  Ok(
+    app
+      .path()
+      .app_data_dir()
+      .map_err(|err| format!("Failed to resolve app data dir: {err}"))?,
+  )
+}
 
+/// The workspace root, or the folder of the file in single-file mode.
+pub fn terminal_working_directory(data: &FsStateData) -> AppResult<PathBuf> {
   if data.root_kind == "single" {
     let single_file = data
       .single_file
+      .as_ref()
       .ok_or_else(|| "Single-file path is not set".to_string())?;
     return Ok(
       single_file
@@ -61,5 +138,5 @@ fn terminal_working_directory(state: &FsState) -> Result<PathBuf, String> {
     );
   }
 
-  Ok(data.root_path)
+  Ok(data.root_path.clone())
 }
@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+pub type AppResult<T> = Result<T, AppError>;
+
+/// Error returned by services and commands. The frontend receives it as
+/// `{code, message, details}`: `code` and `details` are stable so the UI can
+/// react to the kind of failure and show its own localised text, `message`
+/// is an English fallback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+  NotFound {
+    path: String,
+  },
+  AlreadyExists {
+    path: String,
+  },
+  PermissionDenied {
+    path: String,
+  },
+  /// A path the operation may not use, such as one leaving the workspace.
+  InvalidPath {
+    path: String,
+    reason: String,
+  },
+  /// The target changed elsewhere and the user has to decide.
+  Conflict {
+    path: String,
+    reason: String,
+  },
+  /// Not available here, for example in single-file mode.
+  Unsupported {
+    reason: String,
+  },
+  InvalidInput {
+    reason: String,
+  },
+  /// Content that cannot be handled as text, such as a binary file.
+  InvalidFormat {
+    reason: String,
+  },
+  Cancelled {
+    reason: String,
+  },
+  Git {
+    reason: String,
+  },
+  /// Any other I/O failure while running `operation` on `path`.
+  Io {
+    operation: String,
+    path: String,
+    reason: String,
+  },
+  Internal {
+    reason: String,
+  },
+}
+
+impl AppError {
+  /// Classifies an I/O error so not-found and permission failures keep
+  /// their own codes.
+  pub fn io(operation: &str, path: impl AsRef<Path>, err: io::Error) -> Self {
+    let path = path.as_ref().to_string_lossy().to_string();
+    match err.kind() {
+      io::ErrorKind::NotFound => Self::NotFound { path },
+      io::ErrorKind::AlreadyExists => Self::AlreadyExists { path },
+      io::ErrorKind::PermissionDenied => Self::PermissionDenied { path },
+      _ => Self::Io {
+        operation: operation.to_string(),
+        path,
+        reason: err.to_string(),
+      },
+    }
+  }
+
+  pub fn invalid_path(path: impl Into<String>, reason: impl Into<String>) -> Self {
+    Self::InvalidPath {
+      path: path.into(),
+      reason: reason.into(),
+    }
+  }
+
+  pub fn unsupported(reason: impl Into<String>) -> Self {
+    Self::Unsupported {
+      reason: reason.into(),
+    }
+  }
+
+  pub fn invalid_input(reason: impl Into<String>) -> Self {
+    Self::InvalidInput {
+      reason: reason.into(),
+    }
+  }
+
+  pub fn invalid_format(reason: impl Into<String>) -> Self {
+    Self::InvalidFormat {
+      reason: reason.into(),
+    }
+  }
+
+  pub fn git(reason: impl Into<String>) -> Self {
+    Self::Git {
+      reason: reason.into(),
+    }
+  }
+
+  pub fn lock(what: &str) -> Self {
+    Self::Internal {
+      reason: format!("Failed to lock {what}"),
+    }
+  }
+
+  pub fn code(&self) -> &'static str {
+    match self {
+      Self::NotFound { .. } => "not_found",
+      Self::AlreadyExists { .. } => "already_exists",
+      Self::PermissionDenied { .. } => "permission_denied",
+      Self::InvalidPath { .. } => "invalid_path",
+      Self::Conflict { .. } => "conflict",
+      Self::Unsupported { .. } => "unsupported",
+      Self::InvalidInput { .. } => "invalid_input",
+      Self::InvalidFormat { .. } => "invalid_format",
+      Self::Cancelled { .. } => "cancelled",
+      Self::Git { .. } => "git",
+      Self::Io { .. } => "io",
+      Self::Internal { .. } => "internal",
+    }
+  }
+
+  /// Values a localised message can be built from.
+  pub fn details(&self) -> BTreeMap<&'static str, &str> {
+    let mut details = BTreeMap::new();
+    match self {
+      Self::NotFound { path } | Self::AlreadyExists { path } | Self::PermissionDenied { path } => {
+        details.insert("path", path.as_str());
+      }
+      Self::InvalidPath { path, reason } | Self::Conflict { path, reason } => {
+        details.insert("path", path.as_str());
+        details.insert("reason", reason.as_str());
+      }
+      Self::Io {
+        operation,
+        path,
+        reason,
+      } => {
+        details.insert("operation", operation.as_str());
+        details.insert("path", path.as_str());
+        details.insert("reason", reason.as_str());
+      }
+      Self::Unsupported { reason }
+      | Self::InvalidInput { reason }
+      | Self::InvalidFormat { reason }
+      | Self::Cancelled { reason }
+      | Self::Git { reason }
+      | Self::Internal { reason } => {
+        details.insert("reason", reason.as_str());
+      }
+    }
+    details
+  }
+}
+
+impl fmt::Display for AppError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NotFound { path } => write!(f, "Not found: {path}"),
+      Self::AlreadyExists { path } => write!(f, "Already exists: {path}"),
+      Self::PermissionDenied { path } => write!(f, "Permission denied: {path}"),
+      Self::Io {
+        operation, reason, ..
+      } => write!(f, "{operation}: {reason}"),
+      Self::InvalidPath { reason, .. }
+      | Self::Conflict { reason, .. }
+      | Self::Unsupported { reason }
+      | Self::InvalidInput { reason }
+      | Self::InvalidFormat { reason }
+      | Self::Cancelled { reason }
+      | Self::Git { reason }
+      | Self::Internal { reason } => f.write_str(reason),
+    }
+  }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut error = serializer.serialize_struct("AppError", 3)?;
+    error.serialize_field("code", self.code())?;
+    error.serialize_field("message", &self.to_string())?;
+    error.serialize_field("details", &self.details())?;
+    error.end()
+  }
+}
+
+/// Errors of code that still reports plain text.
+impl From<String> for AppError {
+  fn from(reason: String) -> Self {
+    Self::Internal { reason }
+  }
+}
+
+impl From<&str> for AppError {
+  fn from(reason: &str) -> Self {
+    Self::Internal {
+      reason: reason.to_string(),
+    }
+  }
+}
+
+impl From<AppError> for String {
+  fn from(err: AppError) -> Self {
+    err.to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn serializes_code_message_and_details() {
+    let err = AppError::io(
+      "Failed to read file",
+      "notes/a.md",
+      io::Error::from(io::ErrorKind::NotFound),
+    );
+    assert_eq!(
+      serde_json::to_value(&err).expect("error should serialize"),
+      serde_json::json!({
+        "code": "not_found",
+        "message": "Not found: notes/a.md",
+        "details": { "path": "notes/a.md" },
+      })
+    );
+
+    let err = AppError::io(
+      "Failed to write file",
+      "a.md",
+      io::Error::other("disk full"),
+    );
+    assert_eq!(err.code(), "io");
+    assert_eq!(err.to_string(), "Failed to write file: disk full");
+    assert_eq!(err.details()["operation"], "Failed to write file");
+
+    let err = AppError::from("Failed to lock fs state".to_string());
+    assert_eq!(err.code(), "internal");
+    assert_eq!(String::from(err), "Failed to lock fs state");
+  }
+}
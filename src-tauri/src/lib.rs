@@ -1,33 +1,67 @@
-use crate::commands::app::{app_get_platform, menu_dispatch};
+use crate::commands::app::{
+  app_get_event_log, app_get_platform, app_replay_events, menu_dispatch, MenuActionEvent,
+  SingleInstanceEvent,
+};
+use crate::commands::code_runner::{
+  code_block_cancel, code_block_run, code_runner_get_settings, code_runner_set_settings,
+};
 use crate::commands::export::{export_markdown, export_open_output_path};
 use crate::commands::fs::{
-  fs_analyze_markdown_buffer, fs_create_dir, fs_create_file, fs_delete_path, fs_flush_buffers,
-  fs_get_background_tasks, fs_get_buffer_status, fs_get_outline_graph, fs_get_path_metadata,
-  fs_get_root_info, fs_get_snapshot, fs_get_workspace_graph, fs_get_workspace_index,
-  fs_import_markdown_asset, fs_import_markdown_asset_base64, fs_list_entries, fs_move_path,
-  fs_open_file, fs_open_path_in_system, fs_read_file, fs_rebuild_search_index, fs_rename_path,
-  fs_resolve_markdown_asset, fs_search_workspace, fs_set_root, fs_set_single_file,
-  fs_update_buffer, fs_write_file,
+  fs_analyze_markdown_buffer, fs_cancel_background_task, fs_convert_encoding, fs_create_dir,
+  fs_create_file, fs_create_from_template, fs_delete_path, fs_empty_trash, fs_flush_buffers,
+  fs_get_background_task_history, fs_get_background_tasks, fs_get_buffer_status,
+  fs_get_document_cache_stats, fs_get_document_types, fs_get_external_conflict,
+  fs_get_ignore_settings, fs_get_outline_graph, fs_get_path_metadata, fs_get_root_info,
+  fs_get_snapshot, fs_get_template_settings, fs_get_workspace_graph, fs_get_workspace_index,
+  fs_import_markdown_asset, fs_import_markdown_asset_base64, fs_list_entries, fs_list_roots,
+  fs_list_templates, fs_list_trash, fs_mount_root, fs_move_path, fs_open_daily_note, fs_open_file,
+  fs_open_path_in_system, fs_read_file, fs_rebuild_search_index, fs_rename_path,
+  fs_resolve_external_conflict, fs_resolve_markdown_asset, fs_restore_from_trash,
+  fs_search_workspace, fs_set_document_cache_budget, fs_set_ignore_settings, fs_set_root,
+  fs_set_single_file, fs_set_template_settings, fs_unmount_root, fs_update_buffer, fs_write_file,
 };
 use crate::commands::git::{
-  git_commit_all, git_discover_repo, git_get_file_diff, git_get_status, git_init_repo,
+  git_commit_all, git_discover_repo, git_get_conflict, git_get_file_diff, git_get_status,
+  git_init_repo, git_resolve_conflict,
+};
+use crate::commands::history::{
+  fs_get_file_history_config, fs_list_deleted_files, fs_list_file_history, fs_restore_file_version,
+  fs_set_file_history_config,
 };
 use crate::commands::markdown::{list_markdown_files, read_markdown_file, write_markdown_file};
-use crate::commands::terminal::{terminal_close, terminal_create, terminal_resize, terminal_write};
+use crate::commands::recovery::{
+  fs_discard_recoverable_buffers, fs_get_recoverable_buffers, fs_restore_recoverable_buffer,
+};
+use crate::commands::session::{
+  fs_get_last_session, fs_get_recent_workspaces, fs_remove_recent_workspace, fs_save_session,
+  fs_set_recent_workspace_pinned,
+};
+use crate::commands::snapshot::{
+  snapshot_create, snapshot_get_config, snapshot_list, snapshot_restore, snapshot_set_config,
+};
+use crate::commands::tasks::{task_cancel, task_list, task_log, task_run};
+use crate::commands::terminal::{
+  terminal_attach, terminal_close, terminal_create, terminal_get_profiles, terminal_list,
+  terminal_resize, terminal_set_profiles, terminal_write,
+};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc, Mutex, RwLock};
-use tauri::{Emitter, Listener, Manager};
+use tauri::{Listener, Manager};
 
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod cli;
 mod commands;
+mod error;
 mod models;
 mod services;
 mod state;
+#[cfg(test)]
+mod test_support;
 
-use crate::state::{
-  AllowedSystemPathsState, BackgroundTasksState, FsState, FsStateData, FsWatcherState,
-};
+use crate::services::events::emit_event;
+use crate::state::{AllowedSystemPathsState, FsState, FsStateData, FsWatcherState};
 
 fn run_impl() {
   let app_container = futures::executor::block_on(services::di::build_app_container())
@@ -35,24 +69,13 @@ fn run_impl() {
   let app_services = app_container.services;
   let app_lifecycle = app_container.lifecycle;
 
-  let builder = tauri::Builder::default();
-
-  #[cfg(not(any(target_os = "android", target_os = "ios")))]
-  let builder = builder.plugin(tauri_plugin_cli::init());
-
-  let builder = builder
+  let builder = tauri::Builder::default()
     .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
       if let Some(main) = app.get_webview_window("main") {
         let _ = main.show();
         let _ = main.set_focus();
       }
-      let _ = app.emit(
-        "single-instance",
-        serde_json::json!({
-          "args": args,
-          "cwd": cwd,
-        }),
-      );
+      let _ = emit_event(app, "single-instance", &SingleInstanceEvent { args, cwd });
     }))
     .plugin(tauri_plugin_deep_link::init())
     .plugin(tauri_plugin_clipboard_manager::init())
@@ -68,9 +91,9 @@ fn run_impl() {
       root_path: PathBuf::new(),
       internal_root: PathBuf::new(),
       single_file: None,
+      mounted_roots: Vec::new(),
     })))
-    .manage(FsWatcherState(Mutex::new(None)))
-    .manage(BackgroundTasksState(Mutex::new(HashMap::new())))
+    .manage(FsWatcherState(Mutex::new(HashMap::new())))
     .manage(AllowedSystemPathsState(Mutex::new(HashSet::new())));
 
   #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -103,15 +126,33 @@ fn run_impl() {
         data.root_path = internal_root.clone();
         data.internal_root = internal_root;
       }
+      if let (Some(state), Some(services)) = (
+        app_handle.try_state::<FsState>(),
+        app_handle.try_state::<services::AppServices>(),
+      ) {
+        match commands::session::restore_last_session(app_handle, &state, &services) {
+          Ok(true) => commands::session::record_recent_workspace(app_handle, &state, &services),
+          Ok(false) => {}
+          Err(err) => log::warn!("restore last session failed: {err}"),
+        }
+      }
       if let (Some(state), Some(watcher_state)) = (
         app_handle.try_state::<FsState>(),
         app_handle.try_state::<FsWatcherState>(),
       ) {
         commands::fs::start_fs_watcher(app_handle, &state, &watcher_state)?;
       }
+      if let Some(services) = app_handle.try_state::<services::AppServices>() {
+        if let Err(err) = commands::recovery::replay_recovery_journal(app_handle, &services) {
+          log::warn!("replay recovery journal failed: {err}");
+        }
+      }
       commands::fs::start_buffer_flush_worker(app_handle);
+      commands::fs::start_recovery_journal_worker(app_handle);
+      commands::fs::start_snapshot_worker(app_handle);
       if let Some(services) = app_handle.try_state::<services::AppServices>() {
         services.runtime.start_event_worker(app_handle);
+        services.background_tasks.start_event_worker(app_handle);
         if let Err(err) = services.runtime.publish_initial_workspace_event() {
           log::warn!("publish initial workspace event failed: {err}");
         }
@@ -195,22 +236,13 @@ fn run_impl() {
           | "help.about"
       );
       if forward {
-        let _ = app.emit("menu-action", id);
+        let _ = emit_event(app, "menu-action", &MenuActionEvent { id });
       }
     })
     .on_window_event(|window, event| {
-      if window.label() == "main" && matches!(event, tauri::WindowEvent::CloseRequested { .. }) {
-        let app = window.app_handle();
-        if let (Some(state), Some(services)) = (
-          app.try_state::<FsState>(),
-          app.try_state::<services::AppServices>(),
-        ) {
-          let _ = commands::fs::flush_all_buffers(&state, &services);
-        }
-        if let Some(lifecycle) = app.try_state::<services::di::AppLifecycle>() {
-          if let Err(err) = lifecycle.shutdown_blocking() {
-            log::warn!("application lifecycle shutdown failed: {err}");
-          }
+      if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+        if window.label() == "main" {
+          commands::app::on_main_window_close_requested(window.app_handle(), api);
         }
       }
     })
@@ -236,7 +268,20 @@ fn run_impl() {
       fs_update_buffer,
       fs_flush_buffers,
       fs_get_buffer_status,
+      fs_get_external_conflict,
+      fs_resolve_external_conflict,
+      fs_convert_encoding,
       fs_get_background_tasks,
+      fs_get_background_task_history,
+      fs_cancel_background_task,
+      fs_get_document_types,
+      fs_list_roots,
+      fs_mount_root,
+      fs_unmount_root,
+      fs_get_ignore_settings,
+      fs_set_ignore_settings,
+      fs_get_document_cache_stats,
+      fs_set_document_cache_budget,
       fs_get_path_metadata,
       fs_open_path_in_system,
       fs_import_markdown_asset,
@@ -244,23 +289,65 @@ fn run_impl() {
       fs_resolve_markdown_asset,
       fs_write_file,
       fs_create_file,
+      fs_create_from_template,
+      fs_open_daily_note,
+      fs_list_templates,
+      fs_get_template_settings,
+      fs_set_template_settings,
       fs_create_dir,
       fs_delete_path,
+      fs_list_trash,
+      fs_restore_from_trash,
+      fs_empty_trash,
       fs_rename_path,
       fs_move_path,
       app_get_platform,
+      app_get_event_log,
+      app_replay_events,
       menu_dispatch,
       git_discover_repo,
       git_init_repo,
       git_get_status,
       git_get_file_diff,
       git_commit_all,
+      git_get_conflict,
+      git_resolve_conflict,
+      snapshot_get_config,
+      snapshot_set_config,
+      snapshot_create,
+      snapshot_list,
+      snapshot_restore,
+      fs_get_file_history_config,
+      fs_set_file_history_config,
+      fs_list_file_history,
+      fs_list_deleted_files,
+      fs_restore_file_version,
+      fs_get_recoverable_buffers,
+      fs_restore_recoverable_buffer,
+      fs_discard_recoverable_buffers,
+      fs_get_recent_workspaces,
+      fs_set_recent_workspace_pinned,
+      fs_remove_recent_workspace,
+      fs_get_last_session,
+      fs_save_session,
       export_markdown,
       export_open_output_path,
       terminal_create,
+      terminal_get_profiles,
+      terminal_set_profiles,
+      terminal_list,
+      terminal_attach,
       terminal_write,
       terminal_resize,
-      terminal_close
+      terminal_close,
+      code_runner_get_settings,
+      code_runner_set_settings,
+      code_block_run,
+      code_block_cancel,
+      task_list,
+      task_run,
+      task_cancel,
+      task_log
     ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
@@ -272,5 +359,9 @@ pub fn run() {
 }
 
 pub async fn run_async() {
+  #[cfg(not(any(target_os = "android", target_os = "ios")))]
+  if let Some(code) = cli::run_from_args(std::env::args_os().collect()).await {
+    std::process::exit(code);
+  }
   run_impl();
 }
@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize)]
 pub struct MarkdownFile {
@@ -33,6 +33,76 @@ pub struct FsPathMetadata {
   pub size_bytes: u64,
   pub modified_ms: Option<u128>,
   pub readonly: bool,
+  pub text_format: Option<FsTextFormat>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsWorkspaceRoot {
+  pub name: String,
+  pub path: String,
+  pub prefix: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsDocumentType {
+  pub id: String,
+  pub label: String,
+  pub extensions: Vec<String>,
+  pub syntax: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsTextFormat {
+  pub encoding: String,
+  pub bom: bool,
+  pub line_ending: String,
+}
+
+impl Default for FsTextFormat {
+  fn default() -> Self {
+    Self {
+      encoding: "UTF-8".to_string(),
+      bom: false,
+      line_ending: "lf".to_string(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentCacheStats {
+  pub entries: usize,
+  pub dirty_entries: usize,
+  pub parsed_entries: usize,
+  pub bytes: u64,
+  pub budget_bytes: u64,
+  pub hits: u64,
+  pub misses: u64,
+  pub parsed_hits: u64,
+  pub parsed_misses: u64,
+  pub evictions: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsTrashEntry {
+  pub id: String,
+  pub original_path: String,
+  pub name: String,
+  pub kind: String,
+  pub deleted_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsRecoverableBuffer {
+  pub path: String,
+  pub content: String,
+  pub updated_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsTrashRestoreResult {
+  pub id: String,
+  pub original_path: String,
+  pub restored_path: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -40,14 +110,57 @@ pub struct FsBufferStatus {
   pub path: String,
   pub revision: u64,
   pub dirty: bool,
+  pub conflict: bool,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsExternalConflict {
+  pub path: String,
+  pub base_content: Option<String>,
+  pub local_content: String,
+  pub disk_content: String,
+  pub merged_content: String,
+  pub merge_conflicts: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundTaskState {
+  Running,
+  /// A recurring task waiting for its next run.
+  Idle,
+  Succeeded,
+  Failed,
+  Cancelled,
+}
+
+impl BackgroundTaskState {
+  pub fn is_finished(self) -> bool {
+    matches!(self, Self::Succeeded | Self::Failed | Self::Cancelled)
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BackgroundTaskProgress {
+  pub completed: u64,
+  pub total: Option<u64>,
+  /// `completed` as a percentage of `total`, when the total is known.
+  pub percent: Option<u8>,
+  pub step: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct BackgroundTaskStatus {
   pub id: String,
   pub label: String,
-  pub status: String,
+  pub status: BackgroundTaskState,
   pub message: Option<String>,
+  pub progress: Option<BackgroundTaskProgress>,
+  pub cancellable: bool,
+  /// Milliseconds since the Unix epoch.
+  pub started_at: Option<u64>,
+  pub finished_at: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -108,6 +221,13 @@ pub struct FsMarkdownDiagnostic {
   pub severity: String,
 }
 
+/// Diagnostics of one file in a workspace-wide check.
+#[derive(Debug, Clone, Serialize)]
+pub struct FsFileDiagnostics {
+  pub path: String,
+  pub diagnostics: Vec<FsMarkdownDiagnostic>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct FsTextRange {
   pub start: usize,
@@ -222,4 +342,303 @@ pub struct GitFileDiff {
   pub original_content: String,
   pub modified_content: String,
   pub unified_diff: String,
+  pub granularity: String,
+  pub inline_changes: Vec<GitInlineChange>,
+  pub structural_changes: Vec<GitStructuralChange>,
+}
+
+/// A word, sentence or character level change. Ranges are char offsets
+/// into `original_content` / `modified_content`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct GitInlineChange {
+  pub kind: String,
+  pub original_start: usize,
+  pub original_end: usize,
+  pub modified_start: usize,
+  pub modified_end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct GitStructuralChange {
+  pub kind: String,
+  pub heading: String,
+  pub level: usize,
+  pub original_index: Option<usize>,
+  pub modified_index: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct GitConflictHunk {
+  pub index: usize,
+  pub start_line: usize,
+  pub end_line: usize,
+  pub ours_label: String,
+  pub theirs_label: String,
+  pub ours: String,
+  pub base: Option<String>,
+  pub theirs: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GitConflictFile {
+  pub path: String,
+  pub base_content: Option<String>,
+  pub ours_content: Option<String>,
+  pub theirs_content: Option<String>,
+  pub working_content: String,
+  pub hunks: Vec<GitConflictHunk>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitConflictResolution {
+  pub hunk: usize,
+  pub choice: String,
+  pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+  pub enabled: bool,
+  pub interval_secs: u64,
+  pub message_template: String,
+}
+
+impl Default for SnapshotConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      interval_secs: 300,
+      message_template: "Snapshot: {files}".to_string(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+  pub id: String,
+  pub summary: String,
+  pub timestamp_ms: i64,
+  pub target: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileHistoryConfig {
+  pub enabled: bool,
+  pub keep_last: usize,
+  pub keep_hourly_hours: u64,
+  pub keep_daily_days: u64,
+  pub max_file_bytes: u64,
+}
+
+impl Default for FileHistoryConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      keep_last: 20,
+      keep_hourly_hours: 24,
+      keep_daily_days: 30,
+      max_file_bytes: 5 * 1024 * 1024,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileHistoryVersion {
+  pub id: String,
+  pub path: String,
+  pub content_hash: String,
+  pub size: u64,
+  pub timestamp_ms: i64,
+  pub reason: String,
+  pub deleted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceIgnoreSettings {
+  pub use_ignore_files: bool,
+  pub include: Vec<String>,
+  pub exclude: Vec<String>,
+}
+
+impl Default for WorkspaceIgnoreSettings {
+  fn default() -> Self {
+    Self {
+      use_ignore_files: true,
+      include: Vec::new(),
+      exclude: Vec::new(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecentWorkspace {
+  pub root_kind: String,
+  pub path: String,
+  pub last_opened_ms: i64,
+  pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WorkspaceSession {
+  pub root_kind: String,
+  pub root_path: String,
+  pub mounted_roots: Vec<WorkspaceSessionRoot>,
+  pub open_files: Vec<WorkspaceSessionFile>,
+  pub active_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkspaceSessionRoot {
+  pub name: String,
+  pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkspaceSessionFile {
+  pub path: String,
+  #[serde(default)]
+  pub scroll_top: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceTemplateSettings {
+  pub templates_dir: String,
+  pub daily_notes_dir: String,
+  pub daily_note_pattern: String,
+  pub daily_note_template: Option<String>,
+}
+
+impl Default for WorkspaceTemplateSettings {
+  fn default() -> Self {
+    Self {
+      templates_dir: ".marko/templates".to_string(),
+      daily_notes_dir: "daily".to_string(),
+      daily_note_pattern: "%Y-%m-%d".to_string(),
+      daily_note_template: None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsTemplate {
+  pub name: String,
+  pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsCreatedNote {
+  pub path: String,
+  pub created: bool,
+  pub cursor_line: Option<usize>,
+  pub cursor_column: Option<usize>,
+}
+
+/// How code blocks of one language are run. The block is written to a script
+/// file with `extension`, whose path is appended to `args`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CodeInterpreter {
+  pub program: String,
+  pub args: Vec<String>,
+  pub extension: String,
+}
+
+impl Default for CodeInterpreter {
+  fn default() -> Self {
+    Self {
+      program: String::new(),
+      args: Vec::new(),
+      extension: "txt".to_string(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CodeRunnerSettings {
+  /// Interpreters keyed by lowercase fence language.
+  pub interpreters: std::collections::BTreeMap<String, CodeInterpreter>,
+}
+
+impl Default for CodeRunnerSettings {
+  fn default() -> Self {
+    let interpreter = |program: &str, args: &[&str], extension: &str| CodeInterpreter {
+      program: program.to_string(),
+      args: args.iter().map(ToString::to_string).collect(),
+      extension: extension.to_string(),
+    };
+    let interpreters = [
+      ("bash", interpreter("bash", &[], "sh")),
+      ("sh", interpreter("sh", &[], "sh")),
+      ("shell", interpreter("sh", &[], "sh")),
+      ("zsh", interpreter("zsh", &[], "zsh")),
+      ("python", interpreter("python3", &[], "py")),
+      ("py", interpreter("python3", &[], "py")),
+      ("javascript", interpreter("node", &[], "js")),
+      ("js", interpreter("node", &[], "js")),
+      ("ruby", interpreter("ruby", &[], "rb")),
+      (
+        "powershell",
+        interpreter("pwsh", &["-NoProfile", "-File"], "ps1"),
+      ),
+    ];
+    Self {
+      interpreters: interpreters
+        .into_iter()
+        .map(|(language, interpreter)| (language.to_string(), interpreter))
+        .collect(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeRunInfo {
+  pub id: String,
+  pub path: String,
+  pub block_id: String,
+  pub language: String,
+  /// `capture` or `terminal`.
+  pub target: String,
+  /// Terminal session the block runs in, for the `terminal` target.
+  pub terminal_id: Option<String>,
+}
+
+/// A named command from `.marko/tasks.toml`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceTask {
+  pub name: String,
+  pub label: String,
+  pub command: String,
+  pub cwd: Option<String>,
+  pub running: bool,
+}
+
+/// Groups backend events so subscribers can follow one area of the app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventTopic {
+  Workspace,
+  Buffers,
+  Export,
+  Terminal,
+  /// Output and exit of code block runs and workspace tasks.
+  Process,
+  BackgroundTasks,
+  App,
+}
+
+/// A frontend event as kept in the event log.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggedEvent {
+  pub seq: u64,
+  pub name: String,
+  pub topic: EventTopic,
+  pub version: u32,
+  pub emitted_at: u64,
+  pub payload: serde_json::Value,
 }
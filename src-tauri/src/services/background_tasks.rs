@@ -0,0 +1,447 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::sync::broadcast;
+
+use crate::models::{BackgroundTaskProgress, BackgroundTaskState, BackgroundTaskStatus};
+use crate::services::events::emit_event;
+use crate::services::shutdown::ShutdownToken;
+
+/// Finished runs kept for the task history, oldest dropped first.
+const HISTORY_LIMIT: usize = 100;
+const UPDATE_CHANNEL_CAPACITY: usize = 256;
+
+/// Tracks background work for the UI. Every change is sent as one
+/// `background-task` event; finished runs are kept in a bounded history.
+#[derive(Debug)]
+pub struct BackgroundTaskService {
+  inner: Arc<TaskRegistry>,
+  event_worker_started: AtomicBool,
+  shutdown: ShutdownToken,
+}
+
+#[derive(Debug)]
+struct TaskRegistry {
+  tasks: Mutex<HashMap<String, TaskEntry>>,
+  history: Mutex<VecDeque<BackgroundTaskStatus>>,
+  updates: broadcast::Sender<BackgroundTaskStatus>,
+}
+
+#[derive(Debug)]
+struct TaskEntry {
+  status: BackgroundTaskStatus,
+  cancel: Option<Arc<AtomicBool>>,
+}
+
+impl BackgroundTaskService {
+  pub fn new(shutdown: ShutdownToken) -> Self {
+    Self {
+      inner: Arc::new(TaskRegistry {
+        tasks: Mutex::new(HashMap::new()),
+        history: Mutex::new(VecDeque::new()),
+        updates: broadcast::channel(UPDATE_CHANNEL_CAPACITY).0,
+      }),
+      event_worker_started: AtomicBool::new(false),
+      shutdown,
+    }
+  }
+
+  /// Records the state of a task that is not cancellable, such as the
+  /// recurring save queue. Finished states are also added to the history
+  /// unless the task's last entry already ended the same way, so a task that
+  /// keeps failing with the same message is listed once.
+  pub fn set(
+    &self,
+    id: &str,
+    label: &str,
+    status: BackgroundTaskState,
+    message: Option<String>,
+  ) -> Result<(), String> {
+    let mut tasks = self.inner.lock_tasks()?;
+    let previous = tasks.remove(id).map(|entry| entry.status);
+    let was_running = previous
+      .as_ref()
+      .is_some_and(|previous| previous.status == BackgroundTaskState::Running);
+    let started_at = if status == BackgroundTaskState::Running && !was_running {
+      Some(now_millis())
+    } else {
+      previous.and_then(|previous| previous.started_at)
+    };
+    let entry = TaskEntry {
+      status: BackgroundTaskStatus {
+        id: id.to_string(),
+        label: label.to_string(),
+        status,
+        message,
+        progress: None,
+        cancellable: false,
+        started_at,
+        finished_at: (status != BackgroundTaskState::Running).then(now_millis),
+      },
+      cancel: None,
+    };
+    let snapshot = entry.status.clone();
+    tasks.insert(id.to_string(), entry);
+    drop(tasks);
+    if status.is_finished() && self.inner.outcome_changed(&snapshot)? {
+      self.inner.archive(snapshot.clone())?;
+    }
+    self.inner.publish(snapshot);
+    Ok(())
+  }
+
+  /// Starts a cancellable run of `id`, replacing any earlier state.
+  pub fn start(&self, id: &str, label: &str) -> Result<BackgroundTaskHandle, String> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let status = BackgroundTaskStatus {
+      id: id.to_string(),
+      label: label.to_string(),
+      status: BackgroundTaskState::Running,
+      message: None,
+      progress: None,
+      cancellable: true,
+      started_at: Some(now_millis()),
+      finished_at: None,
+    };
+    let previous = self.inner.lock_tasks()?.insert(
+      id.to_string(),
+      TaskEntry {
+        status: status.clone(),
+        cancel: Some(Arc::clone(&cancel)),
+      },
+    );
+    // A run replaced by a newer one of the same task stops early.
+    if let Some(cancel) = previous.and_then(|entry| entry.cancel) {
+      cancel.store(true, Ordering::SeqCst);
+    }
+    self.inner.publish(status);
+    Ok(BackgroundTaskHandle {
+      id: id.to_string(),
+      registry: Arc::clone(&self.inner),
+      cancel,
+    })
+  }
+
+  pub fn cancel(&self, id: &str) -> Result<(), String> {
+    let tasks = self.inner.lock_tasks()?;
+    let entry = tasks
+      .get(id)
+      .filter(|entry| entry.status.status == BackgroundTaskState::Running)
+      .ok_or_else(|| format!("Background task {id} is not running"))?;
+    let cancel = entry
+      .cancel
+      .as_ref()
+      .ok_or_else(|| format!("Background task {id} cannot be cancelled"))?;
+    cancel.store(true, Ordering::SeqCst);
+    Ok(())
+  }
+
+  pub fn list(&self) -> Result<Vec<BackgroundTaskStatus>, String> {
+    let tasks = self.inner.lock_tasks()?;
+    Ok(tasks.values().map(|entry| entry.status.clone()).collect())
+  }
+
+  /// Finished runs, most recent first.
+  pub fn history(&self) -> Result<Vec<BackgroundTaskStatus>, String> {
+    let history = self
+      .inner
+      .history
+      .lock()
+      .map_err(|_| "Failed to lock task history")?;
+    Ok(history.iter().rev().cloned().collect())
+  }
+
+  pub fn subscribe(&self) -> broadcast::Receiver<BackgroundTaskStatus> {
+    self.inner.updates.subscribe()
+  }
+
+  /// Forwards task updates to the frontend as `background-task` events
+  /// until shutdown.
+  pub fn start_event_worker(&self, app: &tauri::AppHandle) {
+    if self.event_worker_started.swap(true, Ordering::SeqCst) {
+      return;
+    }
+    let app = app.clone();
+    let mut updates = self.subscribe();
+    let shutdown = self.shutdown.clone();
+    let worker = shutdown.worker();
+    tauri::async_runtime::spawn(async move {
+      let _worker = worker;
+      loop {
+        let update = tokio::select! {
+          update = updates.recv() => update,
+          _ = shutdown.cancelled() => break,
+        };
+        match update {
+          Ok(status) => {
+            if let Err(err) = emit_event(&app, "background-task", &status) {
+              log::warn!("emit background-task failed: {err}");
+            }
+          }
+          Err(broadcast::error::RecvError::Lagged(skipped)) => {
+            log::warn!("background task events lagged by {skipped} updates");
+          }
+          Err(broadcast::error::RecvError::Closed) => break,
+        }
+      }
+    });
+  }
+}
+
+impl TaskRegistry {
+  fn lock_tasks(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, TaskEntry>>, String> {
+    self
+      .tasks
+      .lock()
+      .map_err(|_| "Failed to lock task state".to_string())
+  }
+
+  fn archive(&self, status: BackgroundTaskStatus) -> Result<(), String> {
+    let mut history = self
+      .history
+      .lock()
+      .map_err(|_| "Failed to lock task history")?;
+    history.push_back(status);
+    while history.len() > HISTORY_LIMIT {
+      history.pop_front();
+    }
+    Ok(())
+  }
+
+  /// Whether `status` ended differently from the last archived run of the
+  /// same task.
+  fn outcome_changed(&self, status: &BackgroundTaskStatus) -> Result<bool, String> {
+    let history = self
+      .history
+      .lock()
+      .map_err(|_| "Failed to lock task history")?;
+    Ok(
+      history
+        .iter()
+        .rev()
+        .find(|previous| previous.id == status.id)
+        .map_or(true, |previous| {
+          previous.status != status.status || previous.message != status.message
+        }),
+    )
+  }
+
+  fn publish(&self, status: BackgroundTaskStatus) {
+    // No receiver just means nobody is listening yet.
+    let _ = self.updates.send(status);
+  }
+}
+
+/// One run of a cancellable task. Long jobs report progress through it and
+/// stop early once [`Self::is_cancelled`] returns `true`.
+#[derive(Debug, Clone)]
+pub struct BackgroundTaskHandle {
+  id: String,
+  registry: Arc<TaskRegistry>,
+  cancel: Arc<AtomicBool>,
+}
+
+impl BackgroundTaskHandle {
+  pub fn is_cancelled(&self) -> bool {
+    self.cancel.load(Ordering::SeqCst)
+  }
+
+  /// Sets the total amount of work and resets progress to zero.
+  pub fn set_total(&self, total: u64) {
+    self.update_progress(|progress| {
+      progress.completed = 0;
+      progress.total = Some(total);
+    });
+  }
+
+  /// Adds `amount` to the completed work. Updates are only published when
+  /// the percentage changes.
+  pub fn advance(&self, amount: u64) {
+    self.update_progress(|progress| progress.completed += amount);
+  }
+
+  pub fn step(&self, step: &str) {
+    self.update_progress(|progress| progress.step = Some(step.to_string()));
+  }
+
+  /// Ends the run and moves it to the history. A cancelled run ends as
+  /// cancelled whatever its result.
+  pub fn finish(self, result: &Result<(), impl std::fmt::Display>) {
+    let (status, message) = match result {
+      _ if self.is_cancelled() => (BackgroundTaskState::Cancelled, None),
+      Ok(()) => (BackgroundTaskState::Succeeded, None),
+      Err(err) => (BackgroundTaskState::Failed, Some(err.to_string())),
+    };
+    let Ok(mut tasks) = self.registry.lock_tasks() else {
+      return;
+    };
+    if !self.owns_entry(&tasks) {
+      return;
+    }
+    let Some(mut entry) = tasks.remove(&self.id) else {
+      return;
+    };
+    drop(tasks);
+    entry.status.status = status;
+    entry.status.message = message;
+    entry.status.cancellable = false;
+    entry.status.finished_at = Some(now_millis());
+    if let Err(err) = self.registry.archive(entry.status.clone()) {
+      log::warn!("archive background task failed: {err}");
+    }
+    self.registry.publish(entry.status);
+  }
+
+  fn update_progress(&self, update: impl FnOnce(&mut BackgroundTaskProgress)) {
+    let Ok(mut tasks) = self.registry.lock_tasks() else {
+      return;
+    };
+    if !self.owns_entry(&tasks) {
+      return;
+    }
+    let Some(entry) = tasks.get_mut(&self.id) else {
+      return;
+    };
+    let previous = entry.status.progress.clone().unwrap_or_default();
+    let mut progress = previous.clone();
+    update(&mut progress);
+    progress.percent = progress.total.map(|total| {
+      (progress.completed.min(total) * 100)
+        .checked_div(total)
+        .unwrap_or(100) as u8
+    });
+    let changed = progress.percent != previous.percent
+      || progress.step != previous.step
+      || progress.total != previous.total
+      || entry.status.progress.is_none();
+    entry.status.progress = Some(progress);
+    if changed {
+      self.registry.publish(entry.status.clone());
+    }
+  }
+
+  /// Whether the entry still belongs to this run rather than a newer one.
+  fn owns_entry(&self, tasks: &HashMap<String, TaskEntry>) -> bool {
+    tasks
+      .get(&self.id)
+      .and_then(|entry| entry.cancel.as_ref())
+      .is_some_and(|cancel| Arc::ptr_eq(cancel, &self.cancel))
+  }
+}
+
+fn now_millis() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_millis() as u64)
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tracks_progress_cancellation_and_history() {
+    let service = BackgroundTaskService::new(ShutdownToken::new());
+    let mut updates = service.subscribe();
+
+    let task = service
+      .start("search-index", "Search index")
+      .expect("task should start");
+    task.set_total(1000);
+    task.advance(1);
+    task.advance(1);
+    task.advance(498);
+    let running = service.list().expect("tasks should list");
+    assert_eq!(running.len(), 1);
+    assert!(running[0].cancellable);
+    let progress = running[0].progress.clone().expect("progress should be set");
+    assert_eq!((progress.completed, progress.percent), (500, Some(50)));
+
+    let mut percents = Vec::new();
+    while let Ok(update) = updates.try_recv() {
+      percents.push(update.progress.and_then(|progress| progress.percent));
+    }
+    // The single-item steps below 1% are not published.
+    assert_eq!(percents, vec![None, Some(0), Some(50)]);
+
+    service.cancel("search-index").expect("task should cancel");
+    assert!(task.is_cancelled());
+    task.finish(&Err("stopped".to_string()));
+    assert!(service.list().expect("tasks should list").is_empty());
+    assert!(service.cancel("search-index").is_err());
+
+    service
+      .set(
+        "buffer-flush",
+        "Save queue",
+        BackgroundTaskState::Running,
+        None,
+      )
+      .expect("task should be set");
+    service
+      .set(
+        "buffer-flush",
+        "Save queue",
+        BackgroundTaskState::Failed,
+        Some("1 file(s) failed to save".to_string()),
+      )
+      .expect("task should be set");
+    assert!(service.cancel("buffer-flush").is_err());
+    let flush = &service.list().expect("tasks should list")[0];
+    assert!(flush.started_at.is_some() && flush.finished_at.is_some());
+
+    // Retries failing the same way are not added to the history again.
+    for message in ["1 file(s) failed to save", "2 file(s) failed to save"] {
+      for _ in 0..2 {
+        service
+          .set(
+            "buffer-flush",
+            "Save queue",
+            BackgroundTaskState::Running,
+            None,
+          )
+          .expect("task should be set");
+        service
+          .set(
+            "buffer-flush",
+            "Save queue",
+            BackgroundTaskState::Failed,
+            Some(message.to_string()),
+          )
+          .expect("task should be set");
+      }
+    }
+
+    let history = service.history().expect("history should list");
+    assert_eq!(history.len(), 3);
+    assert_eq!(history[0].id, "buffer-flush");
+    assert_eq!(
+      history[0].message.as_deref(),
+      Some("2 file(s) failed to save")
+    );
+    assert_eq!(history[1].id, "buffer-flush");
+    assert_eq!(history[2].status, BackgroundTaskState::Cancelled);
+  }
+
+  #[test]
+  fn a_newer_run_replaces_and_cancels_the_older_one() {
+    let service = BackgroundTaskService::new(ShutdownToken::new());
+    let first = service
+      .start("export", "Export")
+      .expect("task should start");
+    let second = service
+      .start("export", "Export")
+      .expect("task should start");
+    assert!(first.is_cancelled());
+
+    first.finish(&Ok::<(), String>(()));
+    assert_eq!(service.list().expect("tasks should list").len(), 1);
+    second.finish(&Ok::<(), String>(()));
+    let history = service.history().expect("history should list");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].status, BackgroundTaskState::Succeeded);
+  }
+}
@@ -0,0 +1,253 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::AppHandle;
+use tokio::process::Command;
+use tokio::sync::oneshot;
+
+use crate::models::{CodeInterpreter, CodeRunnerSettings, EventTopic};
+use crate::services::events::FrontendEvent;
+use crate::services::process::{run_process, CapturedOutput};
+use crate::services::search::stable_hash;
+use crate::services::shutdown::ShutdownToken;
+
+const RUNNERS_DIR: &str = "code-runners";
+const SCRIPTS_DIR: &str = "code-runs";
+const SCRIPT_NAME_ATTEMPTS: u32 = 8;
+/// Output kept for write-back, the most recent bytes win. Everything is
+/// still streamed.
+const CAPTURE_LIMIT_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeRunExitEvent {
+  pub id: String,
+  pub exit_code: Option<i32>,
+  pub cancelled: bool,
+  pub output: String,
+  /// Whether the output was written below the block.
+  pub written: bool,
+}
+
+impl FrontendEvent for CodeRunExitEvent {
+  const TOPIC: EventTopic = EventTopic::Process;
+}
+
+#[derive(Debug, Clone)]
+pub struct CodeRunOutcome {
+  pub exit_code: Option<i32>,
+  pub cancelled: bool,
+  pub output: String,
+}
+
+/// Runs note code blocks as captured processes. Running blocks can be
+/// cancelled by id and are killed on shutdown.
+#[derive(Debug)]
+pub struct CodeRunnerService {
+  runs: Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>,
+  next_id: AtomicU64,
+  shutdown: ShutdownToken,
+}
+
+impl CodeRunnerService {
+  pub fn new(shutdown: ShutdownToken) -> Self {
+    Self {
+      runs: Arc::new(Mutex::new(HashMap::new())),
+      next_id: AtomicU64::new(0),
+      shutdown,
+    }
+  }
+
+  pub fn next_run_id(&self) -> String {
+    format!("run-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+  }
+
+  /// Writes `code` to a new script file under `scripts_parent` (the app
+  /// data dir). The name is unpredictable and the file is created
+  /// exclusively, so nothing else can place or swap the script that runs.
+  pub fn write_script(
+    &self,
+    scripts_parent: &Path,
+    id: &str,
+    interpreter: &CodeInterpreter,
+    code: &str,
+  ) -> Result<PathBuf, String> {
+    let dir = scripts_parent.join(SCRIPTS_DIR);
+    std::fs::create_dir_all(&dir).map_err(|err| format!("Failed to create script dir: {err}"))?;
+    let extension = interpreter.extension.trim_start_matches('.');
+    for _ in 0..SCRIPT_NAME_ATTEMPTS {
+      let path = dir.join(format!("{id}-{:016x}.{extension}", script_nonce()));
+      let mut file = match script_options().open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+        Err(err) => return Err(format!("Failed to create script: {err}")),
+      };
+      let written = file
+        .write_all(code.as_bytes())
+        .and_then(|()| file.write_all(b"\n"));
+      if let Err(err) = written {
+        drop(file);
+        let _ = std::fs::remove_file(&path);
+        return Err(format!("Failed to write script: {err}"));
+      }
+      return Ok(path);
+    }
+    Err("Failed to create a unique script file".to_string())
+  }
+
+  /// Registers a run so [`Self::cancel`] can stop it. Must be called before
+  /// the run id is handed out.
+  pub fn register(&self, id: &str) -> Result<oneshot::Receiver<()>, String> {
+    let (sender, receiver) = oneshot::channel();
+    self
+      .runs
+      .lock()
+      .map_err(|_| "Failed to lock code runs")?
+      .insert(id.to_string(), sender);
+    Ok(receiver)
+  }
+
+  /// Runs `script` with `interpreter` in `cwd`, streaming `code-run-output`
+  /// events until it exits or is cancelled. The script is removed afterwards.
+  pub async fn run_captured(
+    &self,
+    app: AppHandle,
+    id: &str,
+    cancel: oneshot::Receiver<()>,
+    interpreter: &CodeInterpreter,
+    script: &Path,
+    cwd: &Path,
+  ) -> Result<CodeRunOutcome, String> {
+    let mut command = Command::new(&interpreter.program);
+    command.args(&interpreter.args).arg(script).current_dir(cwd);
+    let captured = Arc::new(Mutex::new(CapturedOutput::new(CAPTURE_LIMIT_BYTES)));
+    let result = run_process(
+      app,
+      "code-run-output",
+      id,
+      command,
+      cancel,
+      Arc::clone(&captured),
+      &self.shutdown,
+    )
+    .await;
+    if let Ok(mut runs) = self.runs.lock() {
+      runs.remove(id);
+    }
+    let _ = std::fs::remove_file(script);
+    let outcome = result?;
+    let output = captured
+      .lock()
+      .map_err(|_| "Failed to lock captured output")?
+      .text()
+      .to_string();
+    Ok(CodeRunOutcome {
+      exit_code: outcome.exit_code,
+      cancelled: outcome.cancelled,
+      output,
+    })
+  }
+
+  pub fn cancel(&self, id: &str) -> Result<(), String> {
+    let sender = self
+      .runs
+      .lock()
+      .map_err(|_| "Failed to lock code runs")?
+      .remove(id)
+      .ok_or_else(|| format!("Code run not found: {id}"))?;
+    let _ = sender.send(());
+    Ok(())
+  }
+}
+
+fn script_options() -> OpenOptions {
+  let mut options = OpenOptions::new();
+  options.write(true).create_new(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+  }
+  options
+}
+
+/// Random bits for script names, from the std hasher's per-process keys.
+fn script_nonce() -> u64 {
+  let mut hasher = RandomState::new().build_hasher();
+  hasher.write_u128(
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|duration| duration.as_nanos())
+      .unwrap_or_default(),
+  );
+  hasher.finish()
+}
+
+/// The interpreter for a fence language such as `bash` or `python {run}`.
+pub fn interpreter_for_language<'a>(
+  settings: &'a CodeRunnerSettings,
+  language: &str,
+) -> Result<&'a CodeInterpreter, String> {
+  let language = language
+    .split(|char: char| char.is_whitespace() || matches!(char, ',' | '{'))
+    .next()
+    .unwrap_or_default()
+    .to_lowercase();
+  settings
+    .interpreters
+    .get(&language)
+    .filter(|interpreter| !interpreter.program.trim().is_empty())
+    .ok_or_else(|| format!("No interpreter configured for {language}"))
+}
+
+/// Interpreters of the workspace at `root`, kept under `runners_parent` (the
+/// app data dir) rather than in the workspace: a cloned repository must not
+/// be able to choose the program a code block runs with.
+pub fn read_code_runner_settings(
+  runners_parent: &Path,
+  root: &Path,
+) -> Result<CodeRunnerSettings, String> {
+  match std::fs::read_to_string(runners_file(runners_parent, root)) {
+    Ok(content) => serde_json::from_str(&content)
+      .map_err(|err| format!("Failed to parse code runner settings: {err}")),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(CodeRunnerSettings::default()),
+    Err(err) => Err(format!("Failed to read code runner settings: {err}")),
+  }
+}
+
+pub fn write_code_runner_settings(
+  runners_parent: &Path,
+  root: &Path,
+  settings: &CodeRunnerSettings,
+) -> Result<(), String> {
+  for (language, interpreter) in &settings.interpreters {
+    if language.is_empty() || *language != language.to_lowercase() {
+      return Err(format!("Languages must be lowercase: {language}"));
+    }
+    if interpreter.program.trim().is_empty() {
+      return Err(format!("Interpreter for {language} needs a program"));
+    }
+  }
+  let path = runners_file(runners_parent, root);
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir).map_err(|err| format!("Failed to create dir: {err}"))?;
+  }
+  let content = serde_json::to_string_pretty(settings)
+    .map_err(|err| format!("Failed to serialize code runner settings: {err}"))?;
+  std::fs::write(path, content)
+    .map_err(|err| format!("Failed to write code runner settings: {err}"))
+}
+
+fn runners_file(runners_parent: &Path, root: &Path) -> PathBuf {
+  runners_parent.join(RUNNERS_DIR).join(format!(
+    "{:016x}.json",
+    stable_hash(&root.to_string_lossy())
+  ))
+}
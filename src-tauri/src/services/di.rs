@@ -1,23 +1,43 @@
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use fluxdi::{Application, Error, Injector, Module, ModuleLifecycleFuture, Provider, Shared};
 
 use super::{
+  background_tasks::BackgroundTaskService,
+  code_runner::CodeRunnerService,
   document_store::DocumentStoreService,
   events::{EventBus, RuntimeService},
+  file_history::FileHistoryService,
   git::GitService,
   markdown_assets::MarkdownAssetService,
   markdown_graph::MarkdownGraphService,
   markdown_index::MarkdownIndexService,
   path_resolver::PathResolver,
+  recovery::RecoveryJournalService,
   search::SearchService,
+  session::SessionService,
+  shutdown::ShutdownToken,
+  snapshot::SnapshotService,
+  tasks::TaskRunnerService,
   terminal::TerminalService,
   workspace::WorkspaceService,
   AppServices, ExportService,
 };
 
+/// How long shutdown waits for background workers to stop.
+const WORKER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
 struct AppModule;
 
+fn shutdown_token(injector: &Injector) -> ShutdownToken {
+  injector
+    .try_resolve::<RuntimeService>()
+    .expect("RuntimeService should be registered before its workers")
+    .shutdown_token()
+}
+
 impl Module for AppModule {
   fn configure(&self, injector: &Injector) -> Result<(), Error> {
     injector.try_provide::<ExportService>(Provider::root(|_| Shared::new(ExportService)))?;
@@ -29,7 +49,20 @@ impl Module for AppModule {
           .expect("EventBus should be registered before RuntimeService"),
       ))
     }))?;
+    injector.try_provide::<BackgroundTaskService>(Provider::root(|injector| {
+      Shared::new(BackgroundTaskService::new(shutdown_token(injector)))
+    }))?;
     injector.try_provide::<GitService>(Provider::root(|_| Shared::new(GitService)))?;
+    injector.try_provide::<FileHistoryService>(Provider::root(|_| {
+      Shared::new(FileHistoryService::new())
+    }))?;
+    injector.try_provide::<RecoveryJournalService>(Provider::root(|_| {
+      Shared::new(RecoveryJournalService::new())
+    }))?;
+    injector
+      .try_provide::<SessionService>(Provider::root(|_| Shared::new(SessionService::new())))?;
+    injector
+      .try_provide::<SnapshotService>(Provider::root(|_| Shared::new(SnapshotService::new())))?;
     injector.try_provide::<PathResolver>(Provider::root(|_| Shared::new(PathResolver)))?;
     injector.try_provide::<MarkdownAssetService>(Provider::root(|injector| {
       Shared::new(MarkdownAssetService::new(
@@ -50,8 +83,15 @@ impl Module for AppModule {
     injector
       .try_provide::<MarkdownGraphService>(Provider::root(|_| Shared::new(MarkdownGraphService)))?;
     injector.try_provide::<SearchService>(Provider::root(|_| Shared::new(SearchService::new())))?;
-    injector
-      .try_provide::<TerminalService>(Provider::root(|_| Shared::new(TerminalService::new())))?;
+    injector.try_provide::<CodeRunnerService>(Provider::root(|injector| {
+      Shared::new(CodeRunnerService::new(shutdown_token(injector)))
+    }))?;
+    injector.try_provide::<TaskRunnerService>(Provider::root(|injector| {
+      Shared::new(TaskRunnerService::new(shutdown_token(injector)))
+    }))?;
+    injector.try_provide::<TerminalService>(Provider::root(|injector| {
+      Shared::new(TerminalService::new(shutdown_token(injector)))
+    }))?;
     injector.try_provide::<WorkspaceService>(Provider::root(|injector| {
       let path_resolver = injector
         .try_resolve::<PathResolver>()
@@ -104,9 +144,28 @@ impl Module for AppModule {
 #[derive(Clone)]
 pub struct AppLifecycle {
   application: Shared<Mutex<Option<Application>>>,
+  runtime: Shared<RuntimeService>,
+  close_blocked: Arc<AtomicBool>,
+  closing: Arc<AtomicBool>,
 }
 
 impl AppLifecycle {
+  /// Returns `true` the first time it is called, so a close that could not
+  /// save everything is held back once and a second close goes through.
+  pub fn block_close_once(&self) -> bool {
+    !self.close_blocked.swap(true, Ordering::SeqCst)
+  }
+
+  /// Returns `false` while an earlier close is still saving or shutting
+  /// down, so repeated close requests do not start it twice.
+  pub fn begin_close(&self) -> bool {
+    !self.closing.swap(true, Ordering::SeqCst)
+  }
+
+  pub fn end_close(&self) {
+    self.closing.store(false, Ordering::SeqCst);
+  }
+
   pub fn shutdown_blocking(&self) -> Result<(), Error> {
     let application = {
       let mut guard = self.application.lock().expect("lock app lifecycle");
@@ -115,6 +174,12 @@ impl AppLifecycle {
 
     if let Some(mut application) = application {
       futures::executor::block_on(application.shutdown())?;
+      if !self.runtime.wait_for_workers(WORKER_SHUTDOWN_TIMEOUT) {
+        log::warn!(
+          "{} background workers still running after shutdown",
+          self.runtime.shutdown_token().active_workers()
+        );
+      }
     }
     Ok(())
   }
@@ -131,12 +196,19 @@ pub async fn build_app_container() -> Result<AppContainer, Error> {
 
   let injector = app.injector();
   let services = AppServices {
+    background_tasks: injector.try_resolve::<BackgroundTaskService>()?,
+    code_runner: injector.try_resolve::<CodeRunnerService>()?,
     export: injector.try_resolve::<ExportService>()?,
     documents: injector.try_resolve::<DocumentStoreService>()?,
     events: injector.try_resolve::<EventBus>()?,
+    file_history: injector.try_resolve::<FileHistoryService>()?,
     git: injector.try_resolve::<GitService>()?,
     markdown_assets: injector.try_resolve::<MarkdownAssetService>()?,
+    recovery: injector.try_resolve::<RecoveryJournalService>()?,
     runtime: injector.try_resolve::<RuntimeService>()?,
+    session: injector.try_resolve::<SessionService>()?,
+    snapshots: injector.try_resolve::<SnapshotService>()?,
+    tasks: injector.try_resolve::<TaskRunnerService>()?,
     terminal: injector.try_resolve::<TerminalService>()?,
     workspace: injector.try_resolve::<WorkspaceService>()?,
   };
@@ -144,6 +216,9 @@ pub async fn build_app_container() -> Result<AppContainer, Error> {
   Ok(AppContainer {
     lifecycle: AppLifecycle {
       application: Shared::new(Mutex::new(Some(app))),
+      runtime: services.runtime.clone(),
+      close_blocked: Arc::new(AtomicBool::new(false)),
+      closing: Arc::new(AtomicBool::new(false)),
     },
     services,
   })
@@ -166,6 +241,7 @@ mod tests {
       root_path: std::path::PathBuf::new(),
       internal_root: std::path::PathBuf::new(),
       single_file: None,
+      mounted_roots: Vec::new(),
     }));
 
     let index = services
@@ -175,4 +251,58 @@ mod tests {
       .expect("workspace index should resolve through WorkspaceService");
     assert!(index.files.is_empty());
   }
+
+  #[tokio::test(flavor = "multi_thread")]
+  async fn shutdown_stops_every_worker() {
+    use tauri::Manager;
+
+    use crate::commands::fs::{
+      start_buffer_flush_worker, start_fs_watcher, start_recovery_journal_worker,
+      start_snapshot_worker,
+    };
+    use crate::state::{FsState, FsStateData, FsWatcherState};
+
+    let root = std::env::temp_dir().join(format!("marko-shutdown-{}", std::process::id()));
+    std::fs::create_dir_all(&root).expect("create workspace root");
+    let container = build_app_container()
+      .await
+      .expect("app services should resolve from fluxdi");
+    let token = container.services.runtime.shutdown_token();
+
+    let app = tauri::test::mock_app();
+    app.manage(container.services);
+    app.manage(container.lifecycle);
+    app.manage(FsState(std::sync::RwLock::new(FsStateData {
+      root_kind: "internal".to_string(),
+      root_path: root.clone(),
+      internal_root: root.clone(),
+      single_file: None,
+      mounted_roots: Vec::new(),
+    })));
+    app.manage(FsWatcherState(Mutex::new(std::collections::HashMap::new())));
+    let handle = app.handle().clone();
+
+    start_buffer_flush_worker(&handle);
+    start_recovery_journal_worker(&handle);
+    start_snapshot_worker(&handle);
+    start_fs_watcher(
+      &handle,
+      &app.state::<FsState>(),
+      &app.state::<FsWatcherState>(),
+    )
+    .expect("watcher should start");
+    assert_eq!(token.active_workers(), 4);
+
+    let started = std::time::Instant::now();
+    let shutdown_handle = handle.clone();
+    tokio::task::spawn_blocking(move || crate::commands::app::shutdown_app(&shutdown_handle))
+      .await
+      .expect("shutdown should not panic");
+
+    assert!(token.is_cancelled());
+    assert_eq!(token.active_workers(), 0);
+    assert!(started.elapsed() < WORKER_SHUTDOWN_TIMEOUT);
+    assert!(app.state::<FsWatcherState>().0.lock().unwrap().is_empty());
+    let _ = std::fs::remove_dir_all(root);
+  }
 }
@@ -1,45 +1,61 @@
 mod cache;
+mod durable;
+mod encoding;
 mod entry;
 mod flush;
+mod lru;
+mod merge;
 
 #[cfg(test)]
 mod tests;
 
-use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use fluxdi::Shared;
 
-use crate::models::{FsBufferStatus, FsEntry};
+use crate::error::{AppError, AppResult};
+
+use crate::models::{
+  DocumentCacheStats, FsBufferStatus, FsEntry, FsExternalConflict, FsTextFormat,
+};
 use crate::services::markdown_index::ParsedMarkdownDocument;
 use crate::services::path_resolver::PathResolver;
 use crate::state::{FsState, FsStateData};
 
 use self::cache::{
   cache_clean_document, cache_clean_document_snapshot, clear_clean_document_count,
-  clear_clean_documents, clear_documents, insert_clean_document, invalidate_clean_document_paths,
-  is_workspace_root_path, parsed_markdown_documents_for_snapshots, read_from_document_store,
-  remove_document_path, rename_document_path, snapshot_from_document_store, status_from_document,
-  upsert_document,
+  clear_clean_documents, clear_documents, convert_document_format,
+  dirty_snapshots_from_document_store, external_conflict_from_document_store,
+  insert_clean_document, invalidate_clean_document_paths, is_workspace_root_path,
+  parsed_markdown_documents_for_snapshots, read_from_document_store, remove_document_path,
+  rename_document_path, resolve_external_conflict, snapshot_from_document_store,
+  status_from_document, upsert_document,
 };
+pub(crate) use self::encoding::decode_text;
+use self::encoding::normalize_text_format;
 pub use self::entry::DocumentSnapshot;
-use self::entry::DocumentStoreEntry;
 use self::flush::{
   flush_all_documents_with_status_async_for_resolver, flush_all_documents_with_status_for_resolver,
+  write_durable_async, FlushOutcome,
 };
+use self::lru::{CacheMetrics, DocumentMap};
 
 #[derive(Debug, Clone)]
 pub struct DocumentStoreService {
   path_resolver: Shared<PathResolver>,
-  documents: Arc<Mutex<HashMap<String, DocumentStoreEntry>>>,
+  documents: Arc<Mutex<DocumentMap>>,
+  external_conflicts: Arc<Mutex<Vec<FsExternalConflict>>>,
+  metrics: Arc<CacheMetrics>,
 }
 
 impl DocumentStoreService {
   pub fn new(path_resolver: Shared<PathResolver>) -> Self {
     Self {
       path_resolver,
-      documents: Arc::new(Mutex::new(HashMap::new())),
+      documents: Arc::new(Mutex::new(DocumentMap::default())),
+      external_conflicts: Arc::new(Mutex::new(Vec::new())),
+      metrics: Arc::new(CacheMetrics::default()),
     }
   }
 }
@@ -51,27 +67,27 @@ impl Default for DocumentStoreService {
 }
 
 impl DocumentStoreService {
-  pub fn clear(&self) -> Result<(), String> {
+  pub fn clear(&self) -> AppResult<()> {
     clear_documents(&self.documents)
   }
 
-  pub fn cached_content(&self, path: &str) -> Result<Option<String>, String> {
+  pub fn cached_content(&self, path: &str) -> AppResult<Option<String>> {
     read_from_document_store(path, &self.documents)
   }
 
-  pub fn cached_snapshot(&self, path: &str) -> Result<Option<DocumentSnapshot>, String> {
+  pub fn cached_snapshot(&self, path: &str) -> AppResult<Option<DocumentSnapshot>> {
     snapshot_from_document_store(path, &self.documents)
   }
 
-  pub async fn read_document(&self, state: &FsState, path: &str) -> Result<String, String> {
-    if let Some(content) = self.cached_content(path)? {
-      return Ok(content);
+  pub async fn read_document(&self, state: &FsState, path: &str) -> AppResult<String> {
+    if let Some(snapshot) = self.cached_read(path)? {
+      return Ok(snapshot.content);
     }
 
     let data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
     self.read_document_from_data(&data, path).await
   }
@@ -80,7 +96,7 @@ impl DocumentStoreService {
     &self,
     data: &FsStateData,
     files: &[FsEntry],
-  ) -> Result<Vec<DocumentSnapshot>, String> {
+  ) -> AppResult<Vec<DocumentSnapshot>> {
     let mut documents = Vec::with_capacity(files.len());
     for file in files {
       let snapshot = self
@@ -94,8 +110,8 @@ impl DocumentStoreService {
   pub(crate) fn parsed_markdown_documents_for_snapshots(
     &self,
     snapshots: &[DocumentSnapshot],
-  ) -> Result<Vec<ParsedMarkdownDocument>, String> {
-    parsed_markdown_documents_for_snapshots(&self.documents, snapshots)
+  ) -> AppResult<Vec<ParsedMarkdownDocument>> {
+    parsed_markdown_documents_for_snapshots(&self.documents, &self.metrics, snapshots)
   }
 
   pub fn update_document(
@@ -103,25 +119,74 @@ impl DocumentStoreService {
     state: &FsState,
     path: &str,
     content: &str,
-  ) -> Result<FsBufferStatus, String> {
+  ) -> AppResult<FsBufferStatus> {
     let state_data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
-    let _ = self.path_resolver.resolve(&state_data, path)?;
-    upsert_document(&self.documents, path, content)
+    let resolved = self.path_resolver.resolve(&state_data, path)?;
+    self.restore_evicted(path, &resolved)?;
+    let status = upsert_document(&self.documents, path, content)?;
+    self.touch_and_enforce_budget(path)?;
+    Ok(status)
   }
 
-  pub fn insert_clean(&self, path: &str, content: &str) -> Result<(), String> {
-    insert_clean_document(&self.documents, path, content)
+  /// Replaces a document on disk through the durable write, keeping the
+  /// encoding, BOM and line endings it is saved with, and drops the cached
+  /// copy so the next read loads the new content.
+  pub async fn replace_document(
+    &self,
+    state: &FsState,
+    path: &str,
+    content: String,
+  ) -> AppResult<()> {
+    let data = state
+      .0
+      .read()
+      .map_err(|_| AppError::lock("fs state"))?
+      .clone();
+    let resolved = self.path_resolver.resolve(&data, path)?;
+    let format = self.text_format(path)?;
+    write_durable_async(resolved.clone(), content, format)
+      .await
+      .map_err(|err| AppError::Io {
+        operation: "Failed to write file".to_string(),
+        path: resolved.to_string_lossy().to_string(),
+        reason: err.message,
+      })?;
+    self.remove_path(path)
   }
 
-  pub fn status(&self, path: &str) -> Result<Option<FsBufferStatus>, String> {
+  pub fn cache_stats(&self) -> AppResult<DocumentCacheStats> {
     let documents = self
       .documents
       .lock()
-      .map_err(|_| "Failed to lock document state")?;
+      .map_err(|_| AppError::lock("document state"))?;
+    Ok(self.metrics.stats(&documents))
+  }
+
+  /// Sets the byte budget for cached documents and evicts down to it.
+  pub fn set_cache_budget(&self, budget_bytes: u64) -> AppResult<DocumentCacheStats> {
+    self.metrics.set_budget_bytes(budget_bytes);
+    let mut documents = self
+      .documents
+      .lock()
+      .map_err(|_| AppError::lock("document state"))?;
+    self.metrics.enforce_budget(&mut documents);
+    Ok(self.metrics.stats(&documents))
+  }
+
+  pub fn insert_clean(&self, path: &str, content: &str) -> AppResult<()> {
+    insert_clean_document(&self.documents, path, content)?;
+    self.touch_and_enforce_budget(path)
+  }
+
+  pub fn status(&self, path: &str) -> AppResult<Option<FsBufferStatus>> {
+    let documents = self
+      .documents
+      .lock()
+      .map_err(|_| AppError::lock("document state"))?;
     Ok(
       documents
         .get(path)
@@ -129,11 +194,11 @@ impl DocumentStoreService {
     )
   }
 
-  pub fn remove_path(&self, path: &str) -> Result<(), String> {
+  pub fn remove_path(&self, path: &str) -> AppResult<()> {
     remove_document_path(&self.documents, path)
   }
 
-  pub fn clear_clean(&self) -> Result<(), String> {
+  pub fn clear_clean(&self) -> AppResult<()> {
     clear_clean_documents(&self.documents)
   }
 
@@ -141,11 +206,11 @@ impl DocumentStoreService {
     &self,
     state: &FsState,
     absolute_paths: &[PathBuf],
-  ) -> Result<usize, String> {
+  ) -> AppResult<usize> {
     let data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
     if absolute_paths
       .iter()
@@ -161,59 +226,205 @@ impl DocumentStoreService {
     invalidate_clean_document_paths(&self.documents, &paths)
   }
 
-  pub fn rename_path(&self, from: &str, to: &str) -> Result<(), String> {
+  pub fn rename_path(&self, from: &str, to: &str) -> AppResult<()> {
     rename_document_path(&self.documents, from, to)
   }
 
-  pub fn has_dirty(&self) -> Result<bool, String> {
+  /// Whether a flush has anything to write. Buffers held by an external
+  /// conflict wait for the conflict to be resolved, and buffers that failed
+  /// permanently wait for the next edit.
+  pub fn has_pending_writes(&self) -> AppResult<bool> {
     let documents = self
       .documents
       .lock()
-      .map_err(|_| "Failed to lock document state")?;
-    Ok(documents.values().any(|entry| entry.dirty))
+      .map_err(|_| AppError::lock("document state"))?;
+    let pending = documents.values().any(|entry| entry.awaits_write());
+    Ok(pending)
   }
 
-  pub fn flush_all_with_status(&self, state: &FsState) -> Result<Vec<FsBufferStatus>, String> {
-    flush_all_documents_with_status_for_resolver(&self.path_resolver, &self.documents, state)
+  /// Encoding, BOM and line endings the document is saved with, detected
+  /// when it was loaded.
+  pub fn text_format(&self, path: &str) -> AppResult<Option<FsTextFormat>> {
+    let documents = self
+      .documents
+      .lock()
+      .map_err(|_| AppError::lock("document state"))?;
+    Ok(documents.get(path).and_then(|entry| entry.format.clone()))
+  }
+
+  /// Switches the format a document is saved with and marks it dirty so the
+  /// next flush rewrites the file.
+  pub async fn convert_text_format(
+    &self,
+    state: &FsState,
+    path: &str,
+    format: FsTextFormat,
+  ) -> AppResult<FsBufferStatus> {
+    let format = normalize_text_format(format)?;
+    self.read_document(state, path).await?;
+    convert_document_format(&self.documents, path, format)
+  }
+
+  pub fn dirty_snapshots(&self) -> AppResult<Vec<DocumentSnapshot>> {
+    dirty_snapshots_from_document_store(&self.documents)
+  }
+
+  pub fn flush_all_with_status(&self, state: &FsState) -> AppResult<Vec<FsBufferStatus>> {
+    let outcome =
+      flush_all_documents_with_status_for_resolver(&self.path_resolver, &self.documents, state)?;
+    self.queue_external_conflicts(outcome)
   }
 
   pub async fn flush_all_with_status_async(
     &self,
     state: &FsState,
-  ) -> Result<Vec<FsBufferStatus>, String> {
-    flush_all_documents_with_status_async_for_resolver(&self.path_resolver, &self.documents, state)
-      .await
+  ) -> AppResult<Vec<FsBufferStatus>> {
+    let outcome = flush_all_documents_with_status_async_for_resolver(
+      &self.path_resolver,
+      &self.documents,
+      state,
+    )
+    .await?;
+    self.queue_external_conflicts(outcome)
   }
 
-  async fn read_document_from_data(
+  /// Drains conflicts detected by flushes since the last call. Their buffers
+  /// stay dirty and are not flushed until resolved.
+  pub fn take_external_conflicts(&self) -> AppResult<Vec<FsExternalConflict>> {
+    let mut conflicts = self
+      .external_conflicts
+      .lock()
+      .map_err(|_| AppError::lock("document conflicts"))?;
+    Ok(std::mem::take(&mut *conflicts))
+  }
+
+  pub fn external_conflict(&self, path: &str) -> AppResult<Option<FsExternalConflict>> {
+    external_conflict_from_document_store(&self.documents, path)
+  }
+
+  pub fn resolve_external_conflict(
     &self,
-    data: &FsStateData,
     path: &str,
-  ) -> Result<String, String> {
-    if let Some(content) = self.cached_content(path)? {
-      return Ok(content);
+    choice: &str,
+    content: Option<&str>,
+  ) -> AppResult<FsBufferStatus> {
+    resolve_external_conflict(&self.documents, path, choice, content)
+  }
+
+  fn queue_external_conflicts(
+    &self,
+    (statuses, conflicts): FlushOutcome,
+  ) -> AppResult<Vec<FsBufferStatus>> {
+    if !conflicts.is_empty() {
+      self
+        .external_conflicts
+        .lock()
+        .map_err(|_| AppError::lock("document conflicts"))?
+        .extend(conflicts);
+    }
+    Ok(statuses)
+  }
+
+  async fn read_document_from_data(&self, data: &FsStateData, path: &str) -> AppResult<String> {
+    if let Some(snapshot) = self.cached_read(path)? {
+      return Ok(snapshot.content);
     }
 
+    self.metrics.record_miss();
     let resolved = self.path_resolver.resolve(data, path)?;
-    let content = tokio::fs::read_to_string(resolved)
-      .await
-      .map_err(|err| format!("Failed to read file: {err}"))?;
-    cache_clean_document(&self.documents, path, &content)
+    let modified = modified_time(&resolved).await;
+    let (content, format) = read_decoded(&resolved).await?;
+    let content = cache_clean_document(&self.documents, path, &content, modified, format)?;
+    self.touch_and_enforce_budget(path)?;
+    Ok(content)
   }
 
   async fn read_document_snapshot_from_data(
     &self,
     data: &FsStateData,
     path: &str,
-  ) -> Result<DocumentSnapshot, String> {
-    if let Some(snapshot) = self.cached_snapshot(path)? {
+  ) -> AppResult<DocumentSnapshot> {
+    if let Some(snapshot) = self.cached_read(path)? {
       return Ok(snapshot);
     }
 
+    self.metrics.record_miss();
     let resolved = self.path_resolver.resolve(data, path)?;
-    let content = tokio::fs::read_to_string(resolved)
-      .await
-      .map_err(|err| format!("Failed to read file: {err}"))?;
-    cache_clean_document_snapshot(&self.documents, path, &content)
+    let modified = modified_time(&resolved).await;
+    let (content, format) = read_decoded(&resolved).await?;
+    let snapshot =
+      cache_clean_document_snapshot(&self.documents, path, &content, modified, format)?;
+    self.touch_and_enforce_budget(path)?;
+    Ok(snapshot)
+  }
+
+  /// Returns a cached document as a cache hit and marks it recently used.
+  fn cached_read(&self, path: &str) -> AppResult<Option<DocumentSnapshot>> {
+    let mut documents = self
+      .documents
+      .lock()
+      .map_err(|_| AppError::lock("document state"))?;
+    let Some(mut entry) = documents.get_mut(path) else {
+      return Ok(None);
+    };
+    entry.last_access = self.metrics.tick();
+    self.metrics.record_hit();
+    Ok(Some(DocumentSnapshot {
+      path: path.to_string(),
+      content: entry.content.clone(),
+      content_hash: entry.content_hash,
+    }))
   }
+
+  /// Reloads an evicted document before it is edited, so the edit keeps its
+  /// base content. If the file changed since the eviction it stays evicted
+  /// and the next flush reports the conflict.
+  fn restore_evicted(&self, path: &str, resolved: &std::path::Path) -> AppResult<()> {
+    let evicted = self
+      .documents
+      .lock()
+      .map_err(|_| AppError::lock("document state"))?
+      .is_evicted(path);
+    if !evicted {
+      return Ok(());
+    }
+    let Some((content, _)) = std::fs::read(resolved)
+      .ok()
+      .and_then(|bytes| decode_text(&bytes).ok())
+    else {
+      return Ok(());
+    };
+    self
+      .documents
+      .lock()
+      .map_err(|_| AppError::lock("document state"))?
+      .restore_evicted(path, &content);
+    Ok(())
+  }
+
+  fn touch_and_enforce_budget(&self, path: &str) -> AppResult<()> {
+    let mut documents = self
+      .documents
+      .lock()
+      .map_err(|_| AppError::lock("document state"))?;
+    if let Some(mut entry) = documents.get_mut(path) {
+      entry.last_access = self.metrics.tick();
+    }
+    self.metrics.enforce_budget(&mut documents);
+    Ok(())
+  }
+}
+
+async fn read_decoded(path: &std::path::Path) -> AppResult<(String, FsTextFormat)> {
+  let bytes = tokio::fs::read(path)
+    .await
+    .map_err(|err| AppError::io("Failed to read file", path, err))?;
+  decode_text(&bytes)
+}
+
+async fn modified_time(path: &std::path::Path) -> Option<std::time::SystemTime> {
+  tokio::fs::metadata(path)
+    .await
+    .and_then(|metadata| metadata.modified())
+    .ok()
 }
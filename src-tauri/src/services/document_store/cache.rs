@@ -1,42 +1,46 @@
-use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Mutex;
+use std::time::SystemTime;
 
 use path_clean::PathClean;
 
-use crate::models::FsBufferStatus;
+use crate::error::{AppError, AppResult};
+
+use crate::models::{FsBufferStatus, FsExternalConflict, FsTextFormat};
 use crate::services::markdown_index::{parse_markdown_document, ParsedMarkdownDocument};
+use crate::services::path_resolver::is_same_or_child;
 use crate::state::FsStateData;
 
-use super::entry::{DocumentSnapshot, DocumentStoreEntry, ParsedMarkdownCache};
+use super::encoding::encode_text;
+use super::entry::{DiskState, DocumentSnapshot, DocumentStoreEntry, ParsedMarkdownCache};
+use super::lru::{CacheMetrics, DocumentMap};
+use super::merge::three_way_merge;
 
-pub(super) fn clear_documents(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
-) -> Result<(), String> {
+pub(super) fn clear_documents(documents: &Mutex<DocumentMap>) -> AppResult<()> {
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   documents.clear();
   Ok(())
 }
 
 pub(super) fn read_from_document_store(
   path: &str,
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
-) -> Result<Option<String>, String> {
+  documents: &Mutex<DocumentMap>,
+) -> AppResult<Option<String>> {
   let documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   Ok(documents.get(path).map(|entry| entry.content.clone()))
 }
 
 pub(super) fn snapshot_from_document_store(
   path: &str,
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
-) -> Result<Option<DocumentSnapshot>, String> {
+  documents: &Mutex<DocumentMap>,
+) -> AppResult<Option<DocumentSnapshot>> {
   let documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   Ok(
     documents
       .get(path)
@@ -44,18 +48,37 @@ pub(super) fn snapshot_from_document_store(
   )
 }
 
+pub(super) fn dirty_snapshots_from_document_store(
+  documents: &Mutex<DocumentMap>,
+) -> AppResult<Vec<DocumentSnapshot>> {
+  let documents = documents
+    .lock()
+    .map_err(|_| AppError::lock("document state"))?;
+  Ok(
+    documents
+      .iter()
+      .filter(|(_, entry)| entry.dirty)
+      .map(|(path, entry)| snapshot_from_document(path, entry))
+      .collect(),
+  )
+}
+
 pub(super) fn parsed_markdown_documents_for_snapshots(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
+  documents: &Mutex<DocumentMap>,
+  metrics: &CacheMetrics,
   snapshots: &[DocumentSnapshot],
-) -> Result<Vec<ParsedMarkdownDocument>, String> {
+) -> AppResult<Vec<ParsedMarkdownDocument>> {
   let mut parsed_documents = vec![None; snapshots.len()];
   let mut misses = Vec::new();
 
   {
-    let documents = documents
+    let mut documents = documents
       .lock()
-      .map_err(|_| "Failed to lock document state")?;
+      .map_err(|_| AppError::lock("document state"))?;
     for (index, snapshot) in snapshots.iter().enumerate() {
+      if let Some(mut entry) = documents.get_mut(&snapshot.path) {
+        entry.last_access = metrics.tick();
+      }
       if let Some(document) = documents
         .get(&snapshot.path)
         .and_then(|entry| entry.parsed_markdown.as_ref())
@@ -69,11 +92,12 @@ pub(super) fn parsed_markdown_documents_for_snapshots(
     }
   }
 
+  metrics.record_parsed(snapshots.len() - misses.len(), misses.len());
   if misses.is_empty() {
     return parsed_documents
       .into_iter()
       .collect::<Option<Vec<_>>>()
-      .ok_or_else(|| "Failed to read parsed markdown cache".to_string());
+      .ok_or_else(|| AppError::from("Failed to read parsed markdown cache"));
   }
 
   let parsed_misses = misses
@@ -89,10 +113,10 @@ pub(super) fn parsed_markdown_documents_for_snapshots(
 
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   for (index, document) in parsed_misses {
     let snapshot = &snapshots[index];
-    if let Some(entry) = documents.get_mut(&snapshot.path) {
+    if let Some(mut entry) = documents.get_mut(&snapshot.path) {
       if entry.content_hash == snapshot.content_hash {
         entry.parsed_markdown = Some(ParsedMarkdownCache {
           content_hash: snapshot.content_hash,
@@ -102,89 +126,218 @@ pub(super) fn parsed_markdown_documents_for_snapshots(
     }
     parsed_documents[index] = Some(document);
   }
+  metrics.enforce_budget(&mut documents);
 
   parsed_documents
     .into_iter()
     .collect::<Option<Vec<_>>>()
-    .ok_or_else(|| "Failed to read parsed markdown cache".to_string())
+    .ok_or_else(|| AppError::from("Failed to read parsed markdown cache"))
 }
 
 pub(super) fn upsert_document(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
+  documents: &Mutex<DocumentMap>,
   path: &str,
   content: &str,
-) -> Result<FsBufferStatus, String> {
+) -> AppResult<FsBufferStatus> {
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
 
-  let entry = documents
-    .entry(path.to_string())
-    .or_insert_with(DocumentStoreEntry::empty);
+  let mut entry = documents.get_or_insert(path);
   if entry.content == content {
-    return Ok(status_from_document(path, entry));
+    return Ok(status_from_document(path, &entry));
+  }
+  if !entry.dirty && entry.disk.is_some() {
+    entry.base_content = Some(entry.content.clone());
   }
   entry.update_content(content);
   entry.revision = entry.revision.saturating_add(1);
   entry.dirty = entry.revision != entry.saved_revision;
-  Ok(status_from_document(path, entry))
+  Ok(status_from_document(path, &entry))
 }
 
 pub(super) fn insert_clean_document(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
+  documents: &Mutex<DocumentMap>,
   path: &str,
   content: &str,
-) -> Result<(), String> {
+) -> AppResult<()> {
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   documents.insert(path.to_string(), DocumentStoreEntry::clean(content));
   Ok(())
 }
 
 pub(super) fn cache_clean_document(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
+  documents: &Mutex<DocumentMap>,
   path: &str,
   content: &str,
-) -> Result<String, String> {
+  modified: Option<SystemTime>,
+  format: FsTextFormat,
+) -> AppResult<String> {
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   if let Some(entry) = documents.get(path) {
     return Ok(entry.content.clone());
   }
 
-  documents.insert(path.to_string(), DocumentStoreEntry::clean(content));
+  documents.insert(
+    path.to_string(),
+    DocumentStoreEntry::loaded(content, modified, format),
+  );
   Ok(content.to_string())
 }
 
 pub(super) fn cache_clean_document_snapshot(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
+  documents: &Mutex<DocumentMap>,
   path: &str,
   content: &str,
-) -> Result<DocumentSnapshot, String> {
+  modified: Option<SystemTime>,
+  format: FsTextFormat,
+) -> AppResult<DocumentSnapshot> {
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   if let Some(entry) = documents.get(path) {
     return Ok(snapshot_from_document(path, entry));
   }
 
-  documents.insert(path.to_string(), DocumentStoreEntry::clean(content));
+  documents.insert(
+    path.to_string(),
+    DocumentStoreEntry::loaded(content, modified, format),
+  );
   let entry = documents
     .get(path)
     .ok_or_else(|| "Failed to cache document".to_string())?;
   Ok(snapshot_from_document(path, entry))
 }
 
+pub(super) fn convert_document_format(
+  documents: &Mutex<DocumentMap>,
+  path: &str,
+  format: FsTextFormat,
+) -> AppResult<FsBufferStatus> {
+  let mut documents = documents
+    .lock()
+    .map_err(|_| AppError::lock("document state"))?;
+  let mut entry = documents
+    .get_mut(path)
+    .ok_or_else(|| AppError::invalid_input(format!("Document is not open: {path}")))?;
+  if entry.external_conflict.is_some() {
+    return Err(AppError::Conflict {
+      path: path.to_string(),
+      reason: "The file changed on disk; resolve that change first".to_string(),
+    });
+  }
+  encode_text(&entry.content, &format)?;
+  if entry.format.as_ref() != Some(&format) {
+    entry.format = Some(format);
+    entry.revision = entry.revision.saturating_add(1);
+    entry.dirty = true;
+  }
+  Ok(status_from_document(path, &entry))
+}
+
 pub(super) fn status_from_document(path: &str, entry: &DocumentStoreEntry) -> FsBufferStatus {
   FsBufferStatus {
     path: path.to_string(),
     revision: entry.revision,
     dirty: entry.dirty,
+    conflict: entry.external_conflict.is_some(),
+    error: None,
   }
 }
 
+pub(super) fn external_conflict_from_document(
+  path: &str,
+  entry: &DocumentStoreEntry,
+) -> Option<FsExternalConflict> {
+  let conflict = entry.external_conflict.as_ref()?;
+  let merged = three_way_merge(
+    entry.base_content.as_deref().unwrap_or_default(),
+    &entry.content,
+    &conflict.disk_content,
+  );
+  Some(FsExternalConflict {
+    path: path.to_string(),
+    base_content: entry.base_content.clone(),
+    local_content: entry.content.clone(),
+    disk_content: conflict.disk_content.clone(),
+    merged_content: merged.content,
+    merge_conflicts: merged.conflicts,
+  })
+}
+
+pub(super) fn external_conflict_from_document_store(
+  documents: &Mutex<DocumentMap>,
+  path: &str,
+) -> AppResult<Option<FsExternalConflict>> {
+  let documents = documents
+    .lock()
+    .map_err(|_| AppError::lock("document state"))?;
+  Ok(
+    documents
+      .get(path)
+      .and_then(|entry| external_conflict_from_document(path, entry)),
+  )
+}
+
+/// Resolves a held flush by keeping the local buffer, taking the disk
+/// version, or applying `content` as the merged result.
+pub(super) fn resolve_external_conflict(
+  documents: &Mutex<DocumentMap>,
+  path: &str,
+  choice: &str,
+  content: Option<&str>,
+) -> AppResult<FsBufferStatus> {
+  let mut documents = documents
+    .lock()
+    .map_err(|_| AppError::lock("document state"))?;
+  if !matches!(choice, "local" | "disk" | "merged") {
+    return Err(AppError::invalid_input(format!(
+      "Unsupported conflict resolution: {choice}"
+    )));
+  }
+  if choice == "merged" && content.is_none() {
+    return Err(AppError::invalid_input("Merged content is required"));
+  }
+  let mut entry = documents
+    .get_mut(path)
+    .ok_or_else(|| AppError::invalid_input(format!("Document is not open: {path}")))?;
+  // Resolved already, for example from another window.
+  let conflict = entry
+    .external_conflict
+    .take()
+    .ok_or_else(|| AppError::Conflict {
+      path: path.to_string(),
+      reason: "No external change is waiting to be resolved".to_string(),
+    })?;
+  entry.disk = Some(DiskState {
+    content_hash: conflict.disk_hash,
+    modified: None,
+  });
+  match (choice, content) {
+    ("disk", _) => {
+      entry.update_content(&conflict.disk_content);
+      entry.revision = entry.revision.saturating_add(1);
+      entry.saved_revision = entry.revision;
+      entry.dirty = false;
+      entry.base_content = None;
+    }
+    ("merged", Some(content)) => {
+      if entry.content != content {
+        entry.update_content(content);
+        entry.revision = entry.revision.saturating_add(1);
+      }
+      entry.dirty = entry.revision != entry.saved_revision;
+      entry.base_content = Some(conflict.disk_content);
+    }
+    _ => entry.base_content = Some(conflict.disk_content),
+  }
+  Ok(status_from_document(path, &entry))
+}
+
 fn snapshot_from_document(path: &str, entry: &DocumentStoreEntry) -> DocumentSnapshot {
   DocumentSnapshot {
     path: path.to_string(),
@@ -193,45 +346,39 @@ fn snapshot_from_document(path: &str, entry: &DocumentStoreEntry) -> DocumentSna
   }
 }
 
-pub(super) fn remove_document_path(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
-  path: &str,
-) -> Result<(), String> {
+pub(super) fn remove_document_path(documents: &Mutex<DocumentMap>, path: &str) -> AppResult<()> {
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   documents.retain(|key, _| !is_same_or_child(key, path));
+  documents.forget_evicted(|key| is_same_or_child(key, path));
   Ok(())
 }
 
-pub(super) fn clear_clean_documents(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
-) -> Result<(), String> {
+pub(super) fn clear_clean_documents(documents: &Mutex<DocumentMap>) -> AppResult<()> {
   clear_clean_document_count(documents).map(|_| ())
 }
 
-pub(super) fn clear_clean_document_count(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
-) -> Result<usize, String> {
+pub(super) fn clear_clean_document_count(documents: &Mutex<DocumentMap>) -> AppResult<usize> {
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   let before = documents.len();
   documents.retain(|_, entry| entry.dirty);
   Ok(before.saturating_sub(documents.len()))
 }
 
 pub(super) fn invalidate_clean_document_paths(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
+  documents: &Mutex<DocumentMap>,
   paths: &[String],
-) -> Result<usize, String> {
+) -> AppResult<usize> {
   if paths.is_empty() {
     return Ok(0);
   }
 
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   let before = documents.len();
   documents
     .retain(|key, entry| entry.dirty || !paths.iter().any(|path| is_same_or_child(key, path)));
@@ -239,14 +386,15 @@ pub(super) fn invalidate_clean_document_paths(
 }
 
 pub(super) fn rename_document_path(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
+  documents: &Mutex<DocumentMap>,
   from: &str,
   to: &str,
-) -> Result<(), String> {
+) -> AppResult<()> {
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
 
+  documents.forget_evicted(|key| is_same_or_child(key, from));
   let keys: Vec<String> = documents.keys().cloned().collect();
   for key in keys {
     if !is_same_or_child(&key, from) {
@@ -271,8 +419,8 @@ pub(super) fn is_workspace_root_path(data: &FsStateData, path: &PathBuf) -> bool
       .unwrap_or(false);
   }
   data.root_path.clean() == path
-}
-
-fn is_same_or_child(path: &str, base: &str) -> bool {
-  path == base || path.starts_with(&format!("{base}/"))
+    || data
+      .mounted_roots
+      .iter()
+      .any(|root| root.path.clean() == path)
 }
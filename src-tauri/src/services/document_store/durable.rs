@@ -0,0 +1,178 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::models::FsTextFormat;
+
+use super::encoding::{decode_text, encode_text};
+use super::entry::stable_hash;
+
+const WRITE_ATTEMPTS: u32 = 3;
+const WRITE_RETRY_BASE_MS: u64 = 50;
+
+/// A failed write. Permanent failures, such as a read-only file, are not
+/// retried until the buffer changes again.
+#[derive(Debug, Clone)]
+pub(super) struct WriteError {
+  pub(super) message: String,
+  pub(super) permanent: bool,
+}
+
+impl WriteError {
+  fn io(context: String, err: &io::Error) -> Self {
+    Self {
+      message: format!("{context}: {err}"),
+      permanent: err.kind() == io::ErrorKind::PermissionDenied,
+    }
+  }
+}
+
+/// What ended up on disk after a durable write.
+#[derive(Debug, Clone)]
+pub(super) struct WrittenFile {
+  pub(super) content_hash: u64,
+  pub(super) modified: Option<SystemTime>,
+  pub(super) format: FsTextFormat,
+}
+
+/// Replaces `path` with `content` via a synced temp file and rename, so a
+/// crash or full disk never leaves a half-written note behind. Content is
+/// encoded with `format`, or the format detected from the existing file,
+/// existing permissions are kept, and transient errors are retried with
+/// backoff.
+pub(super) fn write_durable(
+  path: &Path,
+  content: &str,
+  format: Option<&FsTextFormat>,
+) -> Result<WrittenFile, WriteError> {
+  let existing = fs::read(path).ok();
+  let format = match (format, existing.as_deref()) {
+    (Some(format), _) => format.clone(),
+    (None, Some(existing)) => decode_text(existing)
+      .map(|(_, format)| format)
+      .unwrap_or_default(),
+    (None, None) => FsTextFormat::default(),
+  };
+  // Content the encoding cannot represent fails the same way every time.
+  let bytes = encode_text(content, &format).map_err(|err| WriteError {
+    message: err.to_string(),
+    permanent: true,
+  })?;
+
+  if existing.as_deref() != Some(bytes.as_slice()) {
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)
+        .map_err(|err| WriteError::io("Failed to create dir".to_string(), &err))?;
+    }
+    let mut attempt = 0;
+    loop {
+      match replace_file(path, &bytes) {
+        Ok(()) => break,
+        Err(err) if attempt + 1 < WRITE_ATTEMPTS && is_transient(&err) => {
+          attempt += 1;
+          std::thread::sleep(Duration::from_millis(WRITE_RETRY_BASE_MS << attempt));
+        }
+        Err(err) => {
+          return Err(WriteError::io(
+            format!("Failed to write {}", path.display()),
+            &err,
+          ))
+        }
+      }
+    }
+  }
+
+  Ok(WrittenFile {
+    content_hash: stable_hash(content),
+    modified: fs::metadata(path)
+      .and_then(|metadata| metadata.modified())
+      .ok(),
+    format,
+  })
+}
+
+fn replace_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+  let path = resolve_symlink(path)?;
+  let path = path.as_path();
+  let permissions = match fs::metadata(path) {
+    Ok(metadata) if metadata.permissions().readonly() => {
+      return Err(io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "file is read-only",
+      ));
+    }
+    Ok(metadata) => Some(metadata.permissions()),
+    Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+    Err(err) => return Err(err),
+  };
+
+  let tmp = temp_path(path);
+  let result = write_temp_and_rename(&tmp, path, bytes, permissions);
+  if result.is_err() {
+    let _ = fs::remove_file(&tmp);
+  }
+  result
+}
+
+/// Renaming onto a symlink would replace the link with a regular file and
+/// leave its target untouched, so a linked note is written at its target.
+fn resolve_symlink(path: &Path) -> io::Result<PathBuf> {
+  match fs::symlink_metadata(path) {
+    Ok(metadata) if metadata.file_type().is_symlink() => fs::canonicalize(path),
+    _ => Ok(path.to_path_buf()),
+  }
+}
+
+fn write_temp_and_rename(
+  tmp: &Path,
+  path: &Path,
+  bytes: &[u8],
+  permissions: Option<fs::Permissions>,
+) -> io::Result<()> {
+  let mut file = File::create(tmp)?;
+  file.write_all(bytes)?;
+  if let Some(permissions) = permissions {
+    file.set_permissions(permissions)?;
+  }
+  file.sync_all()?;
+  drop(file);
+  fs::rename(tmp, path)?;
+  sync_parent_dir(path);
+  Ok(())
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+  let name = path
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let nanos = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_nanos())
+    .unwrap_or_default();
+  path.with_file_name(format!(".{name}.{}-{nanos}.marko-tmp", std::process::id()))
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) {
+  // Persist the rename itself; failures here only weaken durability.
+  if let Some(parent) = path.parent() {
+    if let Ok(dir) = File::open(parent) {
+      let _ = dir.sync_all();
+    }
+  }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) {}
+
+fn is_transient(err: &io::Error) -> bool {
+  matches!(
+    err.kind(),
+    io::ErrorKind::Interrupted
+      | io::ErrorKind::WouldBlock
+      | io::ErrorKind::TimedOut
+      | io::ErrorKind::ResourceBusy
+  )
+}
@@ -0,0 +1,138 @@
+use std::borrow::Cow;
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+use crate::error::{AppError, AppResult};
+use crate::models::FsTextFormat;
+
+pub(crate) const LINE_ENDING_LF: &str = "lf";
+pub(crate) const LINE_ENDING_CRLF: &str = "crlf";
+
+/// Decodes file bytes for the editor. The encoding comes from a BOM, valid
+/// UTF-8, or a statistical guess; files that consistently use CRLF are
+/// normalized to LF and remember it in the returned format. Content with NUL
+/// characters, decoding errors, or no convincing encoding guess is treated
+/// as binary and rejected.
+pub(crate) fn decode_text(bytes: &[u8]) -> AppResult<(String, FsTextFormat)> {
+  let (encoding, bom_len) = match Encoding::for_bom(bytes) {
+    Some((encoding, bom_len)) => (encoding, bom_len),
+    None if std::str::from_utf8(bytes).is_ok() => (UTF_8, 0),
+    None => {
+      let mut detector = EncodingDetector::new();
+      detector.feed(bytes, true);
+      let (encoding, confident) = detector.guess_assess(None, true);
+      if !confident {
+        return Err(AppError::invalid_format(
+          "File is not text in a recognizable encoding",
+        ));
+      }
+      (encoding, 0)
+    }
+  };
+  let (text, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+  if had_errors {
+    return Err(AppError::invalid_format(format!(
+      "Failed to decode file as {}",
+      encoding.name()
+    )));
+  }
+  if text.contains('\0') {
+    return Err(AppError::invalid_format("File contains binary data"));
+  }
+
+  let crlf = uses_crlf(&text);
+  let text = if crlf {
+    text.replace("\r\n", "\n")
+  } else {
+    text.into_owned()
+  };
+  Ok((
+    text,
+    FsTextFormat {
+      encoding: encoding.name().to_string(),
+      bom: bom_len > 0,
+      line_ending: if crlf {
+        LINE_ENDING_CRLF
+      } else {
+        LINE_ENDING_LF
+      }
+      .to_string(),
+    },
+  ))
+}
+
+/// Encodes editor content back into the bytes described by `format`.
+/// Characters the encoding cannot represent are an error rather than being
+/// silently replaced.
+pub(crate) fn encode_text(content: &str, format: &FsTextFormat) -> AppResult<Vec<u8>> {
+  let encoding = encoding_for_label(&format.encoding)?;
+  let content = if format.line_ending == LINE_ENDING_CRLF {
+    Cow::Owned(content.replace("\r\n", "\n").replace('\n', "\r\n"))
+  } else {
+    Cow::Borrowed(content)
+  };
+
+  let mut bytes = Vec::with_capacity(content.len() + 3);
+  if encoding == UTF_16LE || encoding == UTF_16BE {
+    // encoding_rs only decodes UTF-16; its encoder falls back to UTF-8.
+    if format.bom {
+      bytes.extend_from_slice(if encoding == UTF_16LE {
+        &[0xFF, 0xFE]
+      } else {
+        &[0xFE, 0xFF]
+      });
+    }
+    for unit in content.encode_utf16() {
+      bytes.extend_from_slice(&if encoding == UTF_16LE {
+        unit.to_le_bytes()
+      } else {
+        unit.to_be_bytes()
+      });
+    }
+    return Ok(bytes);
+  }
+
+  if format.bom && encoding == UTF_8 {
+    bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+  }
+  let (encoded, _, had_errors) = encoding.encode(&content);
+  if had_errors {
+    return Err(AppError::invalid_input(format!(
+      "Content contains characters that cannot be saved as {}",
+      encoding.name()
+    )));
+  }
+  bytes.extend_from_slice(&encoded);
+  Ok(bytes)
+}
+
+pub(crate) fn encoding_for_label(label: &str) -> AppResult<&'static Encoding> {
+  Encoding::for_label(label.trim().as_bytes())
+    .ok_or_else(|| AppError::unsupported(format!("Unsupported encoding: {label}")))
+}
+
+/// Validates a requested format and normalizes its labels.
+pub(crate) fn normalize_text_format(format: FsTextFormat) -> AppResult<FsTextFormat> {
+  let encoding = encoding_for_label(&format.encoding)?;
+  let line_ending = match format.line_ending.to_ascii_lowercase().as_str() {
+    LINE_ENDING_LF => LINE_ENDING_LF,
+    LINE_ENDING_CRLF => LINE_ENDING_CRLF,
+    other => {
+      return Err(AppError::unsupported(format!(
+        "Unsupported line ending: {other}"
+      )))
+    }
+  };
+  Ok(FsTextFormat {
+    encoding: encoding.name().to_string(),
+    // Only Unicode encodings have a byte order mark.
+    bom: format.bom && (encoding == UTF_8 || encoding == UTF_16LE || encoding == UTF_16BE),
+    line_ending: line_ending.to_string(),
+  })
+}
+
+fn uses_crlf(text: &str) -> bool {
+  let crlf = text.matches("\r\n").count();
+  crlf > 0 && crlf == text.matches('\n').count()
+}
@@ -1,5 +1,7 @@
 use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
 
+use crate::models::FsTextFormat;
 use crate::services::markdown_index::ParsedMarkdownDocument;
 
 #[derive(Debug, Clone)]
@@ -17,6 +19,36 @@ pub(super) struct DocumentStoreEntry {
   pub(super) revision: u64,
   pub(super) saved_revision: u64,
   pub(super) parsed_markdown: Option<ParsedMarkdownCache>,
+  pub(super) disk: Option<DiskState>,
+  pub(super) base_content: Option<String>,
+  pub(super) external_conflict: Option<ExternalConflict>,
+  pub(super) format: Option<FsTextFormat>,
+  /// Revision whose write failed permanently; it is not retried until the
+  /// buffer is edited again.
+  pub(super) failed_revision: Option<u64>,
+  pub(super) last_access: u64,
+}
+
+/// What the store last knew to be on disk for an entry.
+#[derive(Debug, Clone)]
+pub(super) struct DiskState {
+  pub(super) content_hash: u64,
+  pub(super) modified: Option<SystemTime>,
+}
+
+/// What is kept of a clean entry evicted from the cache.
+#[derive(Debug, Clone)]
+pub(super) struct EvictedEntry {
+  pub(super) disk: DiskState,
+  pub(super) format: Option<FsTextFormat>,
+}
+
+/// Disk content that diverged from a dirty buffer; flushing is held until
+/// the conflict is resolved.
+#[derive(Debug, Clone)]
+pub(super) struct ExternalConflict {
+  pub(super) disk_content: String,
+  pub(super) disk_hash: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -27,18 +59,55 @@ pub(super) struct ParsedMarkdownCache {
 
 impl DocumentStoreEntry {
   pub(super) fn clean(content: &str) -> Self {
+    let content_hash = stable_hash(content);
     Self {
       content: content.to_string(),
-      content_hash: stable_hash(content),
+      content_hash,
       dirty: false,
       revision: 0,
       saved_revision: 0,
       parsed_markdown: None,
+      disk: Some(DiskState {
+        content_hash,
+        modified: None,
+      }),
+      base_content: None,
+      external_conflict: None,
+      format: None,
+      failed_revision: None,
+      last_access: 0,
     }
   }
 
+  pub(super) fn loaded(content: &str, modified: Option<SystemTime>, format: FsTextFormat) -> Self {
+    let mut entry = Self::clean(content);
+    if let Some(disk) = entry.disk.as_mut() {
+      disk.modified = modified;
+    }
+    entry.format = Some(format);
+    entry
+  }
+
   pub(super) fn empty() -> Self {
-    Self::clean("")
+    Self {
+      disk: None,
+      ..Self::clean("")
+    }
+  }
+
+  /// An entry for an evicted document, holding `content` and the disk state
+  /// and format it was evicted with.
+  pub(super) fn from_evicted(content: &str, evicted: EvictedEntry) -> Self {
+    Self {
+      disk: Some(evicted.disk),
+      format: evicted.format,
+      ..Self::clean(content)
+    }
+  }
+
+  /// Whether a flush should write this entry.
+  pub(super) fn awaits_write(&self) -> bool {
+    self.dirty && self.external_conflict.is_none() && self.failed_revision != Some(self.revision)
   }
 
   pub(super) fn update_content(&mut self, content: &str) {
@@ -48,7 +117,7 @@ impl DocumentStoreEntry {
   }
 }
 
-fn stable_hash(value: &str) -> u64 {
+pub(super) fn stable_hash(value: &str) -> u64 {
   let mut hash = StableHasher::default();
   value.hash(&mut hash);
   hash.finish()
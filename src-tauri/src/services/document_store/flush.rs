@@ -1,87 +1,116 @@
-use std::collections::HashMap;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::Mutex;
 
-use crate::models::FsBufferStatus;
+use crate::error::{AppError, AppResult};
+use crate::models::{FsBufferStatus, FsExternalConflict, FsTextFormat};
 use crate::services::path_resolver::PathResolver;
 use crate::state::{FsState, FsStateData};
 
-use super::cache::status_from_document;
-use super::entry::DocumentStoreEntry;
+use super::cache::{external_conflict_from_document, status_from_document};
+use super::durable::{write_durable, WriteError, WrittenFile};
+use super::encoding::decode_text;
+use super::entry::{stable_hash, DiskState, ExternalConflict};
+use super::lru::DocumentMap;
 
 #[derive(Debug, Clone)]
 struct PendingDocumentWrite {
   path: String,
   absolute_path: PathBuf,
   content: String,
+  content_hash: u64,
   revision: u64,
+  disk: Option<DiskState>,
+  format: Option<FsTextFormat>,
 }
 
+pub(super) type FlushOutcome = (Vec<FsBufferStatus>, Vec<FsExternalConflict>);
+
 pub(super) fn flush_all_documents_with_status_for_resolver(
   path_resolver: &PathResolver,
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
+  documents: &Mutex<DocumentMap>,
   state: &FsState,
-) -> Result<Vec<FsBufferStatus>, String> {
+) -> AppResult<FlushOutcome> {
   let state_data = state
     .0
     .read()
-    .map_err(|_| "Failed to lock fs state")?
+    .map_err(|_| AppError::lock("fs state"))?
     .clone();
 
   let pending = {
     let documents = documents
       .lock()
-      .map_err(|_| "Failed to lock document state")?;
+      .map_err(|_| AppError::lock("document state"))?;
     collect_dirty_writes(path_resolver, &state_data, &documents)?
   };
   if pending.is_empty() {
-    return Ok(Vec::new());
+    return Ok((Vec::new(), Vec::new()));
   }
 
-  for item in &pending {
-    write_to_disk(&item.absolute_path, &item.content)?;
+  let mut written = Vec::with_capacity(pending.len());
+  let mut diverged = Vec::new();
+  for item in pending {
+    if let Some(disk_content) = external_change(&item)? {
+      diverged.push((item, disk_content));
+      continue;
+    }
+    let result = write_durable(&item.absolute_path, &item.content, item.format.as_ref());
+    written.push((item, result));
   }
 
-  mark_pending_writes_clean(documents, pending)
+  let conflicts = hold_diverged_writes(documents, diverged)?;
+  Ok((mark_pending_writes_clean(documents, written)?, conflicts))
 }
 
 pub(super) async fn flush_all_documents_with_status_async_for_resolver(
   path_resolver: &PathResolver,
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
+  documents: &Mutex<DocumentMap>,
   state: &FsState,
-) -> Result<Vec<FsBufferStatus>, String> {
+) -> AppResult<FlushOutcome> {
   let state_data = state
     .0
     .read()
-    .map_err(|_| "Failed to lock fs state")?
+    .map_err(|_| AppError::lock("fs state"))?
     .clone();
 
   let pending = {
     let documents = documents
       .lock()
-      .map_err(|_| "Failed to lock document state")?;
+      .map_err(|_| AppError::lock("document state"))?;
     collect_dirty_writes(path_resolver, &state_data, &documents)?
   };
   if pending.is_empty() {
-    return Ok(Vec::new());
+    return Ok((Vec::new(), Vec::new()));
   }
 
-  for item in &pending {
-    write_to_disk_async(&item.absolute_path, &item.content).await?;
+  let mut written = Vec::with_capacity(pending.len());
+  let mut diverged = Vec::new();
+  for item in pending {
+    if let Some(disk_content) = external_change_async(&item).await? {
+      diverged.push((item, disk_content));
+      continue;
+    }
+    let result = write_durable_async(
+      item.absolute_path.clone(),
+      item.content.clone(),
+      item.format.clone(),
+    )
+    .await;
+    written.push((item, result));
   }
 
-  mark_pending_writes_clean(documents, pending)
+  let conflicts = hold_diverged_writes(documents, diverged)?;
+  Ok((mark_pending_writes_clean(documents, written)?, conflicts))
 }
 
 fn collect_dirty_writes(
   path_resolver: &PathResolver,
   state_data: &FsStateData,
-  documents: &HashMap<String, DocumentStoreEntry>,
-) -> Result<Vec<PendingDocumentWrite>, String> {
+  documents: &DocumentMap,
+) -> AppResult<Vec<PendingDocumentWrite>> {
   let mut pending = Vec::new();
-  for (path, entry) in documents {
-    if !entry.dirty {
+  for (path, entry) in documents.iter() {
+    if !entry.awaits_write() {
       continue;
     }
     let absolute_path = path_resolver.resolve(state_data, path)?;
@@ -89,62 +118,157 @@ fn collect_dirty_writes(
       path: path.clone(),
       absolute_path,
       content: entry.content.clone(),
+      content_hash: entry.content_hash,
       revision: entry.revision,
+      disk: entry.disk.clone(),
+      format: entry.format.clone(),
     });
   }
   Ok(pending)
 }
 
-fn write_to_disk(path: &Path, content: &str) -> Result<(), String> {
-  if let Ok(existing) = fs::read_to_string(path) {
-    if existing == content {
-      return Ok(());
+/// Returns the current disk content when it changed since the store last
+/// loaded or wrote it, and differs from what is about to be written.
+fn external_change(item: &PendingDocumentWrite) -> AppResult<Option<String>> {
+  let Some(disk) = item.disk.as_ref() else {
+    return Ok(None);
+  };
+  let metadata = match fs::metadata(&item.absolute_path) {
+    Ok(metadata) => metadata,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+    Err(err) => {
+      return Err(AppError::io(
+        "Failed to read metadata",
+        &item.absolute_path,
+        err,
+      ))
     }
+  };
+  if disk.modified.is_some() && metadata.modified().ok() == disk.modified {
+    return Ok(None);
   }
-  if let Some(parent) = path.parent() {
-    fs::create_dir_all(parent).map_err(|e| format!("Failed to create dir: {e}"))?;
-  }
-  let tmp = path.with_extension("tmp");
-  fs::write(&tmp, content).map_err(|e| format!("Failed to write temp file: {e}"))?;
-  fs::rename(&tmp, path).map_err(|e| format!("Failed to rename temp file: {e}"))?;
-  Ok(())
+  let Some((content, _)) = fs::read(&item.absolute_path)
+    .ok()
+    .and_then(|bytes| decode_text(&bytes).ok())
+  else {
+    return Ok(None);
+  };
+  Ok(diverged_content(item, disk, content))
 }
 
-async fn write_to_disk_async(path: &Path, content: &str) -> Result<(), String> {
-  if let Ok(existing) = tokio::fs::read_to_string(path).await {
-    if existing == content {
-      return Ok(());
+async fn external_change_async(item: &PendingDocumentWrite) -> AppResult<Option<String>> {
+  let Some(disk) = item.disk.as_ref() else {
+    return Ok(None);
+  };
+  let metadata = match tokio::fs::metadata(&item.absolute_path).await {
+    Ok(metadata) => metadata,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+    Err(err) => {
+      return Err(AppError::io(
+        "Failed to read metadata",
+        &item.absolute_path,
+        err,
+      ))
     }
+  };
+  if disk.modified.is_some() && metadata.modified().ok() == disk.modified {
+    return Ok(None);
   }
-  if let Some(parent) = path.parent() {
-    tokio::fs::create_dir_all(parent)
-      .await
-      .map_err(|e| format!("Failed to create dir: {e}"))?;
-  }
-  let tmp = path.with_extension("tmp");
-  tokio::fs::write(&tmp, content)
+  let Some((content, _)) = tokio::fs::read(&item.absolute_path)
     .await
-    .map_err(|e| format!("Failed to write temp file: {e}"))?;
-  tokio::fs::rename(&tmp, path)
+    .ok()
+    .and_then(|bytes| decode_text(&bytes).ok())
+  else {
+    return Ok(None);
+  };
+  Ok(diverged_content(item, disk, content))
+}
+
+fn diverged_content(
+  item: &PendingDocumentWrite,
+  disk: &DiskState,
+  content: String,
+) -> Option<String> {
+  let hash = stable_hash(&content);
+  (hash != disk.content_hash && hash != item.content_hash).then_some(content)
+}
+
+fn hold_diverged_writes(
+  documents: &Mutex<DocumentMap>,
+  diverged: Vec<(PendingDocumentWrite, String)>,
+) -> AppResult<Vec<FsExternalConflict>> {
+  if diverged.is_empty() {
+    return Ok(Vec::new());
+  }
+  let mut documents = documents
+    .lock()
+    .map_err(|_| AppError::lock("document state"))?;
+  let mut conflicts = Vec::new();
+  for (item, disk_content) in diverged {
+    if let Some(mut entry) = documents.get_mut(&item.path) {
+      entry.external_conflict = Some(ExternalConflict {
+        disk_hash: stable_hash(&disk_content),
+        disk_content,
+      });
+      conflicts.extend(external_conflict_from_document(&item.path, &entry));
+    }
+  }
+  Ok(conflicts)
+}
+
+pub(super) async fn write_durable_async(
+  path: PathBuf,
+  content: String,
+  format: Option<FsTextFormat>,
+) -> Result<WrittenFile, WriteError> {
+  tokio::task::spawn_blocking(move || write_durable(&path, &content, format.as_ref()))
     .await
-    .map_err(|e| format!("Failed to rename temp file: {e}"))?;
-  Ok(())
+    .unwrap_or_else(|err| {
+      Err(WriteError {
+        message: format!("Failed to join write task: {err}"),
+        permanent: false,
+      })
+    })
 }
 
+/// Marks written documents clean. Failed writes stay dirty and are reported
+/// through their status instead of failing the whole batch; permanent
+/// failures are reported once and then wait for the next edit.
 fn mark_pending_writes_clean(
-  documents: &Mutex<HashMap<String, DocumentStoreEntry>>,
-  pending: Vec<PendingDocumentWrite>,
-) -> Result<Vec<FsBufferStatus>, String> {
+  documents: &Mutex<DocumentMap>,
+  written: Vec<(PendingDocumentWrite, Result<WrittenFile, WriteError>)>,
+) -> AppResult<Vec<FsBufferStatus>> {
   let mut documents = documents
     .lock()
-    .map_err(|_| "Failed to lock document state")?;
+    .map_err(|_| AppError::lock("document state"))?;
   let mut statuses = Vec::new();
-  for item in pending {
-    if let Some(entry) = documents.get_mut(&item.path) {
+  for (item, result) in written {
+    if let Some(mut entry) = documents.get_mut(&item.path) {
+      let file = match result {
+        Ok(file) => file,
+        Err(err) => {
+          if err.permanent {
+            entry.failed_revision = Some(item.revision);
+          }
+          let mut status = status_from_document(&item.path, &entry);
+          status.error = Some(err.message);
+          statuses.push(status);
+          continue;
+        }
+      };
+      entry.failed_revision = None;
+      entry.disk = Some(DiskState {
+        content_hash: file.content_hash,
+        modified: file.modified,
+      });
+      entry.format = Some(file.format);
       if entry.revision == item.revision {
         entry.dirty = false;
         entry.saved_revision = item.revision;
-        statuses.push(status_from_document(&item.path, entry));
+        entry.base_content = None;
+        statuses.push(status_from_document(&item.path, &entry));
+      } else {
+        entry.base_content = Some(item.content);
       }
     }
   }
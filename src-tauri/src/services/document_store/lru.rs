@@ -0,0 +1,338 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::models::DocumentCacheStats;
+
+use super::entry::{stable_hash, DocumentStoreEntry, EvictedEntry};
+
+pub(super) const DEFAULT_CACHE_BUDGET_BYTES: u64 = 128 * 1024 * 1024;
+
+/// Access clock, byte budget and counters shared by all handles of a store.
+#[derive(Debug)]
+pub(super) struct CacheMetrics {
+  clock: AtomicU64,
+  budget_bytes: AtomicU64,
+  hits: AtomicU64,
+  misses: AtomicU64,
+  parsed_hits: AtomicU64,
+  parsed_misses: AtomicU64,
+  evictions: AtomicU64,
+}
+
+impl Default for CacheMetrics {
+  fn default() -> Self {
+    Self {
+      clock: AtomicU64::new(0),
+      budget_bytes: AtomicU64::new(DEFAULT_CACHE_BUDGET_BYTES),
+      hits: AtomicU64::new(0),
+      misses: AtomicU64::new(0),
+      parsed_hits: AtomicU64::new(0),
+      parsed_misses: AtomicU64::new(0),
+      evictions: AtomicU64::new(0),
+    }
+  }
+}
+
+impl CacheMetrics {
+  pub(super) fn tick(&self) -> u64 {
+    self.clock.fetch_add(1, Ordering::Relaxed) + 1
+  }
+
+  pub(super) fn budget_bytes(&self) -> u64 {
+    self.budget_bytes.load(Ordering::Relaxed)
+  }
+
+  pub(super) fn set_budget_bytes(&self, bytes: u64) {
+    self.budget_bytes.store(bytes, Ordering::Relaxed);
+  }
+
+  pub(super) fn record_hit(&self) {
+    self.hits.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub(super) fn record_miss(&self) {
+    self.misses.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub(super) fn record_parsed(&self, hits: usize, misses: usize) {
+    self.parsed_hits.fetch_add(hits as u64, Ordering::Relaxed);
+    self
+      .parsed_misses
+      .fetch_add(misses as u64, Ordering::Relaxed);
+  }
+
+  pub(super) fn stats(&self, documents: &DocumentMap) -> DocumentCacheStats {
+    DocumentCacheStats {
+      entries: documents.len(),
+      dirty_entries: documents.values().filter(|entry| entry.dirty).count(),
+      parsed_entries: documents
+        .values()
+        .filter(|entry| entry.parsed_markdown.is_some())
+        .count(),
+      bytes: documents.bytes(),
+      budget_bytes: self.budget_bytes(),
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+      parsed_hits: self.parsed_hits.load(Ordering::Relaxed),
+      parsed_misses: self.parsed_misses.load(Ordering::Relaxed),
+      evictions: self.evictions.load(Ordering::Relaxed),
+    }
+  }
+
+  /// Evicts least recently used clean entries until the cache fits its
+  /// budget. Dirty entries are never evicted; if they alone exceed the
+  /// budget only their parsed markdown is dropped.
+  pub(super) fn enforce_budget(&self, documents: &mut DocumentMap) -> usize {
+    let budget = self.budget_bytes();
+    if documents.bytes() <= budget {
+      return 0;
+    }
+
+    let mut excess = documents.bytes() - budget;
+    let mut evict = Vec::new();
+    let mut strip = Vec::new();
+    for (_, path) in &documents.by_access {
+      if excess == 0 {
+        break;
+      }
+      let Some(entry) = documents.entries.get(path) else {
+        continue;
+      };
+      if !entry.dirty {
+        excess = excess.saturating_sub(entry_bytes(entry));
+        evict.push(path.clone());
+      } else if entry.parsed_markdown.is_some() {
+        strip.push(path.clone());
+      }
+    }
+    // Parsed markdown of dirty entries only goes once no clean entry is left.
+    if excess > 0 {
+      for path in &strip {
+        if excess == 0 {
+          break;
+        }
+        if let Some(mut entry) = documents.get_mut(path) {
+          excess = excess.saturating_sub(parsed_bytes(&entry));
+          entry.parsed_markdown = None;
+        }
+      }
+    }
+
+    for path in &evict {
+      documents.evict(path);
+    }
+    self
+      .evictions
+      .fetch_add(evict.len() as u64, Ordering::Relaxed);
+    evict.len()
+  }
+}
+
+/// Cached documents with a running byte total and a queue ordered by last
+/// access, so enforcing the budget never rescans or sorts every entry.
+/// Evicted clean entries leave their disk state behind, so a later edit is
+/// still checked against the file and saved in its format.
+#[derive(Debug, Default)]
+pub(super) struct DocumentMap {
+  entries: HashMap<String, DocumentStoreEntry>,
+  bytes: u64,
+  by_access: BTreeSet<(u64, String)>,
+  evicted: HashMap<String, EvictedEntry>,
+}
+
+impl DocumentMap {
+  pub(super) fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub(super) fn bytes(&self) -> u64 {
+    self.bytes
+  }
+
+  pub(super) fn get(&self, path: &str) -> Option<&DocumentStoreEntry> {
+    self.entries.get(path)
+  }
+
+  /// Mutable access that re-accounts the entry's size and recency when the
+  /// returned guard is dropped.
+  pub(super) fn get_mut<'a>(&'a mut self, path: &'a str) -> Option<EntryMut<'a>> {
+    let entry = self.entries.get_mut(path)?;
+    Some(EntryMut {
+      bytes_before: entry_bytes(entry),
+      access_before: entry.last_access,
+      entry,
+      path,
+      bytes: &mut self.bytes,
+      by_access: &mut self.by_access,
+    })
+  }
+
+  /// Returns the entry for `path`, creating it from its evicted disk state
+  /// or as a new document.
+  pub(super) fn get_or_insert<'a>(&'a mut self, path: &'a str) -> EntryMut<'a> {
+    if !self.entries.contains_key(path) {
+      let entry = match self.evicted.get(path) {
+        Some(evicted) => DocumentStoreEntry::from_evicted("", evicted.clone()),
+        None => DocumentStoreEntry::empty(),
+      };
+      self.insert(path.to_string(), entry);
+    }
+    self
+      .get_mut(path)
+      .unwrap_or_else(|| unreachable!("entry was just inserted"))
+  }
+
+  pub(super) fn insert(&mut self, path: String, entry: DocumentStoreEntry) {
+    self.remove(&path);
+    self.evicted.remove(&path);
+    self.bytes += entry_bytes(&entry);
+    self.by_access.insert((entry.last_access, path.clone()));
+    self.entries.insert(path, entry);
+  }
+
+  pub(super) fn remove(&mut self, path: &str) -> Option<DocumentStoreEntry> {
+    let entry = self.entries.remove(path)?;
+    self.bytes = self.bytes.saturating_sub(entry_bytes(&entry));
+    self
+      .by_access
+      .remove(&(entry.last_access, path.to_string()));
+    Some(entry)
+  }
+
+  /// Re-caches an evicted document whose file still holds the content it
+  /// was evicted with.
+  pub(super) fn restore_evicted(&mut self, path: &str, content: &str) {
+    if self.entries.contains_key(path) {
+      return;
+    }
+    let Some(evicted) = self
+      .evicted
+      .get(path)
+      .filter(|evicted| evicted.disk.content_hash == stable_hash(content))
+      .cloned()
+    else {
+      return;
+    };
+    self.insert(
+      path.to_string(),
+      DocumentStoreEntry::from_evicted(content, evicted),
+    );
+  }
+
+  pub(super) fn is_evicted(&self, path: &str) -> bool {
+    !self.entries.contains_key(path) && self.evicted.contains_key(path)
+  }
+
+  fn evict(&mut self, path: &str) {
+    if let Some(entry) = self.remove(path) {
+      if let Some(disk) = entry.disk {
+        self.evicted.insert(
+          path.to_string(),
+          EvictedEntry {
+            disk,
+            format: entry.format,
+          },
+        );
+      }
+    }
+  }
+
+  /// Drops the entries `keep` rejects; evicted disk state is kept.
+  pub(super) fn retain(&mut self, mut keep: impl FnMut(&str, &DocumentStoreEntry) -> bool) {
+    let removed = self
+      .entries
+      .iter()
+      .filter(|(path, entry)| !keep(path, entry))
+      .map(|(path, _)| path.clone())
+      .collect::<Vec<_>>();
+    for path in removed {
+      self.remove(&path);
+    }
+  }
+
+  /// Drops the disk state of evicted entries `forget` selects.
+  pub(super) fn forget_evicted(&mut self, mut forget: impl FnMut(&str) -> bool) {
+    self.evicted.retain(|path, _| !forget(path));
+  }
+
+  pub(super) fn clear(&mut self) {
+    self.entries.clear();
+    self.by_access.clear();
+    self.evicted.clear();
+    self.bytes = 0;
+  }
+
+  pub(super) fn keys(&self) -> impl Iterator<Item = &String> {
+    self.entries.keys()
+  }
+
+  pub(super) fn values(&self) -> impl Iterator<Item = &DocumentStoreEntry> {
+    self.entries.values()
+  }
+
+  pub(super) fn iter(&self) -> impl Iterator<Item = (&String, &DocumentStoreEntry)> {
+    self.entries.iter()
+  }
+}
+
+/// Mutable entry of a [`DocumentMap`]; dropping it updates the byte total
+/// and the access queue.
+pub(super) struct EntryMut<'a> {
+  entry: &'a mut DocumentStoreEntry,
+  path: &'a str,
+  bytes_before: u64,
+  access_before: u64,
+  bytes: &'a mut u64,
+  by_access: &'a mut BTreeSet<(u64, String)>,
+}
+
+impl Deref for EntryMut<'_> {
+  type Target = DocumentStoreEntry;
+
+  fn deref(&self) -> &Self::Target {
+    self.entry
+  }
+}
+
+impl DerefMut for EntryMut<'_> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    self.entry
+  }
+}
+
+impl Drop for EntryMut<'_> {
+  fn drop(&mut self) {
+    *self.bytes = (*self.bytes + entry_bytes(self.entry)).saturating_sub(self.bytes_before);
+    if self.entry.last_access != self.access_before {
+      self
+        .by_access
+        .remove(&(self.access_before, self.path.to_string()));
+      self
+        .by_access
+        .insert((self.entry.last_access, self.path.to_string()));
+    }
+  }
+}
+
+/// Approximate memory held by an entry. Parsed markdown is estimated as the
+/// size of its source since it is derived from it.
+pub(super) fn entry_bytes(entry: &DocumentStoreEntry) -> u64 {
+  let mut bytes = entry.content.len() as u64;
+  bytes += entry
+    .base_content
+    .as_ref()
+    .map_or(0, |base| base.len() as u64);
+  bytes += entry
+    .external_conflict
+    .as_ref()
+    .map_or(0, |conflict| conflict.disk_content.len() as u64);
+  if entry.parsed_markdown.is_some() {
+    bytes += parsed_bytes(entry);
+  }
+  bytes
+}
+
+fn parsed_bytes(entry: &DocumentStoreEntry) -> u64 {
+  entry.content.len() as u64
+}
@@ -0,0 +1,140 @@
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+
+const LOCAL_MARKER: &str = "<<<<<<< Local";
+const SEPARATOR_MARKER: &str = "=======";
+const DISK_MARKER: &str = ">>>>>>> Disk";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct MergeResult {
+  pub(super) content: String,
+  pub(super) conflicts: usize,
+}
+
+/// A run of base lines `[start, end)` replaced by `lines` on one side.
+struct Hunk<'a> {
+  start: usize,
+  end: usize,
+  lines: Vec<&'a str>,
+}
+
+/// Line-based three-way merge of `local` and `disk` against their common
+/// `base`. Overlapping or adjacent edits that differ are emitted with git
+/// style conflict markers.
+pub(super) fn three_way_merge(base: &str, local: &str, disk: &str) -> MergeResult {
+  let base_lines = base.split_inclusive('\n').collect::<Vec<_>>();
+  let local_hunks = diff_hunks(&base_lines, local);
+  let disk_hunks = diff_hunks(&base_lines, disk);
+
+  let mut content = String::new();
+  let mut conflicts = 0;
+  let mut position = 0;
+  let (mut local_index, mut disk_index) = (0, 0);
+
+  while local_index < local_hunks.len() || disk_index < disk_hunks.len() {
+    let starts_with_local = disk_index >= disk_hunks.len()
+      || (local_index < local_hunks.len()
+        && local_hunks[local_index].start <= disk_hunks[disk_index].start);
+    let (start, mut end) = if starts_with_local {
+      let hunk = &local_hunks[local_index];
+      (hunk.start, hunk.end)
+    } else {
+      let hunk = &disk_hunks[disk_index];
+      (hunk.start, hunk.end)
+    };
+
+    // Grow the region until no hunk from either side touches it.
+    let (local_from, disk_from) = (local_index, disk_index);
+    loop {
+      if local_index < local_hunks.len() && local_hunks[local_index].start <= end {
+        end = end.max(local_hunks[local_index].end);
+        local_index += 1;
+      } else if disk_index < disk_hunks.len() && disk_hunks[disk_index].start <= end {
+        end = end.max(disk_hunks[disk_index].end);
+        disk_index += 1;
+      } else {
+        break;
+      }
+    }
+
+    push_lines(&mut content, &base_lines[position..start]);
+    let local_region = &local_hunks[local_from..local_index];
+    let disk_region = &disk_hunks[disk_from..disk_index];
+    let local_version = apply_hunks(&base_lines, start, end, local_region);
+    let disk_version = apply_hunks(&base_lines, start, end, disk_region);
+    if disk_region.is_empty() || local_version == disk_version {
+      push_lines(&mut content, &local_version);
+    } else if local_region.is_empty() {
+      push_lines(&mut content, &disk_version);
+    } else {
+      conflicts += 1;
+      push_marker(&mut content, LOCAL_MARKER);
+      push_lines(&mut content, &local_version);
+      push_marker(&mut content, SEPARATOR_MARKER);
+      push_lines(&mut content, &disk_version);
+      push_marker(&mut content, DISK_MARKER);
+    }
+    position = end;
+  }
+  push_lines(&mut content, &base_lines[position..]);
+
+  MergeResult { content, conflicts }
+}
+
+fn diff_hunks<'a>(base_lines: &[&str], other: &'a str) -> Vec<Hunk<'a>> {
+  let other_lines = other.split_inclusive('\n').collect::<Vec<_>>();
+  let mut hunks: Vec<Hunk<'a>> = Vec::new();
+  let mut previous_changed = false;
+
+  for op in capture_diff_slices(Algorithm::Myers, base_lines, &other_lines) {
+    let (tag, base_range, other_range) = op.as_tag_tuple();
+    if tag == DiffTag::Equal {
+      previous_changed = false;
+      continue;
+    }
+    let lines = &other_lines[other_range];
+    match hunks.last_mut() {
+      Some(hunk) if previous_changed => {
+        hunk.end = base_range.end;
+        hunk.lines.extend_from_slice(lines);
+      }
+      _ => hunks.push(Hunk {
+        start: base_range.start,
+        end: base_range.end,
+        lines: lines.to_vec(),
+      }),
+    }
+    previous_changed = true;
+  }
+  hunks
+}
+
+fn apply_hunks<'a>(
+  base_lines: &[&'a str],
+  start: usize,
+  end: usize,
+  hunks: &[Hunk<'a>],
+) -> Vec<&'a str> {
+  let mut lines = Vec::new();
+  let mut position = start;
+  for hunk in hunks {
+    lines.extend_from_slice(&base_lines[position..hunk.start]);
+    lines.extend_from_slice(&hunk.lines);
+    position = hunk.end;
+  }
+  lines.extend_from_slice(&base_lines[position..end]);
+  lines
+}
+
+fn push_lines(content: &mut String, lines: &[&str]) {
+  for line in lines {
+    content.push_str(line);
+  }
+}
+
+fn push_marker(content: &mut String, marker: &str) {
+  if !content.is_empty() && !content.ends_with('\n') {
+    content.push('\n');
+  }
+  content.push_str(marker);
+  content.push('\n');
+}
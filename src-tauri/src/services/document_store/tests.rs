@@ -1,22 +1,13 @@
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::sync::RwLock;
-use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::models::FsEntry;
 use crate::state::{FsState, FsStateData};
+use crate::test_support::temp_dir;
 
-use super::DocumentStoreService;
-
-fn temp_root() -> PathBuf {
-  std::env::temp_dir().join(format!(
-    "marko-document-store-test-{}",
-    SystemTime::now()
-      .duration_since(UNIX_EPOCH)
-      .expect("time should be valid")
-      .as_nanos()
-  ))
-}
+use super::merge::three_way_merge;
+use super::{decode_text, DocumentStoreService};
 
 fn test_state(root: &Path) -> FsState {
   FsState(RwLock::new(FsStateData {
@@ -24,12 +15,13 @@ fn test_state(root: &Path) -> FsState {
     root_path: root.to_path_buf(),
     internal_root: root.to_path_buf(),
     single_file: None,
+    mounted_roots: Vec::new(),
   }))
 }
 
 #[tokio::test]
 async fn reads_updates_and_flushes_documents() {
-  let root = temp_root();
+  let root = temp_dir("document-store-test");
   fs::create_dir_all(&root).expect("test root should be created");
   fs::write(root.join("note.md"), "from disk").expect("test file should be written");
 
@@ -101,7 +93,7 @@ fn cloned_store_handles_share_document_state() {
 
 #[tokio::test]
 async fn snapshots_include_content_hash() {
-  let root = temp_root();
+  let root = temp_dir("document-store-test");
   fs::create_dir_all(&root).expect("test root should be created");
   fs::write(root.join("note.md"), "hello").expect("test file should be written");
 
@@ -134,7 +126,7 @@ async fn snapshots_include_content_hash() {
 
 #[test]
 fn clears_clean_documents_and_keeps_dirty_documents() {
-  let root = temp_root();
+  let root = temp_dir("document-store-test");
   fs::create_dir_all(&root).expect("test root should be created");
 
   let store = DocumentStoreService::default();
@@ -160,7 +152,7 @@ fn clears_clean_documents_and_keeps_dirty_documents() {
 
 #[test]
 fn invalidates_clean_documents_for_changed_absolute_paths() {
-  let root = temp_root();
+  let root = temp_dir("document-store-test");
   fs::create_dir_all(root.join("docs")).expect("test root should be created");
 
   let store = DocumentStoreService::default();
@@ -191,7 +183,7 @@ fn invalidates_clean_documents_for_changed_absolute_paths() {
 
 #[test]
 fn parsed_markdown_cache_tracks_content_hash_changes() {
-  let root = temp_root();
+  let root = temp_dir("document-store-test");
   fs::create_dir_all(&root).expect("test root should be created");
 
   let store = DocumentStoreService::default();
@@ -220,3 +212,513 @@ fn parsed_markdown_cache_tracks_content_hash_changes() {
     .expect("markdown should parse");
   assert_eq!(parsed[0].headings[0].text, "Two");
 }
+
+#[tokio::test]
+async fn holds_flush_when_disk_changed_externally() {
+  let root = temp_dir("document-store-test");
+  fs::create_dir_all(&root).expect("test root should be created");
+  fs::write(root.join("note.md"), "one\ntwo\nthree\n").expect("test file should be written");
+
+  let store = DocumentStoreService::default();
+  let state = test_state(&root);
+  store
+    .read_document(&state, "note.md")
+    .await
+    .expect("document should load");
+  store
+    .update_document(&state, "note.md", "ONE\ntwo\nthree\n")
+    .expect("document should update");
+  fs::write(root.join("note.md"), "one\ntwo\nTHREE\n").expect("external write should succeed");
+
+  let statuses = store
+    .flush_all_with_status_async(&state)
+    .await
+    .expect("flush should succeed");
+  assert!(statuses.is_empty());
+  assert_eq!(
+    fs::read_to_string(root.join("note.md")).expect("file should be readable"),
+    "one\ntwo\nTHREE\n"
+  );
+  let conflicts = store
+    .take_external_conflicts()
+    .expect("conflicts should drain");
+  assert_eq!(conflicts.len(), 1);
+  assert_eq!(conflicts[0].merge_conflicts, 0);
+  assert_eq!(conflicts[0].merged_content, "ONE\ntwo\nTHREE\n");
+  assert!(
+    store
+      .status("note.md")
+      .expect("status should read")
+      .expect("status should exist")
+      .conflict
+  );
+  assert!(!store
+    .has_pending_writes()
+    .expect("pending writes should read"));
+
+  store
+    .resolve_external_conflict("note.md", "merged", Some(&conflicts[0].merged_content))
+    .expect("conflict should resolve");
+  assert!(store
+    .has_pending_writes()
+    .expect("pending writes should read"));
+  let statuses = store
+    .flush_all_with_status_async(&state)
+    .await
+    .expect("flush should succeed");
+  assert_eq!(statuses.len(), 1);
+  assert_eq!(
+    fs::read_to_string(root.join("note.md")).expect("file should be readable"),
+    "ONE\ntwo\nTHREE\n"
+  );
+}
+
+#[test]
+fn three_way_merge_marks_overlapping_edits() {
+  let merged = three_way_merge("a\nb\nc\n", "a\nlocal\nc\n", "a\ndisk\nc\n");
+
+  assert_eq!(merged.conflicts, 1);
+  assert_eq!(
+    merged.content,
+    "a\n<<<<<<< Local\nlocal\n=======\ndisk\n>>>>>>> Disk\nc\n"
+  );
+}
+
+#[tokio::test]
+async fn flush_preserves_crlf_and_reports_failed_writes_per_file() {
+  let root = temp_dir("document-store-test");
+  fs::create_dir_all(&root).expect("test root should be created");
+  fs::write(root.join("note.md"), "one\r\ntwo\r\n").expect("test file should be written");
+  fs::write(root.join("blocker"), "not a dir").expect("blocker file should be written");
+
+  let store = DocumentStoreService::default();
+  let state = test_state(&root);
+  store
+    .read_document(&state, "note.md")
+    .await
+    .expect("document should load");
+  store
+    .update_document(&state, "note.md", "one\ntwo\nthree\n")
+    .expect("document should update");
+  store
+    .update_document(&state, "blocker/child.md", "unreachable")
+    .expect("document should update");
+
+  let mut statuses = store
+    .flush_all_with_status_async(&state)
+    .await
+    .expect("flush should not fail the batch");
+  statuses.sort_by(|left, right| left.path.cmp(&right.path));
+  assert_eq!(statuses.len(), 2);
+  assert_eq!(statuses[0].path, "blocker/child.md");
+  assert!(statuses[0].dirty);
+  assert!(statuses[0].error.is_some());
+  assert_eq!(statuses[1].path, "note.md");
+  assert!(!statuses[1].dirty);
+  assert!(statuses[1].error.is_none());
+  assert_eq!(
+    fs::read_to_string(root.join("note.md")).expect("file should be readable"),
+    "one\r\ntwo\r\nthree\r\n"
+  );
+
+  store
+    .update_document(&state, "note.md", "one\ntwo\n")
+    .expect("document should update");
+  let statuses = store
+    .flush_all_with_status_async(&state)
+    .await
+    .expect("flush should succeed");
+  assert!(statuses
+    .iter()
+    .any(|status| status.path == "note.md" && !status.dirty && !status.conflict));
+  assert_eq!(
+    fs::read_to_string(root.join("note.md")).expect("file should be readable"),
+    "one\r\ntwo\r\n"
+  );
+  assert!(fs::read_dir(&root)
+    .expect("root should be readable")
+    .filter_map(Result::ok)
+    .all(|entry| !entry.file_name().to_string_lossy().ends_with(".marko-tmp")));
+}
+
+#[tokio::test]
+async fn preserves_legacy_encoding_bom_and_converts_on_request() {
+  let root = temp_dir("document-store-test");
+  fs::create_dir_all(&root).expect("test root should be created");
+  let (shift_jis, _, _) = encoding_rs::SHIFT_JIS.encode("日本語のメモです。\r\n二行目\r\n");
+  fs::write(root.join("memo.md"), &shift_jis).expect("test file should be written");
+  fs::write(root.join("bom.md"), b"\xEF\xBB\xBFhello\n").expect("test file should be written");
+
+  let store = DocumentStoreService::default();
+  let state = test_state(&root);
+  let memo = store
+    .read_document(&state, "memo.md")
+    .await
+    .expect("legacy encoded document should load");
+  assert_eq!(memo, "日本語のメモです。\n二行目\n");
+  let format = store
+    .text_format("memo.md")
+    .expect("format should read")
+    .expect("format should be detected");
+  assert_eq!(format.encoding, "Shift_JIS");
+  assert_eq!(format.line_ending, "crlf");
+  assert_eq!(
+    store
+      .read_document(&state, "bom.md")
+      .await
+      .expect("document should load"),
+    "hello\n"
+  );
+
+  store
+    .update_document(&state, "memo.md", "日本語のメモです。\n三行目\n")
+    .expect("document should update");
+  store
+    .update_document(&state, "bom.md", "hello again\n")
+    .expect("document should update");
+  store
+    .flush_all_with_status_async(&state)
+    .await
+    .expect("documents should flush");
+  let (expected, _, _) = encoding_rs::SHIFT_JIS.encode("日本語のメモです。\r\n三行目\r\n");
+  assert_eq!(
+    fs::read(root.join("memo.md")).expect("file should be readable"),
+    expected.into_owned()
+  );
+  assert_eq!(
+    fs::read(root.join("bom.md")).expect("file should be readable"),
+    b"\xEF\xBB\xBFhello again\n"
+  );
+
+  let status = store
+    .convert_text_format(
+      &state,
+      "memo.md",
+      crate::models::FsTextFormat {
+        encoding: "utf-8".to_string(),
+        bom: false,
+        line_ending: "lf".to_string(),
+      },
+    )
+    .await
+    .expect("format should convert");
+  assert!(status.dirty);
+  store
+    .flush_all_with_status_async(&state)
+    .await
+    .expect("documents should flush");
+  assert_eq!(
+    fs::read_to_string(root.join("memo.md")).expect("file should be utf-8"),
+    "日本語のメモです。\n三行目\n"
+  );
+  assert!(store
+    .convert_text_format(
+      &state,
+      "memo.md",
+      crate::models::FsTextFormat {
+        encoding: "iso-8859-1".to_string(),
+        bom: false,
+        line_ending: "lf".to_string(),
+      },
+    )
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn replaces_documents_in_their_saved_format() {
+  let root = temp_dir("document-store-test");
+  fs::create_dir_all(&root).expect("test root should be created");
+  let (gbk, _, _) = encoding_rs::GBK.encode("旧的笔记\r\n");
+  fs::write(root.join("note.md"), &gbk).expect("test file should be written");
+
+  let store = DocumentStoreService::default();
+  let state = test_state(&root);
+  store
+    .read_document(&state, "note.md")
+    .await
+    .expect("document should load");
+  store
+    .replace_document(&state, "note.md", "恢复的笔记\n".to_string())
+    .await
+    .expect("document should be replaced");
+  let (expected, _, _) = encoding_rs::GBK.encode("恢复的笔记\r\n");
+  assert_eq!(
+    fs::read(root.join("note.md")).expect("file should be readable"),
+    expected.into_owned()
+  );
+  assert!(store
+    .cached_content("note.md")
+    .expect("cache should read")
+    .is_none());
+
+  store
+    .replace_document(&state, "notes/new.md", "# New\n".to_string())
+    .await
+    .expect("missing document should be created");
+  assert_eq!(
+    fs::read_to_string(root.join("notes/new.md")).expect("file should be utf-8"),
+    "# New\n"
+  );
+  fs::remove_dir_all(root).expect("test root should be removed");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn flush_writes_through_symlinked_notes() {
+  let root = temp_dir("document-store-test");
+  fs::create_dir_all(root.join("shared")).expect("test root should be created");
+  fs::write(root.join("shared/real.md"), "old\n").expect("test file should be written");
+  std::os::unix::fs::symlink(root.join("shared/real.md"), root.join("link.md"))
+    .expect("symlink should be created");
+
+  let store = DocumentStoreService::default();
+  let state = test_state(&root);
+  store
+    .read_document(&state, "link.md")
+    .await
+    .expect("linked document should load");
+  store
+    .update_document(&state, "link.md", "new\n")
+    .expect("document should update");
+  let statuses = store
+    .flush_all_with_status_async(&state)
+    .await
+    .expect("documents should flush");
+  assert!(statuses.iter().all(|status| status.error.is_none()));
+  assert!(fs::symlink_metadata(root.join("link.md"))
+    .expect("link should exist")
+    .file_type()
+    .is_symlink());
+  assert_eq!(
+    fs::read_to_string(root.join("shared/real.md")).expect("target should be readable"),
+    "new\n"
+  );
+  fs::remove_dir_all(root).expect("test root should be removed");
+}
+
+#[tokio::test]
+async fn rejects_binary_files() {
+  let root = temp_dir("document-store-test");
+  fs::create_dir_all(&root).expect("test root should be created");
+  let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x10\0\0\0\x10\x08\x06\0\0\0\x1f\xf3\xffa";
+  fs::write(root.join("image.md"), png).expect("test file should be written");
+
+  for bytes in [&png[..], b"plain\0text"] {
+    let err = decode_text(bytes).expect_err("binary content should be rejected");
+    assert_eq!(err.code(), "invalid_format");
+  }
+
+  let store = DocumentStoreService::default();
+  let state = test_state(&root);
+  let err = store
+    .read_document(&state, "image.md")
+    .await
+    .expect_err("binary file should not open");
+  assert_eq!(err.code(), "invalid_format");
+  assert_eq!(
+    fs::read(root.join("image.md")).expect("file should be readable"),
+    png
+  );
+}
+
+#[tokio::test]
+async fn reports_missing_files_and_stale_conflicts_by_code() {
+  let root = temp_dir("document-store-test");
+  fs::create_dir_all(&root).expect("test root should be created");
+  fs::write(root.join("note.md"), "from disk").expect("test file should be written");
+
+  let store = DocumentStoreService::default();
+  let state = test_state(&root);
+  let err = store
+    .read_document(&state, "missing.md")
+    .await
+    .expect_err("missing file should not open");
+  assert_eq!(err.code(), "not_found");
+
+  store
+    .read_document(&state, "note.md")
+    .await
+    .expect("document should load");
+  let err = store
+    .resolve_external_conflict("note.md", "local", None)
+    .expect_err("there is no conflict to resolve");
+  assert_eq!(err.code(), "conflict");
+}
+
+#[tokio::test]
+async fn reports_permission_denied_once_until_the_next_edit() {
+  let root = temp_dir("document-store-test");
+  fs::create_dir_all(&root).expect("test root should be created");
+  let path = root.join("locked.md");
+  fs::write(&path, "locked").expect("test file should be written");
+  let mut permissions = fs::metadata(&path)
+    .expect("metadata should read")
+    .permissions();
+  permissions.set_readonly(true);
+  fs::set_permissions(&path, permissions).expect("file should become read-only");
+
+  let store = DocumentStoreService::default();
+  let state = test_state(&root);
+  store
+    .read_document(&state, "locked.md")
+    .await
+    .expect("document should load");
+  store
+    .update_document(&state, "locked.md", "edited")
+    .expect("document should update");
+
+  let statuses = store
+    .flush_all_with_status_async(&state)
+    .await
+    .expect("flush should not fail the batch");
+  assert_eq!(statuses.len(), 1);
+  assert!(statuses[0].error.is_some());
+  assert!(!store
+    .has_pending_writes()
+    .expect("pending writes should read"));
+  assert!(store
+    .flush_all_with_status_async(&state)
+    .await
+    .expect("flush should succeed")
+    .is_empty());
+
+  store
+    .update_document(&state, "locked.md", "edited again")
+    .expect("document should update");
+  assert!(store
+    .has_pending_writes()
+    .expect("pending writes should read"));
+
+  let mut permissions = fs::metadata(&path)
+    .expect("metadata should read")
+    .permissions();
+  #[allow(clippy::permissions_set_readonly_false)]
+  permissions.set_readonly(false);
+  let _ = fs::set_permissions(&path, permissions);
+  let _ = fs::remove_dir_all(root);
+}
+
+#[tokio::test]
+async fn evicts_least_recently_used_clean_documents_over_budget() {
+  let root = temp_dir("document-store-test");
+  fs::create_dir_all(&root).expect("test root should be created");
+  for name in ["a.md", "b.md", "c.md"] {
+    fs::write(root.join(name), "0123456789").expect("test file should be written");
+  }
+
+  let store = DocumentStoreService::default();
+  let state = test_state(&root);
+  store.set_cache_budget(35).expect("budget should be set");
+  store
+    .update_document(&state, "dirty.md", "0123456789")
+    .expect("document should update");
+  store
+    .read_document(&state, "a.md")
+    .await
+    .expect("document should load");
+  store
+    .read_document(&state, "b.md")
+    .await
+    .expect("document should load");
+  store
+    .read_document(&state, "a.md")
+    .await
+    .expect("document should load from cache");
+  store
+    .read_document(&state, "c.md")
+    .await
+    .expect("document should load");
+
+  assert!(store
+    .cached_content("b.md")
+    .expect("cache should read")
+    .is_none());
+  assert!(store
+    .cached_content("c.md")
+    .expect("cache should read")
+    .is_some());
+  assert!(store
+    .cached_content("dirty.md")
+    .expect("cache should read")
+    .is_some());
+
+  let stats = store.cache_stats().expect("stats should read");
+  assert_eq!(stats.hits, 1);
+  assert_eq!(stats.misses, 3);
+  assert_eq!(stats.evictions, 1);
+  assert_eq!(stats.bytes, 30);
+  assert_eq!(stats.dirty_entries, 1);
+
+  let stats = store.set_cache_budget(0).expect("budget should be set");
+  assert_eq!(stats.entries, 1);
+  assert!(store
+    .cached_content("dirty.md")
+    .expect("cache should read")
+    .is_some());
+}
+
+#[tokio::test]
+async fn edits_of_evicted_documents_keep_their_disk_state_and_format() {
+  let root = temp_dir("document-store-test");
+  fs::create_dir_all(&root).expect("test root should be created");
+  fs::write(root.join("a.md"), "one\r\ntwo\r\n").expect("test file should be written");
+  fs::write(root.join("b.md"), "from disk\n").expect("test file should be written");
+  fs::write(root.join("c.md"), "0123456789").expect("test file should be written");
+
+  let store = DocumentStoreService::default();
+  let state = test_state(&root);
+  store.set_cache_budget(10).expect("budget should be set");
+  for path in ["a.md", "b.md", "c.md"] {
+    store
+      .read_document(&state, path)
+      .await
+      .expect("document should load");
+  }
+  assert!(store
+    .cached_content("a.md")
+    .expect("cache should read")
+    .is_none());
+  assert!(store
+    .cached_content("b.md")
+    .expect("cache should read")
+    .is_none());
+
+  fs::write(root.join("b.md"), "changed elsewhere\n").expect("test file should be written");
+  store
+    .update_document(&state, "a.md", "one\ntwo\nthree\n")
+    .expect("document should update");
+  store
+    .update_document(&state, "b.md", "from memory\n")
+    .expect("document should update");
+  let statuses = store
+    .flush_all_with_status_async(&state)
+    .await
+    .expect("documents should flush");
+
+  assert_eq!(
+    fs::read_to_string(root.join("a.md")).expect("file should be readable"),
+    "one\r\ntwo\r\nthree\r\n"
+  );
+  assert!(statuses
+    .iter()
+    .any(|status| status.path == "a.md" && !status.dirty));
+  assert_eq!(
+    fs::read_to_string(root.join("b.md")).expect("file should be readable"),
+    "changed elsewhere\n"
+  );
+  let conflicts = store
+    .take_external_conflicts()
+    .expect("conflicts should read");
+  assert_eq!(conflicts.len(), 1);
+  assert_eq!(conflicts[0].path, "b.md");
+  assert_eq!(conflicts[0].disk_content, "changed elsewhere\n");
+  // The clean c.md was evicted; the held b.md counts its disk content too.
+  let stats = store.cache_stats().expect("stats should read");
+  assert_eq!(stats.entries, 2);
+  assert_eq!(
+    stats.bytes,
+    ("one\ntwo\nthree\n".len() + "from memory\n".len() + "changed elsewhere\n".len()) as u64
+  );
+  let _ = fs::remove_dir_all(root);
+}
@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use crate::models::FsDocumentType;
+
+/// How a document's content is parsed for headings and links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentSyntax {
+  Markdown,
+  /// Markdown with JSX blocks and ESM statements, which are skipped.
+  Mdx,
+  Org,
+  /// No structure; only indexed for full-text search.
+  PlainText,
+}
+
+impl DocumentSyntax {
+  fn as_str(self) -> &'static str {
+    match self {
+      DocumentSyntax::Markdown => "markdown",
+      DocumentSyntax::Mdx => "mdx",
+      DocumentSyntax::Org => "org",
+      DocumentSyntax::PlainText => "plaintext",
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct DocumentType {
+  pub id: &'static str,
+  pub label: &'static str,
+  pub extensions: &'static [&'static str],
+  pub syntax: DocumentSyntax,
+}
+
+/// Text formats the workspace opens as documents, in link resolution order.
+const DOCUMENT_TYPES: &[DocumentType] = &[
+  DocumentType {
+    id: "markdown",
+    label: "Markdown",
+    extensions: &["md", "markdown"],
+    syntax: DocumentSyntax::Markdown,
+  },
+  DocumentType {
+    id: "mdx",
+    label: "MDX",
+    extensions: &["mdx"],
+    syntax: DocumentSyntax::Mdx,
+  },
+  DocumentType {
+    id: "quarto",
+    label: "Quarto",
+    extensions: &["qmd"],
+    syntax: DocumentSyntax::Markdown,
+  },
+  DocumentType {
+    id: "org",
+    label: "Org",
+    extensions: &["org"],
+    syntax: DocumentSyntax::Org,
+  },
+  DocumentType {
+    id: "text",
+    label: "Plain text",
+    extensions: &["txt"],
+    syntax: DocumentSyntax::PlainText,
+  },
+];
+
+pub fn document_type_for_path(path: &Path) -> Option<&'static DocumentType> {
+  let extension = path.extension()?.to_str()?;
+  DOCUMENT_TYPES.iter().find(|document_type| {
+    document_type
+      .extensions
+      .iter()
+      .any(|candidate| candidate.eq_ignore_ascii_case(extension))
+  })
+}
+
+pub fn is_document_path(path: &Path) -> bool {
+  document_type_for_path(path).is_some()
+}
+
+/// Syntax of a workspace-relative path, defaulting to markdown.
+pub fn document_syntax(path: &str) -> DocumentSyntax {
+  document_type_for_path(Path::new(path))
+    .map(|document_type| document_type.syntax)
+    .unwrap_or(DocumentSyntax::Markdown)
+}
+
+/// All registered extensions, in link resolution order.
+pub fn document_extensions() -> impl Iterator<Item = &'static str> {
+  DOCUMENT_TYPES
+    .iter()
+    .flat_map(|document_type| document_type.extensions.iter().copied())
+}
+
+pub fn document_type_infos() -> Vec<FsDocumentType> {
+  DOCUMENT_TYPES
+    .iter()
+    .map(|document_type| FsDocumentType {
+      id: document_type.id.to_string(),
+      label: document_type.label.to_string(),
+      extensions: document_type
+        .extensions
+        .iter()
+        .map(|extension| extension.to_string())
+        .collect(),
+      syntax: document_type.syntax.as_str().to_string(),
+    })
+    .collect()
+}
@@ -1,20 +1,52 @@
 use std::{
+  collections::VecDeque,
   path::PathBuf,
   sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex,
   },
-  time::Duration,
+  time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use fluxdi::Shared;
 use serde::Serialize;
-use tauri::{Emitter, Manager};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
 use tauri_plugin_notification::NotificationExt;
 use tokio::sync::broadcast;
 
+use crate::error::{AppError, AppResult};
+use crate::models::{
+  BackgroundTaskStatus, EventTopic, FsBufferStatus, FsExternalConflict, FsSnapshot, LoggedEvent,
+};
+use crate::services::shutdown::ShutdownToken;
 use crate::state::FsState;
 
+/// Frontend events kept for `app_get_event_log` and replay.
+const EVENT_LOG_LIMIT: usize = 500;
+/// Serialized payload bytes kept in the log, so a few large events cannot
+/// hold on to a lot of memory.
+const EVENT_LOG_BYTES_LIMIT: usize = 1024 * 1024;
+
+/// Payload of an event emitted to the frontend. It is sent wrapped in an
+/// [`EventEnvelope`]; bump `VERSION` when the payload shape changes.
+pub trait FrontendEvent: Serialize {
+  const TOPIC: EventTopic;
+  const VERSION: u32 = 1;
+  /// High-volume output streams are sequenced but not kept in the log.
+  const LOGGED: bool = true;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventEnvelope<T> {
+  pub version: u32,
+  pub topic: EventTopic,
+  /// Increases with every emitted event, so gaps show dropped events.
+  pub seq: u64,
+  /// Set when the event is re-emitted from the log.
+  pub replayed: bool,
+  pub payload: T,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ExportTaskEvent {
   pub id: String,
@@ -24,6 +56,28 @@ pub struct ExportTaskEvent {
   pub message: Option<String>,
 }
 
+impl FrontendEvent for ExportTaskEvent {
+  const TOPIC: EventTopic = EventTopic::Export;
+}
+
+impl FrontendEvent for FsSnapshot {
+  const TOPIC: EventTopic = EventTopic::Workspace;
+  /// Each snapshot lists the whole workspace.
+  const LOGGED: bool = false;
+}
+
+impl FrontendEvent for FsBufferStatus {
+  const TOPIC: EventTopic = EventTopic::Buffers;
+}
+
+impl FrontendEvent for FsExternalConflict {
+  const TOPIC: EventTopic = EventTopic::Buffers;
+}
+
+impl FrontendEvent for BackgroundTaskStatus {
+  const TOPIC: EventTopic = EventTopic::BackgroundTasks;
+}
+
 #[derive(Debug, Clone)]
 pub enum AppEvent {
   WorkspaceChanged,
@@ -35,6 +89,20 @@ pub enum AppEvent {
   RuntimeStopping,
 }
 
+impl AppEvent {
+  pub fn topic(&self) -> EventTopic {
+    match self {
+      Self::WorkspaceChanged
+      | Self::FileSystemChanged(_)
+      | Self::AssetChanged
+      | Self::DocumentChanged => EventTopic::Workspace,
+      Self::BuffersFlushed => EventTopic::Buffers,
+      Self::ExportTask(_) => EventTopic::Export,
+      Self::RuntimeStopping => EventTopic::App,
+    }
+  }
+}
+
 #[derive(Debug, Clone)]
 struct CoalescedWorkspaceEvents {
   should_stop: bool,
@@ -42,6 +110,7 @@ struct CoalescedWorkspaceEvents {
   refresh_snapshot: bool,
   rebuild_search_index: bool,
   document_paths: Option<Vec<PathBuf>>,
+  export_tasks: Vec<ExportTaskEvent>,
 }
 
 impl Default for CoalescedWorkspaceEvents {
@@ -52,31 +121,153 @@ impl Default for CoalescedWorkspaceEvents {
       refresh_snapshot: false,
       rebuild_search_index: false,
       document_paths: Some(Vec::new()),
+      export_tasks: Vec::new(),
     }
   }
 }
 
+impl CoalescedWorkspaceEvents {
+  /// Missed events could have touched anything.
+  fn refresh_everything(&mut self) {
+    self.refresh_documents = true;
+    self.refresh_snapshot = true;
+    self.rebuild_search_index = true;
+    self.document_paths = None;
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct EventBus {
   sender: broadcast::Sender<AppEvent>,
+  lagged: Arc<AtomicU64>,
+  log: Arc<Mutex<EventLog>>,
+}
+
+#[derive(Debug, Default)]
+struct EventLog {
+  next_seq: u64,
+  /// Logged events with the serialized size of their payload.
+  entries: VecDeque<(LoggedEvent, usize)>,
+  bytes: usize,
+}
+
+impl EventLog {
+  /// Appends an event, dropping the oldest ones to stay within the count and
+  /// byte limits. An event larger than the whole byte budget is not kept.
+  fn push(&mut self, event: LoggedEvent, size: usize) {
+    if size > EVENT_LOG_BYTES_LIMIT {
+      return;
+    }
+    while self.entries.len() >= EVENT_LOG_LIMIT || self.bytes + size > EVENT_LOG_BYTES_LIMIT {
+      let Some((_, dropped)) = self.entries.pop_front() else {
+        break;
+      };
+      self.bytes -= dropped;
+    }
+    self.bytes += size;
+    self.entries.push_back((event, size));
+  }
+}
+
+/// Counts the bytes written to it, to size a payload without keeping its
+/// JSON text.
+#[derive(Default)]
+struct ByteCount(usize);
+
+impl std::io::Write for ByteCount {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    self.0 += buf.len();
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    Ok(())
+  }
 }
 
 impl EventBus {
   pub fn new(capacity: usize) -> Self {
     let (sender, _) = broadcast::channel(capacity);
-    Self { sender }
+    Self {
+      sender,
+      lagged: Arc::new(AtomicU64::new(0)),
+      log: Arc::new(Mutex::new(EventLog::default())),
+    }
   }
 
-  pub fn publish(&self, event: AppEvent) -> Result<(), String> {
+  pub fn publish(&self, event: AppEvent) -> AppResult<()> {
     self
       .sender
       .send(event)
       .map(|_| ())
-      .map_err(|err| format!("Failed to publish app event: {err}"))
+      .map_err(|err| AppError::from(format!("Failed to publish app event: {err}")))
+  }
+
+  /// Receives the events of `topics`. `RuntimeStopping` is always
+  /// delivered so every subscriber can stop.
+  pub fn subscribe(&self, topics: &[EventTopic]) -> EventSubscription {
+    EventSubscription {
+      receiver: self.sender.subscribe(),
+      topics: topics.to_vec(),
+      lagged: Arc::clone(&self.lagged),
+    }
+  }
+
+  /// Events all subscribers together missed because they fell behind.
+  pub fn lagged_events(&self) -> u64 {
+    self.lagged.load(Ordering::Relaxed)
   }
 
-  pub fn subscribe(&self) -> broadcast::Receiver<AppEvent> {
-    self.sender.subscribe()
+  /// Numbers a frontend event and keeps it in the log unless the payload
+  /// opts out.
+  pub fn record<'a, E: FrontendEvent>(
+    &self,
+    name: &str,
+    payload: &'a E,
+  ) -> AppResult<EventEnvelope<&'a E>> {
+    let mut log = self.log.lock().map_err(|_| AppError::lock("event log"))?;
+    log.next_seq += 1;
+    let seq = log.next_seq;
+    if E::LOGGED {
+      let payload = serde_json::to_value(payload)
+        .map_err(|err| AppError::from(format!("Failed to serialize {name} event: {err}")))?;
+      let mut size = ByteCount::default();
+      serde_json::to_writer(&mut size, &payload)
+        .map_err(|err| AppError::from(format!("Failed to serialize {name} event: {err}")))?;
+      log.push(
+        LoggedEvent {
+          seq,
+          name: name.to_string(),
+          topic: E::TOPIC,
+          version: E::VERSION,
+          emitted_at: now_millis(),
+          payload,
+        },
+        size.0,
+      );
+    }
+    Ok(EventEnvelope {
+      version: E::VERSION,
+      topic: E::TOPIC,
+      seq,
+      replayed: false,
+      payload,
+    })
+  }
+
+  /// Logged events after `since`, oldest first.
+  pub fn log(&self, since: Option<u64>, topic: Option<EventTopic>) -> AppResult<Vec<LoggedEvent>> {
+    let log = self.log.lock().map_err(|_| AppError::lock("event log"))?;
+    Ok(
+      log
+        .entries
+        .iter()
+        .map(|(entry, _)| entry)
+        .filter(|entry| since.map_or(true, |since| entry.seq > since))
+        .filter(|entry| topic.map_or(true, |topic| entry.topic == topic))
+        .cloned()
+        .collect(),
+    )
   }
 }
 
@@ -86,11 +277,105 @@ impl Default for EventBus {
   }
 }
 
+/// A receiver limited to some topics that counts the events it missed.
+#[derive(Debug)]
+pub struct EventSubscription {
+  receiver: broadcast::Receiver<AppEvent>,
+  topics: Vec<EventTopic>,
+  lagged: Arc<AtomicU64>,
+}
+
+impl EventSubscription {
+  pub async fn recv(&mut self) -> Result<AppEvent, broadcast::error::RecvError> {
+    loop {
+      match self.receiver.recv().await {
+        Ok(event) if self.wants(&event) => return Ok(event),
+        Ok(_) => {}
+        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+          self.record_lag(skipped);
+          return Err(broadcast::error::RecvError::Lagged(skipped));
+        }
+        Err(err) => return Err(err),
+      }
+    }
+  }
+
+  pub fn try_recv(&mut self) -> Result<AppEvent, broadcast::error::TryRecvError> {
+    loop {
+      match self.receiver.try_recv() {
+        Ok(event) if self.wants(&event) => return Ok(event),
+        Ok(_) => {}
+        Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+          self.record_lag(skipped);
+          return Err(broadcast::error::TryRecvError::Lagged(skipped));
+        }
+        Err(err) => return Err(err),
+      }
+    }
+  }
+
+  fn wants(&self, event: &AppEvent) -> bool {
+    let topic = event.topic();
+    topic == EventTopic::App || self.topics.contains(&topic)
+  }
+
+  fn record_lag(&self, skipped: u64) {
+    let total = self.lagged.fetch_add(skipped, Ordering::Relaxed) + skipped;
+    log::warn!("app event subscriber lagged by {skipped} events ({total} in total)");
+  }
+}
+
+/// Emits `payload` to the frontend as a versioned [`EventEnvelope`] and
+/// records it in the event log.
+pub fn emit_event<R: Runtime, E: FrontendEvent>(
+  app: &AppHandle<R>,
+  name: &str,
+  payload: &E,
+) -> AppResult<()> {
+  let envelope = match app.try_state::<crate::services::AppServices>() {
+    Some(services) => services.events.record(name, payload)?,
+    None => EventEnvelope {
+      version: E::VERSION,
+      topic: E::TOPIC,
+      seq: 0,
+      replayed: false,
+      payload,
+    },
+  };
+  app
+    .emit(name, envelope)
+    .map_err(|err| AppError::from(format!("Failed to emit {name}: {err}")))
+}
+
+/// Emits logged events again, marked as replayed, for debugging listeners.
+pub fn replay_events(app: &tauri::AppHandle, events: &[LoggedEvent]) {
+  for event in events {
+    let envelope = EventEnvelope {
+      version: event.version,
+      topic: event.topic,
+      seq: event.seq,
+      replayed: true,
+      payload: &event.payload,
+    };
+    if let Err(err) = app.emit(&event.name, envelope) {
+      log::warn!("replay {} failed: {err}", event.name);
+    }
+  }
+}
+
+fn now_millis() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_millis() as u64)
+    .unwrap_or_default()
+}
+
 #[derive(Debug, Clone)]
 pub struct RuntimeService {
   events: Shared<EventBus>,
   container_started: Arc<AtomicBool>,
   event_worker_started: Arc<AtomicBool>,
+  shutdown: ShutdownToken,
 }
 
 impl RuntimeService {
@@ -99,6 +384,7 @@ impl RuntimeService {
       events,
       container_started: Arc::new(AtomicBool::new(false)),
       event_worker_started: Arc::new(AtomicBool::new(false)),
+      shutdown: ShutdownToken::new(),
     }
   }
 
@@ -108,9 +394,21 @@ impl RuntimeService {
 
   pub fn on_container_stop(&self) {
     self.container_started.store(false, Ordering::SeqCst);
+    self.shutdown.cancel();
     let _ = self.events.sender.send(AppEvent::RuntimeStopping);
   }
 
+  /// Token cancelled when the container stops. Background workers select on
+  /// it and hold one of its guards while running.
+  pub fn shutdown_token(&self) -> ShutdownToken {
+    self.shutdown.clone()
+  }
+
+  /// Waits for workers to finish after the container stopped.
+  pub fn wait_for_workers(&self, timeout: Duration) -> bool {
+    self.shutdown.wait_for_workers(timeout)
+  }
+
   #[cfg(test)]
   pub fn is_container_started(&self) -> bool {
     self.container_started.load(Ordering::SeqCst)
@@ -129,10 +427,11 @@ impl RuntimeService {
       app,
       self.events.clone(),
       Arc::clone(&self.event_worker_started),
+      self.shutdown.clone(),
     );
   }
 
-  pub fn publish_initial_workspace_event(&self) -> Result<(), String> {
+  pub fn publish_initial_workspace_event(&self) -> AppResult<()> {
     self.events.publish(AppEvent::WorkspaceChanged)
   }
 }
@@ -141,17 +440,26 @@ fn start_event_worker(
   app: &tauri::AppHandle,
   event_bus: Shared<EventBus>,
   event_worker_started: Arc<AtomicBool>,
+  shutdown: ShutdownToken,
 ) {
   let app_handle = app.clone();
-  let mut receiver = event_bus.subscribe();
+  let mut receiver = event_bus.subscribe(&[
+    EventTopic::Workspace,
+    EventTopic::Buffers,
+    EventTopic::Export,
+  ]);
+  let worker = shutdown.worker();
   tokio::spawn(async move {
+    let _worker = worker;
     loop {
-      let event = match receiver.recv().await {
+      let received = tokio::select! {
+        received = receiver.recv() => received,
+        _ = shutdown.cancelled() => break,
+      };
+      let event = match received {
         Ok(event) => event,
-        Err(broadcast::error::RecvError::Lagged(skipped)) => {
-          log::warn!("app event worker lagged by {skipped} events");
-          AppEvent::FileSystemChanged(Vec::new())
-        }
+        // Missed events could have touched anything.
+        Err(broadcast::error::RecvError::Lagged(_)) => AppEvent::FileSystemChanged(Vec::new()),
         Err(broadcast::error::RecvError::Closed) => break,
       };
       match event {
@@ -201,13 +509,17 @@ fn start_event_worker(
 
 async fn handle_coalesced_workspace_event(
   app: &tauri::AppHandle,
-  receiver: &mut broadcast::Receiver<AppEvent>,
+  receiver: &mut EventSubscription,
   refresh_documents: bool,
   document_paths: Option<Vec<PathBuf>>,
   refresh_snapshot: bool,
   rebuild_search_index: bool,
 ) -> bool {
-  let coalesced = coalesce_workspace_events(app, receiver).await;
+  tokio::time::sleep(Duration::from_millis(80)).await;
+  let coalesced = coalesce_workspace_events(receiver);
+  for payload in coalesced.export_tasks {
+    emit_export_task(app, payload);
+  }
   if coalesced.should_stop {
     return true;
   }
@@ -222,21 +534,13 @@ async fn handle_coalesced_workspace_event(
   false
 }
 
-async fn coalesce_workspace_events(
-  app: &tauri::AppHandle,
-  receiver: &mut broadcast::Receiver<AppEvent>,
-) -> CoalescedWorkspaceEvents {
-  tokio::time::sleep(Duration::from_millis(80)).await;
-
+/// Drains the events queued behind the one being handled into a single
+/// refresh. Export events are passed through untouched.
+fn coalesce_workspace_events(receiver: &mut EventSubscription) -> CoalescedWorkspaceEvents {
   let mut coalesced = CoalescedWorkspaceEvents::default();
   loop {
     match receiver.try_recv() {
-      Ok(AppEvent::WorkspaceChanged) => {
-        coalesced.refresh_documents = true;
-        coalesced.refresh_snapshot = true;
-        coalesced.rebuild_search_index = true;
-        coalesced.document_paths = None;
-      }
+      Ok(AppEvent::WorkspaceChanged) => coalesced.refresh_everything(),
       Ok(AppEvent::FileSystemChanged(paths)) => {
         coalesced.refresh_documents = true;
         coalesced.refresh_snapshot = true;
@@ -254,16 +558,14 @@ async fn coalesce_workspace_events(
         coalesced.refresh_snapshot = true;
         coalesced.rebuild_search_index = true;
       }
-      Ok(AppEvent::ExportTask(payload)) => emit_export_task(app, payload),
+      Ok(AppEvent::ExportTask(payload)) => coalesced.export_tasks.push(payload),
       Ok(AppEvent::RuntimeStopping) => {
         coalesced.should_stop = true;
         return coalesced;
       }
       Err(broadcast::error::TryRecvError::Empty) => return coalesced,
-      Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
-        log::warn!("app event worker coalescing lagged by {skipped} events");
-        return coalesced;
-      }
+      // The receiver skips ahead to the oldest retained event; keep draining.
+      Err(broadcast::error::TryRecvError::Lagged(_)) => coalesced.refresh_everything(),
       Err(broadcast::error::TryRecvError::Closed) => {
         coalesced.should_stop = true;
         return coalesced;
@@ -287,8 +589,7 @@ fn merge_document_paths(
 
 fn emit_export_task(app: &tauri::AppHandle, payload: ExportTaskEvent) {
   notify_export_task(app, &payload);
-
-  if let Err(err) = app.emit("export-task", payload) {
+  if let Err(err) = emit_event(app, "export-task", &payload) {
     log::warn!("emit export-task failed: {err}");
   }
 }
@@ -347,15 +648,22 @@ async fn handle_workspace_event(
 
   if rebuild_search_index {
     match app.path().app_data_dir() {
-      Ok(index_parent) => {
-        if let Err(err) = services
-          .workspace
-          .rebuild_search_index(index_parent, &state)
-          .await
-        {
-          log::warn!("rebuild search index failed: {err}");
+      Ok(index_parent) => match services
+        .background_tasks
+        .start("search-index", "Search index")
+      {
+        Ok(task) => {
+          let result = services
+            .workspace
+            .rebuild_search_index(index_parent, &state, task.clone())
+            .await;
+          if let Err(err) = &result {
+            log::warn!("rebuild search index failed: {err}");
+          }
+          task.finish(&result);
         }
-      }
+        Err(err) => log::warn!("start search index task failed: {err}"),
+      },
       Err(err) => log::warn!("resolve search index dir failed: {err}"),
     }
   }
@@ -366,10 +674,183 @@ async fn handle_workspace_event(
 
   match services.workspace.snapshot(&state).await {
     Ok(snapshot) => {
-      if let Err(err) = app.emit("fs-changed", snapshot) {
+      if let Err(err) = emit_event(app, "fs-changed", &snapshot) {
         log::warn!("emit fs-changed failed: {err}");
       }
     }
     Err(err) => log::warn!("workspace snapshot failed: {err}"),
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn export_event(id: &str) -> ExportTaskEvent {
+    ExportTaskEvent {
+      id: id.to_string(),
+      format: "pdf".to_string(),
+      output_path: "/tmp/note.pdf".to_string(),
+      status: "finished".to_string(),
+      message: None,
+    }
+  }
+
+  #[test]
+  fn coalesces_queued_workspace_events() {
+    let bus = EventBus::new(16);
+    let mut receiver = bus.subscribe(&[EventTopic::Workspace, EventTopic::Export]);
+
+    let coalesced = coalesce_workspace_events(&mut receiver);
+    assert!(!coalesced.refresh_snapshot);
+    assert_eq!(coalesced.document_paths, Some(Vec::new()));
+
+    bus.publish(AppEvent::AssetChanged).expect("publish");
+    let coalesced = coalesce_workspace_events(&mut receiver);
+    assert!(coalesced.refresh_snapshot);
+    assert!(!coalesced.refresh_documents);
+    assert!(!coalesced.rebuild_search_index);
+
+    bus
+      .publish(AppEvent::FileSystemChanged(vec![PathBuf::from("/a.md")]))
+      .expect("publish");
+    bus
+      .publish(AppEvent::ExportTask(export_event("1")))
+      .expect("publish");
+    bus
+      .publish(AppEvent::FileSystemChanged(vec![PathBuf::from("/b.md")]))
+      .expect("publish");
+    bus.publish(AppEvent::DocumentChanged).expect("publish");
+    let coalesced = coalesce_workspace_events(&mut receiver);
+    assert!(coalesced.refresh_documents && coalesced.rebuild_search_index);
+    assert_eq!(
+      coalesced.document_paths,
+      Some(vec![PathBuf::from("/a.md"), PathBuf::from("/b.md")])
+    );
+    assert_eq!(coalesced.export_tasks.len(), 1);
+    assert!(!coalesced.should_stop);
+
+    // An empty path list means "anything may have changed".
+    bus
+      .publish(AppEvent::FileSystemChanged(vec![PathBuf::from("/a.md")]))
+      .expect("publish");
+    bus
+      .publish(AppEvent::FileSystemChanged(Vec::new()))
+      .expect("publish");
+    assert_eq!(
+      coalesce_workspace_events(&mut receiver).document_paths,
+      None
+    );
+
+    bus.publish(AppEvent::DocumentChanged).expect("publish");
+    bus.publish(AppEvent::RuntimeStopping).expect("publish");
+    bus.publish(AppEvent::AssetChanged).expect("publish");
+    assert!(coalesce_workspace_events(&mut receiver).should_stop);
+  }
+
+  #[test]
+  fn lagging_subscriber_refreshes_everything() {
+    let bus = EventBus::new(2);
+    let mut receiver = bus.subscribe(&[EventTopic::Workspace]);
+    for _ in 0..5 {
+      bus.publish(AppEvent::AssetChanged).expect("publish");
+    }
+
+    let coalesced = coalesce_workspace_events(&mut receiver);
+    assert!(coalesced.refresh_documents);
+    assert!(coalesced.rebuild_search_index);
+    assert_eq!(coalesced.document_paths, None);
+    assert_eq!(bus.lagged_events(), 3);
+  }
+
+  #[test]
+  fn subscriptions_filter_by_topic() {
+    let bus = EventBus::default();
+    let mut receiver = bus.subscribe(&[EventTopic::Export]);
+    bus.publish(AppEvent::DocumentChanged).expect("publish");
+    bus
+      .publish(AppEvent::ExportTask(export_event("1")))
+      .expect("publish");
+    bus.publish(AppEvent::BuffersFlushed).expect("publish");
+    bus.publish(AppEvent::RuntimeStopping).expect("publish");
+
+    assert!(matches!(receiver.try_recv(), Ok(AppEvent::ExportTask(_))));
+    assert!(matches!(receiver.try_recv(), Ok(AppEvent::RuntimeStopping)));
+    assert!(matches!(
+      receiver.try_recv(),
+      Err(broadcast::error::TryRecvError::Empty)
+    ));
+  }
+
+  #[derive(Serialize)]
+  struct OutputEvent {
+    data: String,
+  }
+
+  impl FrontendEvent for OutputEvent {
+    const TOPIC: EventTopic = EventTopic::Terminal;
+    const LOGGED: bool = false;
+  }
+
+  #[test]
+  fn records_sequenced_events_for_replay() {
+    let bus = EventBus::default();
+    let export = export_event("1");
+    let first = bus.record("export-task", &export).expect("record");
+    assert_eq!((first.seq, first.topic), (1, EventTopic::Export));
+    let output = OutputEvent {
+      data: "ls".to_string(),
+    };
+    let output = bus.record("terminal-output", &output).expect("record");
+    assert_eq!(output.seq, 2);
+    let export = export_event("2");
+    let last = bus.record("export-task", &export).expect("record");
+    assert_eq!(last.seq, 3);
+
+    let log = bus.log(None, None).expect("log");
+    assert_eq!(
+      log.iter().map(|event| event.seq).collect::<Vec<_>>(),
+      vec![1, 3]
+    );
+    assert_eq!(log[1].payload["id"], "2");
+    assert_eq!(bus.log(Some(1), None).expect("log").len(), 1);
+    assert!(bus
+      .log(None, Some(EventTopic::Terminal))
+      .expect("log")
+      .is_empty());
+
+    for index in 0..EVENT_LOG_LIMIT {
+      bus
+        .record("export-task", &export_event(&index.to_string()))
+        .expect("record");
+    }
+    let log = bus.log(None, None).expect("log");
+    assert_eq!(log.len(), EVENT_LOG_LIMIT);
+    assert_eq!(log[0].seq, 4);
+  }
+
+  #[test]
+  fn caps_the_event_log_by_bytes() {
+    let bus = EventBus::default();
+    let large = |id: &str, bytes: usize| ExportTaskEvent {
+      message: Some("x".repeat(bytes)),
+      ..export_event(id)
+    };
+    for index in 0..8 {
+      bus
+        .record("export-task", &large(&index.to_string(), 200 * 1024))
+        .expect("record");
+    }
+    let log = bus.log(None, None).expect("log");
+    assert_eq!(
+      log.iter().map(|event| event.seq).collect::<Vec<_>>(),
+      vec![4, 5, 6, 7, 8]
+    );
+
+    // Too large to keep at all, and nothing older is dropped for it.
+    let oversized = large("huge", EVENT_LOG_BYTES_LIMIT);
+    let oversized = bus.record("export-task", &oversized).expect("record");
+    assert_eq!(oversized.seq, 9);
+    assert_eq!(bus.log(None, None).expect("log").len(), 5);
+  }
+}
@@ -6,6 +6,9 @@ use mdxport::{Options as PdfExportOptions, Style as PdfExportStyle};
 use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
 use pulldown_cmark_to_cmark::cmark;
 
+use crate::error::{AppError, AppResult};
+use crate::services::background_tasks::BackgroundTaskHandle;
+
 /// Supported export formats.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExportFormat {
@@ -47,14 +50,9 @@ impl ExportFormat {
 pub struct ExportService;
 
 impl ExportService {
-  pub fn export_markdown(
-    &self,
-    markdown: &str,
-    format: &str,
-    output_path: &str,
-  ) -> Result<(), String> {
+  pub fn export_markdown(&self, markdown: &str, format: &str, output_path: &str) -> AppResult<()> {
     let fmt = ExportFormat::from_str(format)
-      .ok_or_else(|| format!("Unsupported export format: {format}"))?;
+      .ok_or_else(|| AppError::unsupported(format!("Unsupported export format: {format}")))?;
 
     match fmt {
       ExportFormat::Pdf => export_to_pdf(markdown, output_path),
@@ -63,17 +61,44 @@ impl ExportService {
     }
   }
 
+  /// Exports on a blocking thread. The file is rendered next to
+  /// `output_path` and only moved into place if `task` was not cancelled.
   pub async fn export_markdown_blocking(
     &self,
     markdown: String,
     format: String,
     output_path: String,
-  ) -> Result<(), String> {
+    task: BackgroundTaskHandle,
+  ) -> AppResult<()> {
     tokio::task::spawn_blocking(move || {
-      ExportService.export_markdown(&markdown, &format, &output_path)
+      if task.is_cancelled() {
+        return Err(export_cancelled());
+      }
+      task.step("Rendering");
+      let partial_path = format!("{output_path}.partial");
+      let result = ExportService
+        .export_markdown(&markdown, &format, &partial_path)
+        .and_then(|()| {
+          if task.is_cancelled() {
+            return Err(export_cancelled());
+          }
+          task.step("Writing");
+          std::fs::rename(&partial_path, &output_path)
+            .map_err(|err| AppError::io("Failed to write export", &output_path, err))
+        });
+      if result.is_err() {
+        let _ = std::fs::remove_file(&partial_path);
+      }
+      result
     })
     .await
-    .map_err(|err| format!("Export task failed: {err}"))?
+    .map_err(|err| AppError::from(format!("Export task failed: {err}")))?
+  }
+}
+
+fn export_cancelled() -> AppError {
+  AppError::Cancelled {
+    reason: "Export cancelled".to_string(),
   }
 }
 
@@ -230,7 +255,7 @@ fn escape_numeric_asterisks(md: &str) -> String {
   out
 }
 
-fn export_to_pdf(markdown: &str, output_path: &str) -> Result<(), String> {
+fn export_to_pdf(markdown: &str, output_path: &str) -> AppResult<()> {
   let normalized = normalize_markdown_for_export(markdown);
   let pdf = mdxport::markdown_to_pdf(
     &normalized,
@@ -242,10 +267,10 @@ fn export_to_pdf(markdown: &str, output_path: &str) -> Result<(), String> {
   )
   .map_err(|e| format!("Failed to export PDF: {}", e))?;
 
-  std::fs::write(output_path, pdf).map_err(|e| format!("Failed to write PDF: {}", e))
+  std::fs::write(output_path, pdf).map_err(|e| AppError::io("Failed to write PDF", output_path, e))
 }
 
-fn export_to_html(markdown: &str, output_path: &str) -> Result<(), String> {
+fn export_to_html(markdown: &str, output_path: &str) -> AppResult<()> {
   let markdown = normalize_markdown_for_export(markdown);
 
   let parser = Parser::new_ext(&markdown, export_markdown_options());
@@ -275,10 +300,11 @@ fn export_to_html(markdown: &str, output_path: &str) -> Result<(), String> {
     body
   );
 
-  std::fs::write(output_path, html).map_err(|e| format!("Failed to write HTML: {}", e))
+  std::fs::write(output_path, html)
+    .map_err(|e| AppError::io("Failed to write HTML", output_path, e))
 }
 
-fn export_to_docx(markdown: &str, output_path: &str) -> Result<(), String> {
+fn export_to_docx(markdown: &str, output_path: &str) -> AppResult<()> {
   let markdown = normalize_markdown_for_export(markdown);
 
   let parser = Parser::new_ext(&markdown, export_markdown_options());
@@ -385,8 +411,8 @@ fn export_to_docx(markdown: &str, output_path: &str) -> Result<(), String> {
 
   docx = flush_para(docx, &mut run_buf, &heading_style);
 
-  let file =
-    std::fs::File::create(output_path).map_err(|e| format!("Failed to create file: {}", e))?;
+  let file = std::fs::File::create(output_path)
+    .map_err(|e| AppError::io("Failed to create file", output_path, e))?;
   docx
     .build()
     .pack(file)
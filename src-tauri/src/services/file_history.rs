@@ -0,0 +1,746 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{FileHistoryConfig, FileHistoryVersion};
+use crate::services::document_store::DocumentSnapshot;
+use crate::services::search::stable_hash;
+
+const HISTORY_CONFIG_FILE: &str = "config.json";
+/// Append-only log of manifest changes, compacted once it holds mostly
+/// removed versions.
+const HISTORY_LOG_FILE: &str = "manifest.jsonl";
+/// Manifest written by earlier versions, imported into the log on first use.
+const LEGACY_MANIFEST_FILE: &str = "manifest.json";
+const HISTORY_BLOB_DIR: &str = "blobs";
+const HISTORY_LOG_COMPACT_SLACK: usize = 256;
+const HOUR_MS: i64 = 60 * 60 * 1000;
+const DAY_MS: i64 = 24 * HOUR_MS;
+
+/// Keeps versioned copies of saved documents in the app data dir so that a
+/// bad save can be undone even when the workspace is not tracked by git.
+/// Off until enabled in settings.
+#[derive(Debug, Default)]
+pub struct FileHistoryService {
+  config: Mutex<Option<FileHistoryConfig>>,
+  manifests: Arc<Mutex<HashMap<PathBuf, HistoryManifest>>>,
+}
+
+/// The versions of one workspace, loaded once and then kept in sync with the
+/// log on disk.
+#[derive(Debug, Default)]
+struct HistoryManifest {
+  versions: Vec<FileHistoryVersion>,
+  /// Number of versions referencing each blob.
+  blob_refs: HashMap<String, usize>,
+  log_records: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum ManifestRecord {
+  Add { version: FileHistoryVersion },
+  Remove { path: String, id: String },
+}
+
+impl FileHistoryService {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn config(&self, history_parent: &Path) -> AppResult<FileHistoryConfig> {
+    let mut config = self
+      .config
+      .lock()
+      .map_err(|_| AppError::lock("file history config"))?;
+    if let Some(config) = config.as_ref() {
+      return Ok(config.clone());
+    }
+    let loaded = read_history_config(history_parent)?;
+    *config = Some(loaded.clone());
+    Ok(loaded)
+  }
+
+  pub fn set_config(
+    &self,
+    history_parent: &Path,
+    config: FileHistoryConfig,
+  ) -> AppResult<FileHistoryConfig> {
+    let config = normalize_history_config(config);
+    write_history_config(history_parent, &config)?;
+    *self
+      .config
+      .lock()
+      .map_err(|_| AppError::lock("file history config"))? = Some(config.clone());
+    Ok(config)
+  }
+
+  pub fn is_enabled(&self, history_parent: &Path) -> AppResult<bool> {
+    Ok(self.config(history_parent)?.enabled)
+  }
+
+  /// Stores `documents` as new versions. Content already recorded as the
+  /// latest version of a path is skipped. Returns the number of new versions.
+  pub async fn record_versions(
+    &self,
+    history_parent: PathBuf,
+    root: PathBuf,
+    documents: Vec<DocumentSnapshot>,
+    reason: &str,
+  ) -> AppResult<usize> {
+    let config = self.config(&history_parent)?;
+    if !config.enabled || documents.is_empty() {
+      return Ok(0);
+    }
+    let deleted = reason == "delete";
+    let reason = reason.to_string();
+    self
+      .with_manifest(history_parent, root, move |dir, manifest| {
+        record_versions(dir, manifest, &config, documents, &reason, deleted)
+      })
+      .await
+  }
+
+  pub async fn list(
+    &self,
+    history_parent: PathBuf,
+    root: PathBuf,
+    path: String,
+  ) -> AppResult<Vec<FileHistoryVersion>> {
+    let mut versions = self
+      .with_manifest(history_parent, root, move |_, manifest| {
+        Ok(
+          manifest
+            .versions
+            .iter()
+            .rev()
+            .filter(|version| version.path == path)
+            .cloned()
+            .collect::<Vec<_>>(),
+        )
+      })
+      .await?;
+    versions.sort_by_key(|version| std::cmp::Reverse(version.timestamp_ms));
+    Ok(versions)
+  }
+
+  /// Lists the latest version of every path whose most recent entry is a
+  /// deletion, so deleted files can be found and restored.
+  pub async fn list_deleted(
+    &self,
+    history_parent: PathBuf,
+    root: PathBuf,
+  ) -> AppResult<Vec<FileHistoryVersion>> {
+    self
+      .with_manifest(history_parent, root, |_, manifest| {
+        let mut latest = BTreeMap::<&str, &FileHistoryVersion>::new();
+        for version in &manifest.versions {
+          match latest.get(version.path.as_str()) {
+            Some(existing) if existing.timestamp_ms > version.timestamp_ms => {}
+            _ => {
+              latest.insert(&version.path, version);
+            }
+          }
+        }
+        Ok(
+          latest
+            .into_values()
+            .filter(|version| version.deleted)
+            .cloned()
+            .collect(),
+        )
+      })
+      .await
+  }
+
+  pub async fn version_content(
+    &self,
+    history_parent: PathBuf,
+    root: PathBuf,
+    path: String,
+    version_id: String,
+  ) -> AppResult<String> {
+    let dir = history_dir(&history_parent, &root);
+    let missing = AppError::NotFound { path: path.clone() };
+    let version = self
+      .with_manifest(history_parent, root, move |_, manifest| {
+        Ok(
+          manifest
+            .versions
+            .iter()
+            .find(|version| version.path == path && version.id == version_id)
+            .cloned(),
+        )
+      })
+      .await?
+      .ok_or(missing)?;
+    let blob = blob_path(&dir, &version.content_hash);
+    tokio::fs::read_to_string(&blob)
+      .await
+      .map_err(|err| AppError::io("Failed to read file history version", &blob, err))
+  }
+
+  /// Runs `f` on a blocking thread with the workspace's manifest, loading it
+  /// from disk the first time.
+  async fn with_manifest<T: Send + 'static>(
+    &self,
+    history_parent: PathBuf,
+    root: PathBuf,
+    f: impl FnOnce(&Path, &mut HistoryManifest) -> AppResult<T> + Send + 'static,
+  ) -> AppResult<T> {
+    let manifests = self.manifests.clone();
+    tokio::task::spawn_blocking(move || {
+      let dir = history_dir(&history_parent, &root);
+      let mut manifests = manifests
+        .lock()
+        .map_err(|_| AppError::lock("file history"))?;
+      let manifest = match manifests.entry(dir.clone()) {
+        std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+        std::collections::hash_map::Entry::Vacant(entry) => entry.insert(load_manifest(&dir)?),
+      };
+      f(&dir, manifest)
+    })
+    .await
+    .map_err(|err| format!("Failed to join file history task: {err}"))?
+  }
+}
+
+impl HistoryManifest {
+  fn from_versions(versions: Vec<FileHistoryVersion>, log_records: usize) -> Self {
+    let mut manifest = Self {
+      versions: Vec::new(),
+      blob_refs: HashMap::new(),
+      log_records,
+    };
+    for version in versions {
+      manifest.add(version);
+    }
+    manifest
+  }
+
+  fn add(&mut self, version: FileHistoryVersion) {
+    *self
+      .blob_refs
+      .entry(version.content_hash.clone())
+      .or_default() += 1;
+    self.versions.push(version);
+  }
+
+  /// Drops a version and returns its blob when no other version uses it.
+  fn release(&mut self, version: &FileHistoryVersion) -> Option<String> {
+    let refs = self.blob_refs.get_mut(&version.content_hash)?;
+    *refs = refs.saturating_sub(1);
+    if *refs > 0 {
+      return None;
+    }
+    self.blob_refs.remove(&version.content_hash);
+    Some(version.content_hash.clone())
+  }
+
+  fn needs_compaction(&self) -> bool {
+    self.log_records > self.versions.len() * 2 + HISTORY_LOG_COMPACT_SLACK
+  }
+}
+
+fn history_root_dir(history_parent: &Path) -> PathBuf {
+  history_parent.join("history")
+}
+
+fn history_dir(history_parent: &Path, root: &Path) -> PathBuf {
+  history_root_dir(history_parent).join(format!("{:016x}", stable_hash(&root.to_string_lossy())))
+}
+
+fn blob_path(dir: &Path, content_hash: &str) -> PathBuf {
+  dir.join(HISTORY_BLOB_DIR).join(content_hash)
+}
+
+/// Names blobs by the git blob id of their content, so equal ids mean equal
+/// bytes.
+fn content_hash(content: &str) -> AppResult<String> {
+  gix::objs::compute_hash(
+    gix::hash::Kind::Sha1,
+    gix::objs::Kind::Blob,
+    content.as_bytes(),
+  )
+  .map(|id| id.to_string())
+  .map_err(|err| AppError::from(format!("Failed to hash file history version: {err}")))
+}
+
+fn read_history_config(history_parent: &Path) -> AppResult<FileHistoryConfig> {
+  let path = history_root_dir(history_parent).join(HISTORY_CONFIG_FILE);
+  match std::fs::read_to_string(&path) {
+    Ok(content) => serde_json::from_str::<FileHistoryConfig>(&content)
+      .map(normalize_history_config)
+      .map_err(|err| {
+        AppError::invalid_input(format!("Failed to parse file history config: {err}"))
+      }),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(FileHistoryConfig::default()),
+    Err(err) => Err(AppError::io(
+      "Failed to read file history config",
+      &path,
+      err,
+    )),
+  }
+}
+
+fn write_history_config(history_parent: &Path, config: &FileHistoryConfig) -> AppResult<()> {
+  let dir = history_root_dir(history_parent);
+  std::fs::create_dir_all(&dir)
+    .map_err(|err| AppError::io("Failed to create file history dir", &dir, err))?;
+  let content = serde_json::to_string_pretty(config)
+    .map_err(|err| format!("Failed to serialize file history config: {err}"))?;
+  let path = dir.join(HISTORY_CONFIG_FILE);
+  std::fs::write(&path, content)
+    .map_err(|err| AppError::io("Failed to write file history config", &path, err))
+}
+
+fn normalize_history_config(mut config: FileHistoryConfig) -> FileHistoryConfig {
+  config.keep_last = config.keep_last.max(1);
+  config
+}
+
+fn load_manifest(dir: &Path) -> AppResult<HistoryManifest> {
+  let path = dir.join(HISTORY_LOG_FILE);
+  let file = match std::fs::File::open(&path) {
+    Ok(file) => file,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return import_legacy_manifest(dir),
+    Err(err) => {
+      return Err(AppError::io(
+        "Failed to read file history manifest",
+        &path,
+        err,
+      ))
+    }
+  };
+  let mut versions = Vec::new();
+  let mut log_records = 0;
+  for line in std::io::BufReader::new(file).lines() {
+    let line =
+      line.map_err(|err| AppError::io("Failed to read file history manifest", &path, err))?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    log_records += 1;
+    // A torn last line from a crash is dropped rather than failing history.
+    match serde_json::from_str::<ManifestRecord>(&line) {
+      Ok(ManifestRecord::Add { version }) => versions.push(version),
+      Ok(ManifestRecord::Remove { path, id }) => {
+        versions.retain(|version: &FileHistoryVersion| version.path != path || version.id != id)
+      }
+      Err(err) => log::warn!("skipped malformed file history record: {err}"),
+    }
+  }
+  Ok(HistoryManifest::from_versions(versions, log_records))
+}
+
+fn import_legacy_manifest(dir: &Path) -> AppResult<HistoryManifest> {
+  let legacy = dir.join(LEGACY_MANIFEST_FILE);
+  let versions = match std::fs::read_to_string(&legacy) {
+    Ok(content) => serde_json::from_str::<Vec<FileHistoryVersion>>(&content).map_err(|err| {
+      AppError::invalid_input(format!("Failed to parse file history manifest: {err}"))
+    })?,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HistoryManifest::default()),
+    Err(err) => {
+      return Err(AppError::io(
+        "Failed to read file history manifest",
+        &legacy,
+        err,
+      ))
+    }
+  };
+  let mut manifest = HistoryManifest::from_versions(versions, 0);
+  compact_manifest(dir, &mut manifest)?;
+  let _ = std::fs::remove_file(legacy);
+  Ok(manifest)
+}
+
+fn append_manifest_records(
+  dir: &Path,
+  manifest: &mut HistoryManifest,
+  records: &[ManifestRecord],
+) -> AppResult<()> {
+  let mut content = String::new();
+  for record in records {
+    let line = serde_json::to_string(record)
+      .map_err(|err| format!("Failed to serialize file history manifest: {err}"))?;
+    content.push_str(&line);
+    content.push('\n');
+  }
+  let path = dir.join(HISTORY_LOG_FILE);
+  std::fs::OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(&path)
+    .and_then(|mut file| file.write_all(content.as_bytes()))
+    .map_err(|err| AppError::io("Failed to write file history manifest", &path, err))?;
+  manifest.log_records += records.len();
+  if manifest.needs_compaction() {
+    compact_manifest(dir, manifest)?;
+  }
+  Ok(())
+}
+
+/// Rewrites the log with one record per live version.
+fn compact_manifest(dir: &Path, manifest: &mut HistoryManifest) -> AppResult<()> {
+  std::fs::create_dir_all(dir)
+    .map_err(|err| AppError::io("Failed to create file history dir", dir, err))?;
+  let mut content = String::new();
+  for version in &manifest.versions {
+    let record = ManifestRecord::Add {
+      version: version.clone(),
+    };
+    let line = serde_json::to_string(&record)
+      .map_err(|err| format!("Failed to serialize file history manifest: {err}"))?;
+    content.push_str(&line);
+    content.push('\n');
+  }
+  let tmp = dir.join(format!("{HISTORY_LOG_FILE}.tmp"));
+  std::fs::write(&tmp, content)
+    .map_err(|err| AppError::io("Failed to write file history manifest", &tmp, err))?;
+  let path = dir.join(HISTORY_LOG_FILE);
+  std::fs::rename(&tmp, &path)
+    .map_err(|err| AppError::io("Failed to replace file history manifest", &path, err))?;
+  manifest.log_records = manifest.versions.len();
+  Ok(())
+}
+
+fn record_versions(
+  dir: &Path,
+  manifest: &mut HistoryManifest,
+  config: &FileHistoryConfig,
+  documents: Vec<DocumentSnapshot>,
+  reason: &str,
+  deleted: bool,
+) -> AppResult<usize> {
+  let blob_dir = dir.join(HISTORY_BLOB_DIR);
+  std::fs::create_dir_all(&blob_dir)
+    .map_err(|err| AppError::io("Failed to create file history dir", &blob_dir, err))?;
+  let timestamp_ms = now_ms();
+  let mut records = Vec::new();
+  let mut touched = HashSet::new();
+
+  for document in documents {
+    let size = document.content.len() as u64;
+    if size > config.max_file_bytes {
+      continue;
+    }
+    let content_hash = content_hash(&document.content)?;
+    let latest = manifest
+      .versions
+      .iter()
+      .filter(|version| version.path == document.path)
+      .max_by_key(|version| version.timestamp_ms);
+    if latest.is_some_and(|latest| latest.content_hash == content_hash && latest.deleted == deleted)
+    {
+      continue;
+    }
+
+    let blob = blob_path(dir, &content_hash);
+    if !manifest.blob_refs.contains_key(&content_hash) || !blob.exists() {
+      std::fs::write(&blob, &document.content)
+        .map_err(|err| AppError::io("Failed to write file history version", &blob, err))?;
+    }
+    let version = FileHistoryVersion {
+      id: format!("{timestamp_ms}-{}", &content_hash[..16]),
+      path: document.path,
+      content_hash,
+      size,
+      timestamp_ms,
+      reason: reason.to_string(),
+      deleted,
+    };
+    touched.insert(version.path.clone());
+    records.push(ManifestRecord::Add {
+      version: version.clone(),
+    });
+    manifest.add(version);
+  }
+
+  if records.is_empty() {
+    return Ok(0);
+  }
+  let recorded = records.len();
+
+  // Retention only needs to look at the paths that just gained a version.
+  let candidates = manifest
+    .versions
+    .iter()
+    .filter(|version| touched.contains(&version.path))
+    .cloned()
+    .collect::<Vec<_>>();
+  let kept = retained_versions(candidates.clone(), config, timestamp_ms)
+    .into_iter()
+    .map(|version| (version.path, version.id))
+    .collect::<HashSet<_>>();
+  let removed = candidates
+    .into_iter()
+    .filter(|version| !kept.contains(&(version.path.clone(), version.id.clone())))
+    .collect::<Vec<_>>();
+  if !removed.is_empty() {
+    manifest.versions.retain(|version| {
+      !touched.contains(&version.path) || kept.contains(&(version.path.clone(), version.id.clone()))
+    });
+  }
+  let mut unreferenced = Vec::new();
+  for version in &removed {
+    if let Some(blob) = manifest.release(version) {
+      unreferenced.push(blob);
+    }
+    records.push(ManifestRecord::Remove {
+      path: version.path.clone(),
+      id: version.id.clone(),
+    });
+  }
+
+  append_manifest_records(dir, manifest, &records)?;
+  for blob in unreferenced {
+    let _ = std::fs::remove_file(blob_path(dir, &blob));
+  }
+  Ok(recorded)
+}
+
+/// Applies the retention policy per path: the newest `keep_last` versions,
+/// plus the newest version of every hour and day inside the configured
+/// windows.
+fn retained_versions(
+  versions: Vec<FileHistoryVersion>,
+  config: &FileHistoryConfig,
+  now_ms: i64,
+) -> Vec<FileHistoryVersion> {
+  let hourly_window = i64::try_from(config.keep_hourly_hours)
+    .unwrap_or(i64::MAX)
+    .saturating_mul(HOUR_MS);
+  let daily_window = i64::try_from(config.keep_daily_days)
+    .unwrap_or(i64::MAX)
+    .saturating_mul(DAY_MS);
+  let mut by_path = BTreeMap::<String, Vec<FileHistoryVersion>>::new();
+  for version in versions.into_iter().rev() {
+    by_path
+      .entry(version.path.clone())
+      .or_default()
+      .push(version);
+  }
+
+  let mut retained = Vec::new();
+  for (_, mut versions) in by_path {
+    versions.sort_by_key(|version| std::cmp::Reverse(version.timestamp_ms));
+    let mut hours = HashSet::new();
+    let mut days = HashSet::new();
+    let mut kept = Vec::new();
+    for (index, version) in versions.into_iter().enumerate() {
+      let age = now_ms.saturating_sub(version.timestamp_ms);
+      let new_hour = age <= hourly_window && hours.insert(version.timestamp_ms / HOUR_MS);
+      let new_day = age <= daily_window && days.insert(version.timestamp_ms / DAY_MS);
+      if index < config.keep_last || new_hour || new_day {
+        kept.push(version);
+      }
+    }
+    // The manifest stays in recording order so ties resolve to the latest.
+    retained.extend(kept.into_iter().rev());
+  }
+  retained
+}
+
+fn now_ms() -> i64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|duration| duration.as_millis() as i64)
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::test_support::temp_dir;
+
+  use super::*;
+
+  fn document(path: &str, content: &str) -> DocumentSnapshot {
+    DocumentSnapshot {
+      path: path.to_string(),
+      content: content.to_string(),
+      content_hash: stable_hash(content),
+    }
+  }
+
+  fn version(path: &str, timestamp_ms: i64) -> FileHistoryVersion {
+    FileHistoryVersion {
+      id: format!("{timestamp_ms}"),
+      path: path.to_string(),
+      content_hash: format!("{timestamp_ms:016x}"),
+      size: 0,
+      timestamp_ms,
+      reason: "save".to_string(),
+      deleted: false,
+    }
+  }
+
+  #[tokio::test]
+  async fn records_deduplicated_versions_and_deleted_files() {
+    let parent = temp_dir("history");
+    let root = PathBuf::from("/workspace");
+    let service = FileHistoryService::new();
+    let skipped = service
+      .record_versions(
+        parent.clone(),
+        root.clone(),
+        vec![document("note.md", "# Off\n")],
+        "save",
+      )
+      .await
+      .expect("disabled history should succeed");
+    assert_eq!(skipped, 0, "history should be opt-in");
+    service
+      .set_config(
+        &parent,
+        FileHistoryConfig {
+          enabled: true,
+          ..FileHistoryConfig::default()
+        },
+      )
+      .expect("history should be enabled");
+
+    for content in ["# One\n", "# One\n", "# Two\n"] {
+      service
+        .record_versions(
+          parent.clone(),
+          root.clone(),
+          vec![document("note.md", content)],
+          "save",
+        )
+        .await
+        .expect("versions should record");
+    }
+    let versions = service
+      .list(parent.clone(), root.clone(), "note.md".to_string())
+      .await
+      .expect("history should list");
+    assert_eq!(versions.len(), 2);
+    let oldest = versions.last().expect("oldest version should exist");
+    let content = service
+      .version_content(
+        parent.clone(),
+        root.clone(),
+        "note.md".to_string(),
+        oldest.id.clone(),
+      )
+      .await
+      .expect("version content should read");
+    assert_eq!(content, "# One\n");
+
+    service
+      .record_versions(
+        parent.clone(),
+        root.clone(),
+        vec![document("note.md", "# Two\n")],
+        "delete",
+      )
+      .await
+      .expect("deletion should record");
+    let deleted = service
+      .list_deleted(parent.clone(), root)
+      .await
+      .expect("deleted files should list");
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].path, "note.md");
+
+    let _ = std::fs::remove_dir_all(parent);
+  }
+
+  #[tokio::test]
+  async fn prunes_blobs_incrementally_and_reloads_from_the_log() {
+    let parent = temp_dir("history-prune");
+    let root = PathBuf::from("/workspace");
+    let config = FileHistoryConfig {
+      enabled: true,
+      keep_last: 2,
+      keep_hourly_hours: 0,
+      keep_daily_days: 0,
+      ..FileHistoryConfig::default()
+    };
+    let service = FileHistoryService::new();
+    service
+      .set_config(&parent, config.clone())
+      .expect("history should be enabled");
+
+    for (path, content) in [
+      ("b.md", "one"),
+      ("a.md", "one"),
+      ("a.md", "two"),
+      ("a.md", "three"),
+    ] {
+      std::thread::sleep(std::time::Duration::from_millis(2));
+      service
+        .record_versions(
+          parent.clone(),
+          root.clone(),
+          vec![document(path, content)],
+          "save",
+        )
+        .await
+        .expect("versions should record");
+    }
+
+    let dir = history_dir(&parent, &root);
+    let blobs = std::fs::read_dir(dir.join(HISTORY_BLOB_DIR))
+      .expect("blobs should list")
+      .count();
+    assert_eq!(blobs, 3, "the blob shared with b.md should survive pruning");
+
+    let reloaded = FileHistoryService::new();
+    reloaded
+      .set_config(&parent, config)
+      .expect("history should be enabled");
+    let versions = reloaded
+      .list(parent.clone(), root.clone(), "a.md".to_string())
+      .await
+      .expect("history should list");
+    assert_eq!(versions.len(), 2);
+    let content = reloaded
+      .version_content(
+        parent.clone(),
+        root,
+        "a.md".to_string(),
+        versions[1].id.clone(),
+      )
+      .await
+      .expect("version content should read");
+    assert_eq!(content, "two");
+
+    let _ = std::fs::remove_dir_all(parent);
+  }
+
+  #[test]
+  fn keeps_recent_hourly_and_daily_versions() {
+    let now = 100 * DAY_MS;
+    let config = FileHistoryConfig {
+      keep_last: 2,
+      keep_hourly_hours: 2,
+      keep_daily_days: 3,
+      ..FileHistoryConfig::default()
+    };
+    let versions = vec![
+      version("note.md", now - 60_000),
+      version("note.md", now - 120_000),
+      version("note.md", now - 180_000),
+      version("note.md", now - HOUR_MS - 60_000),
+      version("note.md", now - HOUR_MS - 120_000),
+      version("note.md", now - 2 * DAY_MS + 120_000),
+      version("note.md", now - 2 * DAY_MS + 60_000),
+      version("note.md", now - 10 * DAY_MS),
+    ];
+
+    let retained = retained_versions(versions, &config, now)
+      .into_iter()
+      .map(|version| now - version.timestamp_ms)
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      retained,
+      vec![2 * DAY_MS - 120_000, HOUR_MS + 60_000, 120_000, 60_000]
+    );
+  }
+}
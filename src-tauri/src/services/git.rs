@@ -1,35 +1,46 @@
+mod conflicts;
+mod prose_diff;
+
 use std::path::{Path, PathBuf};
 
 use gix::bstr::ByteSlice;
+use gix::index::entry::Stage;
 use gix::objs::tree::EntryKind;
 use path_clean::PathClean;
 use similar::TextDiff;
 
-use crate::models::{GitFileChange, GitFileDiff, GitRepoInfo, GitStatusSnapshot};
+use crate::error::{AppError, AppResult};
+use crate::models::{
+  GitConflictFile, GitConflictResolution, GitFileChange, GitFileDiff, GitRepoInfo,
+  GitStatusSnapshot,
+};
+
+use self::conflicts::{apply_conflict_resolutions, conflict_hunks, parse_conflict_segments};
+use self::prose_diff::{inline_changes, normalize_granularity, structural_changes};
 
 #[derive(Debug, Default)]
 pub struct GitService;
 
 impl GitService {
-  pub async fn discover(&self, root_path: String) -> Result<GitRepoInfo, String> {
+  pub async fn discover(&self, root_path: String) -> AppResult<GitRepoInfo> {
     let root = PathBuf::from(root_path);
     tokio::task::spawn_blocking(move || discover_repo(&root))
       .await
-      .map_err(|err| format!("Failed to join git discovery task: {err}"))?
+      .map_err(|err| AppError::from(format!("Failed to join git discovery task: {err}")))?
   }
 
-  pub async fn init(&self, root_path: String) -> Result<GitRepoInfo, String> {
+  pub async fn init(&self, root_path: String) -> AppResult<GitRepoInfo> {
     let root = PathBuf::from(root_path);
     tokio::task::spawn_blocking(move || init_repo(&root))
       .await
-      .map_err(|err| format!("Failed to join git init task: {err}"))?
+      .map_err(|err| AppError::from(format!("Failed to join git init task: {err}")))?
   }
 
-  pub async fn status(&self, root_path: String) -> Result<GitStatusSnapshot, String> {
+  pub async fn status(&self, root_path: String) -> AppResult<GitStatusSnapshot> {
     let root = PathBuf::from(root_path);
     tokio::task::spawn_blocking(move || status_snapshot(&root))
       .await
-      .map_err(|err| format!("Failed to join git status task: {err}"))?
+      .map_err(|err| AppError::from(format!("Failed to join git status task: {err}")))?
   }
 
   pub async fn file_diff(
@@ -37,28 +48,54 @@ impl GitService {
     root_path: String,
     path: String,
     section: Option<String>,
-  ) -> Result<GitFileDiff, String> {
+    granularity: Option<String>,
+  ) -> AppResult<GitFileDiff> {
+    let root = PathBuf::from(root_path);
+    tokio::task::spawn_blocking(move || {
+      file_diff(&root, &path, section.as_deref(), granularity.as_deref())
+    })
+    .await
+    .map_err(|err| AppError::from(format!("Failed to join git diff task: {err}")))?
+  }
+
+  pub async fn conflict_file(&self, root_path: String, path: String) -> AppResult<GitConflictFile> {
+    let root = PathBuf::from(root_path);
+    tokio::task::spawn_blocking(move || conflict_file(&root, &path))
+      .await
+      .map_err(|err| AppError::from(format!("Failed to join git conflict task: {err}")))?
+  }
+
+  /// Writes the resolved file and stages it. Returns the new status and the
+  /// absolute path that was written.
+  pub async fn resolve_conflict(
+    &self,
+    root_path: String,
+    path: String,
+    resolutions: Vec<GitConflictResolution>,
+  ) -> AppResult<(GitStatusSnapshot, PathBuf)> {
     let root = PathBuf::from(root_path);
-    tokio::task::spawn_blocking(move || file_diff(&root, &path, section.as_deref()))
+    tokio::task::spawn_blocking(move || resolve_conflict(&root, &path, &resolutions))
       .await
-      .map_err(|err| format!("Failed to join git diff task: {err}"))?
+      .map_err(|err| AppError::from(format!("Failed to join git conflict task: {err}")))?
   }
 
   pub async fn commit_all(
     &self,
     root_path: String,
     message: String,
-  ) -> Result<GitStatusSnapshot, String> {
+  ) -> AppResult<GitStatusSnapshot> {
     let root = PathBuf::from(root_path);
     tokio::task::spawn_blocking(move || commit_all(&root, &message))
       .await
-      .map_err(|err| format!("Failed to join git commit task: {err}"))?
+      .map_err(|err| AppError::from(format!("Failed to join git commit task: {err}")))?
   }
 }
 
-fn discover_repo(root: &Path) -> Result<GitRepoInfo, String> {
+fn discover_repo(root: &Path) -> AppResult<GitRepoInfo> {
   if !root.exists() {
-    return Err(format!("Path does not exist: {}", root.display()));
+    return Err(AppError::NotFound {
+      path: root.to_string_lossy().to_string(),
+    });
   }
   if !root.is_dir() {
     return Ok(empty_repo_info());
@@ -70,26 +107,28 @@ fn discover_repo(root: &Path) -> Result<GitRepoInfo, String> {
   }
 }
 
-fn init_repo(root: &Path) -> Result<GitRepoInfo, String> {
+fn init_repo(root: &Path) -> AppResult<GitRepoInfo> {
   if !root.exists() {
-    return Err(format!("Path does not exist: {}", root.display()));
+    return Err(AppError::NotFound {
+      path: root.to_string_lossy().to_string(),
+    });
   }
   if !root.is_dir() {
-    return Err(format!(
-      "Git repository can only be initialized for a directory: {}",
-      root.display()
+    return Err(AppError::invalid_path(
+      root.to_string_lossy(),
+      "Git repository can only be initialized for a directory",
     ));
   }
   if gix::discover(root).is_ok() {
     return discover_repo(root);
   }
 
-  let repo =
-    gix::init(root).map_err(|err| format!("Failed to initialize git repository: {err}"))?;
+  let repo = gix::init(root)
+    .map_err(|err| AppError::git(format!("Failed to initialize git repository: {err}")))?;
   Ok(repo_info(&repo))
 }
 
-fn status_snapshot(root: &Path) -> Result<GitStatusSnapshot, String> {
+fn status_snapshot(root: &Path) -> AppResult<GitStatusSnapshot> {
   let repo = match gix::discover(root) {
     Ok(repo) => repo,
     Err(_) => {
@@ -111,14 +150,14 @@ fn status_snapshot(root: &Path) -> Result<GitStatusSnapshot, String> {
 
   let iter = repo
     .status(gix::progress::Discard)
-    .map_err(|err| format!("Failed to prepare git status: {err}"))?
+    .map_err(|err| AppError::git(format!("Failed to prepare git status: {err}")))?
     .untracked_files(gix::status::UntrackedFiles::Files)
     .index_worktree_submodules(None)
     .into_iter(Vec::new())
-    .map_err(|err| format!("Failed to read git status: {err}"))?;
+    .map_err(|err| AppError::git(format!("Failed to read git status: {err}")))?;
 
   for item in iter {
-    let item = item.map_err(|err| format!("Failed to iterate git status: {err}"))?;
+    let item = item.map_err(|err| AppError::git(format!("Failed to iterate git status: {err}")))?;
     match item {
       gix::status::Item::TreeIndex(change) => staged.push(staged_change(change)),
       gix::status::Item::IndexWorktree(change) => {
@@ -152,13 +191,15 @@ fn file_diff(
   root: &Path,
   relative_path: &str,
   section: Option<&str>,
-) -> Result<GitFileDiff, String> {
-  let repo =
-    gix::discover(root).map_err(|err| format!("Failed to discover git repository: {err}"))?;
+  granularity: Option<&str>,
+) -> AppResult<GitFileDiff> {
+  let granularity = normalize_granularity(granularity)?;
+  let repo = gix::discover(root)
+    .map_err(|err| AppError::git(format!("Failed to discover git repository: {err}")))?;
   let safe_path = normalize_repo_relative_path(relative_path)?;
   let workdir = repo
     .workdir()
-    .ok_or_else(|| "Git diff requires a repository with a working tree".to_string())?;
+    .ok_or_else(|| AppError::unsupported("Git diff requires a repository with a working tree"))?;
   let worktree_path = workdir.join(&safe_path);
 
   let head_content = head_blob_content(&repo, &safe_path)?;
@@ -189,6 +230,8 @@ fn file_diff(
     &original_content,
     &modified_content,
   );
+  let inline_changes = inline_changes(&original_content, &modified_content, granularity);
+  let structural_changes = structural_changes(&original_content, &modified_content);
 
   Ok(GitFileDiff {
     path: safe_path.to_string_lossy().to_string(),
@@ -198,44 +241,125 @@ fn file_diff(
     original_content,
     modified_content,
     unified_diff,
+    granularity: granularity.to_string(),
+    inline_changes,
+    structural_changes,
+  })
+}
+
+fn conflict_file(root: &Path, relative_path: &str) -> AppResult<GitConflictFile> {
+  let repo = gix::discover(root)
+    .map_err(|err| AppError::git(format!("Failed to discover git repository: {err}")))?;
+  let safe_path = normalize_repo_relative_path(relative_path)?;
+  let workdir = repo.workdir().ok_or_else(|| {
+    AppError::unsupported("Git conflicts require a repository with a working tree")
+  })?;
+  let working_content = worktree_file_content(&workdir.join(&safe_path))?;
+  let segments = parse_conflict_segments(&working_content);
+
+  Ok(GitConflictFile {
+    path: safe_path.to_string_lossy().to_string(),
+    base_content: index_stage_content(&repo, &safe_path, Stage::Base)?,
+    ours_content: index_stage_content(&repo, &safe_path, Stage::Ours)?,
+    theirs_content: index_stage_content(&repo, &safe_path, Stage::Theirs)?,
+    working_content,
+    hunks: conflict_hunks(&segments),
   })
 }
 
-fn commit_all(root: &Path, message: &str) -> Result<GitStatusSnapshot, String> {
+fn resolve_conflict(
+  root: &Path,
+  relative_path: &str,
+  resolutions: &[GitConflictResolution],
+) -> AppResult<(GitStatusSnapshot, PathBuf)> {
+  let repo = gix::discover(root)
+    .map_err(|err| AppError::git(format!("Failed to discover git repository: {err}")))?;
+  let safe_path = normalize_repo_relative_path(relative_path)?;
+  let workdir = repo.workdir().ok_or_else(|| {
+    AppError::unsupported("Git conflicts require a repository with a working tree")
+  })?;
+  let worktree_path = workdir.join(&safe_path);
+
+  let working_content = worktree_file_content(&worktree_path)?;
+  let resolved =
+    apply_conflict_resolutions(&parse_conflict_segments(&working_content), resolutions)?;
+  std::fs::write(&worktree_path, &resolved)
+    .map_err(|err| AppError::io("Failed to write resolved file", &worktree_path, err))?;
+
+  let blob_id = repo
+    .write_blob(resolved.as_bytes())
+    .map_err(|err| {
+      AppError::git(format!(
+        "Failed to write git blob for {relative_path}: {err}"
+      ))
+    })?
+    .detach();
+  let path = repo_relative_bstr(&safe_path)?;
+  let mut index = repo
+    .open_index()
+    .map_err(|err| AppError::git(format!("Failed to read git index: {err}")))?;
+  let mode = [Stage::Ours, Stage::Theirs, Stage::Base, Stage::Unconflicted]
+    .into_iter()
+    .find_map(|stage| {
+      index
+        .entry_by_path_and_stage(path.as_ref(), stage)
+        .map(|entry| entry.mode)
+    })
+    .unwrap_or(gix::index::entry::Mode::FILE);
+  index.remove_entries(|_, entry_path, _| entry_path == path.as_bstr());
+  index.dangerously_push_entry(
+    gix::index::entry::Stat::default(),
+    blob_id,
+    gix::index::entry::Flags::empty(),
+    mode,
+    path.as_ref(),
+  );
+  index.sort_entries();
+  index
+    .write(Default::default())
+    .map_err(|err| AppError::git(format!("Failed to write git index: {err}")))?;
+
+  Ok((status_snapshot(root)?, worktree_path))
+}
+
+fn commit_all(root: &Path, message: &str) -> AppResult<GitStatusSnapshot> {
   let message = message.trim();
   if message.is_empty() {
-    return Err("Commit message cannot be empty".to_string());
+    return Err(AppError::invalid_input("Commit message cannot be empty"));
   }
 
-  let repo =
-    gix::discover(root).map_err(|err| format!("Failed to discover git repository: {err}"))?;
+  let repo = gix::discover(root)
+    .map_err(|err| AppError::git(format!("Failed to discover git repository: {err}")))?;
   let workdir = repo
     .workdir()
-    .ok_or_else(|| "Git commit requires a repository with a working tree".to_string())?;
+    .ok_or_else(|| AppError::unsupported("Git commit requires a repository with a working tree"))?;
   let snapshot = status_snapshot(root)?;
   if !snapshot.repo.is_repository {
-    return Err("Current directory is not a Git repository".to_string());
+    return Err(AppError::git("Current directory is not a Git repository"));
   }
   if !snapshot.conflicts.is_empty() {
-    return Err("Cannot commit while conflicts are present".to_string());
+    return Err(AppError::Conflict {
+      path: root.to_string_lossy().to_string(),
+      reason: "Cannot commit while conflicts are present".to_string(),
+    });
   }
 
   let changes = all_commit_changes(&snapshot);
   if changes.is_empty() {
-    return Err("No changes to commit".to_string());
+    return Err(AppError::git("No changes to commit"));
   }
 
   let base_tree = match repo.rev_parse_single("HEAD") {
     Ok(head) => head
       .object()
-      .map_err(|err| format!("Failed to read HEAD object: {err}"))?
+      .map_err(|err| AppError::git(format!("Failed to read HEAD object: {err}")))?
       .peel_to_tree()
-      .map_err(|err| format!("Failed to read HEAD tree: {err}"))?,
+      .map_err(|err| AppError::git(format!("Failed to read HEAD tree: {err}")))?,
     Err(_) => repo.empty_tree(),
   };
   let mut editor = base_tree
     .edit()
-    .map_err(|err| format!("Failed to edit git tree: {err}"))?;
+    .map_err(|err| AppError::git(format!("Failed to edit git tree: {err}")))?;
 
   for change in changes {
     let safe_path = normalize_repo_relative_path(&change.path)?;
@@ -244,7 +368,11 @@ fn commit_all(root: &Path, message: &str) -> Result<GitStatusSnapshot, String> {
       let safe_old_path = normalize_repo_relative_path(old_path)?;
       editor
         .remove(safe_old_path.to_string_lossy().replace('\\', "/"))
-        .map_err(|err| format!("Failed to remove renamed git path {old_path}: {err}"))?;
+        .map_err(|err| {
+          AppError::git(format!(
+            "Failed to remove renamed git path {old_path}: {err}"
+          ))
+        })?;
     }
 
     let worktree_path = workdir.join(&safe_path);
@@ -252,29 +380,35 @@ fn commit_all(root: &Path, message: &str) -> Result<GitStatusSnapshot, String> {
       if !worktree_path.is_file() {
         continue;
       }
-      let bytes = std::fs::read(&worktree_path).map_err(|err| {
-        format!(
-          "Failed to read worktree file {}: {err}",
-          worktree_path.display()
-        )
-      })?;
+      let bytes = std::fs::read(&worktree_path)
+        .map_err(|err| AppError::io("Failed to read worktree file", &worktree_path, err))?;
       let blob_id = repo
         .write_blob(bytes)
-        .map_err(|err| format!("Failed to write git blob for {}: {err}", change.path))?
+        .map_err(|err| {
+          AppError::git(format!(
+            "Failed to write git blob for {}: {err}",
+            change.path
+          ))
+        })?
         .detach();
       editor
         .upsert(path_for_editor, EntryKind::Blob, blob_id)
-        .map_err(|err| format!("Failed to update git tree for {}: {err}", change.path))?;
+        .map_err(|err| {
+          AppError::git(format!(
+            "Failed to update git tree for {}: {err}",
+            change.path
+          ))
+        })?;
     } else {
-      editor
-        .remove(path_for_editor)
-        .map_err(|err| format!("Failed to remove git path {}: {err}", change.path))?;
+      editor.remove(path_for_editor).map_err(|err| {
+        AppError::git(format!("Failed to remove git path {}: {err}", change.path))
+      })?;
     }
   }
 
   let tree_id = editor
     .write()
-    .map_err(|err| format!("Failed to write git tree: {err}"))?
+    .map_err(|err| AppError::git(format!("Failed to write git tree: {err}")))?
     .detach();
   let parents = repo
     .head_id()
@@ -295,14 +429,16 @@ fn commit_all(root: &Path, message: &str) -> Result<GitStatusSnapshot, String> {
       tree_id,
       parents,
     )
-    .map_err(|err| format!("Failed to create git commit: {err}"))?;
+    .map_err(|err| AppError::git(format!("Failed to create git commit: {err}")))?;
 
-  let mut index = repo
-    .index_from_tree(&tree_id)
-    .map_err(|err| format!("Failed to update git index from committed tree: {err}"))?;
+  let mut index = repo.index_from_tree(&tree_id).map_err(|err| {
+    AppError::git(format!(
+      "Failed to update git index from committed tree: {err}"
+    ))
+  })?;
   index
     .write(Default::default())
-    .map_err(|err| format!("Failed to write git index: {err}"))?;
+    .map_err(|err| AppError::git(format!("Failed to write git index: {err}")))?;
 
   status_snapshot(root)
 }
@@ -334,56 +470,68 @@ fn repo_info(repo: &gix::Repository) -> GitRepoInfo {
   }
 }
 
-fn head_blob_content(repo: &gix::Repository, relative_path: &Path) -> Result<String, String> {
+fn head_blob_content(repo: &gix::Repository, relative_path: &Path) -> AppResult<String> {
   let tree = match repo.rev_parse_single("HEAD") {
     Ok(head) => head
       .object()
-      .map_err(|err| format!("Failed to read HEAD object: {err}"))?
+      .map_err(|err| AppError::git(format!("Failed to read HEAD object: {err}")))?
       .peel_to_tree()
-      .map_err(|err| format!("Failed to read HEAD tree: {err}"))?,
+      .map_err(|err| AppError::git(format!("Failed to read HEAD tree: {err}")))?,
     Err(_) => return Ok(String::new()),
   };
 
   let entry = tree
     .lookup_entry_by_path(relative_path)
-    .map_err(|err| format!("Failed to find file in HEAD: {err}"))?;
+    .map_err(|err| AppError::git(format!("Failed to find file in HEAD: {err}")))?;
   let Some(entry) = entry else {
     return Ok(String::new());
   };
 
   let blob = entry
     .object()
-    .map_err(|err| format!("Failed to read HEAD entry: {err}"))?
+    .map_err(|err| AppError::git(format!("Failed to read HEAD entry: {err}")))?
     .try_into_blob()
-    .map_err(|err| format!("HEAD entry is not a blob: {err}"))?;
+    .map_err(|err| AppError::git(format!("HEAD entry is not a blob: {err}")))?;
   Ok(bytes_to_string(&blob.data))
 }
 
-fn index_blob_content(
+fn index_blob_content(repo: &gix::Repository, relative_path: &Path) -> AppResult<Option<String>> {
+  let index = repo
+    .index_or_empty()
+    .map_err(|err| AppError::git(format!("Failed to read git index: {err}")))?;
+  let path = repo_relative_bstr(relative_path)?;
+  let Some(entry) = index.entry_by_path(path.as_ref()) else {
+    return Ok(None);
+  };
+  let blob = repo
+    .find_blob(entry.id)
+    .map_err(|err| AppError::git(format!("Failed to read index blob: {err}")))?;
+  Ok(Some(bytes_to_string(&blob.data)))
+}
+
+fn index_stage_content(
   repo: &gix::Repository,
   relative_path: &Path,
-) -> Result<Option<String>, String> {
+  stage: Stage,
+) -> AppResult<Option<String>> {
   let index = repo
     .index_or_empty()
-    .map_err(|err| format!("Failed to read git index: {err}"))?;
+    .map_err(|err| AppError::git(format!("Failed to read git index: {err}")))?;
   let path = repo_relative_bstr(relative_path)?;
-  let Some(entry) = index.entry_by_path(path.as_ref()) else {
+  let Some(entry) = index.entry_by_path_and_stage(path.as_ref(), stage) else {
     return Ok(None);
   };
   let blob = repo
     .find_blob(entry.id)
-    .map_err(|err| format!("Failed to read index blob: {err}"))?;
+    .map_err(|err| AppError::git(format!("Failed to read conflict blob: {err}")))?;
   Ok(Some(bytes_to_string(&blob.data)))
 }
 
-fn worktree_file_content(path: &Path) -> Result<String, String> {
+fn worktree_file_content(path: &Path) -> AppResult<String> {
   match std::fs::read(path) {
     Ok(bytes) => Ok(bytes_to_string(&bytes)),
     Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
-    Err(err) => Err(format!(
-      "Failed to read worktree file {}: {err}",
-      path.display()
-    )),
+    Err(err) => Err(AppError::io("Failed to read worktree file", path, err)),
   }
 }
 
@@ -500,19 +648,16 @@ enum SignatureKind {
   Committer,
 }
 
-fn git_signature(
-  repo: &gix::Repository,
-  kind: SignatureKind,
-) -> Result<gix::actor::Signature, String> {
+fn git_signature(repo: &gix::Repository, kind: SignatureKind) -> AppResult<gix::actor::Signature> {
   let configured = match kind {
     SignatureKind::Author => repo.author(),
     SignatureKind::Committer => repo.committer(),
   };
   if let Some(signature) = configured {
     return signature
-      .map_err(|err| format!("Failed to parse git signature time: {err}"))?
+      .map_err(|err| AppError::git(format!("Failed to parse git signature time: {err}")))?
       .to_owned()
-      .map_err(|err| format!("Failed to parse git signature: {err}"));
+      .map_err(|err| AppError::git(format!("Failed to parse git signature: {err}")));
   }
 
   Ok(gix::actor::Signature {
@@ -550,18 +695,21 @@ fn unified_diff(
     .to_string()
 }
 
-fn repo_relative_bstr(path: &Path) -> Result<gix::bstr::BString, String> {
+fn repo_relative_bstr(path: &Path) -> AppResult<gix::bstr::BString> {
   let value = path
     .to_str()
-    .ok_or_else(|| format!("Git path is not valid UTF-8: {}", path.display()))?
+    .ok_or_else(|| AppError::invalid_path(path.to_string_lossy(), "Git path is not valid UTF-8"))?
     .replace('\\', "/");
   Ok(value.into())
 }
 
-fn normalize_repo_relative_path(relative_path: &str) -> Result<PathBuf, String> {
+fn normalize_repo_relative_path(relative_path: &str) -> AppResult<PathBuf> {
   let path = PathBuf::from(relative_path).clean();
   if path.is_absolute() {
-    return Err("Git path must be repository-relative".to_string());
+    return Err(AppError::invalid_path(
+      relative_path,
+      "Git path must be repository-relative",
+    ));
   }
 
   let mut safe = PathBuf::new();
@@ -572,13 +720,16 @@ fn normalize_repo_relative_path(relative_path: &str) -> Result<PathBuf, String>
       std::path::Component::ParentDir
       | std::path::Component::RootDir
       | std::path::Component::Prefix(_) => {
-        return Err(format!("Invalid git path: {relative_path}"))
+        return Err(AppError::invalid_path(relative_path, "Invalid git path"))
       }
     }
   }
 
   if safe.as_os_str().is_empty() {
-    return Err("Git path cannot be empty".to_string());
+    return Err(AppError::invalid_path(
+      relative_path,
+      "Git path cannot be empty",
+    ));
   }
 
   Ok(safe)
@@ -586,17 +737,13 @@ fn normalize_repo_relative_path(relative_path: &str) -> Result<PathBuf, String>
 
 #[cfg(test)]
 mod tests {
+  use crate::test_support::temp_dir;
+
   use super::*;
 
   #[tokio::test]
   async fn initializes_and_discovers_repository() {
-    let root = std::env::temp_dir().join(format!(
-      "marko-git-service-{}",
-      std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .expect("time should move forward")
-        .as_nanos()
-    ));
+    let root = temp_dir("git-service");
     std::fs::create_dir_all(&root).expect("temp dir should be created");
 
     let service = GitService;
@@ -625,6 +772,7 @@ mod tests {
         root.to_string_lossy().to_string(),
         "note.md".to_string(),
         Some("untracked".to_string()),
+        Some("word".to_string()),
       )
       .await
       .expect("diff should work for untracked file");
@@ -633,6 +781,10 @@ mod tests {
     assert_eq!(diff.original_label, "Empty");
     assert_eq!(diff.modified_label, "Working Tree");
     assert!(diff.unified_diff.contains("+# Note"));
+    assert_eq!(diff.granularity, "word");
+    assert_eq!(diff.inline_changes.len(), 1);
+    assert_eq!(diff.inline_changes[0].kind, "insert");
+    assert_eq!(diff.structural_changes[0].kind, "added");
 
     let committed = service
       .commit_all(root.to_string_lossy().to_string(), "Add note".to_string())
@@ -663,4 +815,85 @@ mod tests {
 
     let _ = std::fs::remove_dir_all(root);
   }
+
+  #[tokio::test]
+  async fn reads_and_resolves_conflicted_file() {
+    let root = temp_dir("git-conflict");
+    std::fs::create_dir_all(&root).expect("temp dir should be created");
+    let root_path = root.to_string_lossy().to_string();
+    let service = GitService;
+    service
+      .init(root_path.clone())
+      .await
+      .expect("init should create repository");
+    std::fs::write(root.join("note.md"), "base\n").expect("worktree file should be written");
+    service
+      .commit_all(root_path.clone(), "Add note".to_string())
+      .await
+      .expect("initial commit should succeed");
+
+    let repo = gix::discover(&root).expect("repository should open");
+    let mut index = repo.open_index().expect("index should open");
+    index.remove_entries(|_, path, _| path == "note.md");
+    for (stage, content) in [
+      (Stage::Base, "base\n"),
+      (Stage::Ours, "ours\n"),
+      (Stage::Theirs, "theirs\n"),
+    ] {
+      let id = repo
+        .write_blob(content.as_bytes())
+        .expect("blob should be written")
+        .detach();
+      index.dangerously_push_entry(
+        gix::index::entry::Stat::default(),
+        id,
+        gix::index::entry::Flags::from_stage(stage),
+        gix::index::entry::Mode::FILE,
+        "note.md".into(),
+      );
+    }
+    index.sort_entries();
+    index
+      .write(Default::default())
+      .expect("conflicted index should be written");
+    std::fs::write(
+      root.join("note.md"),
+      "<<<<<<< ours\nours\n=======\ntheirs\n>>>>>>> theirs\n",
+    )
+    .expect("conflicted worktree file should be written");
+
+    let conflict = service
+      .conflict_file(root_path.clone(), "note.md".to_string())
+      .await
+      .expect("conflict should be readable");
+    assert_eq!(conflict.base_content.as_deref(), Some("base\n"));
+    assert_eq!(conflict.ours_content.as_deref(), Some("ours\n"));
+    assert_eq!(conflict.theirs_content.as_deref(), Some("theirs\n"));
+    assert_eq!(conflict.hunks.len(), 1);
+
+    let (resolved, written) = service
+      .resolve_conflict(
+        root_path.clone(),
+        "note.md".to_string(),
+        vec![GitConflictResolution {
+          hunk: 0,
+          choice: "theirs".to_string(),
+          content: None,
+        }],
+      )
+      .await
+      .expect("conflict should resolve");
+    assert!(resolved.conflicts.is_empty());
+    assert!(written.ends_with("note.md"));
+    assert_eq!(
+      std::fs::read_to_string(root.join("note.md")).expect("resolved file should be readable"),
+      "theirs\n"
+    );
+    service
+      .commit_all(root_path, "Resolve note".to_string())
+      .await
+      .expect("resolved file should commit");
+
+    let _ = std::fs::remove_dir_all(root);
+  }
 }
@@ -0,0 +1,210 @@
+use crate::error::{AppError, AppResult};
+use crate::models::{GitConflictHunk, GitConflictResolution};
+
+const OURS_MARKER: &str = "<<<<<<<";
+const BASE_MARKER: &str = "|||||||";
+const SEPARATOR_MARKER: &str = "=======";
+const THEIRS_MARKER: &str = ">>>>>>>";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum ConflictSegment {
+  Text(String),
+  Conflict(GitConflictHunk),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ConflictSection {
+  Ours,
+  Base,
+  Theirs,
+}
+
+/// Splits `content` into plain text and conflict hunks delimited by git's
+/// merge markers. Both the default and the `diff3` marker styles are
+/// understood; unterminated hunks are kept as plain text.
+pub(super) fn parse_conflict_segments(content: &str) -> Vec<ConflictSegment> {
+  let mut segments = Vec::new();
+  let mut text = String::new();
+  let mut hunk: Option<(GitConflictHunk, ConflictSection, String)> = None;
+
+  for (line_index, line) in content.split_inclusive('\n').enumerate() {
+    let line_number = line_index + 1;
+    let Some((current, section, raw)) = hunk.as_mut() else {
+      if let Some(label) = marker_label(line, OURS_MARKER) {
+        if !text.is_empty() {
+          segments.push(ConflictSegment::Text(std::mem::take(&mut text)));
+        }
+        hunk = Some((
+          GitConflictHunk {
+            index: 0,
+            start_line: line_number,
+            end_line: line_number,
+            ours_label: label,
+            theirs_label: String::new(),
+            ours: String::new(),
+            base: None,
+            theirs: String::new(),
+          },
+          ConflictSection::Ours,
+          line.to_string(),
+        ));
+      } else {
+        text.push_str(line);
+      }
+      continue;
+    };
+
+    raw.push_str(line);
+    if *section == ConflictSection::Ours && marker_label(line, BASE_MARKER).is_some() {
+      *section = ConflictSection::Base;
+      current.base = Some(String::new());
+    } else if *section != ConflictSection::Theirs && marker_label(line, SEPARATOR_MARKER).is_some()
+    {
+      *section = ConflictSection::Theirs;
+    } else if *section == ConflictSection::Theirs {
+      if let Some(label) = marker_label(line, THEIRS_MARKER) {
+        let Some((mut current, _, _)) = hunk.take() else {
+          continue;
+        };
+        current.index = segments
+          .iter()
+          .filter(|segment| matches!(segment, ConflictSegment::Conflict(_)))
+          .count();
+        current.end_line = line_number;
+        current.theirs_label = label;
+        segments.push(ConflictSegment::Conflict(current));
+        continue;
+      }
+      current.theirs.push_str(line);
+    } else if *section == ConflictSection::Base {
+      current.base.get_or_insert_with(String::new).push_str(line);
+    } else {
+      current.ours.push_str(line);
+    }
+  }
+
+  if let Some((_, _, raw)) = hunk {
+    text.push_str(&raw);
+  }
+  if !text.is_empty() {
+    segments.push(ConflictSegment::Text(text));
+  }
+  segments
+}
+
+pub(super) fn conflict_hunks(segments: &[ConflictSegment]) -> Vec<GitConflictHunk> {
+  segments
+    .iter()
+    .filter_map(|segment| match segment {
+      ConflictSegment::Conflict(hunk) => Some(hunk.clone()),
+      ConflictSegment::Text(_) => None,
+    })
+    .collect()
+}
+
+/// Rebuilds the file from `segments`, replacing every hunk with the chosen
+/// resolution. Every hunk must be resolved.
+pub(super) fn apply_conflict_resolutions(
+  segments: &[ConflictSegment],
+  resolutions: &[GitConflictResolution],
+) -> AppResult<String> {
+  let mut out = String::new();
+  for segment in segments {
+    let hunk = match segment {
+      ConflictSegment::Text(text) => {
+        out.push_str(text);
+        continue;
+      }
+      ConflictSegment::Conflict(hunk) => hunk,
+    };
+    let resolution = resolutions
+      .iter()
+      .find(|resolution| resolution.hunk == hunk.index)
+      .ok_or_else(|| format!("Conflict hunk {} is not resolved", hunk.index + 1))?;
+    match resolution.choice.as_str() {
+      "ours" => out.push_str(&hunk.ours),
+      "theirs" => out.push_str(&hunk.theirs),
+      "both" => {
+        out.push_str(&hunk.ours);
+        out.push_str(&hunk.theirs);
+      }
+      "base" => out.push_str(hunk.base.as_deref().ok_or_else(|| {
+        format!(
+          "Conflict hunk {} has no base version; enable diff3 conflict style",
+          hunk.index + 1
+        )
+      })?),
+      "custom" => {
+        let content = resolution.content.as_deref().unwrap_or_default();
+        out.push_str(content);
+        if !content.is_empty() && !content.ends_with('\n') {
+          out.push('\n');
+        }
+      }
+      choice => {
+        return Err(AppError::invalid_input(format!(
+          "Unsupported conflict resolution: {choice}"
+        )))
+      }
+    }
+  }
+  Ok(out)
+}
+
+fn marker_label(line: &str, marker: &str) -> Option<String> {
+  let line = line.trim_end_matches(['\r', '\n']);
+  let rest = line.strip_prefix(marker)?;
+  if rest.is_empty() {
+    return Some(String::new());
+  }
+  rest.strip_prefix(' ').map(|label| label.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CONFLICTED: &str = "# Title\n<<<<<<< HEAD\nours line\n||||||| base\nbase line\n=======\ntheirs line\n>>>>>>> feature\ntail\n";
+
+  #[test]
+  fn parses_diff3_conflict_markers() {
+    let segments = parse_conflict_segments(CONFLICTED);
+    let hunks = conflict_hunks(&segments);
+
+    assert_eq!(segments.len(), 3);
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(hunks[0].start_line, 2);
+    assert_eq!(hunks[0].end_line, 8);
+    assert_eq!(hunks[0].ours_label, "HEAD");
+    assert_eq!(hunks[0].theirs_label, "feature");
+    assert_eq!(hunks[0].ours, "ours line\n");
+    assert_eq!(hunks[0].base.as_deref(), Some("base line\n"));
+    assert_eq!(hunks[0].theirs, "theirs line\n");
+  }
+
+  #[test]
+  fn applies_per_hunk_resolutions() {
+    let segments = parse_conflict_segments(CONFLICTED);
+    let both = apply_conflict_resolutions(
+      &segments,
+      &[GitConflictResolution {
+        hunk: 0,
+        choice: "both".to_string(),
+        content: None,
+      }],
+    )
+    .expect("resolution should apply");
+    assert_eq!(both, "# Title\nours line\ntheirs line\ntail\n");
+
+    let missing = apply_conflict_resolutions(&segments, &[]);
+    assert!(missing.is_err());
+  }
+
+  #[test]
+  fn keeps_unterminated_markers_as_text() {
+    let content = "<<<<<<< HEAD\nnot a conflict\n";
+    let segments = parse_conflict_segments(content);
+
+    assert_eq!(segments, vec![ConflictSegment::Text(content.to_string())]);
+  }
+}
@@ -0,0 +1,406 @@
+use similar::{capture_diff_slices, Algorithm, DiffOp, DiffTag, TextDiff};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{GitInlineChange, GitStructuralChange};
+
+/// Sections whose words overlap less than this are reported as rewritten
+/// rather than edited.
+const REWRITE_RATIO: f32 = 0.5;
+/// Unmatched sections whose bodies overlap at least this much are reported
+/// as a renamed heading instead of a removal and an addition.
+const RENAME_RATIO: f32 = 0.8;
+
+pub(super) fn normalize_granularity(granularity: Option<&str>) -> AppResult<&'static str> {
+  match granularity.unwrap_or("line") {
+    "line" => Ok("line"),
+    "word" => Ok("word"),
+    "sentence" => Ok("sentence"),
+    "char" => Ok("char"),
+    other => Err(AppError::invalid_input(format!(
+      "Unsupported diff granularity: {other}"
+    ))),
+  }
+}
+
+/// Computes sub-line changes between `original` and `modified`. Line
+/// granularity is already covered by the unified diff and yields nothing.
+pub(super) fn inline_changes(
+  original: &str,
+  modified: &str,
+  granularity: &str,
+) -> Vec<GitInlineChange> {
+  match granularity {
+    "word" => collect_inline_changes(&TextDiff::from_words(original, modified)),
+    "char" => collect_inline_changes(&TextDiff::from_chars(original, modified)),
+    "sentence" => {
+      let original_tokens = sentence_tokens(original);
+      let modified_tokens = sentence_tokens(modified);
+      collect_inline_changes(&TextDiff::from_slices(&original_tokens, &modified_tokens))
+    }
+    _ => Vec::new(),
+  }
+}
+
+fn collect_inline_changes(diff: &TextDiff<'_, '_, '_, str>) -> Vec<GitInlineChange> {
+  let original_offsets = char_offsets(diff.old_slices());
+  let modified_offsets = char_offsets(diff.new_slices());
+  let mut changes: Vec<GitInlineChange> = Vec::new();
+
+  for op in diff.ops() {
+    let (tag, original, modified) = op.as_tag_tuple();
+    let kind = match tag {
+      DiffTag::Equal => continue,
+      DiffTag::Delete => "delete",
+      DiffTag::Insert => "insert",
+      DiffTag::Replace => "replace",
+    };
+    let change = GitInlineChange {
+      kind: kind.to_string(),
+      original_start: original_offsets[original.start],
+      original_end: original_offsets[original.end],
+      modified_start: modified_offsets[modified.start],
+      modified_end: modified_offsets[modified.end],
+    };
+
+    // Myers emits a replacement as a delete directly followed by an insert;
+    // fold those back together so the frontend can render one change.
+    if let Some(previous) = changes.last_mut() {
+      if previous.kind == "delete"
+        && change.kind == "insert"
+        && previous.original_end == change.original_start
+        && previous.modified_end == change.modified_start
+      {
+        previous.kind = "replace".to_string();
+        previous.modified_end = change.modified_end;
+        continue;
+      }
+    }
+    changes.push(change);
+  }
+  changes
+}
+
+fn char_offsets(tokens: &[&str]) -> Vec<usize> {
+  let mut offsets = Vec::with_capacity(tokens.len() + 1);
+  let mut offset = 0;
+  offsets.push(offset);
+  for token in tokens {
+    offset += token.chars().count();
+    offsets.push(offset);
+  }
+  offsets
+}
+
+fn sentence_tokens(text: &str) -> Vec<&str> {
+  let mut tokens = Vec::new();
+  let mut start = 0;
+  let mut chars = text.char_indices().peekable();
+
+  while let Some((index, ch)) = chars.next() {
+    let boundary = match ch {
+      '\n' => true,
+      '.' | '!' | '?' => chars.peek().map_or(true, |(_, next)| next.is_whitespace()),
+      _ => false,
+    };
+    if !boundary {
+      continue;
+    }
+
+    let mut end = index + ch.len_utf8();
+    while let Some(&(next_index, next)) = chars.peek() {
+      if next != ' ' && next != '\t' {
+        break;
+      }
+      end = next_index + next.len_utf8();
+      chars.next();
+    }
+    tokens.push(&text[start..end]);
+    start = end;
+  }
+
+  if start < text.len() {
+    tokens.push(&text[start..]);
+  }
+  tokens
+}
+
+struct MarkdownSection {
+  key: String,
+  heading: String,
+  level: usize,
+  body: String,
+}
+
+/// Compares the heading outline of two markdown documents and reports
+/// sections that were added, removed, moved, renamed, edited or rewritten.
+pub(super) fn structural_changes(original: &str, modified: &str) -> Vec<GitStructuralChange> {
+  let original_sections = markdown_sections(original);
+  let modified_sections = markdown_sections(modified);
+  let original_keys = original_sections
+    .iter()
+    .map(|section| section.key.as_str())
+    .collect::<Vec<_>>();
+  let modified_keys = modified_sections
+    .iter()
+    .map(|section| section.key.as_str())
+    .collect::<Vec<_>>();
+
+  let mut matched = Vec::new();
+  let mut removed = Vec::new();
+  let mut added = Vec::new();
+  for op in capture_diff_slices(Algorithm::Myers, &original_keys, &modified_keys) {
+    match op {
+      DiffOp::Equal {
+        old_index,
+        new_index,
+        len,
+      } => matched.extend((0..len).map(|offset| (old_index + offset, new_index + offset, false))),
+      DiffOp::Delete {
+        old_index, old_len, ..
+      } => removed.extend(old_index..old_index + old_len),
+      DiffOp::Insert {
+        new_index, new_len, ..
+      } => added.extend(new_index..new_index + new_len),
+      DiffOp::Replace {
+        old_index,
+        old_len,
+        new_index,
+        new_len,
+      } => {
+        removed.extend(old_index..old_index + old_len);
+        added.extend(new_index..new_index + new_len);
+      }
+    }
+  }
+
+  // A section that disappears in one place and reappears elsewhere moved.
+  removed.retain(|&old_index| {
+    let key = &original_sections[old_index].key;
+    let Some(position) = added
+      .iter()
+      .position(|&new_index| &modified_sections[new_index].key == key)
+    else {
+      return true;
+    };
+    matched.push((old_index, added.remove(position), true));
+    false
+  });
+
+  let mut changes = Vec::new();
+  for (old_index, new_index, moved) in matched {
+    let section = &modified_sections[new_index];
+    if moved {
+      changes.push(structural_change(
+        "moved",
+        section,
+        Some(old_index),
+        Some(new_index),
+      ));
+    }
+    let original_body = &original_sections[old_index].body;
+    if original_body != &section.body {
+      let kind = if word_ratio(original_body, &section.body) < REWRITE_RATIO {
+        "rewritten"
+      } else {
+        "edited"
+      };
+      changes.push(structural_change(
+        kind,
+        section,
+        Some(old_index),
+        Some(new_index),
+      ));
+    }
+  }
+
+  removed.retain(|&old_index| {
+    let body = &original_sections[old_index].body;
+    let Some(position) = added.iter().position(|&new_index| {
+      !body.trim().is_empty()
+        && word_ratio(body, &modified_sections[new_index].body) >= RENAME_RATIO
+    }) else {
+      return true;
+    };
+    let new_index = added.remove(position);
+    changes.push(structural_change(
+      "renamed",
+      &modified_sections[new_index],
+      Some(old_index),
+      Some(new_index),
+    ));
+    false
+  });
+
+  changes.extend(removed.into_iter().map(|old_index| {
+    structural_change(
+      "removed",
+      &original_sections[old_index],
+      Some(old_index),
+      None,
+    )
+  }));
+  changes.extend(added.into_iter().map(|new_index| {
+    structural_change(
+      "added",
+      &modified_sections[new_index],
+      None,
+      Some(new_index),
+    )
+  }));
+  changes.sort_by_key(|change| {
+    (
+      change.modified_index.unwrap_or(usize::MAX),
+      change.original_index.unwrap_or(usize::MAX),
+    )
+  });
+  changes
+}
+
+fn structural_change(
+  kind: &str,
+  section: &MarkdownSection,
+  original_index: Option<usize>,
+  modified_index: Option<usize>,
+) -> GitStructuralChange {
+  GitStructuralChange {
+    kind: kind.to_string(),
+    heading: section.heading.clone(),
+    level: section.level,
+    original_index,
+    modified_index,
+  }
+}
+
+fn word_ratio(original: &str, modified: &str) -> f32 {
+  TextDiff::from_words(original, modified).ratio()
+}
+
+/// Splits markdown into heading-delimited sections. Text before the first
+/// heading becomes a level 0 section with an empty heading.
+fn markdown_sections(content: &str) -> Vec<MarkdownSection> {
+  let mut sections = vec![MarkdownSection {
+    key: "0:".to_string(),
+    heading: String::new(),
+    level: 0,
+    body: String::new(),
+  }];
+  let mut fence: Option<&str> = None;
+
+  for line in content.split_inclusive('\n') {
+    let trimmed = line.trim_start();
+    if let Some(marker) = fence {
+      if trimmed.starts_with(marker) {
+        fence = None;
+      }
+    } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+      fence = Some(&trimmed[..3]);
+    } else if let Some((level, heading)) = atx_heading(line) {
+      let occurrence = sections
+        .iter()
+        .filter(|section| section.level == level && section.heading == heading)
+        .count();
+      sections.push(MarkdownSection {
+        key: format!("{level}:{heading}#{occurrence}"),
+        heading,
+        level,
+        body: String::new(),
+      });
+      continue;
+    }
+
+    if let Some(section) = sections.last_mut() {
+      section.body.push_str(line);
+    }
+  }
+
+  if sections[0].body.trim().is_empty() {
+    sections.remove(0);
+  }
+  sections
+}
+
+fn atx_heading(line: &str) -> Option<(usize, String)> {
+  let line = line.trim_end_matches(['\r', '\n']);
+  let indent = line.len() - line.trim_start_matches(' ').len();
+  if indent > 3 {
+    return None;
+  }
+  let rest = &line[indent..];
+  let level = rest.chars().take_while(|ch| *ch == '#').count();
+  if !(1..=6).contains(&level) {
+    return None;
+  }
+  let text = &rest[level..];
+  if !text.is_empty() && !text.starts_with([' ', '\t']) {
+    return None;
+  }
+  let heading = text.trim().trim_end_matches('#').trim_end().to_string();
+  Some((level, heading))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reports_word_level_ranges_within_a_paragraph() {
+    let changes = inline_changes(
+      "The quick brown fox jumps.",
+      "The quick red fox jumps.",
+      "word",
+    );
+
+    assert_eq!(
+      changes,
+      vec![GitInlineChange {
+        kind: "replace".to_string(),
+        original_start: 10,
+        original_end: 15,
+        modified_start: 10,
+        modified_end: 13,
+      }]
+    );
+  }
+
+  #[test]
+  fn splits_sentences_on_terminal_punctuation() {
+    assert_eq!(
+      sentence_tokens("One. Two? Three\nFour"),
+      vec!["One. ", "Two? ", "Three\n", "Four"]
+    );
+    let changes = inline_changes("One. Two. Three.", "One. Deux. Three.", "sentence");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].kind, "replace");
+    assert_eq!(
+      (changes[0].original_start, changes[0].original_end),
+      (5, 10)
+    );
+  }
+
+  #[test]
+  fn reports_moved_rewritten_and_added_sections() {
+    let original = "# Intro\nhello world\n\n## Usage\nrun the app\n\n## Notes\nkeep this\n";
+    let modified = "# Intro\nsomething entirely different now\n\n## Notes\nkeep this\n\n## Usage\nrun the app\n\n## Extra\nnew\n";
+    let changes = structural_changes(original, modified);
+    let summary = changes
+      .iter()
+      .map(|change| (change.kind.as_str(), change.heading.as_str()))
+      .collect::<Vec<_>>();
+
+    assert!(summary.contains(&("rewritten", "Intro")));
+    assert!(summary.contains(&("added", "Extra")));
+    assert!(
+      summary.contains(&("moved", "Usage")) || summary.contains(&("moved", "Notes")),
+      "expected a moved section in {summary:?}"
+    );
+    assert!(!summary.iter().any(|(kind, _)| *kind == "removed"));
+  }
+
+  #[test]
+  fn ignores_headings_inside_code_fences() {
+    let sections = markdown_sections("# Title\n```sh\n# not a heading\n```\n");
+
+    assert_eq!(sections.len(), 1);
+    assert_eq!(sections[0].heading, "Title");
+  }
+}
@@ -8,7 +8,7 @@ use url::Url;
 
 use crate::{
   models::{FsMarkdownAssetImportResult, FsMarkdownAssetResolveResult},
-  services::path_resolver::PathResolver,
+  services::path_resolver::{mounted_root_prefix, PathResolver},
   state::{FsState, FsStateData},
 };
 
@@ -257,8 +257,7 @@ fn resolve_markdown_asset_blocking(
     document_dir.join(target_path)
   }
   .clean();
-  let relative_path = diff_paths(&absolute_path, workspace_root(data))
-    .map(|path| path.to_string_lossy().replace('\\', "/"));
+  let relative_path = workspace_relative_path(data, &absolute_path).ok();
   let exists = absolute_path.is_file();
 
   Ok(FsMarkdownAssetResolveResult {
@@ -314,7 +313,7 @@ fn media_lookup_target(target: &str) -> &str {
 }
 
 fn ensure_workspace_descendant(data: &FsStateData, path: &Path) -> Result<(), String> {
-  let workspace_root = workspace_root(data);
+  let (workspace_root, _) = workspace_root(data, path);
   if path.starts_with(&workspace_root) {
     return Ok(());
   }
@@ -322,23 +321,39 @@ fn ensure_workspace_descendant(data: &FsStateData, path: &Path) -> Result<(), St
 }
 
 fn workspace_relative_path(data: &FsStateData, path: &Path) -> Result<String, String> {
-  let workspace_root = workspace_root(data);
-  diff_paths(path, &workspace_root)
-    .ok_or_else(|| "Failed to compute workspace relative asset path".to_string())
-    .map(|path| path.to_string_lossy().replace('\\', "/"))
+  let (workspace_root, prefix) = workspace_root(data, path);
+  let relative = diff_paths(path, &workspace_root)
+    .ok_or_else(|| "Failed to compute workspace relative asset path".to_string())?
+    .to_string_lossy()
+    .replace('\\', "/");
+  Ok(match prefix {
+    Some(prefix) => format!("{prefix}/{relative}"),
+    None => relative,
+  })
 }
 
-fn workspace_root(data: &FsStateData) -> PathBuf {
+/// Root `path` belongs to, with the path prefix of a mounted root.
+fn workspace_root(data: &FsStateData, path: &Path) -> (PathBuf, Option<String>) {
   if data.root_kind == "single" {
-    data
+    let root = data
       .single_file
       .as_ref()
       .and_then(|path| path.parent().map(Path::to_path_buf))
-      .unwrap_or_else(|| data.root_path.clone())
-  } else {
-    data.root_path.clone()
+      .unwrap_or_else(|| data.root_path.clone());
+    return (root.clean(), None);
   }
-  .clean()
+  data
+    .mounted_roots
+    .iter()
+    .map(|root| (root.path.clean(), root))
+    .filter(|(root_path, _)| path.starts_with(root_path))
+    .max_by_key(|(root_path, _)| root_path.components().count())
+    .filter(|(root_path, _)| {
+      root_path.components().count() > data.root_path.clean().components().count()
+        || !path.starts_with(data.root_path.clean())
+    })
+    .map(|(root_path, root)| (root_path, Some(mounted_root_prefix(&root.name))))
+    .unwrap_or_else(|| (data.root_path.clean(), None))
 }
 
 fn unique_target_path(asset_dir: &Path, file_name: &str) -> PathBuf {
@@ -440,6 +455,7 @@ mod tests {
       root_path: root.to_path_buf(),
       internal_root: PathBuf::new(),
       single_file: None,
+      mounted_roots: Vec::new(),
     }
   }
 
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
 use camino::Utf8Path;
 use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
@@ -18,6 +19,24 @@ impl MarkdownGraphService {
   pub fn build_workspace_graph(&self, index: &FsWorkspaceIndex) -> FsGraph {
     build_workspace_graph(index)
   }
+
+  pub fn locate_code_block(
+    &self,
+    path: &str,
+    markdown: &str,
+    block_id: &str,
+  ) -> Option<CodeBlockLocation> {
+    locate_code_block(path, markdown, block_id)
+  }
+}
+
+/// A fenced code block found by its outline block id. `range` covers the
+/// fences and ends at the start of the line after the closing fence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlockLocation {
+  pub language: Option<String>,
+  pub code: String,
+  pub range: Range<usize>,
 }
 
 #[derive(Debug)]
@@ -29,6 +48,43 @@ struct HeadingDraft {
 }
 
 pub fn build_outline_graph(path: &str, markdown: &str) -> FsGraph {
+  let (mut nodes, edges, headings) = parse_outline(path, markdown);
+  apply_heading_content(markdown, &headings, &mut nodes);
+
+  FsGraph {
+    mode: "outline".to_string(),
+    nodes,
+    edges,
+  }
+}
+
+pub fn locate_code_block(path: &str, markdown: &str, block_id: &str) -> Option<CodeBlockLocation> {
+  let (_, _, headings) = parse_outline(path, markdown);
+  let index = headings
+    .iter()
+    .position(|heading| block_id.starts_with(&format!("{}:block:", heading.node_id)))?;
+  let content = heading_content_range(markdown, &headings, index);
+  let (block, range) =
+    parse_markdown_block_ranges(&headings[index].node_id, &markdown[content.clone()])
+      .into_iter()
+      .find(|(block, _)| block.id == block_id && block.kind == "code")?;
+  let end = content.start + range.end;
+  let end = if markdown[..end].ends_with('\n') {
+    end
+  } else {
+    line_end_offset(markdown, end)
+  };
+  Some(CodeBlockLocation {
+    language: block.language,
+    code: block.text.unwrap_or_default(),
+    range: content.start + range.start..end,
+  })
+}
+
+fn parse_outline(
+  path: &str,
+  markdown: &str,
+) -> (Vec<FsGraphNode>, Vec<FsGraphEdge>, Vec<ParsedHeading>) {
   let mut nodes = vec![FsGraphNode {
     id: file_node_id(path),
     kind: "file".to_string(),
@@ -117,13 +173,7 @@ pub fn build_outline_graph(path: &str, markdown: &str) -> FsGraph {
     }
   }
 
-  apply_heading_content(markdown, &headings, &mut nodes);
-
-  FsGraph {
-    mode: "outline".to_string(),
-    nodes,
-    edges,
-  }
+  (nodes, edges, headings)
 }
 
 #[derive(Debug)]
@@ -362,16 +412,11 @@ fn apply_heading_content(markdown: &str, headings: &[ParsedHeading], nodes: &mut
     .iter()
     .enumerate()
     .map(|(index, heading)| {
-      let next_heading_start = headings.get(index + 1).map(|next| next.start_offset);
-      let content_end = next_heading_start.unwrap_or(markdown.len());
-      let content_end_line = next_heading_start
-        .map(|offset| line_number(markdown, offset))
+      let content_end_line = headings
+        .get(index + 1)
+        .map(|next| line_number(markdown, next.start_offset))
         .unwrap_or_else(|| markdown.lines().count() + 1);
-      let content = markdown
-        .get(heading.content_start..content_end)
-        .unwrap_or_default()
-        .trim_matches('\n')
-        .to_string();
+      let content = markdown[heading_content_range(markdown, headings, index)].to_string();
       (
         heading.node_id.as_str(),
         (
@@ -394,6 +439,22 @@ fn apply_heading_content(markdown: &str, headings: &[ParsedHeading], nodes: &mut
   });
 }
 
+/// Byte range of a heading's content without surrounding blank lines.
+fn heading_content_range(markdown: &str, headings: &[ParsedHeading], index: usize) -> Range<usize> {
+  let end = headings
+    .get(index + 1)
+    .map(|next| next.start_offset)
+    .unwrap_or(markdown.len());
+  let start = headings[index].content_start.min(end);
+  let content = &markdown[start..end];
+  let leading = content.len() - content.trim_start_matches('\n').len();
+  let trailing = content.len() - content.trim_end_matches('\n').len();
+  if leading == content.len() {
+    return start..start;
+  }
+  start + leading..end - trailing
+}
+
 #[derive(Debug, Default)]
 struct TextDraft {
   text: String,
@@ -413,20 +474,31 @@ struct ListDraft {
 }
 
 fn parse_markdown_blocks(base_id: &str, markdown: &str) -> Vec<FsMarkdownBlock> {
+  parse_markdown_block_ranges(base_id, markdown)
+    .into_iter()
+    .map(|(block, _)| block)
+    .collect()
+}
+
+/// Blocks of a heading's content with the byte range each was parsed from.
+fn parse_markdown_block_ranges(
+  base_id: &str,
+  markdown: &str,
+) -> Vec<(FsMarkdownBlock, Range<usize>)> {
   let mut blocks = Vec::new();
   let mut paragraph: Option<TextDraft> = None;
   let mut blockquote: Option<TextDraft> = None;
   let mut code: Option<CodeDraft> = None;
   let mut list: Option<ListDraft> = None;
 
-  for event in Parser::new_ext(markdown, markdown_options()) {
+  for (event, range) in Parser::new_ext(markdown, markdown_options()).into_offset_iter() {
     match event {
       Event::Start(Tag::Paragraph) if list.is_none() && blockquote.is_none() => {
         paragraph = Some(TextDraft::default());
       }
       Event::End(TagEnd::Paragraph) => {
         if let Some(draft) = paragraph.take() {
-          push_text_block(base_id, &mut blocks, "paragraph", draft.text);
+          push_text_block(base_id, &mut blocks, "paragraph", draft.text, range);
         }
       }
       Event::Start(Tag::BlockQuote(_)) => {
@@ -434,7 +506,7 @@ fn parse_markdown_blocks(base_id: &str, markdown: &str) -> Vec<FsMarkdownBlock>
       }
       Event::End(TagEnd::BlockQuote(_)) => {
         if let Some(draft) = blockquote.take() {
-          push_text_block(base_id, &mut blocks, "blockquote", draft.text);
+          push_text_block(base_id, &mut blocks, "blockquote", draft.text, range);
         }
       }
       Event::Start(Tag::CodeBlock(kind)) => {
@@ -447,15 +519,18 @@ fn parse_markdown_blocks(base_id: &str, markdown: &str) -> Vec<FsMarkdownBlock>
         if let Some(draft) = code.take() {
           let text = draft.text.trim_matches('\n').to_string();
           if !text.is_empty() {
-            blocks.push(FsMarkdownBlock {
-              id: markdown_block_id(base_id, blocks.len()),
-              kind: "code".to_string(),
-              text: Some(text),
-              level: None,
-              language: draft.language,
-              ordered: None,
-              items: None,
-            });
+            blocks.push((
+              FsMarkdownBlock {
+                id: markdown_block_id(base_id, blocks.len()),
+                kind: "code".to_string(),
+                text: Some(text),
+                level: None,
+                language: draft.language,
+                ordered: None,
+                items: None,
+              },
+              range,
+            ));
           }
         }
       }
@@ -475,15 +550,18 @@ fn parse_markdown_blocks(base_id: &str, markdown: &str) -> Vec<FsMarkdownBlock>
             .filter(|item| !item.is_empty())
             .collect::<Vec<_>>();
           if !items.is_empty() {
-            blocks.push(FsMarkdownBlock {
-              id: markdown_block_id(base_id, blocks.len()),
-              kind: "list".to_string(),
-              text: None,
-              level: None,
-              language: None,
-              ordered: Some(draft.ordered),
-              items: Some(items),
-            });
+            blocks.push((
+              FsMarkdownBlock {
+                id: markdown_block_id(base_id, blocks.len()),
+                kind: "list".to_string(),
+                text: None,
+                level: None,
+                language: None,
+                ordered: Some(draft.ordered),
+                items: Some(items),
+              },
+              range,
+            ));
           }
         }
       }
@@ -500,15 +578,18 @@ fn parse_markdown_blocks(base_id: &str, markdown: &str) -> Vec<FsMarkdownBlock>
         }
       }
       Event::Rule => {
-        blocks.push(FsMarkdownBlock {
-          id: markdown_block_id(base_id, blocks.len()),
-          kind: "divider".to_string(),
-          text: None,
-          level: None,
-          language: None,
-          ordered: None,
-          items: None,
-        });
+        blocks.push((
+          FsMarkdownBlock {
+            id: markdown_block_id(base_id, blocks.len()),
+            kind: "divider".to_string(),
+            text: None,
+            level: None,
+            language: None,
+            ordered: None,
+            items: None,
+          },
+          range,
+        ));
       }
       Event::Text(text) | Event::Code(text) => {
         append_markdown_text(
@@ -529,20 +610,29 @@ fn parse_markdown_blocks(base_id: &str, markdown: &str) -> Vec<FsMarkdownBlock>
   blocks
 }
 
-fn push_text_block(base_id: &str, blocks: &mut Vec<FsMarkdownBlock>, kind: &str, text: String) {
+fn push_text_block(
+  base_id: &str,
+  blocks: &mut Vec<(FsMarkdownBlock, Range<usize>)>,
+  kind: &str,
+  text: String,
+  range: Range<usize>,
+) {
   let text = text.trim().to_string();
   if text.is_empty() {
     return;
   }
-  blocks.push(FsMarkdownBlock {
-    id: markdown_block_id(base_id, blocks.len()),
-    kind: kind.to_string(),
-    text: Some(text),
-    level: None,
-    language: None,
-    ordered: None,
-    items: None,
-  });
+  blocks.push((
+    FsMarkdownBlock {
+      id: markdown_block_id(base_id, blocks.len()),
+      kind: kind.to_string(),
+      text: Some(text),
+      level: None,
+      language: None,
+      ordered: None,
+      items: None,
+    },
+    range,
+  ));
 }
 
 fn append_markdown_text(
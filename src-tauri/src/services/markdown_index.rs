@@ -1,5 +1,7 @@
 mod diagnostics;
+mod mdx;
 mod normalize;
+mod org;
 mod parser;
 mod types;
 
@@ -0,0 +1,221 @@
+/// Blanks out MDX-only syntax so the markdown parser does not misread it:
+/// top-level `import`/`export` statements, JSX element and `{...}`
+/// expression blocks, and JSX tags and expressions inside markdown text.
+/// MDX has no raw HTML, so lowercase tags are JSX as well. Masked bytes
+/// become spaces and newlines are kept, so byte offsets and line numbers
+/// still match the original source.
+pub(super) fn mask_mdx_syntax(content: &str) -> String {
+  let lines = content.split_inclusive('\n').collect::<Vec<_>>();
+  let mut masked = String::with_capacity(content.len());
+  let mut fence: Option<String> = None;
+  let mut block: Option<MdxBlock> = None;
+
+  for line in lines {
+    let trimmed = line.trim_start();
+    if block.is_none() {
+      if let Some(marker) = fence.as_deref() {
+        if trimmed.starts_with(marker) {
+          fence = None;
+        }
+        masked.push_str(line);
+        continue;
+      }
+      if let Some(marker) = fence_marker(trimmed) {
+        fence = Some(marker);
+        masked.push_str(line);
+        continue;
+      }
+      block = MdxBlock::start(trimmed);
+    }
+
+    let Some(current) = block.as_mut() else {
+      mask_inline(&mut masked, line);
+      continue;
+    };
+    if current.consume(trimmed) {
+      block = None;
+    }
+    mask_line(&mut masked, line);
+  }
+  masked
+}
+
+enum MdxBlock {
+  /// ESM statement, ends at the next blank line.
+  Esm,
+  /// JSX element, ends once every opened component tag is closed.
+  Jsx { depth: isize },
+  /// Expression, ends once its braces balance.
+  Expression { depth: isize },
+}
+
+impl MdxBlock {
+  fn start(trimmed: &str) -> Option<Self> {
+    if trimmed.starts_with("import ") || trimmed.starts_with("export ") {
+      return Some(MdxBlock::Esm);
+    }
+    let mut chars = trimmed.chars();
+    match (chars.next(), chars.next()) {
+      (Some('<'), Some(next)) if next.is_ascii_alphabetic() || next == '>' => {
+        Some(MdxBlock::Jsx { depth: 0 })
+      }
+      (Some('{'), _) => Some(MdxBlock::Expression { depth: 0 }),
+      _ => None,
+    }
+  }
+
+  /// Consumes a line and returns whether the block ends with it.
+  fn consume(&mut self, trimmed: &str) -> bool {
+    let trimmed = trimmed.trim_end();
+    match self {
+      MdxBlock::Esm => trimmed.is_empty(),
+      MdxBlock::Jsx { depth } => {
+        *depth += jsx_depth_change(trimmed);
+        *depth <= 0
+      }
+      MdxBlock::Expression { depth } => {
+        for char in trimmed.chars() {
+          match char {
+            '{' => *depth += 1,
+            '}' => *depth -= 1,
+            _ => {}
+          }
+        }
+        *depth <= 0
+      }
+    }
+  }
+}
+
+/// Net number of JSX elements a line opens. Self-closing tags and closing
+/// tags on the same line cancel out.
+fn jsx_depth_change(line: &str) -> isize {
+  let bytes = line.as_bytes();
+  let mut change = 0;
+  let mut index = 0;
+  while index < bytes.len() {
+    if bytes[index] == b'<' {
+      let rest = &line[index + 1..];
+      if rest.starts_with('/') {
+        change -= 1;
+      } else if rest.starts_with('>') || rest.starts_with(|char: char| char.is_ascii_alphabetic()) {
+        let tag_end = rest.find('>').unwrap_or(rest.len());
+        if !rest[..tag_end].ends_with('/') {
+          change += 1;
+        }
+      }
+    }
+    index += 1;
+  }
+  change
+}
+
+/// Masks the JSX tags and `{...}` expressions of a markdown line. Code
+/// spans and the text between tags are kept.
+fn mask_inline(masked: &mut String, line: &str) {
+  let bytes = line.as_bytes();
+  let mut copied = 0;
+  let mut index = 0;
+  while index < bytes.len() {
+    let len = match bytes[index] {
+      b'\\' => {
+        index += 2;
+        continue;
+      }
+      b'`' => {
+        let ticks = bytes[index..]
+          .iter()
+          .take_while(|byte| **byte == b'`')
+          .count();
+        let marker = &line[index..index + ticks];
+        index += ticks
+          + line[index + ticks..]
+            .find(marker)
+            .map_or(0, |close| close + ticks);
+        continue;
+      }
+      b'{' => expression_len(&line[index..]),
+      b'<' => jsx_tag_len(&line[index..]),
+      _ => None,
+    };
+    let Some(len) = len else {
+      index += 1;
+      continue;
+    };
+    masked.push_str(&line[copied..index]);
+    mask_line(masked, &line[index..index + len]);
+    index += len;
+    copied = index;
+  }
+  masked.push_str(&line[copied..]);
+}
+
+/// Length of a `{...}` expression closed on the same line.
+fn expression_len(text: &str) -> Option<usize> {
+  let mut depth = 0;
+  for (offset, char) in text.char_indices() {
+    match char {
+      '{' => depth += 1,
+      '}' => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(offset + 1);
+        }
+      }
+      _ => {}
+    }
+  }
+  None
+}
+
+/// Length of a JSX opening, closing or fragment tag closed on the same
+/// line. Comparisons such as `a < b` and autolinks are not tags.
+fn jsx_tag_len(text: &str) -> Option<usize> {
+  let rest = text.strip_prefix('<')?;
+  let rest = rest.strip_prefix('/').unwrap_or(rest);
+  let name_len = rest
+    .find(|char: char| !(char.is_ascii_alphanumeric() || matches!(char, '.' | '-' | '_')))
+    .unwrap_or(rest.len());
+  let (name, tail) = rest.split_at(name_len);
+  let tag_like = if name.is_empty() {
+    tail.starts_with('>')
+  } else {
+    name.starts_with(|char: char| char.is_ascii_alphabetic())
+      && (tail.starts_with(['>', '/']) || tail.starts_with(char::is_whitespace))
+  };
+  if !tag_like {
+    return None;
+  }
+
+  let mut depth = 0;
+  let mut quote = None;
+  for (offset, char) in tail.char_indices() {
+    match (quote, char) {
+      (Some(open), _) if char == open => quote = None,
+      (Some(_), _) => {}
+      (None, '"' | '\'') => quote = Some(char),
+      (None, '{') => depth += 1,
+      (None, '}') => depth -= 1,
+      (None, '>') if depth <= 0 => return Some(text.len() - tail.len() + offset + 1),
+      _ => {}
+    }
+  }
+  None
+}
+
+fn fence_marker(trimmed: &str) -> Option<String> {
+  ["```", "~~~"]
+    .into_iter()
+    .find(|marker| trimmed.starts_with(marker))
+    .map(ToOwned::to_owned)
+}
+
+fn mask_line(masked: &mut String, line: &str) {
+  for char in line.chars() {
+    if char == '\n' || char == '\r' {
+      masked.push(char);
+    } else {
+      masked.extend(std::iter::repeat(' ').take(char.len_utf8()));
+    }
+  }
+}
@@ -11,6 +11,8 @@ use crate::models::{
   FsEntry, FsIndexedMarkdownFile, FsMarkdownAsset, FsMarkdownLink, FsWorkspaceIndex,
 };
 
+use crate::services::document_types::{document_extensions, is_document_path};
+
 use super::parser::slugify;
 use super::types::{ParsedMarkdownDocument, RawMarkdownAsset, RawMarkdownLink};
 
@@ -203,15 +205,10 @@ fn resolve_markdown_target_path(target: &str, existing_paths: &HashSet<String>)
     return normalized;
   }
 
-  let md = format!("{normalized}.md");
-  if existing_paths.contains(&md) {
-    return md;
-  }
-  let markdown = format!("{normalized}.markdown");
-  if existing_paths.contains(&markdown) {
-    return markdown;
-  }
-  md
+  document_extensions()
+    .map(|extension| format!("{normalized}.{extension}"))
+    .find(|candidate| existing_paths.contains(candidate))
+    .unwrap_or_else(|| format!("{normalized}.md"))
 }
 
 fn normalize_workspace_path(value: &str) -> String {
@@ -242,11 +239,10 @@ fn create_file_label(relative_path: &str) -> String {
   let base = Utf8Path::new(relative_path)
     .file_name()
     .unwrap_or(relative_path);
-  base
-    .strip_suffix(".markdown")
-    .or_else(|| base.strip_suffix(".md"))
-    .unwrap_or(base)
-    .to_string()
+  match base.rsplit_once('.') {
+    Some((stem, _)) if is_document_path(Path::new(base)) => stem.to_string(),
+    _ => base.to_string(),
+  }
 }
 
 fn is_external_target(target: &str) -> bool {
@@ -256,6 +252,5 @@ fn is_external_target(target: &str) -> bool {
 }
 
 fn has_markdown_extension(path: &str) -> bool {
-  let lower = path.to_lowercase();
-  lower.ends_with(".md") || lower.ends_with(".markdown")
+  is_document_path(Path::new(path))
 }
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use crate::models::FsMarkdownHeading;
+
+use super::parser::slugify;
+use super::types::{ParsedMarkdownDocument, RawMarkdownAsset, RawMarkdownLink};
+
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "svg", "webp"];
+
+/// Extracts `*` headings and `[[target][description]]` links from an Org
+/// document. `file:` links to images are reported as assets, and links
+/// without a path point at a heading in the same file.
+pub(super) fn parse_org_document(path: &str, content: &str) -> ParsedMarkdownDocument {
+  let mut headings = Vec::new();
+  let mut links = Vec::new();
+  let mut assets = Vec::new();
+  let mut used_slugs = HashMap::<String, usize>::new();
+  let mut in_block = false;
+
+  for (index, line) in content.lines().enumerate() {
+    let line_number = index + 1;
+    let trimmed = line.trim_start();
+    let lower = trimmed.to_ascii_lowercase();
+    if lower.starts_with("#+begin_") {
+      in_block = true;
+      continue;
+    }
+    if lower.starts_with("#+end_") {
+      in_block = false;
+      continue;
+    }
+    if in_block {
+      continue;
+    }
+
+    if let Some((level, text)) = org_heading(line) {
+      let base_slug = {
+        let slug = slugify(text);
+        if slug.is_empty() {
+          format!("heading-{}", headings.len() + 1)
+        } else {
+          slug
+        }
+      };
+      let used_count = used_slugs.get(&base_slug).copied().unwrap_or(0);
+      used_slugs.insert(base_slug.clone(), used_count + 1);
+      headings.push(FsMarkdownHeading {
+        path: path.to_string(),
+        level,
+        text: text.to_string(),
+        slug: if used_count == 0 {
+          base_slug
+        } else {
+          format!("{base_slug}-{used_count}")
+        },
+        line: line_number,
+      });
+    }
+
+    let context = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut rest = line;
+    let mut offset = 0;
+    while let Some(start) = rest.find("[[") {
+      let Some(end) = rest[start..].find("]]") else {
+        break;
+      };
+      let inner = &rest[start + 2..start + end];
+      let (target, description) = match inner.split_once("][") {
+        Some((target, description)) => (target, Some(description)),
+        None => (inner, None),
+      };
+      let column = line[..offset + start].chars().count() + 1;
+      push_org_link(
+        target.trim(),
+        description.map(str::trim),
+        &context,
+        line_number,
+        column,
+        &mut links,
+        &mut assets,
+      );
+      offset += start + end + 2;
+      rest = &line[offset..];
+    }
+  }
+
+  ParsedMarkdownDocument {
+    path: path.to_string(),
+    headings,
+    links,
+    assets,
+  }
+}
+
+fn org_heading(line: &str) -> Option<(u8, &str)> {
+  let stars = line.chars().take_while(|char| *char == '*').count();
+  if stars == 0 || !line[stars..].starts_with(' ') {
+    return None;
+  }
+  let text = line[stars..].trim();
+  // Drop trailing tags such as `:work:urgent:`.
+  let text = match text.rsplit_once(' ') {
+    Some((title, tags)) if tags.len() > 1 && tags.starts_with(':') && tags.ends_with(':') => {
+      title.trim_end()
+    }
+    _ => text,
+  };
+  (!text.is_empty()).then_some((stars.min(6) as u8, text))
+}
+
+fn push_org_link(
+  target: &str,
+  description: Option<&str>,
+  context: &str,
+  line: usize,
+  column: usize,
+  links: &mut Vec<RawMarkdownLink>,
+  assets: &mut Vec<RawMarkdownAsset>,
+) {
+  if target.is_empty() {
+    return;
+  }
+  let target = match target.strip_prefix("file:") {
+    Some(file) => file.replace("::*", "#").replace("::", "#"),
+    None if target.contains("://") || target.starts_with("mailto:") => target.to_string(),
+    None => format!("#{}", target.trim_start_matches('*')),
+  };
+  let is_image = target
+    .rsplit_once('.')
+    .map(|(_, extension)| IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+    .unwrap_or(false);
+  if is_image && description.is_none() {
+    assets.push(RawMarkdownAsset {
+      target,
+      text: String::new(),
+      context: context.to_string(),
+      line,
+      column,
+    });
+    return;
+  }
+  links.push(RawMarkdownLink {
+    text: description.unwrap_or(&target).to_string(),
+    target,
+    link_type: "org".to_string(),
+    context: context.to_string(),
+    line,
+    column,
+  });
+}
@@ -4,10 +4,27 @@ use pulldown_cmark::{Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd
 use slug::slugify as ascii_slugify;
 
 use crate::models::FsMarkdownHeading;
+use crate::services::document_types::{document_syntax, DocumentSyntax};
 
+use super::mdx::mask_mdx_syntax;
+use super::org::parse_org_document;
 use super::types::{ParsedMarkdownDocument, RawMarkdownAsset, RawMarkdownLink};
 
 pub(crate) fn parse_markdown_document(path: &str, content: &str) -> ParsedMarkdownDocument {
+  match document_syntax(path) {
+    DocumentSyntax::Markdown => parse_commonmark_document(path, content),
+    DocumentSyntax::Mdx => parse_commonmark_document(path, &mask_mdx_syntax(content)),
+    DocumentSyntax::Org => parse_org_document(path, content),
+    DocumentSyntax::PlainText => ParsedMarkdownDocument {
+      path: path.to_string(),
+      headings: Vec::new(),
+      links: Vec::new(),
+      assets: Vec::new(),
+    },
+  }
+}
+
+fn parse_commonmark_document(path: &str, content: &str) -> ParsedMarkdownDocument {
   ParsedMarkdownDocument {
     path: path.to_string(),
     headings: extract_headings(path, content),
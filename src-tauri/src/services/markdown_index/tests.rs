@@ -1,6 +1,7 @@
 use crate::models::FsEntry;
 
 use super::build_workspace_index;
+use super::mdx::mask_mdx_syntax;
 
 #[test]
 fn indexes_headings_and_normalized_links() {
@@ -68,3 +69,147 @@ fn indexes_headings_and_normalized_links() {
   );
   assert_eq!(current.assets[0].media_type.as_deref(), Some("image/png"));
 }
+
+#[test]
+fn indexes_mdx_org_and_plain_text_documents() {
+  let files = ["guide.mdx", "plan.org", "notes.txt", "setup.qmd"]
+    .into_iter()
+    .map(|path| FsEntry {
+      path: path.to_string(),
+      name: path.to_string(),
+      kind: "file".to_string(),
+    })
+    .collect::<Vec<_>>();
+  let contents = vec![
+    (
+      "guide.mdx".to_string(),
+      concat!(
+        "import { Chart } from './chart'\n",
+        "\n",
+        "# Guide\n",
+        "<Chart>\n",
+        "# Not a heading\n",
+        "</Chart>\n",
+        "{props.items.map((item) => <Item key={item} />)}\n",
+        "## Usage\n",
+        "See [plan](plan) and [setup](setup).\n",
+      )
+      .to_string(),
+    ),
+    (
+      "plan.org".to_string(),
+      concat!(
+        "#+TITLE: Plan\n",
+        "* Goals :work:\n",
+        "** Next steps\n",
+        "Read [[file:guide.mdx::*Usage][the guide]] and [[*Goals]].\n",
+        "#+BEGIN_SRC sh\n",
+        "* not a heading\n",
+        "#+END_SRC\n",
+        "[[file:diagram.png]]\n",
+      )
+      .to_string(),
+    ),
+    (
+      "notes.txt".to_string(),
+      "# plain\n[not](a-link.md)\n".to_string(),
+    ),
+    ("setup.qmd".to_string(), "# Setup\n".to_string()),
+  ];
+
+  let index = build_workspace_index(&files, &contents);
+  let file = |path: &str| {
+    index
+      .files
+      .iter()
+      .find(|file| file.path == path)
+      .expect("file should be indexed")
+  };
+
+  let guide = file("guide.mdx");
+  let headings = guide
+    .headings
+    .iter()
+    .map(|heading| (heading.text.as_str(), heading.line))
+    .collect::<Vec<_>>();
+  assert_eq!(headings, vec![("Guide", 3), ("Usage", 8)]);
+  assert_eq!(guide.links[0].target_path.as_deref(), Some("plan.org"));
+  assert_eq!(guide.links[1].target_path.as_deref(), Some("setup.qmd"));
+
+  let plan = file("plan.org");
+  let headings = plan
+    .headings
+    .iter()
+    .map(|heading| (heading.level, heading.slug.as_str()))
+    .collect::<Vec<_>>();
+  assert_eq!(headings, vec![(1, "goals"), (2, "next-steps")]);
+  assert_eq!(plan.links[0].link_type, "org");
+  assert_eq!(plan.links[0].text, "the guide");
+  assert_eq!(plan.links[0].target_path.as_deref(), Some("guide.mdx"));
+  assert_eq!(plan.links[0].target_heading_slug.as_deref(), Some("usage"));
+  assert_eq!(plan.links[1].target_path.as_deref(), Some("plan.org"));
+  assert_eq!(plan.links[1].target_heading_slug.as_deref(), Some("goals"));
+  assert_eq!(plan.assets[0].target_path.as_deref(), Some("diagram.png"));
+
+  let notes = file("notes.txt");
+  assert!(notes.headings.is_empty() && notes.links.is_empty());
+  assert_eq!(file("setup.qmd").headings[0].text, "Setup");
+}
+
+#[test]
+fn masks_multi_line_esm_until_the_next_blank_line() {
+  let content = concat!(
+    "import {\n",
+    "  Chart,\n",
+    "  Table\n",
+    "} from './components'\n",
+    "export const meta = {\n",
+    "  title: 'x',\n",
+    "  draft: true\n",
+    "}\n",
+    "\n",
+    "# Report\n",
+  );
+  let masked = mask_mdx_syntax(content);
+  assert_eq!(masked.len(), content.len());
+  let lines = masked.lines().collect::<Vec<_>>();
+  assert!(lines[..9].iter().all(|line| line.trim().is_empty()));
+  assert_eq!(lines[9], "# Report");
+}
+
+#[test]
+fn masks_lowercase_jsx_and_inline_expressions_in_mdx() {
+  let content = concat!(
+    "<div className=\"note\">\n",
+    "# Not a heading\n",
+    "</div>\n",
+    "Total: {items.length} and <Badge color={tone > 1 ? 'red' : 'blue'}>new</Badge>.\n",
+    "Keep `{code}` and <https://example.com>, 1 < 2.\n",
+  );
+  let masked = mask_mdx_syntax(content);
+  assert_eq!(masked.len(), content.len());
+  let lines = masked.lines().collect::<Vec<_>>();
+  assert!(lines[..3].iter().all(|line| line.trim().is_empty()));
+  assert_eq!(
+    lines[3].split_whitespace().collect::<Vec<_>>(),
+    vec!["Total:", "and", "new", "."]
+  );
+  assert_eq!(lines[4], "Keep `{code}` and <https://example.com>, 1 < 2.");
+
+  let files = vec![FsEntry {
+    path: "page.mdx".to_string(),
+    name: "page.mdx".to_string(),
+    kind: "file".to_string(),
+  }];
+  let contents = vec![(
+    "page.mdx".to_string(),
+    format!("{content}\n<section>\n\n## Inside\n\n</section>\n# Title {{year}}\n"),
+  )];
+  let index = build_workspace_index(&files, &contents);
+  let headings = index.files[0]
+    .headings
+    .iter()
+    .map(|heading| heading.text.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(headings, vec!["Title"]);
+}
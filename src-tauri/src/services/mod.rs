@@ -1,34 +1,58 @@
+pub mod background_tasks;
+pub mod code_runner;
 pub mod di;
 pub mod document_store;
+pub mod document_types;
 pub mod events;
 pub mod export;
+pub mod file_history;
 pub mod git;
 pub mod markdown_assets;
 pub mod markdown_graph;
 pub mod markdown_index;
 pub mod path_resolver;
+pub mod process;
+pub mod recovery;
 pub mod search;
+pub mod session;
+pub mod shutdown;
+pub mod snapshot;
+pub mod tasks;
 pub mod terminal;
 pub mod workspace;
 
 use fluxdi::Shared;
 
+use background_tasks::BackgroundTaskService;
+use code_runner::CodeRunnerService;
 use document_store::DocumentStoreService;
 use events::{EventBus, RuntimeService};
 pub use export::ExportService;
+use file_history::FileHistoryService;
 use git::GitService;
 use markdown_assets::MarkdownAssetService;
+use recovery::RecoveryJournalService;
+use session::SessionService;
+use snapshot::SnapshotService;
+use tasks::TaskRunnerService;
 use terminal::TerminalService;
 use workspace::WorkspaceService;
 
 #[derive(Debug, Clone)]
 pub struct AppServices {
+  pub background_tasks: Shared<BackgroundTaskService>,
+  pub code_runner: Shared<CodeRunnerService>,
   pub export: Shared<ExportService>,
   pub documents: Shared<DocumentStoreService>,
   pub events: Shared<EventBus>,
+  pub file_history: Shared<FileHistoryService>,
   pub git: Shared<GitService>,
   pub markdown_assets: Shared<MarkdownAssetService>,
+  pub recovery: Shared<RecoveryJournalService>,
   pub runtime: Shared<RuntimeService>,
+  pub session: Shared<SessionService>,
+  pub snapshots: Shared<SnapshotService>,
+  pub tasks: Shared<TaskRunnerService>,
   pub terminal: Shared<TerminalService>,
   pub workspace: Shared<WorkspaceService>,
 }
@@ -2,13 +2,18 @@ use std::path::{Component, Path, PathBuf};
 
 use path_clean::PathClean;
 
-use crate::state::FsStateData;
+use crate::error::{AppError, AppResult};
+use crate::state::{FsStateData, WorkspaceRoot};
+
+/// Marks the first component of a path inside a mounted root, as in
+/// `@docs/guide.md`.
+pub const MOUNTED_ROOT_PREFIX: char = '@';
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct PathResolver;
 
 impl PathResolver {
-  pub fn resolve(&self, data: &FsStateData, relative: &str) -> Result<PathBuf, String> {
+  pub fn resolve(&self, data: &FsStateData, relative: &str) -> AppResult<PathBuf> {
     resolve_path(data, relative)
   }
 
@@ -17,20 +22,23 @@ impl PathResolver {
   }
 }
 
-pub fn resolve_path(data: &FsStateData, relative: &str) -> Result<PathBuf, String> {
+pub fn resolve_path(data: &FsStateData, relative: &str) -> AppResult<PathBuf> {
   if relative.trim().is_empty() {
-    return Err("Path must not be empty".to_string());
+    return Err(AppError::invalid_path(relative, "Path must not be empty"));
   }
   let rel = Path::new(relative).clean();
   if rel.is_absolute() {
-    return Err("Path must be relative".to_string());
+    return Err(AppError::invalid_path(relative, "Path must be relative"));
   }
   for component in rel.components() {
     if matches!(
       component,
       Component::ParentDir | Component::RootDir | Component::Prefix(_)
     ) {
-      return Err("Parent paths are not allowed".to_string());
+      return Err(AppError::invalid_path(
+        relative,
+        "Parent paths are not allowed",
+      ));
     }
   }
 
@@ -38,18 +46,30 @@ pub fn resolve_path(data: &FsStateData, relative: &str) -> Result<PathBuf, Strin
     let single_file = data
       .single_file
       .as_ref()
-      .ok_or_else(|| "Single-file path is not set".to_string())?;
+      .ok_or_else(|| AppError::from("Single-file path is not set"))?;
     let file_name = single_file
       .file_name()
       .and_then(|name| name.to_str())
-      .ok_or_else(|| "Invalid file name".to_string())?;
+      .ok_or_else(|| AppError::invalid_path(relative, "Invalid file name"))?;
     let normalized_rel = rel.to_string_lossy().replace('\\', "/");
     if normalized_rel != file_name {
-      return Err("Single-file mode only allows operations on the opened file".to_string());
+      return Err(AppError::invalid_path(
+        relative,
+        "Single-file mode only allows operations on the opened file",
+      ));
     }
     return Ok(single_file.clone());
   }
 
+  if let Some((root, rest)) = mounted_root_path(data, &rel) {
+    if rest.as_os_str().is_empty() {
+      return Err(AppError::invalid_path(
+        relative,
+        "Path must point inside the mounted root",
+      ));
+    }
+    return Ok(root.path.join(rest));
+  }
   Ok(data.root_path.join(rel))
 }
 
@@ -67,10 +87,46 @@ pub fn relative_from_absolute(data: &FsStateData, absolute: &Path) -> Option<Str
       .map(|name| name.to_string());
   }
 
-  let root = data.root_path.clean();
+  for root in &data.mounted_roots {
+    if let Some(relative) = strip_root(&absolute, &root.path.clean()) {
+      return Some(format!("{}/{relative}", mounted_root_prefix(&root.name)));
+    }
+  }
+  strip_root(&absolute, &data.root_path.clean())
+}
+
+fn strip_root(absolute: &Path, root: &Path) -> Option<String> {
   let relative = absolute.strip_prefix(root).ok()?;
   if relative.as_os_str().is_empty() {
     return None;
   }
   Some(relative.to_string_lossy().replace('\\', "/"))
 }
+
+/// Whether workspace-relative `path` is `base` or lies below it.
+pub fn is_same_or_child(path: &str, base: &str) -> bool {
+  path == base || path.starts_with(&format!("{base}/"))
+}
+
+/// First path component of entries inside the named mounted root.
+pub fn mounted_root_prefix(name: &str) -> String {
+  format!("{MOUNTED_ROOT_PREFIX}{name}")
+}
+
+/// Name of the mounted root a workspace-relative path points into.
+pub fn mounted_root_name<'a>(data: &'a FsStateData, relative: &str) -> Option<&'a str> {
+  mounted_root_path(data, Path::new(relative)).map(|(root, _)| root.name.as_str())
+}
+
+fn mounted_root_path<'a, 'p>(
+  data: &'a FsStateData,
+  relative: &'p Path,
+) -> Option<(&'a WorkspaceRoot, &'p Path)> {
+  let mut components = relative.components();
+  let Component::Normal(first) = components.next()? else {
+    return None;
+  };
+  let name = first.to_str()?.strip_prefix(MOUNTED_ROOT_PREFIX)?;
+  let root = data.mounted_roots.iter().find(|root| root.name == name)?;
+  Some((root, components.as_path()))
+}
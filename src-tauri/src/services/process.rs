@@ -0,0 +1,296 @@
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::AppHandle;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+use tokio::sync::oneshot;
+
+use crate::models::EventTopic;
+use crate::services::events::{emit_event, FrontendEvent};
+use crate::services::shutdown::ShutdownToken;
+use crate::services::terminal::Utf8Decoder;
+
+/// Time output may keep arriving after the process exited. Processes it
+/// left running in the background can hold the pipes open indefinitely.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessOutputEvent {
+  pub id: String,
+  /// `stdout` or `stderr`.
+  pub stream: String,
+  pub data: String,
+}
+
+impl FrontendEvent for ProcessOutputEvent {
+  const TOPIC: EventTopic = EventTopic::Process;
+  const LOGGED: bool = false;
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessOutcome {
+  pub exit_code: Option<i32>,
+  pub cancelled: bool,
+}
+
+/// Output of a process, keeping the most recent `limit` bytes.
+#[derive(Debug)]
+pub struct CapturedOutput {
+  text: String,
+  limit: usize,
+}
+
+impl CapturedOutput {
+  pub fn new(limit: usize) -> Self {
+    Self {
+      text: String::new(),
+      limit,
+    }
+  }
+
+  pub fn push(&mut self, data: &str) {
+    self.text.push_str(data);
+    if self.text.len() > self.limit {
+      let mut cut = self.text.len() - self.limit;
+      while !self.text.is_char_boundary(cut) {
+        cut += 1;
+      }
+      self.text.drain(..cut);
+    }
+  }
+
+  pub fn text(&self) -> &str {
+    &self.text
+  }
+}
+
+/// A command run through the platform shell.
+pub fn shell_command(command: &str) -> Command {
+  #[cfg(windows)]
+  {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+  }
+
+  #[cfg(not(windows))]
+  {
+    let mut shell = Command::new("sh");
+    shell.args(["-c", command]);
+    shell
+  }
+}
+
+/// Runs `command` until it exits, `cancel` fires or shutdown begins,
+/// streaming stdout and stderr as `event` events and appending them to
+/// `captured`. Cancelling kills the whole process tree, not only the
+/// command itself.
+pub async fn run_process(
+  app: AppHandle,
+  event: &'static str,
+  id: &str,
+  mut command: Command,
+  mut cancel: oneshot::Receiver<()>,
+  captured: Arc<Mutex<CapturedOutput>>,
+  shutdown: &ShutdownToken,
+) -> Result<ProcessOutcome, String> {
+  let _worker = shutdown.worker();
+  let program = command.as_std().get_program().to_string_lossy().to_string();
+  #[cfg(unix)]
+  command.process_group(0);
+  let mut child = command
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .kill_on_drop(true)
+    .spawn()
+    .map_err(|err| format!("Failed to start {program}: {err}"))?;
+  let tree = ProcessTree::attach(&child);
+
+  let mut pumps = tokio::spawn({
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let id = id.to_string();
+    async move {
+      tokio::join!(
+        pump_output(&app, event, &id, "stdout", stdout, &captured),
+        pump_output(&app, event, &id, "stderr", stderr, &captured),
+      );
+    }
+  });
+
+  let cancelled = tokio::select! {
+    _ = child.wait() => false,
+    _ = &mut cancel => true,
+    _ = shutdown.cancelled() => true,
+  };
+  if cancelled {
+    tree.kill();
+    let _ = child.kill().await;
+  }
+  let status = child
+    .wait()
+    .await
+    .map_err(|err| format!("Failed to wait for {program}: {err}"))?;
+  if tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, &mut pumps)
+    .await
+    .is_err()
+  {
+    tree.kill();
+    pumps.abort();
+  }
+
+  Ok(ProcessOutcome {
+    exit_code: status.code(),
+    cancelled,
+  })
+}
+
+/// The processes started by a command: its process group on Unix, a job
+/// object on Windows.
+#[cfg(unix)]
+struct ProcessTree(Option<libc::pid_t>);
+
+#[cfg(unix)]
+impl ProcessTree {
+  /// The command must have been spawned as the leader of a new group.
+  fn attach(child: &Child) -> Self {
+    Self(child.id().and_then(|id| libc::pid_t::try_from(id).ok()))
+  }
+
+  fn kill(&self) {
+    if let Some(group) = self.0 {
+      // SAFETY: killpg only takes plain integers; a group that is already
+      // gone makes it fail with ESRCH.
+      unsafe {
+        libc::killpg(group, libc::SIGKILL);
+      }
+    }
+  }
+}
+
+/// The processes started by a command: its process group on Unix, a job
+/// object on Windows. The job also kills what is left when it is dropped.
+#[cfg(windows)]
+struct ProcessTree(Option<JobHandle>);
+
+/// A job object handle, kept as an integer so the tree can be held across
+/// awaits.
+#[cfg(windows)]
+struct JobHandle(isize);
+
+#[cfg(windows)]
+impl ProcessTree {
+  fn attach(child: &Child) -> Self {
+    use windows_sys::Win32::System::JobObjects::{
+      AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+      SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+      JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    let Some(process) = child.raw_handle() else {
+      return Self(None);
+    };
+    // SAFETY: the job handle is checked before use and closed by
+    // `JobHandle`; `process` stays valid while `child` is alive.
+    unsafe {
+      let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+      if job.is_null() {
+        return Self(None);
+      }
+      let job = JobHandle(job as isize);
+      let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+      limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+      let configured = SetInformationJobObject(
+        job.handle(),
+        JobObjectExtendedLimitInformation,
+        std::ptr::addr_of!(limits).cast(),
+        std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+      ) != 0;
+      if !configured || AssignProcessToJobObject(job.handle(), process.cast()) == 0 {
+        return Self(None);
+      }
+      Self(Some(job))
+    }
+  }
+
+  fn kill(&self) {
+    if let Some(job) = &self.0 {
+      // SAFETY: the handle is open until `job` is dropped.
+      unsafe {
+        windows_sys::Win32::System::JobObjects::TerminateJobObject(job.handle(), 1);
+      }
+    }
+  }
+}
+
+#[cfg(windows)]
+impl JobHandle {
+  fn handle(&self) -> windows_sys::Win32::Foundation::HANDLE {
+    self.0 as windows_sys::Win32::Foundation::HANDLE
+  }
+}
+
+#[cfg(windows)]
+impl Drop for JobHandle {
+  fn drop(&mut self) {
+    // SAFETY: the handle was opened by `CreateJobObjectW` and is closed once.
+    unsafe {
+      windows_sys::Win32::Foundation::CloseHandle(self.handle());
+    }
+  }
+}
+
+async fn pump_output<R: AsyncRead + Unpin>(
+  app: &AppHandle,
+  event: &str,
+  id: &str,
+  stream: &str,
+  reader: Option<R>,
+  captured: &Mutex<CapturedOutput>,
+) {
+  let Some(mut reader) = reader else {
+    return;
+  };
+  let mut decoder = Utf8Decoder::default();
+  let mut buffer = [0_u8; 8192];
+  loop {
+    let (data, done) = match reader.read(&mut buffer).await {
+      Ok(0) | Err(_) => (decoder.finish(), true),
+      Ok(bytes_read) => (decoder.decode(&buffer[..bytes_read]), false),
+    };
+    if !data.is_empty() {
+      if let Ok(mut captured) = captured.lock() {
+        captured.push(&data);
+      }
+      let _ = emit_event(
+        app,
+        event,
+        &ProcessOutputEvent {
+          id: id.to_string(),
+          stream: stream.to_string(),
+          data,
+        },
+      );
+    }
+    if done {
+      break;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::CapturedOutput;
+
+  #[test]
+  fn captured_output_keeps_the_most_recent_bytes() {
+    let mut output = CapturedOutput::new(8);
+    output.push("build ");
+    output.push("ok → done");
+    assert_eq!(output.text(), "→ done");
+  }
+}
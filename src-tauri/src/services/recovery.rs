@@ -0,0 +1,534 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::models::FsRecoverableBuffer;
+use crate::services::document_store::DocumentSnapshot;
+use crate::services::search::stable_hash;
+
+const JOURNAL_DIR: &str = "journal";
+const PENDING_DIR: &str = "pending";
+const JOURNAL_EXTENSION: &str = "jsonl";
+/// Updates are written once edits to a path pause for this long...
+const JOURNAL_IDLE: Duration = Duration::from_millis(750);
+/// ...or at the latest this long after the first unwritten edit.
+const JOURNAL_MAX_DELAY: Duration = Duration::from_secs(5);
+/// Appended records after which a journal is compacted to one per path.
+const JOURNAL_COMPACT_RECORDS: usize = 256;
+
+/// One buffer update as appended to the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalRecord {
+  path: String,
+  content: String,
+  updated_ms: i64,
+}
+
+/// Journal of unsaved buffer content. Buffer updates are coalesced per path
+/// and appended once edits pause, flushes compact the journal down to what is
+/// still dirty, and journals left behind by a crashed session are replayed at
+/// startup into recoverable buffers.
+#[derive(Debug, Default)]
+pub struct RecoveryJournalService {
+  journal: Arc<Mutex<JournalState>>,
+}
+
+#[derive(Debug, Default)]
+struct JournalState {
+  /// Latest unwritten update per journal file and path.
+  pending: BTreeMap<PathBuf, BTreeMap<String, PendingUpdate>>,
+  /// Records appended to each journal since it was last rewritten.
+  appended: HashMap<PathBuf, usize>,
+}
+
+#[derive(Debug)]
+struct PendingUpdate {
+  record: JournalRecord,
+  first: Instant,
+  last: Instant,
+}
+
+impl PendingUpdate {
+  fn is_due(&self) -> bool {
+    self.last.elapsed() >= JOURNAL_IDLE || self.first.elapsed() >= JOURNAL_MAX_DELAY
+  }
+}
+
+impl RecoveryJournalService {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Moves journals from a previous session into the pending recovery set.
+  /// Must run before the current session appends to its own journal.
+  pub fn replay(&self, recovery_parent: &Path) -> AppResult<usize> {
+    let _journal = self.lock()?;
+    let journal_dir = recovery_dir(recovery_parent).join(JOURNAL_DIR);
+    let entries = match std::fs::read_dir(&journal_dir) {
+      Ok(entries) => entries,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+      Err(err) => {
+        return Err(AppError::io(
+          "Failed to read recovery journal dir",
+          &journal_dir,
+          err,
+        ))
+      }
+    };
+
+    let mut replayed = 0;
+    for entry in entries.filter_map(Result::ok) {
+      let path = entry.path();
+      if path.extension().and_then(|ext| ext.to_str()) != Some(JOURNAL_EXTENSION) {
+        continue;
+      }
+      let Some(key) = path.file_stem().and_then(|stem| stem.to_str()) else {
+        continue;
+      };
+      let records = read_journal(&path)?;
+      if !records.is_empty() {
+        let pending_path = pending_file(recovery_parent, key);
+        let mut pending = read_pending(&pending_path)?
+          .into_iter()
+          .map(|buffer| (buffer.path.clone(), buffer))
+          .collect::<BTreeMap<_, _>>();
+        for record in records {
+          match pending.get(&record.path) {
+            Some(existing) if existing.updated_ms > record.updated_ms => {}
+            _ => {
+              pending.insert(
+                record.path.clone(),
+                FsRecoverableBuffer {
+                  path: record.path,
+                  content: record.content,
+                  updated_ms: record.updated_ms,
+                },
+              );
+              replayed += 1;
+            }
+          }
+        }
+        write_pending(&pending_path, &pending.into_values().collect::<Vec<_>>())?;
+      }
+      std::fs::remove_file(&path)
+        .map_err(|err| AppError::io("Failed to remove replayed recovery journal", &path, err))?;
+    }
+    Ok(replayed)
+  }
+
+  /// Queues the latest content of a dirty buffer for the journal of `root`.
+  /// Nothing is written until [`Self::write_pending`] runs.
+  pub fn record_update(
+    &self,
+    recovery_parent: &Path,
+    root: &Path,
+    path: &str,
+    content: &str,
+  ) -> AppResult<()> {
+    let mut journal = self.lock()?;
+    let now = Instant::now();
+    let updates = journal
+      .pending
+      .entry(journal_file(recovery_parent, root))
+      .or_default();
+    let first = updates.get(path).map_or(now, |update| update.first);
+    updates.insert(
+      path.to_string(),
+      PendingUpdate {
+        record: JournalRecord {
+          path: path.to_string(),
+          content: content.to_string(),
+          updated_ms: now_ms(),
+        },
+        first,
+        last: now,
+      },
+    );
+    Ok(())
+  }
+
+  /// Appends queued updates whose edits have paused, or all of them with
+  /// `force`, syncing each journal once. Returns the number of records
+  /// written.
+  pub fn write_pending(&self, force: bool) -> AppResult<usize> {
+    let mut journal = self.lock()?;
+    let mut due = Vec::new();
+    for (file, updates) in journal.pending.iter_mut() {
+      let paths = updates
+        .iter()
+        .filter(|(_, update)| force || update.is_due())
+        .map(|(path, _)| path.clone())
+        .collect::<Vec<_>>();
+      let records = paths
+        .iter()
+        .filter_map(|path| updates.remove(path))
+        .map(|update| update.record)
+        .collect::<Vec<_>>();
+      if !records.is_empty() {
+        due.push((file.clone(), records));
+      }
+    }
+    journal.pending.retain(|_, updates| !updates.is_empty());
+
+    let mut written = 0;
+    let mut failed = None;
+    for (file, records) in due {
+      if let Err(err) = append_journal(&file, &records) {
+        // Keep the updates queued so the next run retries them.
+        let now = Instant::now();
+        let updates = journal.pending.entry(file).or_default();
+        for record in records {
+          updates.insert(
+            record.path.clone(),
+            PendingUpdate {
+              record,
+              first: now,
+              last: now,
+            },
+          );
+        }
+        failed = Some(err);
+        continue;
+      }
+      written += records.len();
+      let appended = journal.appended.entry(file.clone()).or_default();
+      *appended += records.len();
+      if *appended >= JOURNAL_COMPACT_RECORDS {
+        let records = read_journal(&file)?;
+        *appended = records.len();
+        rewrite_journal(&file, &records)?;
+      }
+    }
+    match failed {
+      Some(err) => Err(err),
+      None => Ok(written),
+    }
+  }
+
+  /// Rewrites the journal of `root` to hold only the still dirty buffers,
+  /// removing it entirely once everything has been saved. `dirty` is read
+  /// while the journal is locked, so an update racing the checkpoint is
+  /// either part of the snapshot or queued after it.
+  pub fn checkpoint(
+    &self,
+    recovery_parent: &Path,
+    root: &Path,
+    dirty: impl FnOnce() -> AppResult<Vec<DocumentSnapshot>>,
+  ) -> AppResult<()> {
+    let mut journal = self.lock()?;
+    let dirty = dirty()?;
+    let file = journal_file(recovery_parent, root);
+    // The snapshot is at least as new as anything still queued.
+    journal.pending.remove(&file);
+    journal.appended.remove(&file);
+    if dirty.is_empty() {
+      return match std::fs::remove_file(&file) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(AppError::io(
+          "Failed to remove recovery journal",
+          &file,
+          err,
+        )),
+      };
+    }
+
+    let updated_ms = now_ms();
+    let records = dirty
+      .into_iter()
+      .map(|document| JournalRecord {
+        path: document.path,
+        content: document.content,
+        updated_ms,
+      })
+      .collect::<Vec<_>>();
+    rewrite_journal(&file, &records)
+  }
+
+  pub fn recoverable(
+    &self,
+    recovery_parent: &Path,
+    root: &Path,
+  ) -> AppResult<Vec<FsRecoverableBuffer>> {
+    let _journal = self.lock()?;
+    let mut buffers = read_pending(&pending_file(recovery_parent, &root_key(root)))?;
+    buffers.sort_by_key(|buffer| std::cmp::Reverse(buffer.updated_ms));
+    Ok(buffers)
+  }
+
+  /// Removes recoverable buffers for `paths`, or all of them when `None`,
+  /// and returns the removed entries.
+  pub fn take(
+    &self,
+    recovery_parent: &Path,
+    root: &Path,
+    paths: Option<&[String]>,
+  ) -> AppResult<Vec<FsRecoverableBuffer>> {
+    let _journal = self.lock()?;
+    let pending_path = pending_file(recovery_parent, &root_key(root));
+    let (taken, kept): (Vec<_>, Vec<_>) = read_pending(&pending_path)?
+      .into_iter()
+      .partition(|buffer| paths.map_or(true, |paths| paths.contains(&buffer.path)));
+    if !taken.is_empty() {
+      write_pending(&pending_path, &kept)?;
+    }
+    Ok(taken)
+  }
+
+  fn lock(&self) -> AppResult<MutexGuard<'_, JournalState>> {
+    self
+      .journal
+      .lock()
+      .map_err(|_| AppError::lock("recovery journal"))
+  }
+}
+
+fn recovery_dir(recovery_parent: &Path) -> PathBuf {
+  recovery_parent.join("recovery")
+}
+
+fn root_key(root: &Path) -> String {
+  format!("{:016x}", stable_hash(&root.to_string_lossy()))
+}
+
+fn journal_file(recovery_parent: &Path, root: &Path) -> PathBuf {
+  recovery_dir(recovery_parent)
+    .join(JOURNAL_DIR)
+    .join(format!("{}.{JOURNAL_EXTENSION}", root_key(root)))
+}
+
+fn pending_file(recovery_parent: &Path, key: &str) -> PathBuf {
+  recovery_dir(recovery_parent)
+    .join(PENDING_DIR)
+    .join(format!("{key}.json"))
+}
+
+fn append_journal(file: &Path, records: &[JournalRecord]) -> AppResult<()> {
+  if let Some(dir) = file.parent() {
+    std::fs::create_dir_all(dir)
+      .map_err(|err| AppError::io("Failed to create recovery journal dir", dir, err))?;
+  }
+  let content = journal_lines(records)?;
+  let mut journal = OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(file)
+    .map_err(|err| AppError::io("Failed to open recovery journal", file, err))?;
+  journal
+    .write_all(content.as_bytes())
+    .and_then(|_| journal.sync_data())
+    .map_err(|err| AppError::io("Failed to append recovery journal", file, err))
+}
+
+fn rewrite_journal(file: &Path, records: &[JournalRecord]) -> AppResult<()> {
+  if let Some(dir) = file.parent() {
+    std::fs::create_dir_all(dir)
+      .map_err(|err| AppError::io("Failed to create recovery journal dir", dir, err))?;
+  }
+  let tmp = file.with_extension("jsonl.tmp");
+  std::fs::write(&tmp, journal_lines(records)?)
+    .map_err(|err| AppError::io("Failed to write recovery journal", &tmp, err))?;
+  std::fs::rename(&tmp, file)
+    .map_err(|err| AppError::io("Failed to replace recovery journal", file, err))
+}
+
+fn journal_lines(records: &[JournalRecord]) -> AppResult<String> {
+  let mut content = String::new();
+  for record in records {
+    let line = serde_json::to_string(record)
+      .map_err(|err| format!("Failed to serialize recovery record: {err}"))?;
+    content.push_str(&line);
+    content.push('\n');
+  }
+  Ok(content)
+}
+
+/// Reads journal records, keeping only the latest one per path. A torn last
+/// line from a crash mid-append is ignored.
+fn read_journal(path: &Path) -> AppResult<Vec<JournalRecord>> {
+  let content = std::fs::read_to_string(path)
+    .map_err(|err| AppError::io("Failed to read recovery journal", path, err))?;
+  let mut records = BTreeMap::new();
+  for line in content.lines().filter(|line| !line.trim().is_empty()) {
+    if let Ok(record) = serde_json::from_str::<JournalRecord>(line) {
+      records.insert(record.path.clone(), record);
+    }
+  }
+  Ok(records.into_values().collect())
+}
+
+fn read_pending(path: &Path) -> AppResult<Vec<FsRecoverableBuffer>> {
+  match std::fs::read_to_string(path) {
+    Ok(content) => serde_json::from_str(&content).map_err(|err| {
+      AppError::invalid_input(format!("Failed to parse recoverable buffers: {err}"))
+    }),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+    Err(err) => Err(AppError::io(
+      "Failed to read recoverable buffers",
+      path,
+      err,
+    )),
+  }
+}
+
+fn write_pending(path: &Path, buffers: &[FsRecoverableBuffer]) -> AppResult<()> {
+  if buffers.is_empty() {
+    return match std::fs::remove_file(path) {
+      Ok(()) => Ok(()),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+      Err(err) => Err(AppError::io(
+        "Failed to remove recoverable buffers",
+        path,
+        err,
+      )),
+    };
+  }
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir)
+      .map_err(|err| AppError::io("Failed to create recoverable buffers dir", dir, err))?;
+  }
+  let content = serde_json::to_string(buffers)
+    .map_err(|err| format!("Failed to serialize recoverable buffers: {err}"))?;
+  let tmp = path.with_extension("json.tmp");
+  std::fs::write(&tmp, content)
+    .map_err(|err| AppError::io("Failed to write recoverable buffers", &tmp, err))?;
+  std::fs::rename(&tmp, path)
+    .map_err(|err| AppError::io("Failed to replace recoverable buffers", path, err))
+}
+
+fn now_ms() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_millis() as i64)
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+  use std::sync::Arc;
+
+  use crate::services::document_store::DocumentSnapshot;
+  use crate::test_support::temp_dir;
+
+  use super::RecoveryJournalService;
+
+  fn snapshot(path: &str, content: &str) -> DocumentSnapshot {
+    DocumentSnapshot {
+      path: path.to_string(),
+      content: content.to_string(),
+      content_hash: 0,
+    }
+  }
+
+  #[test]
+  fn replays_journal_from_previous_session() {
+    let parent = temp_dir("recovery-test");
+    let root = Path::new("/workspace");
+    let journal = RecoveryJournalService::new();
+    journal
+      .record_update(&parent, root, "a.md", "first")
+      .expect("update should journal");
+    journal
+      .record_update(&parent, root, "a.md", "second")
+      .expect("update should journal");
+    journal
+      .record_update(&parent, root, "b.md", "saved")
+      .expect("update should journal");
+    assert_eq!(
+      journal.write_pending(true).expect("journal should write"),
+      2
+    );
+    journal
+      .checkpoint(&parent, root, || Ok(vec![snapshot("a.md", "second")]))
+      .expect("checkpoint should compact");
+    journal
+      .record_update(&parent, root, "c.md", "late")
+      .expect("update should journal");
+    assert_eq!(
+      journal.write_pending(false).expect("journal should write"),
+      0,
+      "updates wait for edits to pause"
+    );
+    journal.write_pending(true).expect("journal should write");
+
+    // Simulates the next launch after a crash.
+    let restarted = RecoveryJournalService::new();
+    assert_eq!(restarted.replay(&parent).expect("replay should work"), 2);
+    let buffers = restarted
+      .recoverable(&parent, root)
+      .expect("buffers should list");
+    let mut paths = buffers
+      .iter()
+      .map(|buffer| (buffer.path.as_str(), buffer.content.as_str()))
+      .collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(paths, vec![("a.md", "second"), ("c.md", "late")]);
+    assert_eq!(restarted.replay(&parent).expect("replay should work"), 0);
+
+    let taken = restarted
+      .take(&parent, root, Some(&["a.md".to_string()]))
+      .expect("buffer should be taken");
+    assert_eq!(taken.len(), 1);
+    assert_eq!(
+      restarted
+        .recoverable(&parent, root)
+        .expect("buffers should list")
+        .len(),
+      1
+    );
+    restarted
+      .take(&parent, root, None)
+      .expect("buffers should be discarded");
+    assert!(restarted
+      .recoverable(&parent, root)
+      .expect("buffers should list")
+      .is_empty());
+  }
+
+  #[test]
+  fn update_racing_a_checkpoint_is_not_lost() {
+    let parent = temp_dir("recovery-test");
+    let root = Path::new("/workspace");
+    let journal = Arc::new(RecoveryJournalService::new());
+    journal
+      .record_update(&parent, root, "a.md", "old")
+      .expect("update should journal");
+    journal.write_pending(true).expect("journal should write");
+
+    let mut racing = None;
+    journal
+      .checkpoint(&parent, root, || {
+        // A newer edit arrives while the checkpoint holds its snapshot.
+        let journal = journal.clone();
+        let parent = parent.clone();
+        racing = Some(std::thread::spawn(move || {
+          journal
+            .record_update(&parent, Path::new("/workspace"), "a.md", "new")
+            .expect("update should journal");
+        }));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        Ok(vec![snapshot("a.md", "old")])
+      })
+      .expect("checkpoint should compact");
+    racing
+      .expect("racing update should start")
+      .join()
+      .expect("racing update should finish");
+    journal.write_pending(true).expect("journal should write");
+
+    let restarted = RecoveryJournalService::new();
+    restarted.replay(&parent).expect("replay should work");
+    let buffers = restarted
+      .recoverable(&parent, root)
+      .expect("buffers should list");
+    assert_eq!(buffers.len(), 1);
+    assert_eq!(buffers[0].content, "new");
+  }
+}
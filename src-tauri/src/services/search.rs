@@ -11,7 +11,9 @@ use tantivy::schema::{Field, OwnedValue, Schema, TantivyDocument, STORED, STRING
 use tantivy::snippet::SnippetGenerator;
 use tantivy::{doc, Index};
 
+use crate::error::{AppError, AppResult};
 use crate::models::{FsSearchResult, FsTextRange};
+use crate::services::background_tasks::BackgroundTaskHandle;
 
 const SEARCH_INDEX_VERSION: &str = "v1";
 const SEARCH_MEMORY_BUDGET_BYTES: usize = 50_000_000;
@@ -48,15 +50,19 @@ impl SearchService {
     workspace_key: &str,
     documents: &[SearchDocument],
     signature: u64,
-  ) -> Result<(), String> {
+    task: Option<&BackgroundTaskHandle>,
+  ) -> AppResult<()> {
     let index_dir = workspace_index_dir(index_parent, workspace_key);
     let cache_key = index_dir.to_string_lossy().to_string();
     if self.signature_matches(&cache_key, signature)? && index_dir.join("meta.json").exists() {
+      if let Some(task) = task {
+        task.advance(documents.len() as u64);
+      }
       return Ok(());
     }
 
     fs::create_dir_all(&index_dir)
-      .map_err(|err| format!("Failed to create search index: {err}"))?;
+      .map_err(|err| AppError::io("Failed to create search index", &index_dir, err))?;
     let (index, fields) = open_or_reset_index(&index_dir)?;
     let mut writer = index
       .writer(SEARCH_MEMORY_BUDGET_BYTES)
@@ -65,6 +71,12 @@ impl SearchService {
       .delete_all_documents()
       .map_err(|err| format!("Failed to clear search index: {err}"))?;
     for document in documents {
+      // Dropping the writer without a commit leaves the previous index.
+      if task.is_some_and(BackgroundTaskHandle::is_cancelled) {
+        return Err(AppError::Cancelled {
+          reason: "Search index rebuild cancelled".to_string(),
+        });
+      }
       writer
         .add_document(doc!(
           fields.path => document.path.clone(),
@@ -72,6 +84,9 @@ impl SearchService {
           fields.body => document.body.clone(),
         ))
         .map_err(|err| format!("Failed to index document: {err}"))?;
+      if let Some(task) = task {
+        task.advance(1);
+      }
     }
     writer
       .commit()
@@ -85,7 +100,7 @@ impl SearchService {
     workspace_key: &str,
     query: &str,
     limit: usize,
-  ) -> Result<Vec<FsSearchResult>, String> {
+  ) -> AppResult<Vec<FsSearchResult>> {
     let normalized_query = query.trim();
     if normalized_query.is_empty() {
       return Ok(Vec::new());
@@ -104,7 +119,7 @@ impl SearchService {
     let query_parser = QueryParser::for_index(&index, vec![fields.title, fields.body, fields.path]);
     let parsed_query = query_parser
       .parse_query(normalized_query)
-      .map_err(|err| format!("Failed to parse search query: {err}"))?;
+      .map_err(|err| AppError::invalid_input(format!("Failed to parse search query: {err}")))?;
     let mut snippet_generator =
       SnippetGenerator::create(&searcher, parsed_query.as_ref(), fields.body)
         .map_err(|err| format!("Failed to create search snippet generator: {err}"))?;
@@ -145,19 +160,19 @@ impl SearchService {
     Ok(results)
   }
 
-  fn signature_matches(&self, key: &str, signature: u64) -> Result<bool, String> {
+  fn signature_matches(&self, key: &str, signature: u64) -> AppResult<bool> {
     let signatures = self
       .signatures
       .lock()
-      .map_err(|_| "Failed to lock search signature cache")?;
+      .map_err(|_| AppError::lock("search signature cache"))?;
     Ok(signatures.get(key).copied() == Some(signature))
   }
 
-  fn store_signature(&self, key: String, signature: u64) -> Result<(), String> {
+  fn store_signature(&self, key: String, signature: u64) -> AppResult<()> {
     let mut signatures = self
       .signatures
       .lock()
-      .map_err(|_| "Failed to lock search signature cache")?;
+      .map_err(|_| AppError::lock("search signature cache"))?;
     signatures.insert(key, signature);
     Ok(())
   }
@@ -177,21 +192,22 @@ fn build_schema() -> (Schema, SearchFields) {
   (builder.build(), SearchFields { path, title, body })
 }
 
-fn open_or_reset_index(index_dir: &Path) -> Result<(Index, SearchFields), String> {
+fn open_or_reset_index(index_dir: &Path) -> AppResult<(Index, SearchFields)> {
   let (schema, fields) = build_schema();
-  fs::create_dir_all(index_dir).map_err(|err| format!("Failed to create search index: {err}"))?;
+  fs::create_dir_all(index_dir)
+    .map_err(|err| AppError::io("Failed to create search index", index_dir, err))?;
   let directory = MmapDirectory::open(index_dir).map_err(|err| err.to_string())?;
   match Index::open_or_create(directory, schema.clone()) {
     Ok(index) => Ok((index, fields)),
     Err(_) => {
       fs::remove_dir_all(index_dir)
-        .map_err(|err| format!("Failed to reset search index: {err}"))?;
+        .map_err(|err| AppError::io("Failed to reset search index", index_dir, err))?;
       fs::create_dir_all(index_dir)
-        .map_err(|err| format!("Failed to recreate search index: {err}"))?;
+        .map_err(|err| AppError::io("Failed to recreate search index", index_dir, err))?;
       let directory = MmapDirectory::open(index_dir).map_err(|err| err.to_string())?;
       Index::open_or_create(directory, schema)
         .map(|index| (index, fields))
-        .map_err(|err| format!("Failed to open search index: {err}"))
+        .map_err(|err| AppError::from(format!("Failed to open search index: {err}")))
     }
   }
 }
@@ -203,7 +219,7 @@ fn workspace_index_dir(index_parent: &Path, workspace_key: &str) -> PathBuf {
     .join(format!("{:016x}", stable_hash(workspace_key)))
 }
 
-fn stable_hash(value: &str) -> u64 {
+pub(crate) fn stable_hash(value: &str) -> u64 {
   let mut hash = StableHasher::default();
   value.hash(&mut hash);
   hash.finish()
@@ -0,0 +1,357 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{RecentWorkspace, WorkspaceSession, WorkspaceSessionRoot};
+use crate::state::{FsStateData, WorkspaceRoot};
+
+const SESSION_DIR: &str = "session";
+const RECENT_FILE: &str = "recent.json";
+const LAST_SESSION_FILE: &str = "last.json";
+const MAX_UNPINNED_RECENTS: usize = 20;
+
+/// Recently opened workspaces and the layout of the last session, stored
+/// under the app data dir so the app reopens where it was left.
+#[derive(Debug, Default)]
+pub struct SessionService {
+  session_lock: Arc<Mutex<()>>,
+}
+
+impl SessionService {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Recent workspaces, pinned first. Entries whose folder or file no longer
+  /// exists are pruned.
+  pub fn recent_workspaces(&self, session_parent: &Path) -> AppResult<Vec<RecentWorkspace>> {
+    let _guard = self.lock()?;
+    let recents = read_recents(session_parent)?;
+    let existing = recents
+      .iter()
+      .filter(|recent| recent_exists(recent))
+      .cloned()
+      .collect::<Vec<_>>();
+    if existing.len() != recents.len() {
+      write_recents(session_parent, &existing)?;
+    }
+    Ok(existing)
+  }
+
+  /// Records the workspace in `data` as the most recently opened one and as
+  /// the root of the last session. Open files are kept while the root stays
+  /// the same.
+  pub fn record_workspace(
+    &self,
+    session_parent: &Path,
+    data: &FsStateData,
+  ) -> AppResult<Vec<RecentWorkspace>> {
+    let _guard = self.lock()?;
+    let path = data.root_path.to_string_lossy().to_string();
+    let mut recents = read_recents(session_parent)?;
+    let pinned = recents
+      .iter()
+      .any(|recent| recent.path == path && recent.pinned);
+    recents.retain(|recent| recent.path != path);
+    recents.push(RecentWorkspace {
+      root_kind: data.root_kind.clone(),
+      path: path.clone(),
+      last_opened_ms: now_ms(),
+      pinned,
+    });
+    let recents = sort_recents(recents);
+    write_recents(session_parent, &recents)?;
+
+    let mut session = read_last_session(session_parent)?.unwrap_or_default();
+    if session.root_kind != data.root_kind || session.root_path != path {
+      session = WorkspaceSession {
+        root_kind: data.root_kind.clone(),
+        root_path: path,
+        ..WorkspaceSession::default()
+      };
+    }
+    session.mounted_roots = data
+      .mounted_roots
+      .iter()
+      .map(|root| WorkspaceSessionRoot {
+        name: root.name.clone(),
+        path: root.path.to_string_lossy().to_string(),
+      })
+      .collect();
+    write_json(
+      &session_dir(session_parent).join(LAST_SESSION_FILE),
+      &session,
+    )?;
+    Ok(recents)
+  }
+
+  pub fn set_pinned(
+    &self,
+    session_parent: &Path,
+    path: &str,
+    pinned: bool,
+  ) -> AppResult<Vec<RecentWorkspace>> {
+    let _guard = self.lock()?;
+    let mut recents = read_recents(session_parent)?;
+    let recent = recents
+      .iter_mut()
+      .find(|recent| recent.path == path)
+      .ok_or_else(|| AppError::NotFound {
+        path: path.to_string(),
+      })?;
+    recent.pinned = pinned;
+    let recents = sort_recents(recents);
+    write_recents(session_parent, &recents)?;
+    Ok(recents)
+  }
+
+  pub fn remove_recent(
+    &self,
+    session_parent: &Path,
+    path: &str,
+  ) -> AppResult<Vec<RecentWorkspace>> {
+    let _guard = self.lock()?;
+    let mut recents = read_recents(session_parent)?;
+    recents.retain(|recent| recent.path != path);
+    write_recents(session_parent, &recents)?;
+    Ok(recents)
+  }
+
+  pub fn last_session(&self, session_parent: &Path) -> AppResult<Option<WorkspaceSession>> {
+    let _guard = self.lock()?;
+    read_last_session(session_parent)
+  }
+
+  /// Stores the open files of the current workspace. The root recorded by
+  /// [`Self::record_workspace`] is kept.
+  pub fn save_open_files(
+    &self,
+    session_parent: &Path,
+    data: &FsStateData,
+    session: WorkspaceSession,
+  ) -> AppResult<()> {
+    let _guard = self.lock()?;
+    let session = WorkspaceSession {
+      root_kind: data.root_kind.clone(),
+      root_path: data.root_path.to_string_lossy().to_string(),
+      mounted_roots: read_last_session(session_parent)?
+        .map(|last| last.mounted_roots)
+        .unwrap_or_default(),
+      ..session
+    };
+    write_json(
+      &session_dir(session_parent).join(LAST_SESSION_FILE),
+      &session,
+    )
+  }
+
+  /// Applies the last session's root and mounted roots to `data`. Roots that
+  /// no longer exist are skipped, leaving `data` on its current root.
+  pub fn restore(&self, session_parent: &Path, data: &mut FsStateData) -> AppResult<bool> {
+    let Some(session) = self.last_session(session_parent)? else {
+      return Ok(false);
+    };
+    let root = PathBuf::from(&session.root_path);
+    match session.root_kind.as_str() {
+      "external" if root.is_dir() => {
+        data.root_kind = "external".to_string();
+        data.root_path = root;
+        data.single_file = None;
+      }
+      "single" if root.is_file() => {
+        data.root_kind = "single".to_string();
+        data.root_path = root.clone();
+        data.single_file = Some(root);
+      }
+      "internal" => {}
+      _ => return Ok(false),
+    }
+    data.mounted_roots = session
+      .mounted_roots
+      .into_iter()
+      .map(|root| WorkspaceRoot {
+        name: root.name,
+        path: PathBuf::from(root.path),
+      })
+      .filter(|root| root.path.is_dir())
+      .collect();
+    Ok(true)
+  }
+
+  fn lock(&self) -> AppResult<std::sync::MutexGuard<'_, ()>> {
+    self
+      .session_lock
+      .lock()
+      .map_err(|_| AppError::lock("session state"))
+  }
+}
+
+fn session_dir(session_parent: &Path) -> PathBuf {
+  session_parent.join(SESSION_DIR)
+}
+
+fn recent_exists(recent: &RecentWorkspace) -> bool {
+  let path = Path::new(&recent.path);
+  match recent.root_kind.as_str() {
+    "single" => path.is_file(),
+    _ => path.is_dir(),
+  }
+}
+
+/// Pinned entries first, then by last opened. Unpinned entries beyond the
+/// limit are dropped.
+fn sort_recents(mut recents: Vec<RecentWorkspace>) -> Vec<RecentWorkspace> {
+  recents.sort_by(|a, b| {
+    b.pinned
+      .cmp(&a.pinned)
+      .then(b.last_opened_ms.cmp(&a.last_opened_ms))
+  });
+  let mut unpinned = 0;
+  recents.retain(|recent| {
+    if recent.pinned {
+      return true;
+    }
+    unpinned += 1;
+    unpinned <= MAX_UNPINNED_RECENTS
+  });
+  recents
+}
+
+fn read_recents(session_parent: &Path) -> AppResult<Vec<RecentWorkspace>> {
+  read_json(&session_dir(session_parent).join(RECENT_FILE)).map(Option::unwrap_or_default)
+}
+
+fn write_recents(session_parent: &Path, recents: &[RecentWorkspace]) -> AppResult<()> {
+  write_json(&session_dir(session_parent).join(RECENT_FILE), &recents)
+}
+
+fn read_last_session(session_parent: &Path) -> AppResult<Option<WorkspaceSession>> {
+  read_json(&session_dir(session_parent).join(LAST_SESSION_FILE))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> AppResult<Option<T>> {
+  match std::fs::read_to_string(path) {
+    Ok(content) => match serde_json::from_str(&content) {
+      Ok(value) => Ok(Some(value)),
+      Err(err) => {
+        log::warn!(
+          "discarding unreadable session file {}: {err}",
+          path.display()
+        );
+        Ok(None)
+      }
+    },
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+    Err(err) => Err(AppError::io("Failed to read session", path, err)),
+  }
+}
+
+fn write_json<T: serde::Serialize + ?Sized>(path: &Path, value: &T) -> AppResult<()> {
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir)
+      .map_err(|err| AppError::io("Failed to create session dir", dir, err))?;
+  }
+  let content = serde_json::to_string_pretty(value)
+    .map_err(|err| format!("Failed to serialize session: {err}"))?;
+  std::fs::write(path, content).map_err(|err| AppError::io("Failed to write session", path, err))
+}
+
+fn now_ms() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_millis() as i64)
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use crate::models::{WorkspaceSession, WorkspaceSessionFile};
+  use crate::state::{FsStateData, WorkspaceRoot};
+  use crate::test_support::temp_dir;
+
+  use super::SessionService;
+
+  fn data(root_kind: &str, root_path: PathBuf) -> FsStateData {
+    FsStateData {
+      root_kind: root_kind.to_string(),
+      root_path: root_path.clone(),
+      internal_root: root_path,
+      single_file: None,
+      mounted_roots: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn records_recents_and_restores_last_session() {
+    let base = temp_dir("session-test");
+    let app_data = base.join("app-data");
+    let vault = base.join("vault");
+    let docs = base.join("docs");
+    let gone = base.join("gone");
+    for dir in [&vault, &docs, &gone] {
+      std::fs::create_dir_all(dir).expect("test dir should be created");
+    }
+    let service = SessionService::new();
+
+    service
+      .record_workspace(&app_data, &data("external", gone.clone()))
+      .expect("gone should be recorded");
+    let mut vault_data = data("external", vault.clone());
+    vault_data.mounted_roots.push(WorkspaceRoot {
+      name: "docs".to_string(),
+      path: docs.clone(),
+    });
+    service
+      .record_workspace(&app_data, &vault_data)
+      .expect("vault should be recorded");
+    service
+      .set_pinned(&app_data, &gone.to_string_lossy(), true)
+      .expect("gone should be pinned");
+    service
+      .save_open_files(
+        &app_data,
+        &vault_data,
+        WorkspaceSession {
+          open_files: vec![WorkspaceSessionFile {
+            path: "index.md".to_string(),
+            scroll_top: 120.0,
+          }],
+          active_path: Some("index.md".to_string()),
+          ..WorkspaceSession::default()
+        },
+      )
+      .expect("session should be saved");
+
+    std::fs::remove_dir_all(&gone).expect("gone should be removed");
+    let recents = service
+      .recent_workspaces(&app_data)
+      .expect("recents should load");
+    assert_eq!(recents.len(), 1);
+    assert_eq!(recents[0].path, vault.to_string_lossy());
+
+    let mut restored = data("internal", base.join("internal"));
+    assert!(service
+      .restore(&app_data, &mut restored)
+      .expect("session should restore"));
+    assert_eq!(restored.root_kind, "external");
+    assert_eq!(restored.root_path, vault);
+    assert_eq!(restored.mounted_roots.len(), 1);
+    let session = service
+      .last_session(&app_data)
+      .expect("session should load")
+      .expect("session should exist");
+    assert_eq!(session.open_files[0].scroll_top, 120.0);
+    assert_eq!(session.mounted_roots[0].name, "docs");
+
+    std::fs::remove_dir_all(&vault).expect("vault should be removed");
+    let mut fallback = data("internal", base.join("internal"));
+    assert!(!service
+      .restore(&app_data, &mut fallback)
+      .expect("missing root should be skipped"));
+    assert_eq!(fallback.root_kind, "internal");
+    std::fs::remove_dir_all(base).expect("test dir should be removed");
+  }
+}
@@ -0,0 +1,103 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::watch;
+
+/// Cancellation shared by all background workers. Workers hold a
+/// [`WorkerGuard`] while they run so shutdown can wait for them to finish.
+#[derive(Debug, Clone)]
+pub struct ShutdownToken {
+  inner: Arc<ShutdownInner>,
+}
+
+#[derive(Debug)]
+struct ShutdownInner {
+  cancelled: watch::Sender<bool>,
+  workers: Mutex<usize>,
+  workers_done: Condvar,
+}
+
+impl ShutdownToken {
+  pub fn new() -> Self {
+    Self {
+      inner: Arc::new(ShutdownInner {
+        cancelled: watch::channel(false).0,
+        workers: Mutex::new(0),
+        workers_done: Condvar::new(),
+      }),
+    }
+  }
+
+  pub fn cancel(&self) {
+    self.inner.cancelled.send_replace(true);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    *self.inner.cancelled.borrow()
+  }
+
+  /// Resolves once [`Self::cancel`] has been called.
+  pub async fn cancelled(&self) {
+    let mut receiver = self.inner.cancelled.subscribe();
+    let _ = receiver.wait_for(|cancelled| *cancelled).await;
+  }
+
+  /// Registers a running worker until the guard is dropped.
+  pub fn worker(&self) -> WorkerGuard {
+    if let Ok(mut workers) = self.inner.workers.lock() {
+      *workers += 1;
+    }
+    WorkerGuard {
+      token: self.clone(),
+    }
+  }
+
+  pub fn active_workers(&self) -> usize {
+    self
+      .inner
+      .workers
+      .lock()
+      .map(|workers| *workers)
+      .unwrap_or_default()
+  }
+
+  /// Blocks until every worker has finished. Returns `false` if some were
+  /// still running after `timeout`.
+  pub fn wait_for_workers(&self, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let Ok(mut workers) = self.inner.workers.lock() else {
+      return false;
+    };
+    while *workers > 0 {
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      if remaining.is_zero() {
+        return false;
+      }
+      workers = match self.inner.workers_done.wait_timeout(workers, remaining) {
+        Ok((workers, _)) => workers,
+        Err(_) => return false,
+      };
+    }
+    true
+  }
+}
+
+impl Default for ShutdownToken {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[derive(Debug)]
+pub struct WorkerGuard {
+  token: ShutdownToken,
+}
+
+impl Drop for WorkerGuard {
+  fn drop(&mut self) {
+    if let Ok(mut workers) = self.token.inner.workers.lock() {
+      *workers = workers.saturating_sub(1);
+    }
+    self.token.inner.workers_done.notify_all();
+  }
+}
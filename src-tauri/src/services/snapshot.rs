@@ -0,0 +1,643 @@
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use gix::objs::tree::EntryKind;
+use path_clean::PathClean;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{FsBufferStatus, SnapshotConfig, SnapshotInfo};
+use crate::services::path_resolver::is_same_or_child;
+use crate::services::search::stable_hash;
+
+const SNAPSHOT_TRAILER: &str = "Marko-Snapshot: auto";
+/// Snapshots of git workspaces live under this private ref namespace, so the
+/// user's HEAD, index and branches are never touched.
+const SNAPSHOT_REF_PREFIX: &str = "refs/marko/snapshots";
+const SNAPSHOT_CONFIG_FILE: &str = "config.json";
+const SNAPSHOT_MIN_INTERVAL_SECS: u64 = 10;
+const SNAPSHOT_LIST_LIMIT: usize = 200;
+const SNAPSHOT_SCAN_LIMIT: usize = 2000;
+const SNAPSHOT_SUMMARY_FILES: usize = 5;
+
+#[derive(Debug)]
+pub struct SnapshotService {
+  config: Mutex<Option<SnapshotConfig>>,
+  pending: Mutex<PendingSnapshot>,
+}
+
+#[derive(Debug, Default)]
+struct PendingSnapshot {
+  paths: BTreeSet<String>,
+  since: Option<Instant>,
+}
+
+enum SnapshotTarget {
+  /// The workspace's own repository, under a ref private to this root.
+  Workspace {
+    workdir: PathBuf,
+    reference: String,
+  },
+  Shadow {
+    git_dir: PathBuf,
+  },
+}
+
+/// A repository and the ref its snapshot history hangs off.
+struct SnapshotRepo {
+  repo: gix::Repository,
+  reference: String,
+  target: &'static str,
+}
+
+impl SnapshotService {
+  pub fn new() -> Self {
+    Self {
+      config: Mutex::new(None),
+      pending: Mutex::new(PendingSnapshot::default()),
+    }
+  }
+
+  pub fn config(&self, snapshot_parent: &Path) -> AppResult<SnapshotConfig> {
+    let mut config = self
+      .config
+      .lock()
+      .map_err(|_| AppError::lock("snapshot config"))?;
+    if let Some(config) = config.as_ref() {
+      return Ok(config.clone());
+    }
+    let loaded = read_snapshot_config(snapshot_parent)?;
+    *config = Some(loaded.clone());
+    Ok(loaded)
+  }
+
+  pub fn set_config(
+    &self,
+    snapshot_parent: &Path,
+    config: SnapshotConfig,
+  ) -> AppResult<SnapshotConfig> {
+    let config = normalize_snapshot_config(config);
+    write_snapshot_config(snapshot_parent, &config)?;
+    *self
+      .config
+      .lock()
+      .map_err(|_| AppError::lock("snapshot config"))? = Some(config.clone());
+    Ok(config)
+  }
+
+  pub fn record_flushed(&self, statuses: &[FsBufferStatus]) -> AppResult<()> {
+    self.requeue(
+      statuses
+        .iter()
+        .filter(|status| status.error.is_none())
+        .map(|status| status.path.clone()),
+    )
+  }
+
+  pub fn requeue(&self, paths: impl IntoIterator<Item = String>) -> AppResult<()> {
+    let mut pending = self
+      .pending
+      .lock()
+      .map_err(|_| AppError::lock("snapshot queue"))?;
+    pending.paths.extend(paths);
+    if !pending.paths.is_empty() && pending.since.is_none() {
+      pending.since = Some(Instant::now());
+    }
+    Ok(())
+  }
+
+  /// Takes the batched flushed paths once the configured interval has elapsed.
+  pub fn take_due(&self, snapshot_parent: &Path) -> AppResult<Option<Vec<String>>> {
+    let config = self.config(snapshot_parent)?;
+    let mut pending = self
+      .pending
+      .lock()
+      .map_err(|_| AppError::lock("snapshot queue"))?;
+    if !config.enabled {
+      pending.paths.clear();
+      pending.since = None;
+      return Ok(None);
+    }
+    let due = pending
+      .since
+      .map(|since| since.elapsed() >= Duration::from_secs(config.interval_secs))
+      .unwrap_or(false);
+    if !due {
+      return Ok(None);
+    }
+    pending.since = None;
+    Ok(Some(
+      std::mem::take(&mut pending.paths).into_iter().collect(),
+    ))
+  }
+
+  /// Commits the workspace files to a private ref of its git repository, or
+  /// to a shadow repository under `snapshot_parent` when the workspace is not
+  /// tracked by git.
+  pub async fn create_snapshot(
+    &self,
+    snapshot_parent: PathBuf,
+    root: PathBuf,
+    files: Vec<String>,
+    changed: Vec<String>,
+  ) -> AppResult<Option<SnapshotInfo>> {
+    let config = self.config(&snapshot_parent)?;
+    let message = render_snapshot_message(&config.message_template, &changed);
+    tokio::task::spawn_blocking(move || commit_snapshot(&snapshot_parent, &root, &files, &message))
+      .await
+      .map_err(|err| format!("Failed to join snapshot task: {err}"))?
+  }
+
+  pub async fn list(
+    &self,
+    snapshot_parent: PathBuf,
+    root: PathBuf,
+  ) -> AppResult<Vec<SnapshotInfo>> {
+    tokio::task::spawn_blocking(move || list_snapshots(&snapshot_parent, &root))
+      .await
+      .map_err(|err| format!("Failed to join snapshot list task: {err}"))?
+  }
+
+  /// Writes the files recorded in snapshot `id` back into the workspace and
+  /// returns their workspace-relative paths. Files created after the
+  /// snapshot are left untouched.
+  pub async fn restore(
+    &self,
+    snapshot_parent: PathBuf,
+    root: PathBuf,
+    id: String,
+    paths: Option<Vec<String>>,
+  ) -> AppResult<Vec<String>> {
+    tokio::task::spawn_blocking(move || {
+      restore_snapshot(&snapshot_parent, &root, &id, paths.as_deref())
+    })
+    .await
+    .map_err(|err| format!("Failed to join snapshot restore task: {err}"))?
+  }
+}
+
+impl Default for SnapshotService {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+fn snapshot_dir(snapshot_parent: &Path) -> PathBuf {
+  snapshot_parent.join("snapshots")
+}
+
+fn read_snapshot_config(snapshot_parent: &Path) -> AppResult<SnapshotConfig> {
+  let path = snapshot_dir(snapshot_parent).join(SNAPSHOT_CONFIG_FILE);
+  match std::fs::read_to_string(&path) {
+    Ok(content) => serde_json::from_str::<SnapshotConfig>(&content)
+      .map(normalize_snapshot_config)
+      .map_err(|err| AppError::invalid_input(format!("Failed to parse snapshot config: {err}"))),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(SnapshotConfig::default()),
+    Err(err) => Err(AppError::io("Failed to read snapshot config", &path, err)),
+  }
+}
+
+fn write_snapshot_config(snapshot_parent: &Path, config: &SnapshotConfig) -> AppResult<()> {
+  let dir = snapshot_dir(snapshot_parent);
+  std::fs::create_dir_all(&dir)
+    .map_err(|err| AppError::io("Failed to create snapshot dir", &dir, err))?;
+  let content = serde_json::to_string_pretty(config)
+    .map_err(|err| format!("Failed to serialize snapshot config: {err}"))?;
+  let path = dir.join(SNAPSHOT_CONFIG_FILE);
+  std::fs::write(&path, content)
+    .map_err(|err| AppError::io("Failed to write snapshot config", &path, err))
+}
+
+fn normalize_snapshot_config(mut config: SnapshotConfig) -> SnapshotConfig {
+  config.interval_secs = config.interval_secs.max(SNAPSHOT_MIN_INTERVAL_SECS);
+  if config.message_template.trim().is_empty() {
+    config.message_template = SnapshotConfig::default().message_template;
+  }
+  config
+}
+
+fn snapshot_target(snapshot_parent: &Path, root: &Path) -> SnapshotTarget {
+  let root = root.clean();
+  let root_hash = stable_hash(&root.to_string_lossy());
+  if let Ok(repo) = gix::discover(&root) {
+    if let Some(workdir) = repo.workdir().map(|workdir| workdir.clean()) {
+      if root.starts_with(&workdir) {
+        return SnapshotTarget::Workspace {
+          workdir,
+          reference: format!("{SNAPSHOT_REF_PREFIX}/{root_hash:016x}"),
+        };
+      }
+    }
+  }
+
+  SnapshotTarget::Shadow {
+    git_dir: snapshot_dir(snapshot_parent).join(format!("{root_hash:016x}.git")),
+  }
+}
+
+fn open_snapshot_repo(
+  snapshot_parent: &Path,
+  root: &Path,
+  create: bool,
+) -> AppResult<Option<SnapshotRepo>> {
+  match snapshot_target(snapshot_parent, root) {
+    SnapshotTarget::Workspace { workdir, reference } => Ok(Some(SnapshotRepo {
+      repo: gix::discover(&workdir)
+        .map_err(|err| AppError::git(format!("Failed to discover git repository: {err}")))?,
+      reference,
+      target: "workspace",
+    })),
+    SnapshotTarget::Shadow { git_dir } => {
+      Ok(
+        open_shadow_repo(&git_dir, create)?.map(|repo| SnapshotRepo {
+          repo,
+          reference: "HEAD".to_string(),
+          target: "shadow",
+        }),
+      )
+    }
+  }
+}
+
+fn snapshot_tip(snapshot: &SnapshotRepo) -> Option<gix::ObjectId> {
+  snapshot
+    .repo
+    .find_reference(snapshot.reference.as_str())
+    .ok()?
+    .into_fully_peeled_id()
+    .ok()
+    .map(|id| id.detach())
+}
+
+fn open_shadow_repo(git_dir: &Path, create: bool) -> AppResult<Option<gix::Repository>> {
+  if git_dir.join("HEAD").exists() {
+    return gix::open(git_dir)
+      .map(Some)
+      .map_err(|err| AppError::git(format!("Failed to open snapshot repository: {err}")));
+  }
+  if !create {
+    return Ok(None);
+  }
+  std::fs::create_dir_all(git_dir)
+    .map_err(|err| AppError::io("Failed to create snapshot repository", git_dir, err))?;
+  gix::init_bare(git_dir)
+    .map(Some)
+    .map_err(|err| AppError::git(format!("Failed to initialize snapshot repository: {err}")))
+}
+
+fn commit_snapshot(
+  snapshot_parent: &Path,
+  root: &Path,
+  files: &[String],
+  message: &str,
+) -> AppResult<Option<SnapshotInfo>> {
+  let snapshot = open_snapshot_repo(snapshot_parent, root, true)?
+    .ok_or_else(|| AppError::from("Snapshot repository is not available"))?;
+  let repo = &snapshot.repo;
+  let mut editor = repo
+    .empty_tree()
+    .edit()
+    .map_err(|err| AppError::git(format!("Failed to edit snapshot tree: {err}")))?;
+  for file in files {
+    let path = root.join(file);
+    let bytes = match std::fs::read(&path) {
+      Ok(bytes) => bytes,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+      Err(err) => return Err(AppError::io("Failed to read file for snapshot", &path, err)),
+    };
+    let blob_id = repo
+      .write_blob(bytes)
+      .map_err(|err| AppError::git(format!("Failed to write snapshot blob for {file}: {err}")))?
+      .detach();
+    editor
+      .upsert(file.as_str(), EntryKind::Blob, blob_id)
+      .map_err(|err| AppError::git(format!("Failed to update snapshot tree for {file}: {err}")))?;
+  }
+  let tree_id = editor
+    .write()
+    .map_err(|err| AppError::git(format!("Failed to write snapshot tree: {err}")))?
+    .detach();
+
+  let parent = snapshot_tip(&snapshot);
+  if let Some(parent) = parent {
+    let parent_tree = repo
+      .find_commit(parent)
+      .map_err(|err| AppError::git(format!("Failed to read previous snapshot: {err}")))?
+      .tree_id()
+      .map_err(|err| AppError::git(format!("Failed to read previous snapshot tree: {err}")))?;
+    if parent_tree == tree_id {
+      return Ok(None);
+    }
+  }
+
+  let signature = gix::actor::Signature {
+    name: "marko".into(),
+    email: "marko@local".into(),
+    time: gix::date::Time::now_local_or_utc(),
+  };
+  let mut author_time = gix::date::parse::TimeBuf::default();
+  let mut committer_time = gix::date::parse::TimeBuf::default();
+  let id = repo
+    .commit_as(
+      signature.to_ref(&mut committer_time),
+      signature.to_ref(&mut author_time),
+      snapshot.reference.as_str(),
+      message,
+      tree_id,
+      parent,
+    )
+    .map_err(|err| AppError::git(format!("Failed to create snapshot commit: {err}")))?;
+
+  Ok(Some(SnapshotInfo {
+    id: id.to_string(),
+    summary: message_summary(message),
+    timestamp_ms: signature.time.seconds.saturating_mul(1000),
+    target: snapshot.target.to_string(),
+  }))
+}
+
+fn list_snapshots(snapshot_parent: &Path, root: &Path) -> AppResult<Vec<SnapshotInfo>> {
+  let Some(snapshot) = open_snapshot_repo(snapshot_parent, root, false)? else {
+    return Ok(Vec::new());
+  };
+  let Some(tip) = snapshot_tip(&snapshot) else {
+    return Ok(Vec::new());
+  };
+  let walk = snapshot
+    .repo
+    .rev_walk([tip])
+    .all()
+    .map_err(|err| AppError::git(format!("Failed to walk snapshot history: {err}")))?;
+
+  let mut snapshots = Vec::new();
+  for info in walk.take(SNAPSHOT_SCAN_LIMIT) {
+    let info =
+      info.map_err(|err| AppError::git(format!("Failed to read snapshot history: {err}")))?;
+    let commit = info
+      .object()
+      .map_err(|err| AppError::git(format!("Failed to read snapshot commit: {err}")))?;
+    let message = commit.message_raw_sloppy().to_string();
+    let seconds = commit
+      .time()
+      .map(|time| time.seconds)
+      .map_err(|err| AppError::git(format!("Failed to read snapshot time: {err}")))?;
+    snapshots.push(SnapshotInfo {
+      id: info.id.to_string(),
+      summary: message_summary(&message),
+      timestamp_ms: seconds.saturating_mul(1000),
+      target: snapshot.target.to_string(),
+    });
+    if snapshots.len() >= SNAPSHOT_LIST_LIMIT {
+      break;
+    }
+  }
+  Ok(snapshots)
+}
+
+fn restore_snapshot(
+  snapshot_parent: &Path,
+  root: &Path,
+  id: &str,
+  paths: Option<&[String]>,
+) -> AppResult<Vec<String>> {
+  let snapshot =
+    open_snapshot_repo(snapshot_parent, root, false)?.ok_or_else(|| AppError::NotFound {
+      path: root.to_string_lossy().to_string(),
+    })?;
+  let repo = &snapshot.repo;
+  let tree = repo
+    .find_commit(snapshot_commit(&snapshot, id)?)
+    .map_err(|err| AppError::git(format!("Failed to read snapshot: {err}")))?
+    .tree()
+    .map_err(|err| AppError::git(format!("Failed to read snapshot tree: {err}")))?;
+  let entries = tree
+    .traverse()
+    .breadthfirst
+    .files()
+    .map_err(|err| AppError::git(format!("Failed to read snapshot files: {err}")))?;
+
+  let mut restored = Vec::new();
+  for entry in entries {
+    if !entry.mode.is_blob() {
+      continue;
+    }
+    let repo_path = entry.filepath.to_string();
+    let relative = repo_path.as_str();
+    if let Some(paths) = paths {
+      if !paths.iter().any(|path| is_same_or_child(relative, path)) {
+        continue;
+      }
+    }
+    let target = safe_workspace_join(root, relative)?;
+    let blob = repo.find_blob(entry.oid).map_err(|err| {
+      AppError::git(format!(
+        "Failed to read snapshot blob for {relative}: {err}"
+      ))
+    })?;
+    if let Some(parent) = target.parent() {
+      std::fs::create_dir_all(parent)
+        .map_err(|err| AppError::io("Failed to create dir", parent, err))?;
+    }
+    std::fs::write(&target, &blob.data)
+      .map_err(|err| AppError::io("Failed to restore file", &target, err))?;
+    restored.push(relative.to_string());
+  }
+  Ok(restored)
+}
+
+/// Resolves `id` to a commit on the workspace's snapshot ref. Any other
+/// revision, such as `HEAD` or a branch of a git workspace, is rejected so it
+/// is never restored over the user's files.
+fn snapshot_commit(snapshot: &SnapshotRepo, id: &str) -> AppResult<gix::ObjectId> {
+  let not_a_snapshot =
+    || AppError::invalid_input(format!("{id} is not a snapshot of this workspace"));
+  let id = gix::ObjectId::from_hex(id.as_bytes()).map_err(|_| not_a_snapshot())?;
+  let tip = snapshot_tip(snapshot).ok_or_else(not_a_snapshot)?;
+  let walk = snapshot
+    .repo
+    .rev_walk([tip])
+    .all()
+    .map_err(|err| AppError::git(format!("Failed to walk snapshot history: {err}")))?;
+  for info in walk {
+    let info =
+      info.map_err(|err| AppError::git(format!("Failed to read snapshot history: {err}")))?;
+    if info.id == id {
+      return Ok(id);
+    }
+  }
+  Err(not_a_snapshot())
+}
+
+fn render_snapshot_message(template: &str, changed: &[String]) -> String {
+  let message = template
+    .replace("{count}", &changed.len().to_string())
+    .replace("{files}", &snapshot_file_summary(changed));
+  format!("{}\n\n{SNAPSHOT_TRAILER}\n", message.trim())
+}
+
+fn snapshot_file_summary(changed: &[String]) -> String {
+  let mut names = changed
+    .iter()
+    .map(|path| path.rsplit('/').next().unwrap_or(path).to_string())
+    .collect::<Vec<_>>();
+  names.dedup();
+  if names.is_empty() {
+    return "workspace".to_string();
+  }
+  let hidden = names.len().saturating_sub(SNAPSHOT_SUMMARY_FILES);
+  names.truncate(SNAPSHOT_SUMMARY_FILES);
+  let mut summary = names.join(", ");
+  if hidden > 0 {
+    summary.push_str(&format!(" and {hidden} more"));
+  }
+  summary
+}
+
+fn message_summary(message: &str) -> String {
+  message
+    .lines()
+    .next()
+    .unwrap_or_default()
+    .trim()
+    .to_string()
+}
+
+fn safe_workspace_join(root: &Path, relative: &str) -> AppResult<PathBuf> {
+  let relative = Path::new(relative);
+  if relative
+    .components()
+    .any(|component| !matches!(component, Component::Normal(_)))
+  {
+    return Err(AppError::invalid_path(
+      relative.to_string_lossy(),
+      "Invalid snapshot path",
+    ));
+  }
+  Ok(root.join(relative))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::test_support::temp_dir;
+
+  use super::*;
+
+  #[test]
+  fn renders_message_template_with_changed_files() {
+    let message = render_snapshot_message(
+      "Auto-save {count}: {files}",
+      &["notes/a.md".to_string(), "b.md".to_string()],
+    );
+
+    assert!(message.starts_with("Auto-save 2: a.md, b.md\n"));
+    assert!(message.contains(SNAPSHOT_TRAILER));
+  }
+
+  #[tokio::test]
+  async fn snapshots_and_restores_shadow_repository() {
+    let root = temp_dir("snapshot-root");
+    let parent = temp_dir("snapshot-data");
+    std::fs::create_dir_all(root.join("notes")).expect("workspace should be created");
+    std::fs::write(root.join("notes/a.md"), "first").expect("note should be written");
+
+    let service = SnapshotService::default();
+    let files = vec!["notes/a.md".to_string()];
+    let first = service
+      .create_snapshot(parent.clone(), root.clone(), files.clone(), files.clone())
+      .await
+      .expect("snapshot should be created")
+      .expect("first snapshot should commit");
+    assert_eq!(first.target, "shadow");
+
+    let unchanged = service
+      .create_snapshot(parent.clone(), root.clone(), files.clone(), files.clone())
+      .await
+      .expect("unchanged snapshot should succeed");
+    assert!(unchanged.is_none());
+
+    std::fs::write(root.join("notes/a.md"), "second").expect("note should be updated");
+    service
+      .create_snapshot(parent.clone(), root.clone(), files.clone(), files)
+      .await
+      .expect("second snapshot should be created");
+    let snapshots = service
+      .list(parent.clone(), root.clone())
+      .await
+      .expect("snapshots should list");
+    assert_eq!(snapshots.len(), 2);
+
+    let restored = service
+      .restore(parent.clone(), root.clone(), first.id, None)
+      .await
+      .expect("snapshot should restore");
+    assert_eq!(restored, vec!["notes/a.md".to_string()]);
+    assert_eq!(
+      std::fs::read_to_string(root.join("notes/a.md")).expect("note should be readable"),
+      "first"
+    );
+
+    let _ = std::fs::remove_dir_all(root);
+    let _ = std::fs::remove_dir_all(parent);
+  }
+
+  #[tokio::test]
+  async fn snapshots_git_workspace_to_a_private_ref() {
+    let repo_dir = temp_dir("snapshot-repo");
+    let parent = temp_dir("snapshot-data");
+    let root = repo_dir.join("docs");
+    std::fs::create_dir_all(&root).expect("workspace should be created");
+    gix::init(&repo_dir).expect("repository should be initialized");
+    std::fs::write(root.join("a.md"), "draft").expect("note should be written");
+
+    let service = SnapshotService::default();
+    let files = vec!["a.md".to_string()];
+    let snapshot = service
+      .create_snapshot(parent.clone(), root.clone(), files.clone(), files)
+      .await
+      .expect("snapshot should be created")
+      .expect("snapshot should commit");
+    assert_eq!(snapshot.target, "workspace");
+
+    let repo = gix::open(&repo_dir).expect("repository should open");
+    assert!(repo.head_id().is_err(), "HEAD should stay unborn");
+    assert!(!repo.git_dir().join("index").exists());
+    let reference = format!(
+      "{SNAPSHOT_REF_PREFIX}/{:016x}",
+      stable_hash(&root.clean().to_string_lossy())
+    );
+    assert_eq!(
+      repo
+        .find_reference(reference.as_str())
+        .expect("snapshot ref should exist")
+        .id()
+        .to_string(),
+      snapshot.id
+    );
+
+    std::fs::write(root.join("a.md"), "edited").expect("note should be updated");
+    for revision in [
+      "HEAD",
+      reference.as_str(),
+      "0000000000000000000000000000000000000000",
+    ] {
+      let err = service
+        .restore(parent.clone(), root.clone(), revision.to_string(), None)
+        .await
+        .expect_err("only snapshot commits should restore");
+      assert_eq!(err.code(), "invalid_input");
+    }
+    let restored = service
+      .restore(parent.clone(), root.clone(), snapshot.id, None)
+      .await
+      .expect("snapshot should restore");
+    assert_eq!(restored, vec!["a.md".to_string()]);
+    assert_eq!(
+      std::fs::read_to_string(root.join("a.md")).expect("note should be readable"),
+      "draft"
+    );
+
+    let _ = std::fs::remove_dir_all(repo_dir);
+    let _ = std::fs::remove_dir_all(parent);
+  }
+}
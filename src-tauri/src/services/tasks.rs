@@ -0,0 +1,279 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tokio::sync::oneshot;
+
+use crate::models::{EventTopic, WorkspaceTask};
+use crate::services::events::FrontendEvent;
+use crate::services::process::{run_process, shell_command, CapturedOutput, ProcessOutcome};
+use crate::services::shutdown::ShutdownToken;
+
+const TASKS_FILE: &str = ".marko/tasks.toml";
+/// Log kept per task, the most recent bytes win.
+const TASK_LOG_LIMIT_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskExitEvent {
+  pub name: String,
+  pub exit_code: Option<i32>,
+  pub cancelled: bool,
+}
+
+impl FrontendEvent for TaskExitEvent {
+  const TOPIC: EventTopic = EventTopic::Process;
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TasksFile {
+  tasks: BTreeMap<String, TaskConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct TaskConfig {
+  label: Option<String>,
+  command: String,
+  /// Working directory relative to the workspace root.
+  cwd: Option<String>,
+  env: BTreeMap<String, String>,
+}
+
+/// Runs the named commands of `.marko/tasks.toml`. One run per task at a
+/// time; the log of the last run stays available after it ends. Running
+/// tasks are killed on shutdown.
+#[derive(Debug)]
+pub struct TaskRunnerService {
+  /// Running tasks and the sender that cancels them, taken once used. A
+  /// task stays here until its process has been reaped.
+  running: Arc<Mutex<HashMap<String, Option<oneshot::Sender<()>>>>>,
+  logs: Arc<Mutex<HashMap<String, Arc<Mutex<CapturedOutput>>>>>,
+  shutdown: ShutdownToken,
+}
+
+impl TaskRunnerService {
+  pub fn new(shutdown: ShutdownToken) -> Self {
+    Self {
+      running: Arc::new(Mutex::new(HashMap::new())),
+      logs: Arc::new(Mutex::new(HashMap::new())),
+      shutdown,
+    }
+  }
+
+  pub fn list(&self, root: &Path) -> Result<Vec<WorkspaceTask>, String> {
+    let running = self
+      .running
+      .lock()
+      .map_err(|_| "Failed to lock running tasks")?;
+    Ok(
+      read_tasks(root)?
+        .into_iter()
+        .map(|(name, config)| WorkspaceTask {
+          label: config.label.unwrap_or_else(|| name.clone()),
+          running: running.contains_key(&name),
+          name,
+          command: config.command,
+          cwd: config.cwd,
+        })
+        .collect(),
+    )
+  }
+
+  /// Marks `name` as running and prepares its command. Fails if the task is
+  /// unknown or already running.
+  pub fn start(&self, root: &Path, name: &str) -> Result<TaskStart, String> {
+    let config = read_tasks(root)?
+      .remove(name)
+      .ok_or_else(|| format!("Task not found: {name}"))?;
+    let cwd = task_dir(root, name, config.cwd.as_deref())?;
+    let mut running = self
+      .running
+      .lock()
+      .map_err(|_| "Failed to lock running tasks")?;
+    if running.contains_key(name) {
+      return Err(format!("Task {name} is already running"));
+    }
+    let (sender, cancel) = oneshot::channel();
+    running.insert(name.to_string(), Some(sender));
+    let log = Arc::new(Mutex::new(CapturedOutput::new(TASK_LOG_LIMIT_BYTES)));
+    self
+      .logs
+      .lock()
+      .map_err(|_| "Failed to lock task logs")?
+      .insert(name.to_string(), Arc::clone(&log));
+
+    let mut command = shell_command(&config.command);
+    command.current_dir(cwd).envs(&config.env);
+    Ok(TaskStart {
+      name: name.to_string(),
+      label: config.label.unwrap_or_else(|| name.to_string()),
+      command,
+      cancel,
+      log,
+    })
+  }
+
+  /// Runs a started task to completion, streaming `task-output` events.
+  pub async fn run(&self, app: AppHandle, start: TaskStart) -> Result<ProcessOutcome, String> {
+    let result = run_process(
+      app,
+      "task-output",
+      &start.name,
+      start.command,
+      start.cancel,
+      start.log,
+      &self.shutdown,
+    )
+    .await;
+    if let Ok(mut running) = self.running.lock() {
+      running.remove(&start.name);
+    }
+    result
+  }
+
+  /// Releases a started task that will not be run after all.
+  pub fn discard(&self, start: TaskStart) {
+    if let Ok(mut running) = self.running.lock() {
+      running.remove(&start.name);
+    }
+  }
+
+  /// Asks a running task to stop. It stays running, and cannot be started
+  /// again, until [`Self::run`] has reaped its process.
+  pub fn cancel(&self, name: &str) -> Result<(), String> {
+    let sender = self
+      .running
+      .lock()
+      .map_err(|_| "Failed to lock running tasks")?
+      .get_mut(name)
+      .ok_or_else(|| format!("Task {name} is not running"))?
+      .take();
+    if let Some(sender) = sender {
+      let _ = sender.send(());
+    }
+    Ok(())
+  }
+
+  /// Output of the current or last run of `name`.
+  pub fn log(&self, name: &str) -> Result<String, String> {
+    let logs = self.logs.lock().map_err(|_| "Failed to lock task logs")?;
+    let Some(log) = logs.get(name) else {
+      return Ok(String::new());
+    };
+    let log = log.lock().map_err(|_| "Failed to lock task log")?;
+    Ok(log.text().to_string())
+  }
+}
+
+/// A task marked as running, ready for [`TaskRunnerService::run`].
+pub struct TaskStart {
+  pub name: String,
+  pub label: String,
+  command: tokio::process::Command,
+  cancel: oneshot::Receiver<()>,
+  log: Arc<Mutex<CapturedOutput>>,
+}
+
+/// The working directory of a task. It must be a folder inside the
+/// workspace, so a task file cannot run commands elsewhere.
+fn task_dir(root: &Path, name: &str, cwd: Option<&str>) -> Result<PathBuf, String> {
+  let relative = Path::new(cwd.unwrap_or_default());
+  if relative
+    .components()
+    .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+  {
+    return Err(format!(
+      "Working directory of task {name} must be inside the workspace"
+    ));
+  }
+  let dir = root.join(relative);
+  if !dir.is_dir() {
+    return Err(format!(
+      "Working directory of task {name} is not a folder: {}",
+      relative.display()
+    ));
+  }
+  Ok(dir)
+}
+
+fn read_tasks(root: &Path) -> Result<BTreeMap<String, TaskConfig>, String> {
+  let content = match std::fs::read_to_string(root.join(TASKS_FILE)) {
+    Ok(content) => content,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+    Err(err) => return Err(format!("Failed to read tasks: {err}")),
+  };
+  let file: TasksFile =
+    toml::from_str(&content).map_err(|err| format!("Failed to parse {TASKS_FILE}: {err}"))?;
+  for (name, config) in &file.tasks {
+    if config.command.trim().is_empty() {
+      return Err(format!("Task {name} has no command"));
+    }
+  }
+  Ok(file.tasks)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::test_support::temp_dir;
+
+  use super::*;
+
+  #[test]
+  fn lists_tasks_from_toml() {
+    let root = temp_dir("tasks");
+    std::fs::create_dir_all(root.join(".marko")).expect("root should be created");
+    let service = TaskRunnerService::new(ShutdownToken::new());
+    assert!(service.list(&root).expect("tasks should list").is_empty());
+
+    std::fs::write(
+      root.join(TASKS_FILE),
+      "[tasks.lint]\ncommand = \"markdownlint .\"\n\n[tasks.build-docs]\nlabel = \"Build docs\"\ncommand = \"mdbook build\"\ncwd = \"docs\"\nenv = { RUST_LOG = \"info\" }\n",
+    )
+    .expect("tasks should be written");
+    let tasks = service.list(&root).expect("tasks should list");
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].name, "build-docs");
+    assert_eq!(tasks[0].label, "Build docs");
+    assert_eq!(tasks[0].cwd.as_deref(), Some("docs"));
+    assert_eq!(tasks[1].label, "lint");
+
+    let start = service.start(&root, "lint").expect("lint should start");
+    assert_eq!(start.label, "lint");
+    assert!(service.start(&root, "lint").is_err());
+    assert!(service.list(&root).expect("tasks should list")[1].running);
+    service.cancel("lint").expect("lint should cancel");
+    // Still running until its process has been reaped.
+    assert!(service.start(&root, "lint").is_err());
+    assert!(service.list(&root).expect("tasks should list")[1].running);
+    assert!(service.start(&root, "missing").is_err());
+
+    assert!(service.start(&root, "build-docs").is_err());
+    std::fs::create_dir_all(root.join("docs")).expect("docs should be created");
+    let start = service
+      .start(&root, "build-docs")
+      .expect("build-docs should start");
+    service.discard(start);
+    assert!(!service.list(&root).expect("tasks should list")[0].running);
+
+    std::fs::write(
+      root.join(TASKS_FILE),
+      "[tasks.up]\ncommand = \"ls\"\ncwd = \"../\"\n\n[tasks.abs]\ncommand = \"ls\"\ncwd = \"/tmp\"\n",
+    )
+    .expect("tasks should be written");
+    for name in ["up", "abs"] {
+      let err = service
+        .start(&root, name)
+        .err()
+        .expect("task outside the workspace should not start");
+      assert!(err.contains("inside the workspace"), "{err}");
+    }
+
+    std::fs::write(root.join(TASKS_FILE), "[tasks.empty]\ncommand = \"\"\n")
+      .expect("tasks should be written");
+    assert!(service.list(&root).is_err());
+    std::fs::remove_dir_all(root).expect("test dir should be removed");
+  }
+}
@@ -1,25 +1,73 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io::{Read, Write};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
-use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use portable_pty::{native_pty_system, Child, CommandBuilder, ExitStatus, MasterPty, PtySize};
 use serde::Serialize;
-use tauri::{AppHandle, Emitter};
+use tauri::AppHandle;
+
+use crate::models::EventTopic;
+use crate::services::events::{emit_event, FrontendEvent};
+use crate::services::shutdown::ShutdownToken;
+
+mod profiles;
+mod utf8;
+
+pub use profiles::{
+  read_terminal_profiles, write_terminal_profiles, TerminalLaunch, TerminalProfileSettings,
+};
+pub use utf8::Utf8Decoder;
+
+/// Bytes of output kept per session for replay on attach.
+const SCROLLBACK_LIMIT_BYTES: usize = 512 * 1024;
+/// Minimum time between two output events of a session. Output arriving in
+/// between is coalesced into the next event.
+const OUTPUT_FLUSH_INTERVAL: Duration = Duration::from_millis(16);
+/// Size at which a batch stops collecting. Together with the interval this
+/// caps the output rate of a session.
+const OUTPUT_BATCH_LIMIT_BYTES: usize = 64 * 1024;
+/// Decoded reads queued for the emitter. When the queue is full the reader
+/// stops reading, the pty buffer fills up and the process blocks on write.
+const OUTPUT_QUEUE_LEN: usize = 8;
+/// Time a shell gets to exit after SIGHUP before it is killed.
+const TERMINAL_KILL_TIMEOUT: Duration = Duration::from_secs(2);
+const TERMINAL_EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 #[derive(Debug, Clone, Serialize)]
 pub struct TerminalSessionInfo {
   pub id: String,
   pub shell: String,
   pub cwd: String,
+  pub profile: Option<String>,
+  pub running: bool,
+  pub exit_code: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct TerminalOutputEvent {
   pub id: String,
   pub data: String,
+  /// Stream offset of the first byte of `data`.
+  pub offset: u64,
+}
+
+impl FrontendEvent for TerminalOutputEvent {
+  const TOPIC: EventTopic = EventTopic::Terminal;
+  const LOGGED: bool = false;
+}
+
+/// Scrollback of a session and the stream offset where live output resumes.
+/// Output events with an `offset` below `offset` are already included.
+#[derive(Debug, Clone, Serialize)]
+pub struct TerminalAttachment {
+  pub session: TerminalSessionInfo,
+  pub scrollback: String,
+  pub offset: u64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -29,15 +77,53 @@ pub struct TerminalExitEvent {
   pub signal: Option<String>,
 }
 
+impl FrontendEvent for TerminalExitEvent {
+  const TOPIC: EventTopic = EventTopic::Terminal;
+}
+
 pub struct TerminalService {
   sessions: Arc<Mutex<HashMap<String, TerminalSession>>>,
   next_id: AtomicU64,
+  shutdown: ShutdownToken,
 }
 
 struct TerminalSession {
+  order: u64,
+  info: TerminalSessionInfo,
   master: Box<dyn MasterPty + Send>,
   writer: Arc<Mutex<Box<dyn Write + Send>>>,
-  killer: Arc<Mutex<Box<dyn ChildKiller + Send + Sync>>>,
+  /// Asks the wait thread to terminate the process.
+  stop: Arc<AtomicBool>,
+  scrollback: Arc<Mutex<Scrollback>>,
+}
+
+/// Bounded output history. Whole chunks are dropped from the front once the
+/// limit is exceeded, so multi-byte characters are never split.
+#[derive(Debug, Default)]
+struct Scrollback {
+  chunks: VecDeque<String>,
+  bytes: usize,
+  /// Total bytes ever appended, i.e. the stream offset of the next chunk.
+  end_offset: u64,
+}
+
+impl Scrollback {
+  fn push(&mut self, chunk: String, limit: usize) -> u64 {
+    let offset = self.end_offset;
+    self.end_offset += chunk.len() as u64;
+    self.bytes += chunk.len();
+    self.chunks.push_back(chunk);
+    while self.bytes > limit && self.chunks.len() > 1 {
+      if let Some(dropped) = self.chunks.pop_front() {
+        self.bytes -= dropped.len();
+      }
+    }
+    offset
+  }
+
+  fn contents(&self) -> String {
+    self.chunks.iter().map(String::as_str).collect()
+  }
 }
 
 impl std::fmt::Debug for TerminalService {
@@ -50,23 +136,26 @@ impl std::fmt::Debug for TerminalService {
 }
 
 impl TerminalService {
-  pub fn new() -> Self {
+  /// Sessions are terminated when `shutdown` is cancelled.
+  pub fn new(shutdown: ShutdownToken) -> Self {
     Self {
       sessions: Arc::new(Mutex::new(HashMap::new())),
       next_id: AtomicU64::new(1),
+      shutdown,
     }
   }
 
   pub fn create(
     &self,
     app: AppHandle,
-    cwd: PathBuf,
+    launch: TerminalLaunch,
     rows: u16,
     cols: u16,
   ) -> Result<TerminalSessionInfo, String> {
-    let id = format!("terminal-{}", self.next_id.fetch_add(1, Ordering::Relaxed));
-    let shell = default_shell();
-    let cwd = normalize_cwd(cwd)?;
+    let order = self.next_id.fetch_add(1, Ordering::Relaxed);
+    let id = format!("terminal-{order}");
+    let shell = launch.program;
+    let cwd = normalize_cwd(launch.cwd)?;
     let size = normalized_size(rows, cols);
     let pty_system = native_pty_system();
     let pair = pty_system
@@ -76,12 +165,15 @@ impl TerminalService {
     command.cwd(cwd.as_os_str());
     command.env("TERM", "xterm-256color");
     command.env("COLORTERM", "truecolor");
+    command.args(&launch.args);
+    for (key, value) in &launch.env {
+      command.env(key, value);
+    }
 
     let child = pair
       .slave
       .spawn_command(command)
       .map_err(|err| format!("Failed to spawn terminal shell: {err}"))?;
-    let killer = Arc::new(Mutex::new(child.clone_killer()));
     let reader = pair
       .master
       .try_clone_reader()
@@ -93,10 +185,23 @@ impl TerminalService {
         .map_err(|err| format!("Failed to open terminal input: {err}"))?,
     ));
 
+    let info = TerminalSessionInfo {
+      id: id.clone(),
+      shell,
+      cwd: cwd.to_string_lossy().to_string(),
+      profile: launch.profile,
+      running: true,
+      exit_code: None,
+    };
+    let scrollback = Arc::new(Mutex::new(Scrollback::default()));
+    let stop = Arc::new(AtomicBool::new(false));
     let session = TerminalSession {
+      order,
+      info: info.clone(),
       master: pair.master,
       writer,
-      killer,
+      stop: Arc::clone(&stop),
+      scrollback: Arc::clone(&scrollback),
     };
     self
       .sessions
@@ -104,13 +209,56 @@ impl TerminalService {
       .map_err(|_| "Failed to lock terminal sessions")?
       .insert(id.clone(), session);
 
-    spawn_reader_thread(app.clone(), id.clone(), reader);
-    spawn_wait_thread(app, id.clone(), Arc::clone(&self.sessions), child);
-
-    Ok(TerminalSessionInfo {
+    spawn_reader_thread(app.clone(), id.clone(), reader, scrollback, &self.shutdown);
+    spawn_wait_thread(
+      app,
       id,
-      shell,
-      cwd: cwd.to_string_lossy().to_string(),
+      Arc::clone(&self.sessions),
+      child,
+      stop,
+      self.shutdown.clone(),
+      launch.temp_files,
+    );
+
+    Ok(info)
+  }
+
+  /// Sessions in creation order, including exited ones that were not closed.
+  pub fn list(&self) -> Result<Vec<TerminalSessionInfo>, String> {
+    let sessions = self
+      .sessions
+      .lock()
+      .map_err(|_| "Failed to lock terminal sessions")?;
+    let mut sessions = sessions.values().collect::<Vec<_>>();
+    sessions.sort_by_key(|session| session.order);
+    Ok(
+      sessions
+        .into_iter()
+        .map(|session| session.info.clone())
+        .collect(),
+    )
+  }
+
+  /// Returns the scrollback of a session. Live output continues through
+  /// `terminal-output` events from the returned offset.
+  pub fn attach(&self, id: &str) -> Result<TerminalAttachment, String> {
+    let (info, scrollback) = {
+      let sessions = self
+        .sessions
+        .lock()
+        .map_err(|_| "Failed to lock terminal sessions")?;
+      let session = sessions
+        .get(id)
+        .ok_or_else(|| format!("Terminal session not found: {id}"))?;
+      (session.info.clone(), Arc::clone(&session.scrollback))
+    };
+    let scrollback = scrollback
+      .lock()
+      .map_err(|_| "Failed to lock terminal scrollback")?;
+    Ok(TerminalAttachment {
+      session: info,
+      scrollback: scrollback.contents(),
+      offset: scrollback.end_offset,
     })
   }
 
@@ -150,6 +298,8 @@ impl TerminalService {
       .map_err(|err| format!("Failed to resize terminal: {err}"))
   }
 
+  /// Removes a session. Its process gets SIGHUP and is killed if it has
+  /// not exited after [`TERMINAL_KILL_TIMEOUT`].
   pub fn close(&self, id: &str) -> Result<(), String> {
     let session = self
       .sessions
@@ -157,11 +307,7 @@ impl TerminalService {
       .map_err(|_| "Failed to lock terminal sessions")?
       .remove(id);
     if let Some(session) = session {
-      let mut killer = session
-        .killer
-        .lock()
-        .map_err(|_| "Failed to lock terminal process")?;
-      let _ = killer.kill();
+      session.stop.store(true, Ordering::SeqCst);
     }
     Ok(())
   }
@@ -177,59 +323,129 @@ impl TerminalService {
 
 impl Default for TerminalService {
   fn default() -> Self {
-    Self::new()
+    Self::new(ShutdownToken::new())
   }
 }
 
 impl Drop for TerminalService {
   fn drop(&mut self) {
-    let session_ids = self
-      .sessions
-      .lock()
-      .map(|sessions| sessions.keys().cloned().collect::<Vec<_>>())
-      .unwrap_or_default();
-    for id in session_ids {
-      let _ = self.close(&id);
+    if let Ok(sessions) = self.sessions.lock() {
+      for session in sessions.values() {
+        session.stop.store(true, Ordering::SeqCst);
+      }
     }
   }
 }
 
-fn spawn_reader_thread(app: AppHandle, id: String, mut reader: Box<dyn Read + Send>) {
+fn spawn_reader_thread(
+  app: AppHandle,
+  id: String,
+  mut reader: Box<dyn Read + Send>,
+  scrollback: Arc<Mutex<Scrollback>>,
+  shutdown: &ShutdownToken,
+) {
+  let (sender, receiver) = mpsc::sync_channel::<String>(OUTPUT_QUEUE_LEN);
+  // The reader ends with the pty, i.e. once the wait thread has ended the
+  // process; the emitter ends with the reader.
+  let reader_worker = shutdown.worker();
   thread::spawn(move || {
+    let _worker = reader_worker;
+    let mut decoder = Utf8Decoder::default();
     let mut buffer = [0_u8; 8192];
     loop {
       match reader.read(&mut buffer) {
-        Ok(0) => break,
+        Ok(0) | Err(_) => break,
         Ok(bytes_read) => {
-          let data = String::from_utf8_lossy(&buffer[..bytes_read]).to_string();
-          let _ = app.emit(
-            "terminal-output",
-            TerminalOutputEvent {
-              id: id.clone(),
-              data,
-            },
-          );
+          let data = decoder.decode(&buffer[..bytes_read]);
+          if !data.is_empty() && sender.send(data).is_err() {
+            return;
+          }
         }
-        Err(_) => break,
       }
     }
+    let rest = decoder.finish();
+    if !rest.is_empty() {
+      let _ = sender.send(rest);
+    }
+  });
+  let emitter_worker = shutdown.worker();
+  thread::spawn(move || {
+    let _worker = emitter_worker;
+    emit_output_batches(app, id, receiver, scrollback);
   });
 }
 
+/// Emits queued output as `terminal-output` events, at most one per
+/// [`OUTPUT_FLUSH_INTERVAL`]. Output after a quiet period is sent right away.
+fn emit_output_batches(
+  app: AppHandle,
+  id: String,
+  receiver: Receiver<String>,
+  scrollback: Arc<Mutex<Scrollback>>,
+) {
+  let mut next_flush = Instant::now();
+  while let Ok(mut data) = receiver.recv() {
+    while data.len() < OUTPUT_BATCH_LIMIT_BYTES {
+      let wait = next_flush.saturating_duration_since(Instant::now());
+      let next = if wait.is_zero() {
+        receiver.try_recv().ok()
+      } else {
+        receiver.recv_timeout(wait).ok()
+      };
+      let Some(chunk) = next else {
+        break;
+      };
+      data.push_str(&chunk);
+    }
+    // A full batch still waits for its slot.
+    thread::sleep(next_flush.saturating_duration_since(Instant::now()));
+
+    // Emitting under the lock keeps attach snapshots and live events
+    // from overlapping or leaving gaps.
+    let Ok(mut scrollback) = scrollback.lock() else {
+      break;
+    };
+    let offset = scrollback.push(data.clone(), SCROLLBACK_LIMIT_BYTES);
+    let _ = emit_event(
+      &app,
+      "terminal-output",
+      &TerminalOutputEvent {
+        id: id.clone(),
+        data,
+        offset,
+      },
+    );
+    next_flush = Instant::now() + OUTPUT_FLUSH_INTERVAL;
+  }
+}
+
 fn spawn_wait_thread(
   app: AppHandle,
   id: String,
   sessions: Arc<Mutex<HashMap<String, TerminalSession>>>,
-  mut child: Box<dyn portable_pty::Child + Send + Sync>,
+  mut child: Box<dyn Child + Send + Sync>,
+  stop: Arc<AtomicBool>,
+  shutdown: ShutdownToken,
+  temp_files: Vec<PathBuf>,
 ) {
+  let worker = shutdown.worker();
   thread::spawn(move || {
-    let status = child.wait().ok();
+    let _worker = worker;
+    let status = wait_for_exit(child.as_mut(), &stop, &shutdown, TERMINAL_KILL_TIMEOUT);
+    for file in temp_files {
+      let _ = std::fs::remove_file(file);
+    }
+    // Exited sessions stay listed with their scrollback until closed.
     if let Ok(mut sessions) = sessions.lock() {
-      sessions.remove(&id);
+      if let Some(session) = sessions.get_mut(&id) {
+        session.info.running = false;
+        session.info.exit_code = status.as_ref().map(|status| status.exit_code());
+      }
     }
-    let _ = app.emit(
+    let _ = emit_event(
+      &app,
       "terminal-exit",
-      TerminalExitEvent {
+      &TerminalExitEvent {
         id,
         exit_code: status.as_ref().map(|status| status.exit_code()),
         signal: status.and_then(|status| status.signal().map(ToString::to_string)),
@@ -238,6 +454,37 @@ fn spawn_wait_thread(
   });
 }
 
+/// Waits for the process to exit. Once `stop` is set or shutdown begins it
+/// gets SIGHUP, and is killed if it is still running after `kill_timeout`.
+fn wait_for_exit(
+  child: &mut (dyn Child + Send + Sync),
+  stop: &AtomicBool,
+  shutdown: &ShutdownToken,
+  kill_timeout: Duration,
+) -> Option<ExitStatus> {
+  let mut kill_deadline: Option<Instant> = None;
+  loop {
+    match child.try_wait() {
+      Ok(Some(status)) => return Some(status),
+      Ok(None) => {}
+      Err(_) => return None,
+    }
+    match kill_deadline {
+      None if stop.load(Ordering::SeqCst) || shutdown.is_cancelled() => {
+        let _ = child.clone_killer().kill();
+        kill_deadline = Some(Instant::now() + kill_timeout);
+      }
+      Some(deadline) if Instant::now() >= deadline => {
+        // Escalates to SIGKILL on unix.
+        let _ = child.kill();
+        return child.wait().ok();
+      }
+      _ => {}
+    }
+    thread::sleep(TERMINAL_EXIT_POLL_INTERVAL);
+  }
+}
+
 fn normalized_size(rows: u16, cols: u16) -> PtySize {
   PtySize {
     rows: rows.clamp(8, 200),
@@ -285,3 +532,57 @@ fn default_shell() -> String {
     })
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn scrollback_drops_oldest_chunks_and_tracks_offsets() {
+    let mut scrollback = Scrollback::default();
+    assert_eq!(scrollback.push("hello ".to_string(), 12), 0);
+    assert_eq!(scrollback.push("wörld ".to_string(), 12), 6);
+    assert_eq!(scrollback.push("again".to_string(), 12), 13);
+
+    assert_eq!(scrollback.contents(), "wörld again");
+    assert_eq!(scrollback.end_offset, 18);
+  }
+
+  #[cfg(unix)]
+  fn spawn_pty_child(script: &str) -> (Box<dyn Child + Send + Sync>, portable_pty::PtyPair) {
+    let pair = native_pty_system()
+      .openpty(normalized_size(24, 80))
+      .expect("pty should open");
+    let mut command = CommandBuilder::new("sh");
+    command.args(["-c", script]);
+    let child = pair
+      .slave
+      .spawn_command(command)
+      .expect("child should spawn");
+    // Dropping the master would hang the child up before the test runs.
+    (child, pair)
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn stops_processes_on_shutdown_and_kills_those_ignoring_sighup() {
+    let shutdown = ShutdownToken::new();
+    let stop = AtomicBool::new(false);
+    let timeout = Duration::from_millis(300);
+
+    let (mut polite, _pty) = spawn_pty_child("sleep 5");
+    thread::sleep(Duration::from_millis(100));
+    shutdown.cancel();
+    let started = Instant::now();
+    assert!(wait_for_exit(polite.as_mut(), &stop, &shutdown, timeout).is_some());
+    assert!(started.elapsed() < timeout);
+
+    let shutdown = ShutdownToken::new();
+    let (mut stubborn, _pty) = spawn_pty_child("trap '' HUP; sleep 5");
+    thread::sleep(Duration::from_millis(100));
+    stop.store(true, Ordering::SeqCst);
+    let started = Instant::now();
+    assert!(wait_for_exit(stubborn.as_mut(), &stop, &shutdown, timeout).is_some());
+    assert!(started.elapsed() >= timeout);
+  }
+}
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::services::search::stable_hash;
+
+const PROFILES_DIR: &str = "terminal-profiles";
+const CWD_POLICIES: [&str; 3] = ["workspace_root", "file_directory", "fixed"];
+
+/// A named way to launch a terminal. An empty `program` uses the user's
+/// default shell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalProfile {
+  pub name: String,
+  pub program: String,
+  pub args: Vec<String>,
+  pub env: BTreeMap<String, String>,
+  /// `workspace_root`, `file_directory` or `fixed`.
+  pub cwd_policy: String,
+  /// Directory for the `fixed` policy, absolute or relative to the root.
+  pub cwd: Option<String>,
+}
+
+impl Default for TerminalProfile {
+  fn default() -> Self {
+    Self {
+      name: String::new(),
+      program: String::new(),
+      args: Vec::new(),
+      env: BTreeMap::new(),
+      cwd_policy: "workspace_root".to_string(),
+      cwd: None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalProfileSettings {
+  pub default_profile: Option<String>,
+  pub profiles: Vec<TerminalProfile>,
+}
+
+impl TerminalProfileSettings {
+  /// The named profile, or the default one when `name` is `None`.
+  pub fn profile(&self, name: Option<&str>) -> Result<Option<&TerminalProfile>, String> {
+    let Some(name) = name.or(self.default_profile.as_deref()) else {
+      return Ok(None);
+    };
+    self
+      .profiles
+      .iter()
+      .find(|profile| profile.name == name)
+      .map(Some)
+      .ok_or_else(|| format!("Terminal profile not found: {name}"))
+  }
+}
+
+/// Everything needed to spawn a terminal process.
+#[derive(Debug, Clone)]
+pub struct TerminalLaunch {
+  pub profile: Option<String>,
+  pub program: String,
+  pub args: Vec<String>,
+  pub env: BTreeMap<String, String>,
+  pub cwd: PathBuf,
+  /// Files removed once the process exits, such as a script it runs.
+  pub temp_files: Vec<PathBuf>,
+}
+
+impl TerminalLaunch {
+  /// Resolves a profile against the workspace root and the directory of the
+  /// file the terminal is opened for.
+  pub fn for_profile(
+    profile: Option<&TerminalProfile>,
+    workspace_root: &Path,
+    file_directory: Option<&Path>,
+  ) -> Self {
+    let Some(profile) = profile else {
+      return Self {
+        profile: None,
+        program: super::default_shell(),
+        args: Vec::new(),
+        env: BTreeMap::new(),
+        cwd: workspace_root.to_path_buf(),
+        temp_files: Vec::new(),
+      };
+    };
+    let cwd = match profile.cwd_policy.as_str() {
+      "file_directory" => file_directory.unwrap_or(workspace_root).to_path_buf(),
+      "fixed" => profile
+        .cwd
+        .as_deref()
+        .map(|cwd| workspace_root.join(cwd))
+        .unwrap_or_else(|| workspace_root.to_path_buf()),
+      _ => workspace_root.to_path_buf(),
+    };
+    Self {
+      profile: Some(profile.name.clone()),
+      program: if profile.program.trim().is_empty() {
+        super::default_shell()
+      } else {
+        profile.program.clone()
+      },
+      args: profile.args.clone(),
+      env: profile.env.clone(),
+      cwd,
+      temp_files: Vec::new(),
+    }
+  }
+}
+
+/// Profiles of the workspace at `root`, kept under `profiles_parent` (the
+/// app data dir) rather than in the workspace: a cloned repository must not
+/// be able to choose what a new terminal runs.
+pub fn read_terminal_profiles(
+  profiles_parent: &Path,
+  root: &Path,
+) -> Result<TerminalProfileSettings, String> {
+  match std::fs::read_to_string(profiles_file(profiles_parent, root)) {
+    Ok(content) => serde_json::from_str(&content)
+      .map_err(|err| format!("Failed to parse terminal profiles: {err}")),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+      Ok(TerminalProfileSettings::default())
+    }
+    Err(err) => Err(format!("Failed to read terminal profiles: {err}")),
+  }
+}
+
+pub fn write_terminal_profiles(
+  profiles_parent: &Path,
+  root: &Path,
+  settings: &TerminalProfileSettings,
+) -> Result<(), String> {
+  for (index, profile) in settings.profiles.iter().enumerate() {
+    if profile.name.trim().is_empty() {
+      return Err("Terminal profile names must not be empty".to_string());
+    }
+    if settings.profiles[..index]
+      .iter()
+      .any(|other| other.name == profile.name)
+    {
+      return Err(format!("Duplicate terminal profile: {}", profile.name));
+    }
+    if !CWD_POLICIES.contains(&profile.cwd_policy.as_str()) {
+      return Err(format!(
+        "Invalid working directory policy for {}: {}",
+        profile.name, profile.cwd_policy
+      ));
+    }
+    if profile.cwd_policy == "fixed" && profile.cwd.as_deref().map_or(true, str::is_empty) {
+      return Err(format!(
+        "Profile {} needs a working directory",
+        profile.name
+      ));
+    }
+  }
+  settings.profile(None)?;
+
+  let path = profiles_file(profiles_parent, root);
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir).map_err(|err| format!("Failed to create dir: {err}"))?;
+  }
+  let content = serde_json::to_string_pretty(settings)
+    .map_err(|err| format!("Failed to serialize terminal profiles: {err}"))?;
+  std::fs::write(path, content).map_err(|err| format!("Failed to write terminal profiles: {err}"))
+}
+
+fn profiles_file(profiles_parent: &Path, root: &Path) -> PathBuf {
+  profiles_parent.join(PROFILES_DIR).join(format!(
+    "{:016x}.json",
+    stable_hash(&root.to_string_lossy())
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::test_support::temp_dir;
+
+  use super::*;
+
+  fn profile(name: &str, cwd_policy: &str, cwd: Option<&str>) -> TerminalProfile {
+    TerminalProfile {
+      name: name.to_string(),
+      program: "bash".to_string(),
+      cwd_policy: cwd_policy.to_string(),
+      cwd: cwd.map(ToString::to_string),
+      ..TerminalProfile::default()
+    }
+  }
+
+  #[test]
+  fn persists_profiles_and_resolves_working_directories() {
+    let root = temp_dir("terminal-profiles");
+    let app_data = root.join("app-data");
+    std::fs::create_dir_all(&root).expect("root should be created");
+    assert!(read_terminal_profiles(&app_data, &root)
+      .expect("missing profiles should read")
+      .profiles
+      .is_empty());
+
+    let mut settings = TerminalProfileSettings {
+      default_profile: Some("notes".to_string()),
+      profiles: vec![
+        profile("notes", "file_directory", None),
+        profile("build", "fixed", Some("site")),
+      ],
+    };
+    settings.profiles[1]
+      .env
+      .insert("NODE_ENV".to_string(), "production".to_string());
+    write_terminal_profiles(&app_data, &root, &settings).expect("profiles should be written");
+    let settings = read_terminal_profiles(&app_data, &root).expect("profiles should read");
+    assert!(!root.join(".marko").exists());
+    assert!(read_terminal_profiles(&app_data, &root.join("other"))
+      .expect("other workspace should read")
+      .profiles
+      .is_empty());
+
+    let notes = TerminalLaunch::for_profile(
+      settings.profile(None).expect("default should exist"),
+      &root,
+      Some(Path::new("/tmp/notes")),
+    );
+    assert_eq!(notes.profile.as_deref(), Some("notes"));
+    assert_eq!(notes.cwd, Path::new("/tmp/notes"));
+    let build = TerminalLaunch::for_profile(
+      settings.profile(Some("build")).expect("build should exist"),
+      &root,
+      None,
+    );
+    assert_eq!(build.cwd, root.join("site"));
+    assert_eq!(build.env["NODE_ENV"], "production");
+    assert!(settings.profile(Some("missing")).is_err());
+
+    let duplicate = TerminalProfileSettings {
+      default_profile: None,
+      profiles: vec![
+        profile("a", "workspace_root", None),
+        profile("a", "workspace_root", None),
+      ],
+    };
+    assert!(write_terminal_profiles(&app_data, &root, &duplicate).is_err());
+    let unfixed = TerminalProfileSettings {
+      default_profile: None,
+      profiles: vec![profile("a", "fixed", None)],
+    };
+    assert!(write_terminal_profiles(&app_data, &root, &unfixed).is_err());
+    std::fs::remove_dir_all(root).expect("test dir should be removed");
+  }
+}
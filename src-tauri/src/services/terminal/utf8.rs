@@ -0,0 +1,71 @@
+/// Decodes a byte stream read in arbitrary chunks. A multi-byte character
+/// split across reads is held back until its remaining bytes arrive; invalid
+/// sequences become U+FFFD as with `String::from_utf8_lossy`.
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+  pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+  pub fn decode(&mut self, bytes: &[u8]) -> String {
+    self.pending.extend_from_slice(bytes);
+    let mut decoded = String::with_capacity(self.pending.len());
+    let mut input = self.pending.as_slice();
+    loop {
+      match std::str::from_utf8(input) {
+        Ok(valid) => {
+          decoded.push_str(valid);
+          input = &[];
+          break;
+        }
+        Err(err) => {
+          let (valid, rest) = input.split_at(err.valid_up_to());
+          // The prefix was just validated.
+          decoded.push_str(std::str::from_utf8(valid).unwrap_or_default());
+          match err.error_len() {
+            Some(invalid_len) => {
+              decoded.push(char::REPLACEMENT_CHARACTER);
+              input = &rest[invalid_len..];
+            }
+            // An incomplete character at the end: wait for more bytes.
+            None => {
+              input = rest;
+              break;
+            }
+          }
+        }
+      }
+    }
+    self.pending = input.to_vec();
+    decoded
+  }
+
+  /// Flushes bytes of an unfinished character at the end of the stream.
+  pub fn finish(&mut self) -> String {
+    let rest = String::from_utf8_lossy(&self.pending).to_string();
+    self.pending.clear();
+    rest
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Utf8Decoder;
+
+  #[test]
+  fn decodes_characters_split_across_reads() {
+    let text = "日志 ok 🙂 完成";
+    let bytes = text.as_bytes();
+    for split in 0..=bytes.len() {
+      let mut decoder = Utf8Decoder::default();
+      let mut decoded = decoder.decode(&bytes[..split]);
+      decoded.push_str(&decoder.decode(&bytes[split..]));
+      decoded.push_str(&decoder.finish());
+      assert_eq!(decoded, text, "split at byte {split}");
+    }
+
+    let mut decoder = Utf8Decoder::default();
+    assert_eq!(decoder.decode(b"a\xffb\xe6\x97"), "a\u{fffd}b");
+    assert_eq!(decoder.finish(), "\u{fffd}");
+  }
+}
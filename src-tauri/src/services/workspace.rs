@@ -1,7 +1,12 @@
+mod code_blocks;
 mod files;
 mod fs;
+mod ignore_rules;
 mod index;
 mod model;
+mod roots;
+mod templates;
+mod trash;
 
 use std::sync::{Arc, Mutex};
 
@@ -15,6 +20,7 @@ use crate::services::{
 use self::model::WorkspaceIndexCache;
 
 pub use self::fs::ensure_default_file;
+pub use self::ignore_rules::IgnoreRules;
 
 #[derive(Debug, Clone)]
 pub struct WorkspaceService {
@@ -24,6 +30,7 @@ pub struct WorkspaceService {
   markdown_graph: Shared<MarkdownGraphService>,
   search: Shared<SearchService>,
   index_cache: Arc<Mutex<Option<WorkspaceIndexCache>>>,
+  trash_lock: Arc<Mutex<()>>,
 }
 
 impl WorkspaceService {
@@ -41,6 +48,7 @@ impl WorkspaceService {
       markdown_graph,
       search,
       index_cache: Arc::new(Mutex::new(None)),
+      trash_lock: Arc::new(Mutex::new(())),
     }
   }
 }
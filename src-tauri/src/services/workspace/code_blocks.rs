@@ -0,0 +1,170 @@
+use crate::error::{AppError, AppResult};
+use crate::services::markdown_graph::CodeBlockLocation;
+use crate::state::FsState;
+
+use super::WorkspaceService;
+
+const OUTPUT_LANGUAGE: &str = "output";
+
+impl WorkspaceService {
+  /// Finds a fenced code block of a note by its outline block id.
+  pub async fn code_block(
+    &self,
+    path: &str,
+    block_id: &str,
+    state: &FsState,
+  ) -> AppResult<CodeBlockLocation> {
+    let content = self.read_file(path, state).await?;
+    self
+      .markdown_graph
+      .locate_code_block(path, &content, block_id)
+      .ok_or_else(|| AppError::invalid_input(format!("Code block not found: {block_id}")))
+  }
+
+  /// Writes `output` as a fenced `output` block below the code block,
+  /// replacing the output of a previous run. The note's buffer is updated,
+  /// so unsaved edits are kept. Fails with a conflict if the block no longer
+  /// holds `code`, the code that produced the output.
+  pub async fn write_code_output(
+    &self,
+    path: &str,
+    block_id: &str,
+    code: &str,
+    output: &str,
+    state: &FsState,
+  ) -> AppResult<()> {
+    let content = self.read_file(path, state).await?;
+    let block = self
+      .markdown_graph
+      .locate_code_block(path, &content, block_id)
+      .ok_or_else(|| AppError::invalid_input(format!("Code block not found: {block_id}")))?;
+    if block.code != code {
+      return Err(AppError::Conflict {
+        path: path.to_string(),
+        reason: "The code block changed while it was running".to_string(),
+      });
+    }
+    let content = insert_output_block(&content, block.range.end, output);
+    self.update_buffer(path, &content, state).map(|_| ())
+  }
+}
+
+/// Inserts an output block at `offset`, the end of a code block. An output
+/// block that directly follows, separated by blank lines at most, is replaced.
+fn insert_output_block(markdown: &str, offset: usize, output: &str) -> String {
+  let rest = &markdown[offset..];
+  let rest = match existing_output_block_len(rest) {
+    Some(len) => &rest[len..],
+    None => rest,
+  };
+
+  let output = output.trim_end_matches(['\n', '\r']).replace("\r\n", "\n");
+  let longest_run = output
+    .split(|char| char != '`')
+    .map(str::len)
+    .max()
+    .unwrap_or_default();
+  let fence = "`".repeat(longest_run.max(2) + 1);
+
+  let mut next = String::with_capacity(markdown.len() + output.len() + 32);
+  next.push_str(&markdown[..offset]);
+  if !next.is_empty() && !next.ends_with('\n') {
+    next.push('\n');
+  }
+  next.push_str(&format!("\n{fence}{OUTPUT_LANGUAGE}\n"));
+  if !output.is_empty() {
+    next.push_str(&output);
+    next.push('\n');
+  }
+  next.push_str(&fence);
+  next.push('\n');
+  if !rest.is_empty() && !rest.starts_with('\n') {
+    next.push('\n');
+  }
+  next.push_str(rest);
+  next
+}
+
+/// Length of the blank lines and `output` block at the start of `markdown`.
+fn existing_output_block_len(markdown: &str) -> Option<usize> {
+  let start = markdown.len() - markdown.trim_start_matches(['\n', '\r']).len();
+  let mut lines = markdown[start..].split_inclusive('\n');
+  let first = lines.next()?;
+  let opening = first.trim_end();
+  let fence_len = opening.len() - opening.trim_start_matches('`').len();
+  if fence_len < 3 || opening[fence_len..].trim() != OUTPUT_LANGUAGE {
+    return None;
+  }
+  let mut len = start + first.len();
+  for line in lines {
+    len += line.len();
+    let trimmed = line.trim_end();
+    if trimmed.len() >= fence_len && trimmed.chars().all(|char| char == '`') {
+      return Some(len);
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::RwLock;
+
+  use crate::state::FsStateData;
+  use crate::test_support::temp_dir;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn locates_code_blocks_and_replaces_their_output() {
+    let root = temp_dir("code-blocks");
+    std::fs::create_dir_all(&root).expect("root should be created");
+    std::fs::write(
+      root.join("runbook.md"),
+      "# Deploy\n\nCheck the host.\n\n```bash\necho hi\n```\nDone.\n",
+    )
+    .expect("runbook should be written");
+    let state = FsState(RwLock::new(FsStateData {
+      root_kind: "external".to_string(),
+      root_path: root.clone(),
+      internal_root: root.clone(),
+      single_file: None,
+      mounted_roots: Vec::new(),
+    }));
+    let service = WorkspaceService::default();
+    let block_id = "heading:runbook.md:deploy:block:1";
+
+    let block = service
+      .code_block("runbook.md", block_id, &state)
+      .await
+      .expect("code block should be found");
+    assert_eq!(block.language.as_deref(), Some("bash"));
+    assert_eq!(block.code, "echo hi");
+    assert!(service
+      .code_block("runbook.md", "heading:runbook.md:deploy:block:0", &state)
+      .await
+      .is_err());
+
+    service
+      .write_code_output("runbook.md", block_id, "echo hi", "hi\n", &state)
+      .await
+      .expect("output should be written");
+    service
+      .write_code_output("runbook.md", block_id, "echo hi", "a ``` fence\n", &state)
+      .await
+      .expect("output should be replaced");
+    let err = service
+      .write_code_output("runbook.md", block_id, "echo bye", "bye\n", &state)
+      .await
+      .expect_err("output of changed code should be refused");
+    assert_eq!(err.code(), "conflict");
+    assert_eq!(
+      service
+        .read_file("runbook.md", &state)
+        .await
+        .expect("runbook should read"),
+      "# Deploy\n\nCheck the host.\n\n```bash\necho hi\n```\n\n````output\na ``` fence\n````\n\nDone.\n"
+    );
+    std::fs::remove_dir_all(root).expect("test dir should be removed");
+  }
+}
@@ -1,28 +1,35 @@
 use std::path::PathBuf;
 use std::time::UNIX_EPOCH;
 
-use crate::models::{FsBufferStatus, FsEntry, FsPathMetadata, FsRootInfo, FsSnapshot};
+use crate::error::{AppError, AppResult};
+use crate::models::{
+  FsBufferStatus, FsEntry, FsPathMetadata, FsRootInfo, FsSnapshot, WorkspaceIgnoreSettings,
+};
+use crate::services::document_store::decode_text;
+use crate::services::document_types::is_document_path;
 use crate::state::FsState;
 
-use super::fs::{
-  ensure_default_file_async, ensure_workspace_mode, is_markdown, list_entries_async,
-};
+use super::fs::{ensure_default_file_async, ensure_workspace_mode, list_entries_async};
+use super::ignore_rules::{read_ignore_settings, write_ignore_settings};
 use super::WorkspaceService;
 
+/// Larger files are not read just to report their text format.
+const TEXT_FORMAT_MAX_BYTES: u64 = 8 * 1024 * 1024;
+
 impl WorkspaceService {
-  pub fn root_info(&self, state: &FsState) -> Result<FsRootInfo, String> {
-    let data = state.0.read().map_err(|_| "Failed to lock fs state")?;
+  pub fn root_info(&self, state: &FsState) -> AppResult<FsRootInfo> {
+    let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
     Ok(FsRootInfo {
       kind: data.root_kind.clone(),
       path: data.root_path.to_string_lossy().to_string(),
     })
   }
 
-  pub async fn snapshot(&self, state: &FsState) -> Result<FsSnapshot, String> {
+  pub async fn snapshot(&self, state: &FsState) -> AppResult<FsSnapshot> {
     let data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
     let entries = list_entries_async(data.clone()).await?;
     Ok(FsSnapshot {
@@ -34,19 +41,18 @@ impl WorkspaceService {
     })
   }
 
-  pub async fn set_root(
-    &self,
-    path: Option<String>,
-    state: &FsState,
-  ) -> Result<FsRootInfo, String> {
+  pub async fn set_root(&self, path: Option<String>, state: &FsState) -> AppResult<FsRootInfo> {
     let selected_root = match path {
       Some(path) => {
-        let root = PathBuf::from(path);
+        let root = PathBuf::from(&path);
         let metadata = tokio::fs::metadata(&root)
           .await
-          .map_err(|_| "Selected path is not a directory".to_string())?;
+          .map_err(|err| AppError::io("Failed to open folder", &root, err))?;
         if !metadata.is_dir() {
-          return Err("Selected path is not a directory".to_string());
+          return Err(AppError::invalid_path(
+            path,
+            "Selected path is not a directory",
+          ));
         }
         Some(root)
       }
@@ -54,7 +60,7 @@ impl WorkspaceService {
     };
 
     let root_info = {
-      let mut data = state.0.write().map_err(|_| "Failed to lock fs state")?;
+      let mut data = state.0.write().map_err(|_| AppError::lock("fs state"))?;
       match selected_root {
         Some(root) => {
           data.root_kind = "external".to_string();
@@ -67,6 +73,8 @@ impl WorkspaceService {
           data.single_file = None;
         }
       }
+      // Mounted roots belong to the workspace they were added to.
+      data.mounted_roots.clear();
       FsRootInfo {
         kind: data.root_kind.clone(),
         path: data.root_path.to_string_lossy().to_string(),
@@ -82,23 +90,27 @@ impl WorkspaceService {
     Ok(root_info)
   }
 
-  pub async fn set_single_file(&self, path: String, state: &FsState) -> Result<FsRootInfo, String> {
-    let file_path = PathBuf::from(path);
+  pub async fn set_single_file(&self, path: String, state: &FsState) -> AppResult<FsRootInfo> {
+    let file_path = PathBuf::from(&path);
     let metadata = tokio::fs::metadata(&file_path)
       .await
-      .map_err(|_| "Selected path is not a file".to_string())?;
+      .map_err(|err| AppError::io("Failed to open file", &file_path, err))?;
     if !metadata.is_file() {
-      return Err("Selected path is not a file".to_string());
+      return Err(AppError::invalid_path(path, "Selected path is not a file"));
     }
-    if !is_markdown(&file_path) {
-      return Err("Selected file is not a Markdown file".to_string());
+    if !is_document_path(&file_path) {
+      return Err(AppError::invalid_path(
+        path,
+        "Selected file is not a supported document",
+      ));
     }
 
     let root_info = {
-      let mut data = state.0.write().map_err(|_| "Failed to lock fs state")?;
+      let mut data = state.0.write().map_err(|_| AppError::lock("fs state"))?;
       data.root_kind = "single".to_string();
       data.root_path = file_path.clone();
       data.single_file = Some(file_path.clone());
+      data.mounted_roots.clear();
       FsRootInfo {
         kind: data.root_kind.clone(),
         path: data.root_path.to_string_lossy().to_string(),
@@ -110,20 +122,20 @@ impl WorkspaceService {
     Ok(root_info)
   }
 
-  pub async fn list_entries(&self, state: &FsState) -> Result<Vec<FsEntry>, String> {
+  pub async fn list_entries(&self, state: &FsState) -> AppResult<Vec<FsEntry>> {
     let data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
     list_entries_async(data).await
   }
 
-  pub async fn read_file(&self, path: &str, state: &FsState) -> Result<String, String> {
+  pub async fn read_file(&self, path: &str, state: &FsState) -> AppResult<String> {
     self.documents.read_document(state, path).await
   }
 
-  pub async fn open_file(&self, path: &str, state: &FsState) -> Result<String, String> {
+  pub async fn open_file(&self, path: &str, state: &FsState) -> AppResult<String> {
     self.documents.read_document(state, path).await
   }
 
@@ -132,28 +144,59 @@ impl WorkspaceService {
     path: &str,
     content: &str,
     state: &FsState,
-  ) -> Result<FsBufferStatus, String> {
+  ) -> AppResult<FsBufferStatus> {
     self.documents.update_document(state, path, content)
   }
 
-  pub async fn flush_buffers(&self, state: &FsState) -> Result<Vec<FsBufferStatus>, String> {
+  pub async fn flush_buffers(&self, state: &FsState) -> AppResult<Vec<FsBufferStatus>> {
     self.documents.flush_all_with_status_async(state).await
   }
 
-  pub async fn path_metadata(
+  pub fn ignore_settings(&self, state: &FsState) -> AppResult<WorkspaceIgnoreSettings> {
+    let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+    ensure_workspace_mode(&data)?;
+    read_ignore_settings(&data.root_path)
+  }
+
+  pub fn set_ignore_settings(
     &self,
-    path: String,
+    settings: WorkspaceIgnoreSettings,
     state: &FsState,
-  ) -> Result<FsPathMetadata, String> {
+  ) -> AppResult<WorkspaceIgnoreSettings> {
+    let root = {
+      let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+      ensure_workspace_mode(&data)?;
+      data.root_path.clone()
+    };
+    write_ignore_settings(&root, &settings)?;
+    self.clear_index_cache();
+    Ok(settings)
+  }
+
+  pub async fn path_metadata(&self, path: String, state: &FsState) -> AppResult<FsPathMetadata> {
     let data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
     let resolved = self.path_resolver.resolve(&data, &path)?;
     let metadata = tokio::fs::metadata(&resolved)
       .await
-      .map_err(|err| format!("Failed to read metadata: {err}"))?;
+      .map_err(|err| AppError::io("Failed to read metadata", &path, err))?;
+
+    let text_format = if metadata.is_dir() {
+      None
+    } else if let Some(format) = self.documents.text_format(&path)? {
+      Some(format)
+    } else if metadata.len() <= TEXT_FORMAT_MAX_BYTES {
+      tokio::fs::read(&resolved)
+        .await
+        .ok()
+        .and_then(|bytes| decode_text(&bytes).ok())
+        .map(|(_, format)| format)
+    } else {
+      None
+    };
 
     let modified_ms = metadata
       .modified()
@@ -172,38 +215,34 @@ impl WorkspaceService {
       size_bytes: metadata.len(),
       modified_ms,
       readonly: metadata.permissions().readonly(),
+      text_format,
     })
   }
 
-  pub fn write_file_buffered(
-    &self,
-    path: &str,
-    content: &str,
-    state: &FsState,
-  ) -> Result<(), String> {
+  pub fn write_file_buffered(&self, path: &str, content: &str, state: &FsState) -> AppResult<()> {
     self.update_buffer(path, content, state).map(|_| ())
   }
 
-  pub async fn create_file(&self, path: String, state: &FsState) -> Result<(), String> {
+  pub async fn create_file(&self, path: String, state: &FsState) -> AppResult<()> {
     let data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
     ensure_workspace_mode(&data)?;
     let resolved = self.path_resolver.resolve(&data, &path)?;
     if let Some(parent) = resolved.parent() {
       tokio::fs::create_dir_all(parent)
         .await
-        .map_err(|err| format!("Failed to create dir: {err}"))?;
+        .map_err(|err| AppError::io("Failed to create dir", &path, err))?;
     }
     let created = if !tokio::fs::try_exists(&resolved)
       .await
-      .map_err(|err| format!("Failed to check file: {err}"))?
+      .map_err(|err| AppError::io("Failed to check file", &path, err))?
     {
       tokio::fs::write(resolved, "")
         .await
-        .map_err(|err| format!("Failed to create file: {err}"))?;
+        .map_err(|err| AppError::io("Failed to create file", &path, err))?;
       true
     } else {
       false
@@ -218,51 +257,51 @@ impl WorkspaceService {
     Ok(())
   }
 
-  pub async fn create_dir(&self, path: String, state: &FsState) -> Result<(), String> {
+  pub async fn create_dir(&self, path: String, state: &FsState) -> AppResult<()> {
     let data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
     ensure_workspace_mode(&data)?;
     let resolved = self.path_resolver.resolve(&data, &path)?;
     tokio::fs::create_dir_all(resolved)
       .await
-      .map_err(|err| format!("Failed to create dir: {err}"))?;
+      .map_err(|err| AppError::io("Failed to create dir", &path, err))?;
     self.clear_index_cache();
     Ok(())
   }
 
-  pub async fn delete_path(&self, path: String, state: &FsState) -> Result<(), String> {
+  pub async fn delete_path(&self, path: String, state: &FsState) -> AppResult<()> {
     let data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
     ensure_workspace_mode(&data)?;
     let resolved = self.path_resolver.resolve(&data, &path)?;
     let metadata = tokio::fs::metadata(&resolved)
       .await
-      .map_err(|err| format!("Failed to read metadata: {err}"))?;
+      .map_err(|err| AppError::io("Failed to read metadata", &path, err))?;
     if metadata.is_dir() {
       tokio::fs::remove_dir_all(resolved)
         .await
-        .map_err(|err| format!("Failed to delete dir: {err}"))?;
+        .map_err(|err| AppError::io("Failed to delete dir", &path, err))?;
     } else {
       tokio::fs::remove_file(resolved)
         .await
-        .map_err(|err| format!("Failed to delete file: {err}"))?;
+        .map_err(|err| AppError::io("Failed to delete file", &path, err))?;
     }
     self.documents.remove_path(&path)?;
     self.clear_index_cache();
     Ok(())
   }
 
-  pub async fn rename_path(&self, from: String, to: String, state: &FsState) -> Result<(), String> {
+  pub async fn rename_path(&self, from: String, to: String, state: &FsState) -> AppResult<()> {
     let data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
     ensure_workspace_mode(&data)?;
     let from_path = self.path_resolver.resolve(&data, &from)?;
@@ -270,17 +309,17 @@ impl WorkspaceService {
     if let Some(parent) = to_path.parent() {
       tokio::fs::create_dir_all(parent)
         .await
-        .map_err(|err| format!("Failed to create dir: {err}"))?;
+        .map_err(|err| AppError::io("Failed to create dir", &to, err))?;
     }
     tokio::fs::rename(from_path, to_path)
       .await
-      .map_err(|err| format!("Failed to rename: {err}"))?;
+      .map_err(|err| AppError::io("Failed to rename", &from, err))?;
     self.documents.rename_path(&from, &to)?;
     self.clear_index_cache();
     Ok(())
   }
 
-  pub async fn move_path(&self, from: String, to: String, state: &FsState) -> Result<(), String> {
+  pub async fn move_path(&self, from: String, to: String, state: &FsState) -> AppResult<()> {
     self.rename_path(from, to, state).await
   }
 }
@@ -3,37 +3,45 @@ use std::path::{Path, PathBuf};
 
 use pathdiff::diff_paths;
 
+use crate::error::{AppError, AppResult};
 use crate::models::FsEntry;
+use crate::services::document_types::is_document_path;
+use crate::services::path_resolver::mounted_root_prefix;
 use crate::state::FsStateData;
 
-pub fn ensure_default_file(root: &Path) -> Result<(), String> {
+use super::ignore_rules::IgnoreRules;
+use super::templates::default_note_content;
+
+pub fn ensure_default_file(root: &Path) -> AppResult<()> {
   if !root.exists() {
-    fs::create_dir_all(root).map_err(|err| format!("Failed to create dir: {err}"))?;
+    fs::create_dir_all(root).map_err(|err| AppError::io("Failed to create dir", root, err))?;
   }
+  let rules = IgnoreRules::load(root);
   for entry in walkdir::WalkDir::new(root)
     .min_depth(1)
     .into_iter()
-    .filter_entry(|entry| !is_hidden(entry.path()))
+    .filter_entry(|entry| !rules.is_ignored(entry.path(), entry.file_type().is_dir()))
   {
     let entry = entry.map_err(|err| err.to_string())?;
-    if entry.file_type().is_file() && is_markdown(entry.path()) {
+    if entry.file_type().is_file() && is_document_path(entry.path()) {
       return Ok(());
     }
   }
   let default_path = root.join("Untitled.md");
   if !default_path.exists() {
-    fs::write(default_path, "").map_err(|err| format!("Failed to create default file: {err}"))?;
+    fs::write(&default_path, default_note_content(root, "Untitled"))
+      .map_err(|err| AppError::io("Failed to create default file", &default_path, err))?;
   }
   Ok(())
 }
 
-pub(super) async fn ensure_default_file_async(root: PathBuf) -> Result<(), String> {
+pub(super) async fn ensure_default_file_async(root: PathBuf) -> AppResult<()> {
   tokio::task::spawn_blocking(move || ensure_default_file(&root))
     .await
     .map_err(|err| format!("Default file task failed: {err}"))?
 }
 
-pub fn list_entries(data: &FsStateData) -> Result<Vec<FsEntry>, String> {
+pub fn list_entries(data: &FsStateData) -> AppResult<Vec<FsEntry>> {
   if data.root_kind == "single" {
     if let Some(single_file) = &data.single_file {
       let name = single_file
@@ -50,15 +58,34 @@ pub fn list_entries(data: &FsStateData) -> Result<Vec<FsEntry>, String> {
     return Ok(vec![]);
   }
 
-  let root = &data.root_path;
   let mut entries = Vec::new();
+  list_root_entries(&data.root_path, None, &mut entries)?;
+  for root in &data.mounted_roots {
+    let prefix = mounted_root_prefix(&root.name);
+    entries.push(FsEntry {
+      path: prefix.clone(),
+      name: root.name.clone(),
+      kind: "folder".to_string(),
+    });
+    list_root_entries(&root.path, Some(&prefix), &mut entries)?;
+  }
+  entries.sort_by(|a, b| a.path.cmp(&b.path));
+  Ok(entries)
+}
+
+fn list_root_entries(
+  root: &Path,
+  prefix: Option<&str>,
+  entries: &mut Vec<FsEntry>,
+) -> AppResult<()> {
   if !root.exists() {
-    return Ok(entries);
+    return Ok(());
   }
+  let rules = IgnoreRules::load(root);
   for entry in walkdir::WalkDir::new(root)
     .min_depth(1)
     .into_iter()
-    .filter_entry(|entry| !is_hidden(entry.path()))
+    .filter_entry(|entry| !rules.is_ignored(entry.path(), entry.file_type().is_dir()))
   {
     let entry = entry.map_err(|err| err.to_string())?;
     let path = entry.path();
@@ -66,52 +93,37 @@ pub fn list_entries(data: &FsStateData) -> Result<Vec<FsEntry>, String> {
       .ok_or_else(|| "Failed to compute relative path".to_string())?
       .to_string_lossy()
       .replace('\\', "/");
-    if entry.file_type().is_dir() {
-      entries.push(FsEntry {
-        path: rel.clone(),
-        name: entry.file_name().to_string_lossy().to_string(),
-        kind: "folder".to_string(),
-      });
-      continue;
-    }
-    if !is_markdown(path) {
+    let rel = match prefix {
+      Some(prefix) => format!("{prefix}/{rel}"),
+      None => rel,
+    };
+    let kind = if entry.file_type().is_dir() {
+      "folder"
+    } else if is_document_path(path) {
+      "file"
+    } else {
       continue;
-    }
+    };
     entries.push(FsEntry {
-      path: rel.clone(),
+      path: rel,
       name: entry.file_name().to_string_lossy().to_string(),
-      kind: "file".to_string(),
+      kind: kind.to_string(),
     });
   }
-  entries.sort_by(|a, b| a.path.cmp(&b.path));
-  Ok(entries)
+  Ok(())
 }
 
-pub(super) async fn list_entries_async(data: FsStateData) -> Result<Vec<FsEntry>, String> {
+pub(super) async fn list_entries_async(data: FsStateData) -> AppResult<Vec<FsEntry>> {
   tokio::task::spawn_blocking(move || list_entries(&data))
     .await
     .map_err(|err| format!("List entries task failed: {err}"))?
 }
 
-pub(super) fn ensure_workspace_mode(data: &FsStateData) -> Result<(), String> {
+pub(super) fn ensure_workspace_mode(data: &FsStateData) -> AppResult<()> {
   if data.root_kind == "single" {
-    return Err("Operation is not supported in single-file mode".to_string());
+    return Err(AppError::unsupported(
+      "Operation is not supported in single-file mode",
+    ));
   }
   Ok(())
 }
-
-fn is_hidden(path: &Path) -> bool {
-  path
-    .file_name()
-    .and_then(|name| name.to_str())
-    .map(|name| name.starts_with('.'))
-    .unwrap_or(false)
-}
-
-pub fn is_markdown(path: &Path) -> bool {
-  path
-    .extension()
-    .and_then(|ext| ext.to_str())
-    .map(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
-    .unwrap_or(false)
-}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use crate::error::{AppError, AppResult};
+use crate::models::WorkspaceIgnoreSettings;
+
+const IGNORE_FILE_NAMES: [&str; 2] = [".gitignore", ".markoignore"];
+const SETTINGS_DIR: &str = ".marko";
+const SETTINGS_FILE: &str = "workspace.json";
+
+/// Gitignore-style rules for a workspace root: `.gitignore` and
+/// `.markoignore` files in any directory, plus the workspace's include and
+/// exclude globs, which take precedence over the files.
+#[derive(Debug)]
+pub struct IgnoreRules {
+  root: PathBuf,
+  use_ignore_files: bool,
+  settings: Option<Gitignore>,
+  directories: Mutex<HashMap<PathBuf, Option<Gitignore>>>,
+}
+
+impl IgnoreRules {
+  pub fn load(root: &Path) -> Self {
+    let settings = read_ignore_settings(root).unwrap_or_else(|err| {
+      log::warn!("read workspace ignore settings failed: {err}");
+      WorkspaceIgnoreSettings::default()
+    });
+    Self::with_settings(root, &settings)
+  }
+
+  pub fn with_settings(root: &Path, settings: &WorkspaceIgnoreSettings) -> Self {
+    Self {
+      root: root.to_path_buf(),
+      use_ignore_files: settings.use_ignore_files,
+      settings: settings_matcher(root, settings),
+      directories: Mutex::new(HashMap::new()),
+    }
+  }
+
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  /// Whether `path` (absolute, below the root) should be left out of the
+  /// workspace. Hidden paths are always ignored.
+  pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+    let Ok(relative) = path.strip_prefix(&self.root) else {
+      return false;
+    };
+    if relative.components().any(|component| {
+      component
+        .as_os_str()
+        .to_str()
+        .is_some_and(|name| name.starts_with('.'))
+    }) {
+      return true;
+    }
+
+    if let Some(settings) = &self.settings {
+      match settings.matched_path_or_any_parents(path, is_dir) {
+        Match::Ignore(_) => return true,
+        Match::Whitelist(_) => return false,
+        Match::None => {}
+      }
+    }
+    if !self.use_ignore_files {
+      return false;
+    }
+
+    // Deeper ignore files take precedence, as with git.
+    let mut directories = match self.directories.lock() {
+      Ok(directories) => directories,
+      Err(_) => return false,
+    };
+    for dir in path.ancestors().skip(1) {
+      if !dir.starts_with(&self.root) {
+        break;
+      }
+      let matcher = directories
+        .entry(dir.to_path_buf())
+        .or_insert_with(|| directory_matcher(dir));
+      if let Some(matcher) = matcher {
+        match matcher.matched_path_or_any_parents(path, is_dir) {
+          Match::Ignore(_) => return true,
+          Match::Whitelist(_) => return false,
+          Match::None => {}
+        }
+      }
+    }
+    false
+  }
+
+  /// Like `is_ignored`, taking the kind from disk. A path that no longer
+  /// exists is ignored if it would be as either a file or a directory, so
+  /// deleting an ignored directory is not reported as a change.
+  pub fn is_ignored_path(&self, path: &Path) -> bool {
+    match std::fs::symlink_metadata(path) {
+      Ok(metadata) => self.is_ignored(path, metadata.is_dir()),
+      Err(_) => self.is_ignored(path, false) || self.is_ignored(path, true),
+    }
+  }
+
+  /// Whether a change to `path` can change these rules.
+  pub fn is_rules_file(&self, path: &Path) -> bool {
+    path == settings_path(&self.root)
+      || path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| IGNORE_FILE_NAMES.contains(&name))
+  }
+}
+
+pub fn read_ignore_settings(root: &Path) -> AppResult<WorkspaceIgnoreSettings> {
+  let path = settings_path(root);
+  match std::fs::read_to_string(&path) {
+    Ok(content) => serde_json::from_str(&content)
+      .map_err(|err| AppError::invalid_input(format!("Failed to parse workspace settings: {err}"))),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+      Ok(WorkspaceIgnoreSettings::default())
+    }
+    Err(err) => Err(AppError::io(
+      "Failed to read workspace settings",
+      &path,
+      err,
+    )),
+  }
+}
+
+pub fn write_ignore_settings(root: &Path, settings: &WorkspaceIgnoreSettings) -> AppResult<()> {
+  let mut errors = Vec::new();
+  for glob in settings.include.iter().chain(&settings.exclude) {
+    let mut builder = GitignoreBuilder::new(root);
+    if let Err(err) = builder.add_line(None, glob) {
+      errors.push(format!("{glob}: {err}"));
+    }
+  }
+  if !errors.is_empty() {
+    return Err(AppError::invalid_input(format!(
+      "Invalid ignore globs: {}",
+      errors.join(", ")
+    )));
+  }
+
+  let path = settings_path(root);
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir).map_err(|err| AppError::io("Failed to create dir", dir, err))?;
+  }
+  let content = serde_json::to_string_pretty(settings)
+    .map_err(|err| format!("Failed to serialize workspace settings: {err}"))?;
+  std::fs::write(&path, content)
+    .map_err(|err| AppError::io("Failed to write workspace settings", &path, err))
+}
+
+fn settings_path(root: &Path) -> PathBuf {
+  root.join(SETTINGS_DIR).join(SETTINGS_FILE)
+}
+
+fn settings_matcher(root: &Path, settings: &WorkspaceIgnoreSettings) -> Option<Gitignore> {
+  if settings.include.is_empty() && settings.exclude.is_empty() {
+    return None;
+  }
+  let mut builder = GitignoreBuilder::new(root);
+  for glob in &settings.exclude {
+    if let Err(err) = builder.add_line(None, glob) {
+      log::warn!("invalid exclude glob {glob}: {err}");
+    }
+  }
+  // Later lines win, so includes override excludes.
+  for glob in &settings.include {
+    if let Err(err) = builder.add_line(None, &format!("!{glob}")) {
+      log::warn!("invalid include glob {glob}: {err}");
+    }
+  }
+  builder.build().ok()
+}
+
+fn directory_matcher(dir: &Path) -> Option<Gitignore> {
+  let files = IGNORE_FILE_NAMES
+    .iter()
+    .map(|name| dir.join(name))
+    .filter(|path| path.is_file())
+    .collect::<Vec<_>>();
+  if files.is_empty() {
+    return None;
+  }
+  let mut builder = GitignoreBuilder::new(dir);
+  for file in files {
+    if let Some(err) = builder.add(&file) {
+      log::warn!("parse ignore file {} failed: {err}", file.display());
+    }
+  }
+  builder.build().ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use crate::models::WorkspaceIgnoreSettings;
+  use crate::services::workspace::fs::list_entries;
+  use crate::state::FsStateData;
+  use crate::test_support::temp_dir;
+
+  use super::{write_ignore_settings, IgnoreRules};
+
+  #[test]
+  fn honours_ignore_files_and_workspace_globs() {
+    let root = temp_dir("ignore-test");
+    for dir in ["node_modules/pkg", "docs/build", "docs/vendor", "notes"] {
+      fs::create_dir_all(root.join(dir)).expect("test dir should be created");
+    }
+    for file in [
+      "node_modules/pkg/README.md",
+      "docs/build/out.md",
+      "docs/vendor/keep.md",
+      "docs/vendor/skip.md",
+      "docs/guide.md",
+      "notes/draft.md",
+    ] {
+      fs::write(root.join(file), "# note").expect("test file should be written");
+    }
+    fs::write(root.join(".gitignore"), "node_modules/\n").expect("gitignore should be written");
+    fs::write(
+      root.join("docs/.markoignore"),
+      "build/\nvendor/*.md\n!vendor/keep.md\n",
+    )
+    .expect("markoignore should be written");
+    write_ignore_settings(
+      &root,
+      &WorkspaceIgnoreSettings {
+        use_ignore_files: true,
+        include: vec!["docs/build/".to_string()],
+        exclude: vec!["notes/".to_string()],
+      },
+    )
+    .expect("settings should be written");
+
+    let data = FsStateData {
+      root_kind: "external".to_string(),
+      root_path: root.clone(),
+      internal_root: root.clone(),
+      single_file: None,
+      mounted_roots: Vec::new(),
+    };
+    let files = list_entries(&data)
+      .expect("entries should list")
+      .into_iter()
+      .filter(|entry| entry.kind == "file")
+      .map(|entry| entry.path)
+      .collect::<Vec<_>>();
+    assert_eq!(
+      files,
+      vec!["docs/build/out.md", "docs/guide.md", "docs/vendor/keep.md"]
+    );
+
+    let rules = IgnoreRules::with_settings(&root, &WorkspaceIgnoreSettings::default());
+    assert!(rules.is_ignored(&root.join("node_modules/pkg/README.md"), false));
+    assert!(rules.is_ignored(&root.join(".marko/workspace.json"), false));
+    assert!(!rules.is_ignored(&root.join("notes/draft.md"), false));
+
+    fs::remove_dir_all(root.join("docs/build")).expect("test dir should be removed");
+    let rules = IgnoreRules::with_settings(&root, &WorkspaceIgnoreSettings::default());
+    assert!(rules.is_ignored_path(&root.join("docs/build")));
+    assert!(!rules.is_ignored_path(&root.join("docs/guide.md")));
+    assert!(rules.is_rules_file(&root.join("docs/.markoignore")));
+    assert!(rules.is_rules_file(&root.join(".marko/workspace.json")));
+    assert!(!rules.is_rules_file(&root.join("docs/guide.md")));
+  }
+}
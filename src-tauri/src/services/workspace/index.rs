@@ -1,15 +1,21 @@
 use std::path::PathBuf;
 
-use crate::models::{FsGraph, FsMarkdownDiagnostic, FsSearchResult, FsWorkspaceIndex};
+use crate::error::{AppError, AppResult};
+use crate::models::{
+  FsFileDiagnostics, FsGraph, FsMarkdownDiagnostic, FsSearchResult, FsWorkspaceIndex,
+};
+use crate::services::background_tasks::BackgroundTaskHandle;
 use crate::services::search::SearchDocument;
 use crate::state::FsState;
 
 use super::fs::list_entries_async;
-use super::model::{workspace_search_key, WorkspaceDocuments, WorkspaceIndexCache};
+use super::model::{
+  mounted_search_keys, workspace_search_key, WorkspaceDocuments, WorkspaceIndexCache,
+};
 use super::WorkspaceService;
 
 impl WorkspaceService {
-  pub async fn workspace_index(&self, state: &FsState) -> Result<FsWorkspaceIndex, String> {
+  pub async fn workspace_index(&self, state: &FsState) -> AppResult<FsWorkspaceIndex> {
     let workspace = self.workspace_documents(state).await?;
     self.workspace_index_from_documents(workspace).await
   }
@@ -19,7 +25,7 @@ impl WorkspaceService {
     path: String,
     content: String,
     state: &FsState,
-  ) -> Result<Vec<FsMarkdownDiagnostic>, String> {
+  ) -> AppResult<Vec<FsMarkdownDiagnostic>> {
     if self.documents.cached_content(&path)?.as_deref() == Some(content.as_str()) {
       let index = self.workspace_index(state).await?;
       return Ok(self.markdown_index.diagnostics_for_file(&index, &path));
@@ -45,26 +51,65 @@ impl WorkspaceService {
       markdown_index.diagnostics_for_file(&index, &path)
     })
     .await
-    .map_err(|err| format!("Markdown analysis task failed: {err}"))
+    .map_err(|err| AppError::from(format!("Markdown analysis task failed: {err}")))
   }
 
-  pub async fn workspace_graph(&self, state: &FsState) -> Result<FsGraph, String> {
+  /// Diagnostics of every document that has some, ordered by path.
+  pub async fn workspace_diagnostics(&self, state: &FsState) -> AppResult<Vec<FsFileDiagnostics>> {
+    let index = self.workspace_index(state).await?;
+    let markdown_index = self.markdown_index.clone();
+    tokio::task::spawn_blocking(move || {
+      let mut files = index
+        .files
+        .iter()
+        .map(|file| FsFileDiagnostics {
+          path: file.path.clone(),
+          diagnostics: markdown_index.diagnostics_for_file(&index, &file.path),
+        })
+        .filter(|file| !file.diagnostics.is_empty())
+        .collect::<Vec<_>>();
+      files.sort_by(|a, b| a.path.cmp(&b.path));
+      files
+    })
+    .await
+    .map_err(|err| AppError::from(format!("Workspace check task failed: {err}")))
+  }
+
+  pub async fn workspace_graph(&self, state: &FsState) -> AppResult<FsGraph> {
     let index = self.workspace_index(state).await?;
     let markdown_graph = self.markdown_graph.clone();
     tokio::task::spawn_blocking(move || markdown_graph.build_workspace_graph(&index))
       .await
-      .map_err(|err| format!("Workspace graph task failed: {err}"))
+      .map_err(|err| AppError::from(format!("Workspace graph task failed: {err}")))
   }
 
   pub async fn rebuild_search_index(
     &self,
     index_parent: PathBuf,
     state: &FsState,
-  ) -> Result<(), String> {
-    let (workspace_key, signature, documents) = self.search_documents(state).await?;
+    task: BackgroundTaskHandle,
+  ) -> AppResult<()> {
+    task.step("Reading documents");
+    let roots = self.search_documents(state).await?;
     let search = self.search.clone();
     tokio::task::spawn_blocking(move || {
-      search.rebuild_index_with_signature(&index_parent, &workspace_key, &documents, signature)
+      task.set_total(
+        roots
+          .iter()
+          .map(|(_, _, documents)| documents.len() as u64)
+          .sum(),
+      );
+      task.step("Indexing");
+      for (workspace_key, signature, documents) in roots {
+        search.rebuild_index_with_signature(
+          &index_parent,
+          &workspace_key,
+          &documents,
+          signature,
+          Some(&task),
+        )?;
+      }
+      Ok(())
     })
     .await
     .map_err(|err| format!("Search index task failed: {err}"))?
@@ -76,24 +121,36 @@ impl WorkspaceService {
     query: String,
     limit: usize,
     state: &FsState,
-  ) -> Result<Vec<FsSearchResult>, String> {
-    let (workspace_key, signature, documents) = self.search_documents(state).await?;
+  ) -> AppResult<Vec<FsSearchResult>> {
+    let roots = self.search_documents(state).await?;
     let search = self.search.clone();
     tokio::task::spawn_blocking(move || {
-      search.rebuild_index_with_signature(&index_parent, &workspace_key, &documents, signature)?;
-      search.search(&index_parent, &workspace_key, &query, limit)
+      let mut results = Vec::new();
+      for (workspace_key, signature, documents) in roots {
+        search.rebuild_index_with_signature(
+          &index_parent,
+          &workspace_key,
+          &documents,
+          signature,
+          None,
+        )?;
+        results.extend(search.search(&index_parent, &workspace_key, &query, limit)?);
+      }
+      results.sort_by(|a, b| b.score.total_cmp(&a.score));
+      results.truncate(limit.clamp(1, 100));
+      Ok(results)
     })
     .await
     .map_err(|err| format!("Search task failed: {err}"))?
   }
 
-  pub async fn outline_graph(&self, path: &str, state: &FsState) -> Result<FsGraph, String> {
+  pub async fn outline_graph(&self, path: &str, state: &FsState) -> AppResult<FsGraph> {
     let content = self.read_file(path, state).await?;
     let path = path.to_string();
     let markdown_graph = self.markdown_graph.clone();
     tokio::task::spawn_blocking(move || markdown_graph.build_outline_graph(&path, &content))
       .await
-      .map_err(|err| format!("Outline graph task failed: {err}"))
+      .map_err(|err| AppError::from(format!("Outline graph task failed: {err}")))
   }
 
   pub fn clear_index_cache(&self) {
@@ -110,7 +167,7 @@ impl WorkspaceService {
   async fn workspace_index_from_documents(
     &self,
     workspace: WorkspaceDocuments,
-  ) -> Result<FsWorkspaceIndex, String> {
+  ) -> AppResult<FsWorkspaceIndex> {
     let signature = workspace.signature();
     if let Some(index) = self.cached_workspace_index(&workspace.workspace_key, signature)? {
       return Ok(index);
@@ -136,11 +193,11 @@ impl WorkspaceService {
     &self,
     workspace_key: &str,
     signature: u64,
-  ) -> Result<Option<FsWorkspaceIndex>, String> {
+  ) -> AppResult<Option<FsWorkspaceIndex>> {
     let cache = self
       .index_cache
       .lock()
-      .map_err(|_| "Failed to lock workspace index cache")?;
+      .map_err(|_| AppError::lock("workspace index cache"))?;
     Ok(cache.as_ref().and_then(|cache| {
       if cache.workspace_key == workspace_key && cache.signature == signature {
         Some(cache.index.clone())
@@ -155,11 +212,11 @@ impl WorkspaceService {
     workspace_key: String,
     signature: u64,
     index: FsWorkspaceIndex,
-  ) -> Result<(), String> {
+  ) -> AppResult<()> {
     let mut cache = self
       .index_cache
       .lock()
-      .map_err(|_| "Failed to lock workspace index cache")?;
+      .map_err(|_| AppError::lock("workspace index cache"))?;
     *cache = Some(WorkspaceIndexCache {
       workspace_key,
       signature,
@@ -168,13 +225,14 @@ impl WorkspaceService {
     Ok(())
   }
 
-  async fn workspace_documents(&self, state: &FsState) -> Result<WorkspaceDocuments, String> {
+  async fn workspace_documents(&self, state: &FsState) -> AppResult<WorkspaceDocuments> {
     let data = state
       .0
       .read()
-      .map_err(|_| "Failed to lock fs state")?
+      .map_err(|_| AppError::lock("fs state"))?
       .clone();
     let workspace_key = workspace_search_key(&data);
+    let mounted_keys = mounted_search_keys(&data);
     let entries = list_entries_async(data.clone()).await?;
     let files = entries
       .into_iter()
@@ -187,6 +245,7 @@ impl WorkspaceService {
 
     Ok(WorkspaceDocuments {
       workspace_key,
+      mounted_keys,
       files,
       documents,
     })
@@ -195,8 +254,8 @@ impl WorkspaceService {
   async fn search_documents(
     &self,
     state: &FsState,
-  ) -> Result<(String, u64, Vec<SearchDocument>), String> {
+  ) -> AppResult<Vec<(String, u64, Vec<SearchDocument>)>> {
     let workspace = self.workspace_documents(state).await?;
-    Ok(workspace.into_search_documents())
+    Ok(workspace.into_root_search_documents())
   }
 }
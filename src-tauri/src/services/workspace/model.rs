@@ -1,8 +1,10 @@
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
 use crate::models::{FsEntry, FsWorkspaceIndex};
 use crate::services::document_store::DocumentSnapshot;
+use crate::services::path_resolver::MOUNTED_ROOT_PREFIX;
 use crate::services::search::SearchDocument;
 use crate::state::FsStateData;
 
@@ -16,6 +18,8 @@ pub(super) struct WorkspaceIndexCache {
 #[derive(Debug, Clone)]
 pub(super) struct WorkspaceDocuments {
   pub(super) workspace_key: String,
+  /// Search key of each mounted root, by root name.
+  pub(super) mounted_keys: HashMap<String, String>,
   pub(super) files: Vec<FsEntry>,
   pub(super) documents: Vec<DocumentSnapshot>,
 }
@@ -25,18 +29,37 @@ impl WorkspaceDocuments {
     workspace_documents_signature(&self.workspace_key, &self.files, &self.documents)
   }
 
-  pub(super) fn into_search_documents(self) -> (String, u64, Vec<SearchDocument>) {
-    let signature = self.signature();
-    let documents = self
-      .documents
+  /// Splits the documents into one search index per root, each keyed by the
+  /// root's own search key.
+  pub(super) fn into_root_search_documents(self) -> Vec<(String, u64, Vec<SearchDocument>)> {
+    let mut roots = Vec::<RootDocuments>::new();
+    root_slot(&mut roots, &self.workspace_key);
+    for file in self.files {
+      let key = root_search_key(&self.workspace_key, &self.mounted_keys, &file.path);
+      let index = root_slot(&mut roots, key);
+      roots[index].1.push(file);
+    }
+    for document in self.documents {
+      let key = root_search_key(&self.workspace_key, &self.mounted_keys, &document.path);
+      let index = root_slot(&mut roots, key);
+      roots[index].2.push(document);
+    }
+
+    roots
       .into_iter()
-      .map(|document| SearchDocument {
-        title: file_label(&document.path),
-        path: document.path,
-        body: document.content,
+      .map(|(key, files, documents)| {
+        let signature = workspace_documents_signature(&key, &files, &documents);
+        let documents = documents
+          .into_iter()
+          .map(|document| SearchDocument {
+            title: file_label(&document.path),
+            path: document.path,
+            body: document.content,
+          })
+          .collect();
+        (key, signature, documents)
       })
-      .collect();
-    (self.workspace_key, signature, documents)
+      .collect()
   }
 }
 
@@ -44,6 +67,46 @@ pub(super) fn workspace_search_key(data: &FsStateData) -> String {
   format!("{}:{}", data.root_kind, data.root_path.to_string_lossy())
 }
 
+/// Mounted roots are keyed like the same directory opened as an external
+/// root, so each keeps its index directory across both uses.
+pub(super) fn mounted_search_keys(data: &FsStateData) -> HashMap<String, String> {
+  data
+    .mounted_roots
+    .iter()
+    .map(|root| {
+      (
+        root.name.clone(),
+        format!("external:{}", root.path.to_string_lossy()),
+      )
+    })
+    .collect()
+}
+
+type RootDocuments = (String, Vec<FsEntry>, Vec<DocumentSnapshot>);
+
+fn root_slot(roots: &mut Vec<RootDocuments>, key: &str) -> usize {
+  match roots.iter().position(|(root_key, _, _)| root_key == key) {
+    Some(index) => index,
+    None => {
+      roots.push((key.to_string(), Vec::new(), Vec::new()));
+      roots.len() - 1
+    }
+  }
+}
+
+fn root_search_key<'a>(
+  workspace_key: &'a str,
+  mounted_keys: &'a HashMap<String, String>,
+  path: &str,
+) -> &'a str {
+  path
+    .split('/')
+    .next()
+    .and_then(|first| first.strip_prefix(MOUNTED_ROOT_PREFIX))
+    .and_then(|name| mounted_keys.get(name))
+    .map_or(workspace_key, String::as_str)
+}
+
 fn file_label(path: &str) -> String {
   let file_name = path.rsplit('/').next().unwrap_or(path);
   file_name
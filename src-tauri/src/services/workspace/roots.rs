@@ -0,0 +1,301 @@
+use std::path::PathBuf;
+
+use crate::error::{AppError, AppResult};
+use crate::models::FsWorkspaceRoot;
+use crate::services::path_resolver::{mounted_root_name, mounted_root_prefix};
+use crate::state::{FsState, FsStateData, WorkspaceRoot};
+
+use super::fs::ensure_workspace_mode;
+use super::WorkspaceService;
+
+impl WorkspaceService {
+  pub fn mounted_roots(&self, state: &FsState) -> AppResult<Vec<FsWorkspaceRoot>> {
+    let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+    Ok(root_infos(&data))
+  }
+
+  /// Mounts another directory next to the current root. Its entries are
+  /// listed, indexed and resolved under `@name/`.
+  pub async fn mount_root(
+    &self,
+    name: String,
+    path: String,
+    state: &FsState,
+  ) -> AppResult<Vec<FsWorkspaceRoot>> {
+    let name = name.trim().to_string();
+    if name.is_empty()
+      || !name
+        .chars()
+        .all(|char| char.is_alphanumeric() || matches!(char, '-' | '_' | '.'))
+    {
+      return Err(AppError::invalid_input(
+        "Root name may only contain letters, digits, '-', '_' and '.'",
+      ));
+    }
+    let root = PathBuf::from(&path);
+    let metadata = tokio::fs::metadata(&root)
+      .await
+      .map_err(|err| AppError::io("Failed to open folder", &root, err))?;
+    if !metadata.is_dir() {
+      return Err(AppError::invalid_path(
+        path,
+        "Selected path is not a directory",
+      ));
+    }
+    let root = tokio::fs::canonicalize(&root)
+      .await
+      .map_err(|err| AppError::io("Failed to open folder", &root, err))?;
+    let existing = {
+      let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+      std::iter::once(data.root_path.clone())
+        .chain(
+          data
+            .mounted_roots
+            .iter()
+            .map(|mounted| mounted.path.clone()),
+        )
+        .collect::<Vec<_>>()
+    };
+    // A root inside another would list, index and watch its files twice.
+    for existing in existing {
+      let existing = tokio::fs::canonicalize(&existing).await.unwrap_or(existing);
+      if root.starts_with(&existing) || existing.starts_with(&root) {
+        return Err(AppError::invalid_path(
+          path,
+          "Selected directory overlaps a folder already in the workspace",
+        ));
+      }
+    }
+
+    let roots = {
+      let mut data = state.0.write().map_err(|_| AppError::lock("fs state"))?;
+      ensure_workspace_mode(&data)?;
+      if data
+        .mounted_roots
+        .iter()
+        .any(|mounted| mounted.name == name)
+      {
+        return Err(AppError::AlreadyExists {
+          path: mounted_root_prefix(&name),
+        });
+      }
+      if data
+        .mounted_roots
+        .iter()
+        .any(|mounted| mounted.path == root)
+      {
+        return Err(AppError::invalid_path(
+          path,
+          "Selected directory is already part of the workspace",
+        ));
+      }
+      data.mounted_roots.push(WorkspaceRoot { name, path: root });
+      root_infos(&data)
+    };
+    self.clear_index_cache();
+    Ok(roots)
+  }
+
+  /// Unmounts a root. Fails while it still has unsaved buffers.
+  pub fn unmount_root(&self, name: &str, state: &FsState) -> AppResult<Vec<FsWorkspaceRoot>> {
+    let mut data = state.0.write().map_err(|_| AppError::lock("fs state"))?;
+    if !data.mounted_roots.iter().any(|root| root.name == name) {
+      return Err(AppError::NotFound {
+        path: mounted_root_prefix(name),
+      });
+    }
+    let has_dirty = self
+      .documents
+      .dirty_snapshots()?
+      .iter()
+      .any(|document| mounted_root_name(&data, &document.path) == Some(name));
+    if has_dirty {
+      return Err(AppError::Conflict {
+        path: mounted_root_prefix(name),
+        reason: format!("Save open changes in {name} before removing it"),
+      });
+    }
+
+    data.mounted_roots.retain(|root| root.name != name);
+    self.documents.remove_path(&mounted_root_prefix(name))?;
+    self.clear_index_cache();
+    Ok(root_infos(&data))
+  }
+}
+
+fn root_infos(data: &FsStateData) -> Vec<FsWorkspaceRoot> {
+  data
+    .mounted_roots
+    .iter()
+    .map(|root| FsWorkspaceRoot {
+      name: root.name.clone(),
+      path: root.path.to_string_lossy().to_string(),
+      prefix: mounted_root_prefix(&root.name),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::RwLock;
+
+  use crate::test_support::temp_dir;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn lists_indexes_and_searches_mounted_roots() {
+    let base = temp_dir("roots");
+    let vault = base.join("vault");
+    let docs = base.join("repo/docs");
+    std::fs::create_dir_all(&vault).expect("vault should be created");
+    std::fs::create_dir_all(docs.join("guides")).expect("docs should be created");
+    std::fs::write(
+      vault.join("index.md"),
+      "# Index\nSee [setup](@docs/guides/setup.md#install).\n",
+    )
+    .expect("vault note should be written");
+    std::fs::write(
+      docs.join("guides/setup.md"),
+      "# Setup\n## Install\nRun the zephyr installer. Back to [[index]].\n",
+    )
+    .expect("docs note should be written");
+    let state = FsState(RwLock::new(FsStateData {
+      root_kind: "external".to_string(),
+      root_path: vault.clone(),
+      internal_root: vault.clone(),
+      single_file: None,
+      mounted_roots: Vec::new(),
+    }));
+    let service = WorkspaceService::default();
+
+    let roots = service
+      .mount_root(
+        "docs".to_string(),
+        docs.to_string_lossy().to_string(),
+        &state,
+      )
+      .await
+      .expect("docs should mount");
+    assert_eq!(roots[0].prefix, "@docs");
+    assert!(service
+      .mount_root(
+        "docs".to_string(),
+        base.to_string_lossy().to_string(),
+        &state
+      )
+      .await
+      .is_err());
+
+    let files = service
+      .list_entries(&state)
+      .await
+      .expect("entries should list")
+      .into_iter()
+      .map(|entry| entry.path)
+      .collect::<Vec<_>>();
+    assert_eq!(
+      files,
+      vec!["@docs", "@docs/guides", "@docs/guides/setup.md", "index.md"]
+    );
+    assert_eq!(
+      service
+        .read_file("@docs/guides/setup.md", &state)
+        .await
+        .expect("mounted file should read")
+        .lines()
+        .next(),
+      Some("# Setup")
+    );
+
+    let index = service
+      .workspace_index(&state)
+      .await
+      .expect("index should build");
+    let vault_note = index
+      .files
+      .iter()
+      .find(|file| file.path == "index.md")
+      .expect("vault note should be indexed");
+    assert_eq!(
+      vault_note.links[0].target_path.as_deref(),
+      Some("@docs/guides/setup.md")
+    );
+    assert_eq!(
+      vault_note.links[0].target_heading_slug.as_deref(),
+      Some("install")
+    );
+    let docs_note = index
+      .files
+      .iter()
+      .find(|file| file.path == "@docs/guides/setup.md")
+      .expect("docs note should be indexed");
+    assert_eq!(docs_note.links[0].target_path.as_deref(), Some("index.md"));
+
+    let results = service
+      .search_workspace(base.join("app-data"), "zephyr".to_string(), 10, &state)
+      .await
+      .expect("search should run");
+    assert_eq!(results[0].path, "@docs/guides/setup.md");
+
+    service
+      .unmount_root("docs", &state)
+      .expect("docs should unmount");
+    assert!(service
+      .read_file("@docs/guides/setup.md", &state)
+      .await
+      .is_err());
+    std::fs::remove_dir_all(base).expect("test dir should be removed");
+  }
+
+  #[tokio::test]
+  async fn rejects_overlapping_roots_and_forgets_them_with_the_root() {
+    let base = temp_dir("roots-overlap");
+    let vault = base.join("vault");
+    let other = base.join("other");
+    for dir in [vault.join("inner"), other.join("nested")] {
+      std::fs::create_dir_all(dir).expect("test dir should be created");
+    }
+    let state = FsState(RwLock::new(FsStateData {
+      root_kind: "external".to_string(),
+      root_path: vault.clone(),
+      internal_root: vault.clone(),
+      single_file: None,
+      mounted_roots: Vec::new(),
+    }));
+    let service = WorkspaceService::default();
+    let mount = |name: &str, path: PathBuf| {
+      service.mount_root(name.to_string(), path.to_string_lossy().to_string(), &state)
+    };
+
+    for (name, path) in [
+      ("inner", vault.join("inner")),
+      ("base", base.clone()),
+      ("alias", vault.join("inner/../inner")),
+    ] {
+      let err = mount(name, path)
+        .await
+        .expect_err("overlapping root should be rejected");
+      assert_eq!(err.code(), "invalid_path");
+    }
+    mount("other", other.clone())
+      .await
+      .expect("other should mount");
+    for path in [other.join("nested"), base.join("other/../other")] {
+      assert!(mount("again", path).await.is_err());
+    }
+
+    service
+      .set_root(
+        Some(base.join("other").to_string_lossy().to_string()),
+        &state,
+      )
+      .await
+      .expect("root should change");
+    assert!(service
+      .mounted_roots(&state)
+      .expect("roots should list")
+      .is_empty());
+    std::fs::remove_dir_all(base).expect("test dir should be removed");
+  }
+}
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{FsCreatedNote, FsTemplate, WorkspaceTemplateSettings};
+use crate::services::document_types::is_document_path;
+use crate::services::path_resolver::resolve_path;
+use crate::state::{FsState, FsStateData};
+
+use super::fs::ensure_workspace_mode;
+use super::WorkspaceService;
+
+const SETTINGS_FILE: &str = ".marko/templates.json";
+const DEFAULT_TEMPLATE: &str = "default";
+const DEFAULT_DAILY_NOTE: &str = "# {{title}}\n\n{{cursor}}";
+
+impl WorkspaceService {
+  pub fn template_settings(&self, state: &FsState) -> AppResult<WorkspaceTemplateSettings> {
+    let data = state.0.read().map_err(|_| AppError::lock("fs state"))?;
+    ensure_workspace_mode(&data)?;
+    read_template_settings(&data.root_path)
+  }
+
+  pub fn set_template_settings(
+    &self,
+    settings: WorkspaceTemplateSettings,
+    state: &FsState,
+  ) -> AppResult<WorkspaceTemplateSettings> {
+    let data = state
+      .0
+      .read()
+      .map_err(|_| AppError::lock("fs state"))?
+      .clone();
+    ensure_workspace_mode(&data)?;
+    resolve_path(&data, &settings.templates_dir)?;
+    if !settings.daily_notes_dir.trim().is_empty() {
+      resolve_path(&data, &settings.daily_notes_dir)?;
+    }
+    format_date(&settings.daily_note_pattern, Local::now().naive_local())?;
+    write_template_settings(&data.root_path, &settings)?;
+    Ok(settings)
+  }
+
+  pub async fn list_templates(&self, state: &FsState) -> AppResult<Vec<FsTemplate>> {
+    let data = state
+      .0
+      .read()
+      .map_err(|_| AppError::lock("fs state"))?
+      .clone();
+    ensure_workspace_mode(&data)?;
+    tokio::task::spawn_blocking(move || {
+      let settings = read_template_settings(&data.root_path)?;
+      list_templates(&data, &settings)
+    })
+    .await
+    .map_err(|err| format!("List templates task failed: {err}"))?
+  }
+
+  /// Creates `path` from a template, or with just its variables expanded when
+  /// no template is given. Fails if the file exists.
+  pub async fn create_from_template(
+    &self,
+    path: String,
+    template: Option<String>,
+    variables: HashMap<String, String>,
+    state: &FsState,
+  ) -> AppResult<FsCreatedNote> {
+    let data = state
+      .0
+      .read()
+      .map_err(|_| AppError::lock("fs state"))?
+      .clone();
+    ensure_workspace_mode(&data)?;
+    let settings = read_template_settings(&data.root_path)?;
+    let template = match template {
+      Some(name) => read_template(&data, &settings, &name).await?,
+      None => String::new(),
+    };
+    let now = Local::now().naive_local();
+    let rendered = render_template(
+      &template,
+      &TemplateContext {
+        title: &note_title(&path),
+        now,
+        variables: &variables,
+      },
+    )?;
+    self.create_note(&data, path, rendered).await
+  }
+
+  /// Opens the daily note for `date` (`YYYY-MM-DD`, today by default),
+  /// creating it from the configured template if it does not exist yet.
+  pub async fn open_daily_note(
+    &self,
+    date: Option<String>,
+    state: &FsState,
+  ) -> AppResult<FsCreatedNote> {
+    let data = state
+      .0
+      .read()
+      .map_err(|_| AppError::lock("fs state"))?
+      .clone();
+    ensure_workspace_mode(&data)?;
+    let settings = read_template_settings(&data.root_path)?;
+    let now = Local::now().naive_local();
+    let now = match date {
+      Some(date) => NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|err| AppError::invalid_input(format!("Invalid date {date}: {err}")))?
+        .and_time(now.time()),
+      None => now,
+    };
+    let name = format_date(&settings.daily_note_pattern, now)?;
+    let directory = settings.daily_notes_dir.trim().trim_matches('/');
+    let path = if directory.is_empty() {
+      format!("{name}.md")
+    } else {
+      format!("{directory}/{name}.md")
+    };
+    if resolve_path(&data, &path)?.exists() {
+      return Ok(FsCreatedNote {
+        path,
+        created: false,
+        cursor_line: None,
+        cursor_column: None,
+      });
+    }
+
+    let template = match &settings.daily_note_template {
+      Some(name) => read_template(&data, &settings, name).await?,
+      None => DEFAULT_DAILY_NOTE.to_string(),
+    };
+    let rendered = render_template(
+      &template,
+      &TemplateContext {
+        title: &note_title(&path),
+        now,
+        variables: &HashMap::new(),
+      },
+    )?;
+    self.create_note(&data, path, rendered).await
+  }
+
+  async fn create_note(
+    &self,
+    data: &FsStateData,
+    path: String,
+    rendered: RenderedTemplate,
+  ) -> AppResult<FsCreatedNote> {
+    let resolved = resolve_path(data, &path)?;
+    if let Some(parent) = resolved.parent() {
+      tokio::fs::create_dir_all(parent)
+        .await
+        .map_err(|err| AppError::io("Failed to create dir", parent, err))?;
+    }
+    tokio::fs::OpenOptions::new()
+      .write(true)
+      .create_new(true)
+      .open(&resolved)
+      .await
+      .map_err(|err| match err.kind() {
+        std::io::ErrorKind::AlreadyExists => AppError::AlreadyExists { path: path.clone() },
+        _ => AppError::io("Failed to create file", &resolved, err),
+      })?;
+    tokio::fs::write(&resolved, &rendered.content)
+      .await
+      .map_err(|err| AppError::io("Failed to write file", &resolved, err))?;
+
+    self.documents.insert_clean(&path, &rendered.content)?;
+    self.clear_index_cache();
+    Ok(FsCreatedNote {
+      path,
+      created: true,
+      cursor_line: rendered.cursor.map(|(line, _)| line),
+      cursor_column: rendered.cursor.map(|(_, column)| column),
+    })
+  }
+}
+
+/// Content for the default note of a new workspace: the `default` template
+/// when the workspace has one, otherwise empty.
+pub(super) fn default_note_content(root: &Path, title: &str) -> String {
+  let template = read_template_settings(root)
+    .ok()
+    .and_then(|settings| {
+      let dir = root.join(&settings.templates_dir);
+      std::fs::read_to_string(dir.join(format!("{DEFAULT_TEMPLATE}.md"))).ok()
+    })
+    .unwrap_or_default();
+  render_template(
+    &template,
+    &TemplateContext {
+      title,
+      now: Local::now().naive_local(),
+      variables: &HashMap::new(),
+    },
+  )
+  .map(|rendered| rendered.content)
+  .unwrap_or(template)
+}
+
+struct TemplateContext<'a> {
+  title: &'a str,
+  now: NaiveDateTime,
+  variables: &'a HashMap<String, String>,
+}
+
+#[derive(Debug)]
+struct RenderedTemplate {
+  content: String,
+  /// 1-based line and column of the `{{cursor}}` marker.
+  cursor: Option<(usize, usize)>,
+}
+
+/// Expands `{{title}}`, `{{date}}`, `{{time}}` (both accept a strftime
+/// format, as in `{{date:%A}}`), `{{cursor}}` and caller variables. Values
+/// in the template's front matter are defaults that variables of the same
+/// name replace. Unknown placeholders are kept.
+fn render_template(template: &str, context: &TemplateContext<'_>) -> AppResult<RenderedTemplate> {
+  let template = apply_front_matter_defaults(template, context.variables);
+  let mut content = String::with_capacity(template.len());
+  let mut cursor_offset = None;
+  let mut rest = template.as_str();
+  while let Some(start) = rest.find("{{") {
+    let Some(end) = rest[start..].find("}}") else {
+      break;
+    };
+    content.push_str(&rest[..start]);
+    let placeholder = &rest[start..start + end + 2];
+    let inner = placeholder[2..placeholder.len() - 2].trim();
+    let (name, format) = match inner.split_once(':') {
+      Some((name, format)) => (name.trim(), Some(format.trim())),
+      None => (inner, None),
+    };
+    match name {
+      "cursor" => {
+        cursor_offset.get_or_insert(content.len());
+      }
+      "title" => content.push_str(context.title),
+      "date" => content.push_str(&format_date(format.unwrap_or("%Y-%m-%d"), context.now)?),
+      "time" => content.push_str(&format_date(format.unwrap_or("%H:%M"), context.now)?),
+      _ => match context.variables.get(name) {
+        Some(value) => content.push_str(value),
+        None => content.push_str(placeholder),
+      },
+    }
+    rest = &rest[start + end + 2..];
+  }
+  content.push_str(rest);
+
+  let cursor = cursor_offset.map(|offset| {
+    let before = &content[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (line, before[line_start..].chars().count() + 1)
+  });
+  Ok(RenderedTemplate { content, cursor })
+}
+
+fn apply_front_matter_defaults(template: &str, variables: &HashMap<String, String>) -> String {
+  let Some(body) = template.strip_prefix("---\n") else {
+    return template.to_string();
+  };
+  let Some(end) = body.find("\n---") else {
+    return template.to_string();
+  };
+  let front_matter = body[..end]
+    .lines()
+    .map(|line| {
+      let value = line
+        .split_once(':')
+        .filter(|(key, _)| !key.starts_with(char::is_whitespace))
+        .and_then(|(key, _)| variables.get(key.trim()).map(|value| (key, value)));
+      match value {
+        Some((key, value)) => format!("{key}: {value}"),
+        None => line.to_string(),
+      }
+    })
+    .collect::<Vec<_>>()
+    .join("\n");
+  format!("---\n{front_matter}{}", &body[end..])
+}
+
+fn format_date(pattern: &str, now: NaiveDateTime) -> AppResult<String> {
+  let items = StrftimeItems::new(pattern).collect::<Vec<_>>();
+  if items.iter().any(|item| matches!(item, Item::Error)) {
+    return Err(AppError::invalid_input(format!(
+      "Invalid date format: {pattern}"
+    )));
+  }
+  Ok(now.format_with_items(items.into_iter()).to_string())
+}
+
+fn note_title(path: &str) -> String {
+  let name = path.rsplit('/').next().unwrap_or(path);
+  name
+    .rsplit_once('.')
+    .filter(|_| is_document_path(Path::new(name)))
+    .map_or(name, |(stem, _)| stem)
+    .to_string()
+}
+
+fn list_templates(
+  data: &FsStateData,
+  settings: &WorkspaceTemplateSettings,
+) -> AppResult<Vec<FsTemplate>> {
+  let dir = resolve_path(data, &settings.templates_dir)?;
+  if !dir.is_dir() {
+    return Ok(Vec::new());
+  }
+  let mut templates = Vec::new();
+  for entry in walkdir::WalkDir::new(&dir).min_depth(1) {
+    let entry = entry.map_err(|err| AppError::io("Failed to list templates", &dir, err.into()))?;
+    if !entry.file_type().is_file() || !is_document_path(entry.path()) {
+      continue;
+    }
+    let relative = entry
+      .path()
+      .strip_prefix(&dir)
+      .map_err(|_| "Failed to compute template path".to_string())?
+      .to_string_lossy()
+      .replace('\\', "/");
+    templates.push(FsTemplate {
+      name: note_title(&relative),
+      path: format!(
+        "{}/{relative}",
+        settings.templates_dir.trim_end_matches('/')
+      ),
+    });
+  }
+  templates.sort_by(|a, b| a.path.cmp(&b.path));
+  Ok(templates)
+}
+
+/// Reads a template by name (its path below the templates folder, with or
+/// without extension).
+async fn read_template(
+  data: &FsStateData,
+  settings: &WorkspaceTemplateSettings,
+  name: &str,
+) -> AppResult<String> {
+  let dir = resolve_path(data, &settings.templates_dir)?;
+  let name = name.trim().trim_start_matches('/');
+  let candidates = std::iter::once(name.to_string())
+    .chain(
+      crate::services::document_types::document_extensions()
+        .map(|extension| format!("{name}.{extension}")),
+    )
+    .collect::<Vec<_>>();
+  for candidate in candidates {
+    let path = resolve_path(data, &format!("{}/{candidate}", settings.templates_dir))?;
+    if path.starts_with(&dir) && path.is_file() {
+      return tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|err| AppError::io("Failed to read template", &path, err));
+    }
+  }
+  Err(AppError::NotFound {
+    path: format!("{}/{name}", settings.templates_dir.trim_end_matches('/')),
+  })
+}
+
+fn read_template_settings(root: &Path) -> AppResult<WorkspaceTemplateSettings> {
+  let path = settings_path(root);
+  match std::fs::read_to_string(&path) {
+    Ok(content) => serde_json::from_str(&content)
+      .map_err(|err| AppError::invalid_input(format!("Failed to parse template settings: {err}"))),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+      Ok(WorkspaceTemplateSettings::default())
+    }
+    Err(err) => Err(AppError::io("Failed to read template settings", &path, err)),
+  }
+}
+
+fn write_template_settings(root: &Path, settings: &WorkspaceTemplateSettings) -> AppResult<()> {
+  let path = settings_path(root);
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir).map_err(|err| AppError::io("Failed to create dir", dir, err))?;
+  }
+  let content = serde_json::to_string_pretty(settings)
+    .map_err(|err| format!("Failed to serialize template settings: {err}"))?;
+  std::fs::write(&path, content)
+    .map_err(|err| AppError::io("Failed to write template settings", &path, err))
+}
+
+fn settings_path(root: &Path) -> PathBuf {
+  root.join(SETTINGS_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use std::sync::RwLock;
+
+  use chrono::NaiveDate;
+
+  use crate::test_support::temp_dir;
+
+  use super::*;
+
+  #[test]
+  fn renders_variables_cursor_and_front_matter_defaults() {
+    let now = NaiveDate::from_ymd_opt(2026, 3, 9)
+      .expect("date should be valid")
+      .and_hms_opt(14, 5, 0)
+      .expect("time should be valid");
+    let variables = HashMap::from([
+      ("status".to_string(), "active".to_string()),
+      ("project".to_string(), "Marko".to_string()),
+    ]);
+    let rendered = render_template(
+      "---\nstatus: draft\ntags: [meeting]\n---\n# {{title}} ({{ date }})\n{{date:%A}} {{time}} for {{project}}\n- {{cursor}}\n{{unknown}}\n",
+      &TemplateContext {
+        title: "Standup",
+        now,
+        variables: &variables,
+      },
+    )
+    .expect("template should render");
+
+    assert_eq!(
+      rendered.content,
+      "---\nstatus: active\ntags: [meeting]\n---\n# Standup (2026-03-09)\nMonday 14:05 for Marko\n- \n{{unknown}}\n"
+    );
+    assert_eq!(rendered.cursor, Some((7, 3)));
+    assert!(format_date("%Q", now).is_err());
+  }
+
+  #[tokio::test]
+  async fn creates_notes_from_templates_and_daily_notes() {
+    let root = temp_dir("templates");
+    std::fs::create_dir_all(root.join(".marko/templates/work")).expect("templates should exist");
+    std::fs::write(
+      root.join(".marko/templates/work/meeting.md"),
+      "# {{title}}\n{{cursor}}",
+    )
+    .expect("template should be written");
+    std::fs::write(
+      root.join(".marko/templates/daily.md"),
+      "# {{date:%d %B %Y}}\n",
+    )
+    .expect("template should be written");
+    let state = FsState(RwLock::new(FsStateData {
+      root_kind: "external".to_string(),
+      root_path: root.clone(),
+      internal_root: root.clone(),
+      single_file: None,
+      mounted_roots: Vec::new(),
+    }));
+    let service = WorkspaceService::default();
+
+    let templates = service
+      .list_templates(&state)
+      .await
+      .expect("templates should list");
+    let names = templates
+      .iter()
+      .map(|template| template.name.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(names, vec!["daily", "meeting"]);
+
+    let note = service
+      .create_from_template(
+        "notes/Planning.md".to_string(),
+        Some("work/meeting".to_string()),
+        HashMap::new(),
+        &state,
+      )
+      .await
+      .expect("note should be created");
+    assert_eq!((note.cursor_line, note.cursor_column), (Some(2), Some(1)));
+    assert_eq!(
+      std::fs::read_to_string(root.join("notes/Planning.md")).expect("note should exist"),
+      "# Planning\n"
+    );
+    let err = service
+      .create_from_template(
+        "notes/Planning.md".to_string(),
+        None,
+        HashMap::new(),
+        &state,
+      )
+      .await
+      .expect_err("existing note should not be replaced");
+    assert_eq!(
+      err,
+      AppError::AlreadyExists {
+        path: "notes/Planning.md".to_string()
+      }
+    );
+
+    service
+      .set_template_settings(
+        WorkspaceTemplateSettings {
+          daily_notes_dir: "journal".to_string(),
+          daily_note_pattern: "%Y/%m-%d".to_string(),
+          daily_note_template: Some("daily".to_string()),
+          ..WorkspaceTemplateSettings::default()
+        },
+        &state,
+      )
+      .expect("settings should save");
+    let daily = service
+      .open_daily_note(Some("2026-03-09".to_string()), &state)
+      .await
+      .expect("daily note should be created");
+    assert_eq!(daily.path, "journal/2026/03-09.md");
+    assert!(daily.created);
+    assert_eq!(
+      std::fs::read_to_string(root.join("journal/2026/03-09.md")).expect("daily note should exist"),
+      "# 09 March 2026\n"
+    );
+    let reopened = service
+      .open_daily_note(Some("2026-03-09".to_string()), &state)
+      .await
+      .expect("daily note should reopen");
+    assert!(!reopened.created);
+    let err = service
+      .open_daily_note(Some("2026-13-40".to_string()), &state)
+      .await
+      .expect_err("invalid dates should be rejected");
+    assert_eq!(err.code(), "invalid_input");
+    std::fs::remove_dir_all(root).expect("test dir should be removed");
+  }
+}
@@ -0,0 +1,406 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{FsTrashEntry, FsTrashRestoreResult};
+use crate::services::path_resolver::{is_same_or_child, resolve_path};
+use crate::services::search::stable_hash;
+use crate::state::{FsState, FsStateData};
+
+use super::fs::ensure_workspace_mode;
+use super::WorkspaceService;
+
+const TRASH_MANIFEST_FILE: &str = "manifest.json";
+const TRASH_ITEMS_DIR: &str = "items";
+
+impl WorkspaceService {
+  /// Moves `path` into the app-level trash of the current workspace. Unsaved
+  /// edits below it are saved first, so the trashed copy keeps them.
+  pub async fn trash_path(
+    &self,
+    path: String,
+    trash_parent: PathBuf,
+    state: &FsState,
+  ) -> AppResult<FsTrashEntry> {
+    let data = self.trash_state_data(state)?;
+    self.flush_buffers(state).await?;
+    let original_path = path.trim_matches('/').replace('\\', "/");
+    if let Some(unsaved) = self
+      .documents
+      .dirty_snapshots()?
+      .into_iter()
+      .find(|snapshot| is_same_or_child(&snapshot.path, &original_path))
+    {
+      return Err(AppError::Conflict {
+        path: unsaved.path,
+        reason: "Unsaved changes could not be saved before moving to the trash".to_string(),
+      });
+    }
+    let resolved = self.path_resolver.resolve(&data, &path)?;
+    let trash_dir = trash_dir(&trash_parent, &data);
+    let trash_lock = self.trash_lock.clone();
+    let entry = tokio::task::spawn_blocking(move || {
+      let _guard = trash_lock.lock().map_err(|_| AppError::lock("trash"))?;
+      move_to_trash(&trash_dir, &resolved, &path)
+    })
+    .await
+    .map_err(|err| format!("Failed to join trash task: {err}"))??;
+    self.documents.remove_path(&entry.original_path)?;
+    self.clear_index_cache();
+    Ok(entry)
+  }
+
+  pub async fn list_trash(
+    &self,
+    trash_parent: PathBuf,
+    state: &FsState,
+  ) -> AppResult<Vec<FsTrashEntry>> {
+    let data = self.trash_state_data(state)?;
+    let trash_dir = trash_dir(&trash_parent, &data);
+    let trash_lock = self.trash_lock.clone();
+    let mut entries = tokio::task::spawn_blocking(move || {
+      let _guard = trash_lock.lock().map_err(|_| AppError::lock("trash"))?;
+      read_trash_manifest(&trash_dir)
+    })
+    .await
+    .map_err(|err| format!("Failed to join trash task: {err}"))??;
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_ms));
+    Ok(entries)
+  }
+
+  /// Moves a trashed item back to its original path. When that path is taken
+  /// the item is restored next to it under a "(restored)" name.
+  pub async fn restore_from_trash(
+    &self,
+    id: String,
+    trash_parent: PathBuf,
+    state: &FsState,
+  ) -> AppResult<FsTrashRestoreResult> {
+    let data = self.trash_state_data(state)?;
+    let trash_dir = trash_dir(&trash_parent, &data);
+    let trash_lock = self.trash_lock.clone();
+    let result = tokio::task::spawn_blocking(move || {
+      let _guard = trash_lock.lock().map_err(|_| AppError::lock("trash"))?;
+      restore_from_trash(&trash_dir, &data, &id)
+    })
+    .await
+    .map_err(|err| format!("Failed to join trash task: {err}"))??;
+    self.documents.remove_path(&result.restored_path)?;
+    self.clear_index_cache();
+    Ok(result)
+  }
+
+  /// Permanently removes the given trash items, or all of them when `ids` is
+  /// `None`. Returns the number of removed items.
+  pub async fn empty_trash(
+    &self,
+    ids: Option<Vec<String>>,
+    trash_parent: PathBuf,
+    state: &FsState,
+  ) -> AppResult<usize> {
+    let data = self.trash_state_data(state)?;
+    let trash_dir = trash_dir(&trash_parent, &data);
+    let trash_lock = self.trash_lock.clone();
+    tokio::task::spawn_blocking(move || {
+      let _guard = trash_lock.lock().map_err(|_| AppError::lock("trash"))?;
+      empty_trash(&trash_dir, ids.as_deref())
+    })
+    .await
+    .map_err(|err| format!("Failed to join trash task: {err}"))?
+  }
+
+  fn trash_state_data(&self, state: &FsState) -> AppResult<FsStateData> {
+    let data = state
+      .0
+      .read()
+      .map_err(|_| AppError::lock("fs state"))?
+      .clone();
+    ensure_workspace_mode(&data)?;
+    Ok(data)
+  }
+}
+
+fn trash_dir(trash_parent: &Path, data: &FsStateData) -> PathBuf {
+  trash_parent.join("trash").join(format!(
+    "{:016x}",
+    stable_hash(&data.root_path.to_string_lossy())
+  ))
+}
+
+fn read_trash_manifest(trash_dir: &Path) -> AppResult<Vec<FsTrashEntry>> {
+  let path = trash_dir.join(TRASH_MANIFEST_FILE);
+  match std::fs::read_to_string(&path) {
+    Ok(content) => Ok(
+      serde_json::from_str(&content)
+        .map_err(|err| AppError::invalid_input(format!("Failed to parse trash manifest: {err}")))?,
+    ),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+    Err(err) => Err(AppError::io("Failed to read trash manifest", &path, err)),
+  }
+}
+
+fn write_trash_manifest(trash_dir: &Path, entries: &[FsTrashEntry]) -> AppResult<()> {
+  std::fs::create_dir_all(trash_dir)
+    .map_err(|err| AppError::io("Failed to create trash dir", trash_dir, err))?;
+  let content = serde_json::to_string_pretty(entries)
+    .map_err(|err| format!("Failed to serialize trash manifest: {err}"))?;
+  let path = trash_dir.join(TRASH_MANIFEST_FILE);
+  std::fs::write(&path, content)
+    .map_err(|err| AppError::io("Failed to write trash manifest", &path, err))
+}
+
+fn move_to_trash(trash_dir: &Path, resolved: &Path, path: &str) -> AppResult<FsTrashEntry> {
+  let metadata = std::fs::metadata(resolved)
+    .map_err(|err| AppError::io("Failed to read metadata", path, err))?;
+  let deleted_ms = now_ms();
+  let original_path = path.trim_matches('/').replace('\\', "/");
+  let items_dir = trash_dir.join(TRASH_ITEMS_DIR);
+  let mut entries = read_trash_manifest(trash_dir)?;
+  let entry = FsTrashEntry {
+    id: unused_trash_id(
+      &format!("{deleted_ms:x}-{:016x}", stable_hash(&original_path)),
+      &entries,
+      &items_dir,
+    ),
+    name: resolved
+      .file_name()
+      .map(|name| name.to_string_lossy().to_string())
+      .unwrap_or_else(|| original_path.clone()),
+    original_path,
+    kind: if metadata.is_dir() { "dir" } else { "file" }.to_string(),
+    deleted_ms,
+  };
+
+  std::fs::create_dir_all(&items_dir)
+    .map_err(|err| AppError::io("Failed to create trash dir", &items_dir, err))?;
+  move_path(resolved, &items_dir.join(&entry.id))?;
+
+  entries.push(entry.clone());
+  write_trash_manifest(trash_dir, &entries)?;
+  Ok(entry)
+}
+
+/// `base`, or `base` with a counter when the same path was already trashed
+/// within the same millisecond.
+fn unused_trash_id(base: &str, entries: &[FsTrashEntry], items_dir: &Path) -> String {
+  let mut id = base.to_string();
+  let mut attempt = 1;
+  while entries.iter().any(|entry| entry.id == id) || items_dir.join(&id).exists() {
+    id = format!("{base}-{attempt}");
+    attempt += 1;
+  }
+  id
+}
+
+fn restore_from_trash(
+  trash_dir: &Path,
+  data: &FsStateData,
+  id: &str,
+) -> AppResult<FsTrashRestoreResult> {
+  let mut entries = read_trash_manifest(trash_dir)?;
+  let index = entries
+    .iter()
+    .position(|entry| entry.id == id)
+    .ok_or_else(|| AppError::NotFound {
+      path: id.to_string(),
+    })?;
+  let restored_path = available_restore_path(data, &entries[index].original_path)?;
+  let target = resolve_path(data, &restored_path)?;
+  if let Some(parent) = target.parent() {
+    std::fs::create_dir_all(parent)
+      .map_err(|err| AppError::io("Failed to create dir", parent, err))?;
+  }
+  move_path(&trash_dir.join(TRASH_ITEMS_DIR).join(id), &target)?;
+
+  let entry = entries.remove(index);
+  write_trash_manifest(trash_dir, &entries)?;
+  Ok(FsTrashRestoreResult {
+    id: entry.id,
+    original_path: entry.original_path,
+    restored_path,
+  })
+}
+
+fn empty_trash(trash_dir: &Path, ids: Option<&[String]>) -> AppResult<usize> {
+  let entries = read_trash_manifest(trash_dir)?;
+  let (removed, kept): (Vec<_>, Vec<_>) = entries
+    .into_iter()
+    .partition(|entry| ids.map_or(true, |ids| ids.contains(&entry.id)));
+  for entry in &removed {
+    let item = trash_dir.join(TRASH_ITEMS_DIR).join(&entry.id);
+    let result = if item.is_dir() {
+      std::fs::remove_dir_all(&item)
+    } else {
+      std::fs::remove_file(&item)
+    };
+    match result {
+      Ok(()) => {}
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+      Err(err) => return Err(AppError::io("Failed to empty trash", &item, err)),
+    }
+  }
+  write_trash_manifest(trash_dir, &kept)?;
+  Ok(removed.len())
+}
+
+fn available_restore_path(data: &FsStateData, original_path: &str) -> AppResult<String> {
+  if !resolve_path(data, original_path)?.exists() {
+    return Ok(original_path.to_string());
+  }
+
+  let path = Path::new(original_path);
+  let stem = path
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let extension = path
+    .extension()
+    .map(|extension| format!(".{}", extension.to_string_lossy()))
+    .unwrap_or_default();
+  let parent = path
+    .parent()
+    .map(|parent| parent.to_string_lossy().replace('\\', "/"))
+    .filter(|parent| !parent.is_empty())
+    .map(|parent| format!("{parent}/"))
+    .unwrap_or_default();
+
+  let mut attempt = 1;
+  loop {
+    let suffix = if attempt == 1 {
+      " (restored)".to_string()
+    } else {
+      format!(" (restored {attempt})")
+    };
+    let candidate = format!("{parent}{stem}{suffix}{extension}");
+    if !resolve_path(data, &candidate)?.exists() {
+      return Ok(candidate);
+    }
+    attempt += 1;
+  }
+}
+
+/// Renames `from` to `to`, falling back to copy and delete when the trash
+/// lives on a different filesystem than the workspace.
+fn move_path(from: &Path, to: &Path) -> AppResult<()> {
+  if std::fs::rename(from, to).is_ok() {
+    return Ok(());
+  }
+
+  if from.is_dir() {
+    for entry in walkdir::WalkDir::new(from) {
+      let entry = entry.map_err(|err| AppError::io("Failed to read dir", from, err.into()))?;
+      let relative = entry
+        .path()
+        .strip_prefix(from)
+        .map_err(|err| format!("Failed to move path: {err}"))?;
+      let target = to.join(relative);
+      if entry.file_type().is_dir() {
+        std::fs::create_dir_all(&target)
+          .map_err(|err| AppError::io("Failed to create dir", &target, err))?;
+      } else {
+        std::fs::copy(entry.path(), &target)
+          .map_err(|err| AppError::io("Failed to copy file", entry.path(), err))?;
+      }
+    }
+    std::fs::remove_dir_all(from).map_err(|err| AppError::io("Failed to delete dir", from, err))
+  } else {
+    std::fs::copy(from, to).map_err(|err| AppError::io("Failed to copy file", from, err))?;
+    std::fs::remove_file(from).map_err(|err| AppError::io("Failed to delete file", from, err))
+  }
+}
+
+fn now_ms() -> i64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|duration| duration.as_millis() as i64)
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::RwLock;
+
+  use crate::test_support::temp_dir;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn trashes_and_restores_with_collision_suffix() {
+    let base = temp_dir("trash");
+    let root = base.join("workspace");
+    let trash_parent = base.join("app-data");
+    std::fs::create_dir_all(root.join("notes")).expect("workspace should be created");
+    std::fs::write(root.join("notes/a.md"), "# A\n").expect("note should be written");
+    let state = FsState(RwLock::new(FsStateData {
+      root_kind: "external".to_string(),
+      root_path: root.clone(),
+      internal_root: root.clone(),
+      single_file: None,
+      mounted_roots: Vec::new(),
+    }));
+    let service = WorkspaceService::default();
+    service
+      .documents
+      .update_document(&state, "notes/a.md", "# A edited\n")
+      .expect("buffer should update");
+
+    let entry = service
+      .trash_path("notes".to_string(), trash_parent.clone(), &state)
+      .await
+      .expect("folder should move to trash");
+    assert_eq!(entry.kind, "dir");
+    assert!(!root.join("notes").exists());
+
+    std::fs::create_dir_all(root.join("notes")).expect("replacement folder should be created");
+    let restored = service
+      .restore_from_trash(entry.id, trash_parent.clone(), &state)
+      .await
+      .expect("folder should restore");
+    assert_eq!(restored.restored_path, "notes (restored)");
+    assert_eq!(
+      std::fs::read_to_string(root.join("notes (restored)/a.md")).expect("note should restore"),
+      "# A edited\n"
+    );
+
+    service
+      .trash_path(
+        "notes (restored)/a.md".to_string(),
+        trash_parent.clone(),
+        &state,
+      )
+      .await
+      .expect("file should move to trash");
+    assert_eq!(
+      service
+        .list_trash(trash_parent.clone(), &state)
+        .await
+        .expect("trash should list")
+        .len(),
+      1
+    );
+    assert_eq!(
+      service
+        .empty_trash(None, trash_parent.clone(), &state)
+        .await
+        .expect("trash should empty"),
+      1
+    );
+
+    let _ = std::fs::remove_dir_all(base);
+  }
+
+  #[test]
+  fn trash_ids_stay_unique_within_a_millisecond() {
+    let items_dir = temp_dir("trash-ids");
+    std::fs::create_dir_all(items_dir.join("1-ab-1")).expect("item should be created");
+    let entries = vec![FsTrashEntry {
+      id: "1-ab".to_string(),
+      name: "a.md".to_string(),
+      original_path: "a.md".to_string(),
+      kind: "file".to_string(),
+      deleted_ms: 1,
+    }];
+    assert_eq!(unused_trash_id("1-cd", &entries, &items_dir), "1-cd");
+    assert_eq!(unused_trash_id("1-ab", &entries, &items_dir), "1-ab-2");
+    std::fs::remove_dir_all(items_dir).expect("test dir should be removed");
+  }
+}
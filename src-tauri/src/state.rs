@@ -1,4 +1,3 @@
-use crate::models::BackgroundTaskStatus;
 use notify::RecommendedWatcher;
 use notify_debouncer_mini::Debouncer;
 use std::collections::{HashMap, HashSet};
@@ -11,12 +10,19 @@ pub struct FsStateData {
   pub root_path: PathBuf,
   pub internal_root: PathBuf,
   pub single_file: Option<PathBuf>,
+  /// Additional roots mounted next to `root_path`, addressed as `@name/...`.
+  pub mounted_roots: Vec<WorkspaceRoot>,
 }
 
-pub struct FsState(pub RwLock<FsStateData>);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceRoot {
+  pub name: String,
+  pub path: PathBuf,
+}
 
-pub struct FsWatcherState(pub Mutex<Option<Debouncer<RecommendedWatcher>>>);
+pub struct FsState(pub RwLock<FsStateData>);
 
-pub struct BackgroundTasksState(pub Mutex<HashMap<String, BackgroundTaskStatus>>);
+/// One watcher per workspace root, keyed by the watched path.
+pub struct FsWatcherState(pub Mutex<HashMap<PathBuf, Debouncer<RecommendedWatcher>>>);
 
 pub struct AllowedSystemPathsState(pub Mutex<HashSet<PathBuf>>);
@@ -0,0 +1,16 @@
+//! Helpers shared by the unit tests.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Fresh path below the system temp directory, named after `name`. The
+/// directory is not created; tests remove it when they are done.
+pub fn temp_dir(name: &str) -> PathBuf {
+  std::env::temp_dir().join(format!(
+    "marko-{name}-{}",
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .expect("time should move forward")
+      .as_nanos()
+  ))
+}
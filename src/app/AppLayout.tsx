@@ -26,6 +26,8 @@ import type {
   WorkspaceTab,
 } from '@/store/useAppStore'
 import { useCallback, useEffect, useLayoutEffect, useMemo, useRef, useState } from 'react'
+import { errorMessage } from '@/services/appError'
+import { listenBackendEvent } from '@/services/eventBridge'
 import { exportApi } from '@/services/exportApi'
 import { fsApi, type FsWorkspaceIndex } from '@/services/fsApi'
 import { requestExportContent } from '@/utils/exportContent'
@@ -392,7 +394,7 @@ export default function AppLayout() {
             rootPath,
             activePath,
           })
-        })().catch((err) => window.alert(String(err)))
+        })().catch((err) => window.alert(errorMessage(err)))
         return
       }
       if (id === 'view.wysiwyg') currentState.setViewMode('wysiwyg')
@@ -439,12 +441,10 @@ export default function AppLayout() {
 
     let unlisten: (() => void) | undefined
     if (isTauriRuntime()) {
-      void import('@tauri-apps/api/event').then(({ listen }) => {
-        void listen<string>('menu-action', (event) => {
-          handleMenuAction(event.payload)
-        }).then((fn) => {
-          unlisten = fn
-        })
+      void listenBackendEvent<{ id: string }>('menu-action', (event) => {
+        handleMenuAction(event.payload.id)
+      }).then((fn) => {
+        unlisten = fn
       })
     }
 
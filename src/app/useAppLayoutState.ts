@@ -6,6 +6,7 @@ import { buildFileTree } from '@/logic/fileTree'
 import { useProjectLoader } from '@/app/useProjectLoader'
 import { useEditorBuffer } from '@/app/useEditorBuffer'
 import { useGraphData } from '@/app/useGraphData'
+import { listenBackendEvent } from '@/services/eventBridge'
 import { fsSnapshotSchema } from '@/services/fsApi'
 import { useWorkspaceIndex } from '@/app/useWorkspaceIndex'
 import { useLayoutStoreSlice, useWorkspaceStoreSlice } from '@/store/selectors'
@@ -143,8 +144,7 @@ export function useAppLayoutState() {
   useEffect(() => {
     let unlisten: (() => void) | undefined
     const setup = async () => {
-      const { listen } = await import('@tauri-apps/api/event')
-      unlisten = await listen<unknown>('fs-changed', (event) => {
+      unlisten = await listenBackendEvent('fs-changed', (event) => {
         const parsed = fsSnapshotSchema.safeParse(event.payload)
         if (!parsed.success) return
         void loadWorkspace({
@@ -34,6 +34,10 @@ vi.mock('@tauri-apps/api/event', () => ({
   }),
 }))
 
+const bufferStatusEvent = (payload: unknown) => ({
+  payload: { version: 1, topic: 'buffers', seq: 1, replayed: false, payload },
+})
+
 const Harness = () => {
   const buffer = useEditorBuffer({
     activePath: 'notes/current.md',
@@ -133,13 +137,13 @@ describe('useEditorBuffer', () => {
     expect(screen.getByText('saving:true')).toBeInTheDocument()
 
     await act(async () => {
-      eventHandlers.get('fs-buffer-status')?.({
-        payload: {
+      eventHandlers.get('fs-buffer-status')?.(
+        bufferStatusEvent({
           path: 'notes/current.md',
           revision: 1,
           dirty: false,
-        },
-      })
+        }),
+      )
     })
 
     expect(screen.getByText('saved:false')).toBeInTheDocument()
@@ -171,13 +175,13 @@ describe('useEditorBuffer', () => {
     )
 
     await act(async () => {
-      eventHandlers.get('fs-buffer-status')?.({
-        payload: {
+      eventHandlers.get('fs-buffer-status')?.(
+        bufferStatusEvent({
           path: 'notes/current.md',
           revision: 1,
           dirty: true,
-        },
-      })
+        }),
+      )
     })
 
     expect(screen.getByText('unsaved:true')).toBeInTheDocument()
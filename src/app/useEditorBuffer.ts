@@ -1,6 +1,8 @@
 import { useCallback, useEffect, useMemo, useRef, useState } from 'react'
 import { useLatest, useUnmount } from 'ahooks'
 import { produce } from 'immer'
+import { errorMessage } from '@/services/appError'
+import { listenBackendEvent } from '@/services/eventBridge'
 import { fsApi, fsBufferStatusSchema } from '@/services/fsApi'
 import { isTauriRuntime } from '@/utils/tauri'
 
@@ -180,7 +182,7 @@ export function useEditorBuffer({ activePath, workspaceKey }: UseEditorBufferArg
         console.error('open file failed', error)
         setPathSaveState(requestWorkspace, activePath, {
           status: 'error',
-          message: String(error),
+          message: errorMessage(error),
         })
       })
       .finally(() => {
@@ -194,26 +196,13 @@ export function useEditorBuffer({ activePath, workspaceKey }: UseEditorBufferArg
     let cancelled = false
     let unlisten: (() => void) | undefined
 
-    void import('@tauri-apps/api/event').then(({ listen }) =>
-      listen<unknown>('fs-buffer-status', (event) => {
-        const parsed = fsBufferStatusSchema.safeParse(event.payload)
-        if (!parsed.success) return
-
-        const { path, revision, dirty } = parsed.data
-        const currentWorkspace = workspaceKeyRef.current
-        if (dirty) {
-          const revisionVersion = revisionVersionRef.current[path]?.[revision]
-          const revisionContent = revisionContentRef.current[path]?.[revision]
-          if (revisionVersion == null || revisionContent == null) return
-
-          const hasNewChange = changeVersionRef.current[path] !== revisionVersion
-          const currentValue = fileContentsRef.current[path] ?? ''
-          if (hasNewChange || currentValue !== revisionContent) return
-
-          markPathDirty(currentWorkspace, path, { status: 'saving' })
-          return
-        }
+    void listenBackendEvent('fs-buffer-status', (event) => {
+      const parsed = fsBufferStatusSchema.safeParse(event.payload)
+      if (!parsed.success) return
 
+      const { path, revision, dirty } = parsed.data
+      const currentWorkspace = workspaceKeyRef.current
+      if (dirty) {
         const revisionVersion = revisionVersionRef.current[path]?.[revision]
         const revisionContent = revisionContentRef.current[path]?.[revision]
         if (revisionVersion == null || revisionContent == null) return
@@ -222,15 +211,26 @@ export function useEditorBuffer({ activePath, workspaceKey }: UseEditorBufferArg
         const currentValue = fileContentsRef.current[path] ?? ''
         if (hasNewChange || currentValue !== revisionContent) return
 
-        markPathClean(currentWorkspace, path, revisionContent)
-      }).then((nextUnlisten) => {
-        if (cancelled) {
-          nextUnlisten()
-          return
-        }
-        unlisten = nextUnlisten
-      }),
-    )
+        markPathDirty(currentWorkspace, path, { status: 'saving' })
+        return
+      }
+
+      const revisionVersion = revisionVersionRef.current[path]?.[revision]
+      const revisionContent = revisionContentRef.current[path]?.[revision]
+      if (revisionVersion == null || revisionContent == null) return
+
+      const hasNewChange = changeVersionRef.current[path] !== revisionVersion
+      const currentValue = fileContentsRef.current[path] ?? ''
+      if (hasNewChange || currentValue !== revisionContent) return
+
+      markPathClean(currentWorkspace, path, revisionContent)
+    }).then((nextUnlisten) => {
+      if (cancelled) {
+        nextUnlisten()
+        return
+      }
+      unlisten = nextUnlisten
+    })
 
     return () => {
       cancelled = true
@@ -337,7 +337,7 @@ export function useEditorBuffer({ activePath, workspaceKey }: UseEditorBufferArg
             console.error('update buffer failed', error)
             markPathDirty(currentWorkspace, path, {
               status: 'error',
-              message: String(error),
+              message: errorMessage(error),
             })
           })
         delete syncTimers.current[path]
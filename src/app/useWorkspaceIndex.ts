@@ -1,5 +1,6 @@
 import { useEffect, useMemo } from 'react'
 import { useQuery, useQueryClient } from '@tanstack/react-query'
+import { listenBackendEvent } from '@/services/eventBridge'
 import { fsApi, fsBufferStatusSchema, type FsWorkspaceIndex } from '@/services/fsApi'
 import type { FileEntry } from '@/store/useAppStore'
 import { isTauriRuntime } from '@/utils/tauri'
@@ -23,21 +24,19 @@ export function useWorkspaceIndex(entries: FileEntry[], enabled: boolean) {
 
     let cancelled = false
     let unlisten: (() => void) | undefined
-    void import('@tauri-apps/api/event').then(({ listen }) =>
-      listen<unknown>('fs-buffer-status', (event) => {
-        const parsed = fsBufferStatusSchema.safeParse(event.payload)
-        if (!parsed.success) return
-        void queryClient.invalidateQueries({ queryKey: ['workspace-index'] }).catch((error) => {
-          console.error('refresh workspace index failed', error)
-        })
-      }).then((nextUnlisten) => {
-        if (cancelled) {
-          nextUnlisten()
-          return
-        }
-        unlisten = nextUnlisten
-      }),
-    )
+    void listenBackendEvent('fs-buffer-status', (event) => {
+      const parsed = fsBufferStatusSchema.safeParse(event.payload)
+      if (!parsed.success) return
+      void queryClient.invalidateQueries({ queryKey: ['workspace-index'] }).catch((error) => {
+        console.error('refresh workspace index failed', error)
+      })
+    }).then((nextUnlisten) => {
+      if (cancelled) {
+        nextUnlisten()
+        return
+      }
+      unlisten = nextUnlisten
+    })
 
     return () => {
       cancelled = true
@@ -3,6 +3,7 @@ import { toast } from 'sonner'
 import { useI18n } from '@/i18n/useI18n'
 import { Spinner } from '@/components/ui/spinner'
 import { isTauriRuntime } from '@/utils/tauri'
+import { listenBackendEvent } from '@/services/eventBridge'
 import { exportApi } from '@/services/exportApi'
 
 type ExportTaskStatus = 'started' | 'finished' | 'failed'
@@ -33,50 +34,47 @@ export default function ExportStatusOverlay() {
     let unlisten: (() => void) | undefined
     let disposed = false
 
-    void import('@tauri-apps/api/event').then(({ listen }) => {
-      if (disposed) return
-      void listen<ExportTaskPayload>('export-task', (event) => {
-        const task = event.payload
-        const format = getFormatLabel(task.format)
-        const description =
-          task.status === 'failed'
-            ? task.message || getOutputName(task.output_path)
-            : getOutputName(task.output_path)
+    void listenBackendEvent<ExportTaskPayload>('export-task', (event) => {
+      const task = event.payload
+      const format = getFormatLabel(task.format)
+      const description =
+        task.status === 'failed'
+          ? task.message || getOutputName(task.output_path)
+          : getOutputName(task.output_path)
 
-        if (task.status === 'started') {
-          toast.loading(t('export.running', { format }), {
-            id: task.id,
-            description,
-            icon: <Spinner className="size-4" />,
-          })
-          return
-        }
-
-        if (task.status === 'finished') {
-          toast.success(t('export.finished', { format }), {
-            id: task.id,
-            description,
-            action: {
-              label: t('export.openFile'),
-              onClick: () => {
-                void exportApi.openExportedFile(task.output_path)
-              },
-            },
-          })
-          return
-        }
+      if (task.status === 'started') {
+        toast.loading(t('export.running', { format }), {
+          id: task.id,
+          description,
+          icon: <Spinner className="size-4" />,
+        })
+        return
+      }
 
-        toast.error(t('export.failed', { format }), {
+      if (task.status === 'finished') {
+        toast.success(t('export.finished', { format }), {
           id: task.id,
           description,
+          action: {
+            label: t('export.openFile'),
+            onClick: () => {
+              void exportApi.openExportedFile(task.output_path)
+            },
+          },
         })
-      }).then((nextUnlisten) => {
-        if (disposed) {
-          nextUnlisten()
-          return
-        }
-        unlisten = nextUnlisten
+        return
+      }
+
+      toast.error(t('export.failed', { format }), {
+        id: task.id,
+        description,
       })
+    }).then((nextUnlisten) => {
+      if (disposed) {
+        nextUnlisten()
+        return
+      }
+      unlisten = nextUnlisten
     })
 
     return () => {
@@ -16,6 +16,7 @@ import {
 import { Tooltip, TooltipContent, TooltipProvider, TooltipTrigger } from '@/components/ui/tooltip'
 import { gitApi, type GitDiffRequest, type GitFileChange } from '@/services/gitApi'
 import { fsApi } from '@/services/fsApi'
+import { listenBackendEvent } from '@/services/eventBridge'
 import { isTauriRuntime } from '@/utils/tauri'
 import { useI18n } from '@/i18n/useI18n'
 import { countChangedFiles, gitStatusQueryKey } from '@/logic/gitStatus'
@@ -98,12 +99,10 @@ export default function ScmPanel({ rootPath, rootKind, collapsed, onOpenDiff }:
     if (!enabled) return
     let unlisten: (() => void) | undefined
 
-    void import('@tauri-apps/api/event').then(({ listen }) => {
-      void listen('fs-changed', () => {
-        debouncedInvalidateStatus()
-      }).then((fn) => {
-        unlisten = fn
-      })
+    void listenBackendEvent('fs-changed', () => {
+      debouncedInvalidateStatus()
+    }).then((fn) => {
+      unlisten = fn
     })
 
     return () => {
@@ -1,4 +1,4 @@
-import { useCallback, useMemo, useRef, useState } from 'react'
+import { useCallback, useEffect, useMemo, useRef, useState } from 'react'
 import { AlertTriangle, Loader2, Plus, RotateCcw, Terminal as TerminalIcon, X } from 'lucide-react'
 import TerminalSessionPane, {
   type TerminalRuntimeState,
@@ -8,6 +8,7 @@ import { shellName } from '@/components/terminal/terminalTheme'
 import { Button } from '@/components/ui/button'
 import { Tooltip, TooltipContent, TooltipProvider, TooltipTrigger } from '@/components/ui/tooltip'
 import { useI18n } from '@/i18n/useI18n'
+import { terminalApi } from '@/services/terminalApi'
 import type { ThemeMode } from '@/store/useAppStore'
 import { cn } from '@/lib/utils'
 import { isTauriRuntime } from '@/utils/tauri'
@@ -19,6 +20,7 @@ type TerminalPanelProps = {
 }
 
 type TerminalTab = TerminalRuntimeState & {
+  attachSessionId: string | null
   index: number
   key: string
   restartKey: number
@@ -28,8 +30,9 @@ function initialTerminalStatus(): TerminalStatus {
   return isTauriRuntime() ? 'connecting' : 'unavailable'
 }
 
-function createTerminalTab(index: number): TerminalTab {
+function createTerminalTab(index: number, attachSessionId: string | null = null): TerminalTab {
   return {
+    attachSessionId,
     error: null,
     index,
     key: `terminal-tab-${index}`,
@@ -52,9 +55,39 @@ function TerminalStatusIcon({ status }: { status: TerminalStatus }) {
 export default function TerminalPanel({ onClose, theme, visible }: TerminalPanelProps) {
   const { t } = useI18n()
   const nextTabIndexRef = useRef(2)
-  const [tabs, setTabs] = useState<TerminalTab[]>(() => [createTerminalTab(1)])
+  // In the desktop app the shells outlive the webview, so the first tabs come
+  // from the backend's session list rather than a fresh shell.
+  const [tabs, setTabs] = useState<TerminalTab[]>(() =>
+    isTauriRuntime() ? [] : [createTerminalTab(1)],
+  )
   const [activeTabKey, setActiveTabKey] = useState('terminal-tab-1')
 
+  useEffect(() => {
+    if (!isTauriRuntime()) return
+
+    let cancelled = false
+    void terminalApi
+      .list()
+      .catch(() => [])
+      .then((sessions) => {
+        if (cancelled) return
+        for (const session of sessions) {
+          if (session.running === false) void terminalApi.close(session.id).catch(() => undefined)
+        }
+        const running = sessions.filter((session) => session.running !== false)
+        const restored =
+          running.length > 0
+            ? running.map((session, position) => createTerminalTab(position + 1, session.id))
+            : [createTerminalTab(1)]
+        nextTabIndexRef.current = restored.length + 1
+        setTabs(restored)
+        setActiveTabKey(restored[0].key)
+      })
+    return () => {
+      cancelled = true
+    }
+  }, [])
+
   const statusLabel = useCallback(
     (status: TerminalStatus) =>
       status === 'connected'
@@ -107,6 +140,7 @@ export default function TerminalPanel({ onClose, theme, visible }: TerminalPanel
         tab.key === activeTabKey
           ? {
               ...tab,
+              attachSessionId: null,
               error: null,
               restartKey: tab.restartKey + 1,
               session: null,
@@ -121,8 +155,8 @@ export default function TerminalPanel({ onClose, theme, visible }: TerminalPanel
     () => tabs.find((tab) => tab.key === activeTabKey) ?? tabs[0],
     [activeTabKey, tabs],
   )
-  const activeStatusLabel = statusLabel(activeTab.status)
-  const activeSessionLabel = activeTab.session
+  const activeStatusLabel = statusLabel(activeTab?.status ?? initialTerminalStatus())
+  const activeSessionLabel = activeTab?.session
     ? `${shellName(activeTab.session.shell)} · ${activeTab.session.cwd}`
     : activeStatusLabel
 
@@ -241,6 +275,7 @@ export default function TerminalPanel({ onClose, theme, visible }: TerminalPanel
             <TerminalSessionPane
               key={tab.key}
               active={tab.key === activeTabKey}
+              attachSessionId={tab.attachSessionId}
               exitedLabel={t('terminal.exited')}
               restartKey={tab.restartKey}
               statusLabel={statusLabel(tab.status)}
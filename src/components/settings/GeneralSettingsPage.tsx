@@ -1,4 +1,5 @@
 import type { ElementType } from 'react'
+import { useMutation, useQuery, useQueryClient } from '@tanstack/react-query'
 import { Controller, useForm } from 'react-hook-form'
 import { zodResolver } from '@hookform/resolvers/zod'
 import { z } from 'zod'
@@ -12,6 +13,10 @@ import {
   type MarkdownAssetImportStrategy,
 } from '@/store/useAppStore'
 import SettingsRow from '@/components/settings/SettingsRow'
+import { fsApi, type FileHistoryConfig } from '@/services/fsApi'
+import { isTauriRuntime } from '@/utils/tauri'
+
+const fileHistoryConfigQueryKey = ['file-history-config'] as const
 
 const fileViews: Array<{ value: FileViewKind; labelKey: string; icon: ElementType }> = [
   { value: 'edit', labelKey: 'editor.modeWysiwyg', icon: PenLine },
@@ -45,6 +50,22 @@ export default function GeneralSettingsPage() {
   const setMarkdownAssetImportStrategy = useAppStore(
     (state) => state.setMarkdownAssetImportStrategy,
   )
+  const queryClient = useQueryClient()
+  const fileHistoryQuery = useQuery({
+    queryKey: fileHistoryConfigQueryKey,
+    queryFn: () => fsApi.getFileHistoryConfig(),
+    enabled: isTauriRuntime(),
+  })
+  const fileHistoryMutation = useMutation({
+    mutationFn: (config: FileHistoryConfig) => fsApi.setFileHistoryConfig(config),
+    onSuccess: (config) => {
+      queryClient.setQueryData(fileHistoryConfigQueryKey, config)
+    },
+    onError: (error) => {
+      console.error('update file history config failed', error)
+    },
+  })
+  const fileHistoryConfig = fileHistoryQuery.data
   const form = useForm<GeneralSettingsValues>({
     mode: 'onChange',
     resolver: zodResolver(generalSettingsSchema),
@@ -115,6 +136,21 @@ export default function GeneralSettingsPage() {
           />
         }
       />
+      {fileHistoryConfig ? (
+        <SettingsRow
+          title={t('settings.fileHistory')}
+          description={t('settings.fileHistoryDescription')}
+          control={
+            <Switch
+              checked={fileHistoryConfig.enabled}
+              disabled={fileHistoryMutation.isPending}
+              onCheckedChange={(checked) => {
+                fileHistoryMutation.mutate({ ...fileHistoryConfig, enabled: checked })
+              }}
+            />
+          }
+        />
+      ) : null}
       <section className="settings-row-surface rounded-md p-3">
         <div className="mb-1 text-sm font-medium">{t('settings.defaultFileView')}</div>
         <div className="mb-3 text-xs leading-5 text-muted-foreground">
@@ -3,7 +3,12 @@ import { FitAddon } from '@xterm/addon-fit'
 import { Unicode11Addon } from '@xterm/addon-unicode11'
 import { WebLinksAddon } from '@xterm/addon-web-links'
 import { Terminal } from '@xterm/xterm'
-import { terminalApi, type TerminalSessionInfo } from '@/services/terminalApi'
+import { errorMessage } from '@/services/appError'
+import {
+  terminalApi,
+  type TerminalOutputEvent,
+  type TerminalSessionInfo,
+} from '@/services/terminalApi'
 import type { ThemeMode } from '@/store/useAppStore'
 import { cn } from '@/lib/utils'
 import { isTauriRuntime } from '@/utils/tauri'
@@ -23,6 +28,8 @@ export type TerminalRuntimeState = {
 
 type TerminalSessionPaneProps = {
   active: boolean
+  /** Backend session to reattach to instead of starting a new shell. */
+  attachSessionId?: string | null
   exitedLabel: string
   restartKey: number
   statusLabel: string
@@ -39,6 +46,7 @@ function applyTerminalTheme(terminal: Terminal) {
 
 export default function TerminalSessionPane({
   active,
+  attachSessionId = null,
   exitedLabel,
   restartKey,
   statusLabel,
@@ -156,7 +164,7 @@ export default function TerminalSessionPane({
       inputBuffer = ''
       if (!id) return
       void terminalApi.write(id, data).catch((err) => {
-        setError(String(err))
+        setError(errorMessage(err))
         setStatus('error')
       })
     }
@@ -191,45 +199,87 @@ export default function TerminalSessionPane({
       inputFlushTimer = window.setTimeout(flushInput, 0)
     })
 
+    const markExited = () => {
+      sessionIdRef.current = null
+      setStatus('exited')
+      if (outputFrame !== null) {
+        window.cancelAnimationFrame(outputFrame)
+        flushOutput()
+      }
+      terminal.writeln('')
+      terminal.writeln(exitedLabel)
+    }
+
+    // Output below this offset is already part of the attached scrollback;
+    // until the attachment arrives, live output is held back.
+    let resumeOffset: number | null = attachSessionId ? null : 0
+    let heldOutput: TerminalOutputEvent[] = []
+
+    const subscribe = (id: string) => {
+      unsubscribeTerminalEvents = subscribeTerminalSessionEvents(id, {
+        onExit: () => {
+          if (!disposed) markExited()
+        },
+        onOutput: (event) => {
+          if (disposed) return
+          if (resumeOffset === null) {
+            heldOutput.push(event)
+          } else if (event.offset >= resumeOffset) {
+            queueOutput(event.data)
+          }
+        },
+      })
+    }
+
+    const connected = (nextSession: TerminalSessionInfo) => {
+      setSession(nextSession)
+      setStatus('connected')
+      scheduleResize()
+      if (activeRef.current) terminal.focus()
+    }
+
+    const attach = async (id: string) => {
+      await primeTerminalEventListeners()
+      if (disposed) return
+      sessionIdRef.current = id
+      subscribe(id)
+      const attachment = await terminalApi.attach(id)
+      if (disposed) return
+
+      queueOutput(attachment.scrollback)
+      resumeOffset = attachment.offset
+      for (const event of heldOutput) {
+        if (event.offset >= attachment.offset) queueOutput(event.data)
+      }
+      heldOutput = []
+      if (attachment.session.running === false) {
+        setSession(attachment.session)
+        markExited()
+        return
+      }
+      connected(attachment.session)
+    }
+
+    const create = async () => {
+      void primeTerminalEventListeners()
+      const nextSession = await terminalApi.create(terminal.rows, terminal.cols)
+      if (disposed) {
+        void terminalApi.close(nextSession.id).catch(() => undefined)
+        return
+      }
+      subscribe(nextSession.id)
+      sessionIdRef.current = nextSession.id
+      connected(nextSession)
+    }
+
     setSession(null)
     setError(null)
     setStatus('connecting')
-    primeTerminalEventListeners()
-    void terminalApi
-      .create(terminal.rows, terminal.cols)
-      .then((nextSession) => {
-        if (disposed) {
-          void terminalApi.close(nextSession.id).catch(() => undefined)
-          return
-        }
-        unsubscribeTerminalEvents = subscribeTerminalSessionEvents(nextSession.id, {
-          onExit: () => {
-            if (disposed) return
-
-            sessionIdRef.current = null
-            setStatus('exited')
-            if (outputFrame !== null) {
-              window.cancelAnimationFrame(outputFrame)
-              flushOutput()
-            }
-            terminal.writeln('')
-            terminal.writeln(exitedLabel)
-          },
-          onOutput: (event) => {
-            if (!disposed) queueOutput(event.data)
-          },
-        })
-        sessionIdRef.current = nextSession.id
-        setSession(nextSession)
-        setStatus('connected')
-        scheduleResize()
-        if (activeRef.current) terminal.focus()
-      })
-      .catch((err) => {
-        if (disposed) return
-        setError(String(err))
-        setStatus('error')
-      })
+    void (attachSessionId ? attach(attachSessionId) : create()).catch((err) => {
+      if (disposed) return
+      setError(errorMessage(err))
+      setStatus('error')
+    })
 
     return () => {
       disposed = true
@@ -248,7 +298,7 @@ export default function TerminalSessionPane({
       lastSizeRef.current = null
       terminalRef.current = null
     }
-  }, [closeSession, exitedLabel, restartKey])
+  }, [attachSessionId, closeSession, exitedLabel, restartKey])
 
   useEffect(() => {
     const terminal = terminalRef.current
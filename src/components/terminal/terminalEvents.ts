@@ -6,6 +6,7 @@ import {
   type TerminalExitEvent,
   type TerminalOutputEvent,
 } from '@/services/terminalApi'
+import { listenBackendEvent } from '@/services/eventBridge'
 import { isTauriRuntime } from '@/utils/tauri'
 
 type TerminalEventHandlers = {
@@ -55,34 +56,35 @@ function dispatchExit(event: TerminalExitEvent) {
 }
 
 function ensureTerminalEventListeners() {
-  if (!isTauriRuntime() || listenerPromise) return
-
-  listenerPromise = import('@tauri-apps/api/event')
-    .then(async ({ listen }) => {
-      outputUnlisten = await listen<unknown>('terminal-output', (event) => {
-        const payload = terminalOutputEventSchema.safeParse(event.payload)
-        if (payload.success) dispatchOutput(payload.data)
-      })
-      exitUnlisten = await listen<unknown>('terminal-exit', (event) => {
-        const payload = terminalExitEventSchema.safeParse(event.payload)
-        if (payload.success) dispatchExit(payload.data)
-      })
+  if (!isTauriRuntime()) return Promise.resolve()
+  if (listenerPromise) return listenerPromise
+
+  listenerPromise = (async () => {
+    outputUnlisten = await listenBackendEvent('terminal-output', (event) => {
+      const payload = terminalOutputEventSchema.safeParse(event.payload)
+      if (payload.success) dispatchOutput(payload.data)
     })
-    .catch(() => {
-      listenerPromise = null
-      outputUnlisten?.()
-      exitUnlisten?.()
-      outputUnlisten = null
-      exitUnlisten = null
+    exitUnlisten = await listenBackendEvent('terminal-exit', (event) => {
+      const payload = terminalExitEventSchema.safeParse(event.payload)
+      if (payload.success) dispatchExit(payload.data)
     })
+  })().catch(() => {
+    listenerPromise = null
+    outputUnlisten?.()
+    exitUnlisten?.()
+    outputUnlisten = null
+    exitUnlisten = null
+  })
+  return listenerPromise
 }
 
+/** Starts the backend listeners; resolves once output events can be received. */
 export function primeTerminalEventListeners() {
-  ensureTerminalEventListeners()
+  return ensureTerminalEventListeners()
 }
 
 export function subscribeTerminalSessionEvents(sessionId: string, handlers: TerminalEventHandlers) {
-  ensureTerminalEventListeners()
+  void ensureTerminalEventListeners()
   updateSubscriberCount(sessionId, 1)
 
   const handleOutput = (event: TerminalOutputEvent) => {
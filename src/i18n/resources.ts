@@ -128,6 +128,8 @@ export const resources = {
         '控制插入图片或附件时是否复制到当前文档的 .assets 目录。',
       'settings.assetStrategyCopy': '复制到 标题.assets',
       'settings.assetStrategyPreserve': '保留原有路径',
+      'settings.fileHistory': '本地文件历史',
+      'settings.fileHistoryDescription': '保存时在应用数据目录中保留文件的历史版本，可随时恢复。',
       'settings.graphEditor': '图谱编辑器',
       'settings.graphMiniMap': '显示小地图',
       'settings.graphMiniMapDescription': '在 React Flow 画布右下角显示导航小地图。',
@@ -277,6 +279,18 @@ export const resources = {
       'inspector.unknown': '未知',
       'common.yes': '是',
       'common.no': '否',
+      'error.notFound': '找不到 {{path}}',
+      'error.alreadyExists': '{{path}} 已存在',
+      'error.permissionDenied': '没有权限访问 {{path}}',
+      'error.invalidPath': '路径 {{path}} 无效：{{reason}}',
+      'error.conflict': '{{path}} 已在别处修改：{{reason}}',
+      'error.unsupported': '当前不支持此操作：{{reason}}',
+      'error.invalidInput': '输入无效：{{reason}}',
+      'error.invalidFormat': '无法作为文本处理：{{reason}}',
+      'error.cancelled': '已取消',
+      'error.git': 'Git 操作失败：{{reason}}',
+      'error.io': '无法访问 {{path}}：{{reason}}',
+      'error.internal': '内部错误：{{reason}}',
     },
   },
   'en-US': {
@@ -405,6 +419,9 @@ export const resources = {
         'Choose whether inserted images and attachments are copied into the current document asset folder.',
       'settings.assetStrategyCopy': 'Copy to title.assets',
       'settings.assetStrategyPreserve': 'Keep original path',
+      'settings.fileHistory': 'Local file history',
+      'settings.fileHistoryDescription':
+        'Keep earlier versions of saved files in the app data folder so they can be restored.',
       'settings.graphEditor': 'Graph Editor',
       'settings.graphMiniMap': 'Show minimap',
       'settings.graphMiniMapDescription': 'Show the React Flow navigation minimap on the canvas.',
@@ -558,6 +575,18 @@ export const resources = {
       'inspector.unknown': 'Unknown',
       'common.yes': 'Yes',
       'common.no': 'No',
+      'error.notFound': '{{path}} was not found',
+      'error.alreadyExists': '{{path}} already exists',
+      'error.permissionDenied': 'Permission denied: {{path}}',
+      'error.invalidPath': 'Invalid path {{path}}: {{reason}}',
+      'error.conflict': '{{path}} changed elsewhere: {{reason}}',
+      'error.unsupported': 'Not supported here: {{reason}}',
+      'error.invalidInput': 'Invalid input: {{reason}}',
+      'error.invalidFormat': 'Cannot be handled as text: {{reason}}',
+      'error.cancelled': 'Cancelled',
+      'error.git': 'Git failed: {{reason}}',
+      'error.io': 'Cannot access {{path}}: {{reason}}',
+      'error.internal': 'Internal error: {{reason}}',
     },
   },
 } as const
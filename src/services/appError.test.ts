@@ -0,0 +1,25 @@
+import { beforeEach, describe, expect, it } from 'vitest'
+import i18n from '@/i18n/setup'
+import { errorMessage } from '@/services/appError'
+
+describe('errorMessage', () => {
+  beforeEach(async () => {
+    await i18n.changeLanguage('en-US')
+  })
+
+  it('localises backend errors from their code and details', async () => {
+    const error = {
+      code: 'already_exists',
+      message: 'Already exists: notes/a.md',
+      details: { path: 'notes/a.md' },
+    }
+    expect(errorMessage(error)).toBe('notes/a.md already exists')
+    await i18n.changeLanguage('zh-CN')
+    expect(errorMessage(error)).toBe('notes/a.md 已存在')
+  })
+
+  it('falls back to plain errors', () => {
+    expect(errorMessage(new Error('boom'))).toBe('boom')
+    expect(errorMessage('failed')).toBe('failed')
+  })
+})
@@ -0,0 +1,67 @@
+import { z } from 'zod'
+import i18n from '@/i18n/setup'
+
+export const appErrorCodeSchema = z.enum([
+  'not_found',
+  'already_exists',
+  'permission_denied',
+  'invalid_path',
+  'conflict',
+  'unsupported',
+  'invalid_input',
+  'invalid_format',
+  'cancelled',
+  'git',
+  'io',
+  'internal',
+])
+
+export const appErrorSchema = z.object({
+  code: appErrorCodeSchema,
+  message: z.string(),
+  details: z.record(z.string(), z.string()),
+})
+
+export type AppErrorCode = z.infer<typeof appErrorCodeSchema>
+export type AppError = z.infer<typeof appErrorSchema>
+
+/** Backend command errors arrive as `{code, message, details}`. */
+export function parseAppError(error: unknown): AppError | null {
+  const parsed = appErrorSchema.safeParse(error)
+  return parsed.success ? parsed.data : null
+}
+
+export function isAppErrorCode(error: unknown, code: AppErrorCode) {
+  return parseAppError(error)?.code === code
+}
+
+const errorMessageKeys: Record<AppErrorCode, string> = {
+  not_found: 'error.notFound',
+  already_exists: 'error.alreadyExists',
+  permission_denied: 'error.permissionDenied',
+  invalid_path: 'error.invalidPath',
+  conflict: 'error.conflict',
+  unsupported: 'error.unsupported',
+  invalid_input: 'error.invalidInput',
+  invalid_format: 'error.invalidFormat',
+  cancelled: 'error.cancelled',
+  git: 'error.git',
+  io: 'error.io',
+  internal: 'error.internal',
+}
+
+/**
+ * Localised text for an error. Backend errors are translated from their
+ * code and details; the English `message` is only a fallback.
+ */
+export function errorMessage(error: unknown) {
+  const appError = parseAppError(error)
+  if (appError) {
+    return i18n.t(errorMessageKeys[appError.code], {
+      ...appError.details,
+      defaultValue: appError.message,
+    })
+  }
+  if (error instanceof Error) return error.message
+  return String(error)
+}
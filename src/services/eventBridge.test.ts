@@ -0,0 +1,37 @@
+import { beforeEach, describe, expect, it, vi } from 'vitest'
+import { listenBackendEvent } from '@/services/eventBridge'
+
+const eventHandlers = vi.hoisted(() => new Map<string, (event: { payload: unknown }) => void>())
+
+vi.mock('@tauri-apps/api/event', () => ({
+  listen: vi.fn(async (event: string, handler: (event: { payload: unknown }) => void) => {
+    eventHandlers.set(event, handler)
+    return vi.fn()
+  }),
+}))
+
+const menuActionEvent = (replayed: boolean) => ({
+  payload: { version: 1, topic: 'app', seq: 1, replayed, payload: { id: 'new-file' } },
+})
+
+describe('listenBackendEvent', () => {
+  beforeEach(() => {
+    eventHandlers.clear()
+  })
+
+  it('drops replayed events unless the listener opts in', async () => {
+    const handler = vi.fn()
+    await listenBackendEvent('menu-action', handler)
+    eventHandlers.get('menu-action')?.(menuActionEvent(true))
+    expect(handler).not.toHaveBeenCalled()
+    eventHandlers.get('menu-action')?.(menuActionEvent(false))
+    expect(handler).toHaveBeenCalledTimes(1)
+
+    const debugHandler = vi.fn()
+    await listenBackendEvent('menu-action', debugHandler, { replayed: true })
+    eventHandlers.get('menu-action')?.(menuActionEvent(true))
+    expect(debugHandler).toHaveBeenCalledWith(
+      expect.objectContaining({ name: 'menu-action', replayed: true, payload: { id: 'new-file' } }),
+    )
+  })
+})
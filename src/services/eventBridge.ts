@@ -0,0 +1,126 @@
+import { invoke } from '@tauri-apps/api/core'
+import type { UnlistenFn } from '@tauri-apps/api/event'
+import { z } from 'zod'
+
+export const backendEventTopicSchema = z.enum([
+  'workspace',
+  'buffers',
+  'export',
+  'terminal',
+  'process',
+  'background_tasks',
+  'app',
+])
+
+export type BackendEventTopic = z.infer<typeof backendEventTopicSchema>
+
+/** Every event the backend emits, by topic. */
+export const backendEventTopics = {
+  'fs-changed': 'workspace',
+  'fs-buffer-status': 'buffers',
+  'fs-external-conflict': 'buffers',
+  'export-task': 'export',
+  'terminal-output': 'terminal',
+  'terminal-exit': 'terminal',
+  'code-run-output': 'process',
+  'code-run-exit': 'process',
+  'task-output': 'process',
+  'task-exit': 'process',
+  'background-task': 'background_tasks',
+  'menu-action': 'app',
+  'app-close-blocked': 'app',
+  'single-instance': 'app',
+} as const satisfies Record<string, BackendEventTopic>
+
+export type BackendEventName = keyof typeof backendEventTopics
+
+const eventEnvelopeSchema = z.object({
+  version: z.number(),
+  topic: backendEventTopicSchema,
+  seq: z.number(),
+  replayed: z.boolean(),
+  payload: z.unknown(),
+})
+
+export type BackendEvent<T = unknown> = Omit<z.infer<typeof eventEnvelopeSchema>, 'payload'> & {
+  name: BackendEventName
+  payload: T
+}
+
+export const loggedEventSchema = z.object({
+  seq: z.number(),
+  name: z.string(),
+  topic: backendEventTopicSchema,
+  version: z.number(),
+  emitted_at: z.number(),
+  payload: z.unknown(),
+})
+
+export type LoggedEvent = z.infer<typeof loggedEventSchema>
+
+export interface BackendEventListenOptions {
+  /**
+   * Also receive events re-emitted by `eventLogApi.replay`. Off by default,
+   * so a replay never re-runs menu actions or overwrites current state.
+   */
+  replayed?: boolean
+}
+
+/**
+ * Listens to a backend event and unwraps its versioned envelope. Payloads
+ * are not validated here; listeners parse them with their own schema.
+ */
+export async function listenBackendEvent<T = unknown>(
+  name: BackendEventName,
+  handler: (event: BackendEvent<T>) => void,
+  options: BackendEventListenOptions = {},
+): Promise<UnlistenFn> {
+  const { listen } = await import('@tauri-apps/api/event')
+  return listen<unknown>(name, (event) => {
+    const envelope = eventEnvelopeSchema.safeParse(event.payload)
+    if (!envelope.success) {
+      console.warn(`ignored malformed ${name} event`, event.payload)
+      return
+    }
+    if (envelope.data.replayed && !options.replayed) return
+    handler({ ...envelope.data, name, payload: envelope.data.payload as T })
+  })
+}
+
+/** Listens to every event of a topic. */
+export async function listenBackendTopic(
+  topic: BackendEventTopic,
+  handler: (event: BackendEvent) => void,
+  options: BackendEventListenOptions = {},
+): Promise<UnlistenFn> {
+  const names = (Object.keys(backendEventTopics) as BackendEventName[]).filter(
+    (name) => backendEventTopics[name] === topic,
+  )
+  const unlisteners = await Promise.all(
+    names.map((name) => listenBackendEvent(name, handler, options)),
+  )
+  return () => {
+    for (const unlisten of unlisteners) unlisten()
+  }
+}
+
+export const eventLogApi = {
+  /** Logged events after `since`, oldest first. Output streams are not logged. */
+  async getLog(since?: number, topic?: BackendEventTopic) {
+    const result = await invoke<unknown>('app_get_event_log', {
+      since: since ?? null,
+      topic: topic ?? null,
+    })
+    return z.array(loggedEventSchema).parse(result)
+  },
+  /**
+   * Emits logged events again with `replayed` set. Only listeners that opt
+   * in with `{ replayed: true }` receive them.
+   */
+  replay(since?: number, topic?: BackendEventTopic) {
+    return invoke<number>('app_replay_events', {
+      since: since ?? null,
+      topic: topic ?? null,
+    })
+  },
+}
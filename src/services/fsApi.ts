@@ -32,11 +32,32 @@ export const fsBufferStatusSchema = z.object({
   dirty: z.boolean(),
 })
 
+export const fileHistoryConfigSchema = z.object({
+  enabled: z.boolean(),
+  keep_last: z.number(),
+  keep_hourly_hours: z.number(),
+  keep_daily_days: z.number(),
+  max_file_bytes: z.number(),
+})
+
+export type FileHistoryConfig = z.infer<typeof fileHistoryConfigSchema>
+
+export const backgroundTaskProgressSchema = z.object({
+  completed: z.number(),
+  total: z.number().nullable().optional(),
+  percent: z.number().nullable().optional(),
+  step: z.string().nullable().optional(),
+})
+
 export const backgroundTaskStatusSchema = z.object({
   id: z.string(),
   label: z.string(),
-  status: z.enum(['idle', 'running', 'error']),
+  status: z.enum(['running', 'idle', 'succeeded', 'failed', 'cancelled']),
   message: z.string().nullable().optional(),
+  progress: backgroundTaskProgressSchema.nullable().optional(),
+  cancellable: z.boolean(),
+  started_at: z.number().nullable().optional(),
+  finished_at: z.number().nullable().optional(),
 })
 
 export const fsMarkdownHeadingSchema = z.object({
@@ -253,6 +274,21 @@ export const fsApi = {
     const result = await invoke<unknown>('fs_get_background_tasks')
     return z.array(backgroundTaskStatusSchema).parse(result)
   },
+  async getBackgroundTaskHistory() {
+    const result = await invoke<unknown>('fs_get_background_task_history')
+    return z.array(backgroundTaskStatusSchema).parse(result)
+  },
+  async getFileHistoryConfig() {
+    const result = await invoke<unknown>('fs_get_file_history_config')
+    return fileHistoryConfigSchema.parse(result)
+  },
+  async setFileHistoryConfig(config: FileHistoryConfig) {
+    const result = await invoke<unknown>('fs_set_file_history_config', { config })
+    return fileHistoryConfigSchema.parse(result)
+  },
+  cancelBackgroundTask(id: string) {
+    return invoke('fs_cancel_background_task', { id })
+  },
   createFile(path: string) {
     return invoke('fs_create_file', { path })
   },
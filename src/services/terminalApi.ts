@@ -5,11 +5,15 @@ export const terminalSessionSchema = z.object({
   id: z.string(),
   shell: z.string(),
   cwd: z.string(),
+  profile: z.string().nullable().optional(),
+  running: z.boolean().optional(),
+  exit_code: z.number().nullable().optional(),
 })
 
 export const terminalOutputEventSchema = z.object({
   id: z.string(),
   data: z.string(),
+  offset: z.number(),
 })
 
 export const terminalExitEventSchema = z.object({
@@ -18,15 +22,30 @@ export const terminalExitEventSchema = z.object({
   signal: z.string().nullable().optional(),
 })
 
+export const terminalAttachmentSchema = z.object({
+  session: terminalSessionSchema,
+  scrollback: z.string(),
+  offset: z.number(),
+})
+
 export type TerminalSessionInfo = z.infer<typeof terminalSessionSchema>
 export type TerminalOutputEvent = z.infer<typeof terminalOutputEventSchema>
 export type TerminalExitEvent = z.infer<typeof terminalExitEventSchema>
+export type TerminalAttachment = z.infer<typeof terminalAttachmentSchema>
 
 export const terminalApi = {
   async create(rows: number, cols: number) {
     const result = await invoke<unknown>('terminal_create', { rows, cols })
     return terminalSessionSchema.parse(result)
   },
+  async list() {
+    const result = await invoke<unknown>('terminal_list')
+    return z.array(terminalSessionSchema).parse(result)
+  },
+  async attach(id: string) {
+    const result = await invoke<unknown>('terminal_attach', { id })
+    return terminalAttachmentSchema.parse(result)
+  },
   write(id: string, data: string) {
     return invoke<void>('terminal_write', { id, data })
   },
@@ -1,4 +1,5 @@
 import { create } from 'zustand'
+import { errorMessage } from '@/services/appError'
 
 type MarkdownAssetSyncStore = {
   pending: number
@@ -10,11 +11,6 @@ type MarkdownAssetSyncStore = {
   clearFailures: () => void
 }
 
-const errorMessage = (error: unknown) => {
-  if (error instanceof Error) return error.message
-  return String(error)
-}
-
 export const useMarkdownAssetSyncStore = create<MarkdownAssetSyncStore>((set) => ({
   pending: 0,
   failed: 0,